@@ -2,4 +2,21 @@ extern crate napi_build;
 
 fn main() {
   napi_build::setup();
+
+  // The version of the npm package, which is released rather than the crate.
+  let package = std::fs::read_to_string("package.json").unwrap_or_default();
+  let version = package
+    .split("\"version\":")
+    .nth(1)
+    .and_then(|rest| rest.split('"').nth(1))
+    .unwrap_or(env!("CARGO_PKG_VERSION"));
+
+  println!("cargo:rerun-if-changed=package.json");
+  println!("cargo:rustc-env=PDF_SIGN_VERSION={}", version);
+
+  // The target triple of the build, reported by `capabilities()`.
+  println!(
+    "cargo:rustc-env=PDF_SIGN_TARGET={}",
+    std::env::var("TARGET").unwrap_or_default()
+  );
 }
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use crate::CliError;
+
+/// The arguments of a subcommand: `--name value` (or `--name=value`) options, `--name` flags and
+/// positional arguments.
+pub struct Args {
+  options: HashMap<String, String>,
+  flags: Vec<String>,
+  positional: Vec<String>,
+}
+
+impl Args {
+  /// Parse arguments, treating the names in `flags` as options without a value.
+  pub fn parse(args: impl IntoIterator<Item = String>, flags: &[&str]) -> Result<Self, CliError> {
+    let mut parsed = Self {
+      options: HashMap::new(),
+      flags: Vec::new(),
+      positional: Vec::new(),
+    };
+
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+      let Some(name) = arg.strip_prefix("--") else {
+        parsed.positional.push(arg);
+        continue;
+      };
+
+      if let Some((name, value)) = name.split_once('=') {
+        parsed.options.insert(name.to_string(), value.to_string());
+      } else if flags.contains(&name) {
+        parsed.flags.push(name.to_string());
+      } else {
+        let value = args
+          .next()
+          .ok_or_else(|| CliError::Usage(format!("Missing value for --{}", name)))?;
+        parsed.options.insert(name.to_string(), value);
+      }
+    }
+
+    Ok(parsed)
+  }
+
+  pub fn value(&self, name: &str) -> Option<&str> {
+    self.options.get(name).map(String::as_str)
+  }
+
+  pub fn required(&self, name: &str) -> Result<&str, CliError> {
+    self
+      .value(name)
+      .ok_or_else(|| CliError::Usage(format!("Missing --{}", name)))
+  }
+
+  pub fn flag(&self, name: &str) -> bool {
+    self.flags.iter().any(|flag| flag == name)
+  }
+
+  /// The only positional argument, e.g. the file to sign.
+  pub fn input(&self, what: &str) -> Result<&str, CliError> {
+    match self.positional.as_slice() {
+      [input] => Ok(input),
+      [] => Err(CliError::Usage(format!("Missing {}", what))),
+      _ => Err(CliError::Usage(format!("Expected a single {}", what))),
+    }
+  }
+}
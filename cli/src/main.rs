@@ -0,0 +1,363 @@
+//! `pdf-sign`, a command line interface for creating, verifying and inspecting the CMS
+//! signatures embedded in PDF documents, using the same code as the Node.js package.
+
+mod args;
+
+use std::fmt;
+use std::process::ExitCode;
+
+use bcder::Oid;
+use cryptographic_message_syntax::asn1::rfc5652::OID_ID_DATA;
+use cryptographic_message_syntax::{Bytes, SignerInfo};
+use pdf_sign_core::certificates::LazyCertificate;
+use pdf_sign_core::credentials;
+use pdf_sign_core::errors::CmsError;
+use pdf_sign_core::gcloud_signer::GCloudSigner;
+use pdf_sign_core::input::{self, InputFormat};
+use pdf_sign_core::keys::{self, P12Contents};
+use pdf_sign_core::signing::{self, SignedDataBuilder, SignerBuilder};
+use pdf_sign_core::{parse_digest_algorithm, validation};
+use x509_certificate::KeyInfoSigner;
+use zeroize::Zeroizing;
+
+use args::Args;
+
+const USAGE: &str = "Usage:
+  pdf-sign sign <file> (--p12 <file> [--password <password> | --password-source <source>] | --cert <file> --key <file> | --cert <file> --kms <key path>)
+                [--timestamp-server <url>] [--attached] [--out <file>]
+  pdf-sign verify <signature> [--content <file>]
+  pdf-sign timestamp <file> --timestamp-server <url> [--digest-algorithm <name>] [--out <file>]
+  pdf-sign inspect <signature>
+
+Signatures are DER encoded CMS SignedData, written to <file>.p7s and timestamp tokens to
+<file>.tst unless --out is given. Certificates and private keys are PEM or DER encoded, --kms takes the
+name of a Google Cloud KMS key version. --password-source reads the password from env:NAME, an
+environment variable, or fd:N, a file descriptor, so it doesn't appear in the arguments. verify checks the signatures and content digests, not
+whether the certificates are trusted.";
+
+pub enum CliError {
+  Usage(String),
+  Cms(CmsError),
+}
+
+impl From<CmsError> for CliError {
+  fn from(error: CmsError) -> Self {
+    CliError::Cms(error)
+  }
+}
+
+impl fmt::Display for CliError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CliError::Usage(message) => write!(f, "{}", message),
+      CliError::Cms(error) => match error.cause() {
+        Some(cause) => write!(f, "{} ({}): {}", error, error.code(), cause),
+        None => write!(f, "{} ({})", error, error.code()),
+      },
+    }
+  }
+}
+
+fn main() -> ExitCode {
+  let mut args = std::env::args().skip(1);
+
+  let result = match args.next().as_deref() {
+    Some("sign") => Args::parse(args, &["attached"]).and_then(sign),
+    Some("verify") => Args::parse(args, &[]).and_then(verify),
+    Some("timestamp") => Args::parse(args, &[]).and_then(timestamp),
+    Some("inspect") => Args::parse(args, &[]).and_then(inspect),
+    Some("help" | "--help" | "-h") => {
+      println!("{}", USAGE);
+      Ok(ExitCode::SUCCESS)
+    }
+    Some(command) => Err(CliError::Usage(format!("Unknown command {}", command))),
+    None => Err(CliError::Usage(String::from("Missing command"))),
+  };
+
+  match result {
+    Ok(code) => code,
+    Err(error @ CliError::Usage(_)) => {
+      eprintln!("pdf-sign: {}\n\n{}", error, USAGE);
+      ExitCode::from(2)
+    }
+    Err(error) => {
+      eprintln!("pdf-sign: {}", error);
+      ExitCode::FAILURE
+    }
+  }
+}
+
+/// Sign a file with a P12 container, a PKCS#8 key or a Google Cloud KMS key.
+fn sign(args: Args) -> Result<ExitCode, CliError> {
+  let input = args.input("file to sign")?;
+  let timestamp_server = args.value("timestamp-server");
+
+  // Detached signatures only need the digest, so the file isn't read into memory.
+  let content = args.flag("attached").then(|| read(input)).transpose()?;
+
+  validation::signing_options(
+    content.as_deref(),
+    Some(input),
+    None,
+    None,
+    timestamp_server,
+  )?;
+
+  let (signing_key, certs): (Box<dyn KeyInfoSigner>, _) =
+    match (args.value("p12"), args.value("key"), args.value("kms")) {
+      (Some(p12), None, None) => {
+        let p12 = Zeroizing::new(read(p12)?);
+        validation::p12("p12", &p12)?;
+
+        let password = match (args.value("password"), args.value("password-source")) {
+          (password, None) => Zeroizing::new(password.unwrap_or_default().to_string()),
+          (None, Some(source)) => credentials::read_string("password-source", source)?,
+          (Some(_), Some(_)) => {
+            return Err(CliError::Usage(String::from(
+              "Expected only one of --password and --password-source",
+            )))
+          }
+        };
+
+        let P12Contents {
+          private_key,
+          certificates,
+        } = keys::read_p12(&p12, &password)?;
+
+        let signing_key = keys::private_key_from_der(&private_key)?;
+
+        let certs = certificates
+          .into_iter()
+          .map(LazyCertificate::from_der)
+          .collect::<Result<Vec<_>, _>>()?;
+
+        (Box::new(signing_key), certs)
+      }
+      (None, Some(key), None) => {
+        let key = Zeroizing::new(read(key)?);
+        validation::encoded("key", &key, "a private key", InputFormat::Auto)?;
+
+        let signing_key = input::private_key("key", &key, None, InputFormat::Auto)?;
+
+        (Box::new(signing_key), read_certificates(&args)?)
+      }
+      (None, None, Some(key_path)) => {
+        validation::key_path("kms", key_path)?;
+
+        (
+          Box::new(GCloudSigner::new(key_path.to_string())?),
+          read_certificates(&args)?,
+        )
+      }
+      _ => {
+        return Err(CliError::Usage(String::from(
+          "Expected exactly one of --p12, --key or --kms",
+        )))
+      }
+    };
+
+  let signing_certificate = certs
+    .first()
+    .ok_or(CmsError::NoCertificate)?
+    .parsed()?
+    .clone();
+  let mut signer = SignerBuilder::new(signing_key.as_ref(), signing_certificate);
+
+  if let Some(timestamp_server) = timestamp_server {
+    signer = signer
+      .time_stamp_url(timestamp_server)
+      .map_err(|error| CmsError::TimestampServerParseError.caused_by(error))?;
+  }
+
+  let builder = SignedDataBuilder::default()
+    .content_type(Oid(Bytes::from(OID_ID_DATA.as_ref())))
+    .signing_time(Some(chrono::Utc::now().into()))
+    .signer(signer)
+    .certificates(certs.into_iter());
+
+  let builder = match content {
+    Some(content) => builder.content_inline(content),
+    None => builder.content_file(input),
+  };
+
+  let output = output(&args, input, "p7s");
+  write(&output, &builder.build_der()?)?;
+  println!("Wrote signature to {}", output);
+
+  Ok(ExitCode::SUCCESS)
+}
+
+/// Verify the signatures and content digests of signed data, exiting with a failure if any of
+/// them is invalid.
+fn verify(args: Args) -> Result<ExitCode, CliError> {
+  let signed_data = parse_signed_data(args.input("signature")?)?;
+  let content = args.value("content").map(read).transpose()?;
+
+  if content.is_none() && signed_data.signed_content().is_none() {
+    return Err(CliError::Usage(String::from(
+      "Missing --content for a detached signature",
+    )));
+  }
+
+  let mut valid = true;
+
+  for (index, signer) in signed_data.signers().enumerate() {
+    let result = signer
+      .verify_signature_with_signed_data(&signed_data)
+      .and_then(|()| match &content {
+        Some(content) => signer.verify_message_digest_with_content(content),
+        None => signer.verify_message_digest_with_signed_data(&signed_data),
+      });
+
+    let name = signer_name(&signed_data, signer);
+
+    match result {
+      Ok(()) => {
+        let timestamp = match signer.verify_time_stamp_token() {
+          Ok(Some(())) => ", timestamp valid",
+          Ok(None) => "",
+          Err(_) => {
+            valid = false;
+            ", timestamp invalid"
+          }
+        };
+
+        println!("Signer {}: {} valid{}", index + 1, name, timestamp);
+      }
+      Err(error) => {
+        valid = false;
+        println!("Signer {}: {} invalid: {}", index + 1, name, error);
+      }
+    }
+  }
+
+  if signed_data.signers().next().is_none() {
+    println!("No signers");
+    valid = false;
+  }
+
+  Ok(if valid {
+    ExitCode::SUCCESS
+  } else {
+    ExitCode::FAILURE
+  })
+}
+
+/// Request an RFC 3161 timestamp token for a file.
+fn timestamp(args: Args) -> Result<ExitCode, CliError> {
+  let input = args.input("file to timestamp")?;
+  let content = read(input)?;
+  let timestamp_server = args.required("timestamp-server")?;
+  let digest_algorithm = args.value("digest-algorithm");
+
+  validation::signing_options(Some(&content), None, None, digest_algorithm, None)?;
+
+  let url = validation::timestamp_server("timestamp-server", timestamp_server)?;
+
+  let token = signing::time_stamp_token(&url, &content, parse_digest_algorithm(digest_algorithm)?)?;
+
+  let output = output(&args, input, "tst");
+  write(&output, token.as_slice())?;
+  println!("Wrote timestamp token to {}", output);
+
+  Ok(ExitCode::SUCCESS)
+}
+
+/// Print the certificates and signers of signed data.
+fn inspect(args: Args) -> Result<ExitCode, CliError> {
+  let signed_data = parse_signed_data(args.input("signature")?)?;
+
+  println!(
+    "Content: {}",
+    match signed_data.signed_content() {
+      Some(content) => format!("attached, {} bytes", content.len()),
+      None => String::from("detached"),
+    }
+  );
+
+  for (index, cert) in signed_data.certificates().enumerate() {
+    println!(
+      "Certificate {}: {}, issued by {}, valid from {} until {}",
+      index + 1,
+      cert.subject_common_name().unwrap_or_default(),
+      cert.issuer_common_name().unwrap_or_default(),
+      cert.validity_not_before(),
+      cert.validity_not_after(),
+    );
+  }
+
+  for (index, signer) in signed_data.signers().enumerate() {
+    println!(
+      "Signer {}: {}",
+      index + 1,
+      signer_name(&signed_data, signer)
+    );
+    println!("  Digest algorithm: {:?}", signer.digest_algorithm());
+    println!("  Signature algorithm: {:?}", signer.signature_algorithm());
+
+    if let Some(signing_time) = signer
+      .signed_attributes()
+      .and_then(|attributes| attributes.signing_time())
+    {
+      println!("  Signing time: {}", signing_time);
+    }
+
+    if let Ok(Some(token)) = signer.time_stamp_token_signed_data() {
+      let names = token
+        .signers()
+        .map(|tsa| signer_name(&token, tsa))
+        .collect::<Vec<_>>();
+
+      println!("  Timestamped by: {}", names.join(", "));
+    }
+  }
+
+  Ok(ExitCode::SUCCESS)
+}
+
+fn read(path: &str) -> Result<Vec<u8>, CmsError> {
+  std::fs::read(path)
+    .map_err(|error| CmsError::FileReadError.caused_by(format!("{}: {}", path, error)))
+}
+
+fn write(path: &str, data: &[u8]) -> Result<(), CmsError> {
+  std::fs::write(path, data)
+    .map_err(|error| CmsError::FileWriteError.caused_by(format!("{}: {}", path, error)))
+}
+
+/// The `--out` option, or the input with another extension appended.
+fn output(args: &Args, input: &str, extension: &str) -> String {
+  args
+    .value("out")
+    .map(String::from)
+    .unwrap_or_else(|| format!("{}.{}", input, extension))
+}
+
+/// The PEM or DER certificates of the `--cert` option, with the signing certificate first.
+fn read_certificates(args: &Args) -> Result<Vec<LazyCertificate>, CliError> {
+  let cert = read(args.required("cert")?)?;
+  validation::encoded("cert", &cert, "a certificate", InputFormat::Auto)?;
+
+  Ok(input::certificates("cert", &cert, InputFormat::Auto)?)
+}
+
+fn parse_signed_data(path: &str) -> Result<cryptographic_message_syntax::SignedData, CmsError> {
+  cryptographic_message_syntax::SignedData::parse_ber(&read(path)?)
+    .map_err(|error| CmsError::SignedDataParseError.caused_by(error))
+}
+
+/// The common name of the certificate of a signer, if it's included in the signed data.
+fn signer_name(
+  signed_data: &cryptographic_message_syntax::SignedData,
+  signer: &SignerInfo,
+) -> String {
+  signer
+    .certificate_issuer_and_serial()
+    .and_then(|(issuer, serial)| {
+      signed_data
+        .certificates()
+        .find(|cert| cert.issuer_name() == issuer && cert.serial_number_asn1() == serial)
+    })
+    .and_then(|cert| cert.subject_common_name())
+    .unwrap_or_else(|| String::from("unknown certificate"))
+}
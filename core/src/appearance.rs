@@ -0,0 +1,614 @@
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::errors::CmsError;
+
+/// The name of the font in the resources of the appearance, one of the standard 14 fonts, so it
+/// doesn't have to be embedded.
+pub const FONT_RESOURCE: &str = "F1";
+pub const BASE_FONT: &str = "Helvetica";
+pub const FONT_ENCODING: &str = "WinAnsiEncoding";
+
+/// The name of the image in the resources of the appearance.
+pub const IMAGE_RESOURCE: &str = "Im1";
+
+/// The bits per component of every image, as PNG images are converted to 8 bits and DCTDecode
+/// only supports 8-bit JPEG images.
+pub const BITS_PER_COMPONENT: u8 = 8;
+
+/// The space between the edge of the appearance and its image and text, in points.
+const PADDING: f64 = 2.0;
+
+/// The largest font size the text is fitted with.
+const MAX_FONT_SIZE: f64 = 12.0;
+
+/// The distance between baselines, relative to the font size.
+const LEADING: f64 = 1.2;
+
+/// The ascender of Helvetica, relative to the font size.
+const ASCENT: f64 = 0.718;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_SIGNATURE: &[u8] = b"\xff\xd8";
+
+/// The visible appearance of a signature, a stamp with the name of the signer, the reason,
+/// location and time of the signature and an image of a handwritten signature.
+///
+/// The image takes the left half and the text the right half when there are both, otherwise
+/// either fills the appearance. The text is fitted to its half unless the font size is given.
+pub struct Appearance<'a> {
+  /// The size of the appearance in points, that of the widget annotation on the page.
+  pub width: f64,
+  pub height: f64,
+  pub name: Option<&'a str>,
+  pub reason: Option<&'a str>,
+  pub location: Option<&'a str>,
+  pub signing_time: chrono::DateTime<chrono::Utc>,
+  /// A PNG or JPEG image.
+  pub image: Option<&'a [u8]>,
+  pub font_size: Option<f64>,
+}
+
+/// The content stream of an appearance, with what goes in its resources.
+pub struct AppearanceStream {
+  /// The content stream of the form XObject, in the coordinates of a BBox of [0 0 width height],
+  /// which uses [FONT_RESOURCE] and [IMAGE_RESOURCE].
+  pub content: Vec<u8>,
+  /// The font size the text was fitted with, or the given one.
+  pub font_size: f64,
+  pub image: Option<Image>,
+}
+
+/// An image XObject for [IMAGE_RESOURCE], with [BITS_PER_COMPONENT] per component.
+pub struct Image {
+  pub width: u32,
+  pub height: u32,
+  /// DeviceGray, DeviceRGB or DeviceCMYK.
+  pub color_space: &'static str,
+  /// DCTDecode for JPEG images, FlateDecode for PNG images.
+  pub filter: &'static str,
+  /// Whether the samples are inverted, as Adobe writes CMYK JPEG images, for a Decode array of
+  /// [1 0 1 0 1 0 1 0].
+  pub inverted: bool,
+  pub data: Vec<u8>,
+  /// The alpha channel of a PNG image, for an SMask of the same size with a DeviceGray color
+  /// space and FlateDecode.
+  pub soft_mask: Option<Vec<u8>>,
+}
+
+impl Appearance<'_> {
+  /// Build the content stream of the appearance.
+  pub fn build(&self) -> Result<AppearanceStream, CmsError> {
+    positive("width", self.width)?;
+    positive("height", self.height)?;
+    if let Some(font_size) = self.font_size {
+      positive("fontSize", font_size)?;
+    }
+
+    let image = self.image.map(image).transpose()?;
+
+    let mut lines = Vec::new();
+    lines.extend(self.name.map(String::from));
+    lines.extend(self.reason.map(|reason| format!("Reason: {}", reason)));
+    lines.extend(
+      self
+        .location
+        .map(|location| format!("Location: {}", location)),
+    );
+    lines.push(format!(
+      "Date: {}",
+      self.signing_time.format("%Y-%m-%d %H:%M:%S UTC")
+    ));
+    let lines = lines.iter().map(|line| win_ansi(line)).collect::<Vec<_>>();
+
+    let inner_width = (self.width - 2.0 * PADDING).max(0.0);
+    let inner_height = (self.height - 2.0 * PADDING).max(0.0);
+    let (image_width, text_x) = match image {
+      Some(_) => (inner_width / 2.0, PADDING + inner_width / 2.0),
+      None => (0.0, PADDING),
+    };
+    let text_width = inner_width - image_width;
+
+    let mut content = String::from("q\n");
+
+    if let Some(image) = &image {
+      // Scale the image to fit its half, keeping its aspect ratio, and center it there.
+      let scale =
+        (image_width / f64::from(image.width)).min(inner_height / f64::from(image.height));
+      let (width, height) = (
+        f64::from(image.width) * scale,
+        f64::from(image.height) * scale,
+      );
+
+      content.push_str(&format!(
+        "q {} 0 0 {} {} {} cm /{} Do Q\n",
+        number(width),
+        number(height),
+        number(PADDING + (image_width - width) / 2.0),
+        number(PADDING + (inner_height - height) / 2.0),
+        IMAGE_RESOURCE
+      ));
+    }
+
+    let font_size = self.font_size.unwrap_or_else(|| {
+      let widest = lines
+        .iter()
+        .map(|line| text_width_of(line))
+        .fold(0.0, f64::max);
+
+      (inner_height / (lines.len() as f64 * LEADING))
+        .min(text_width / widest)
+        .min(MAX_FONT_SIZE)
+    });
+
+    content.push_str(&format!(
+      "BT\n/{} {} Tf\n{} TL\n{} {} Td\n",
+      FONT_RESOURCE,
+      number(font_size),
+      number(font_size * LEADING),
+      number(text_x),
+      number(PADDING + inner_height - font_size * ASCENT)
+    ));
+    for (index, line) in lines.iter().enumerate() {
+      if index > 0 {
+        content.push_str("T*\n");
+      }
+      content.push_str(&format!("({}) Tj\n", escape(line)));
+    }
+    content.push_str("ET\nQ\n");
+
+    tracing::debug!(
+      width = self.width,
+      height = self.height,
+      font_size,
+      image = image.is_some(),
+      "Built signature appearance"
+    );
+
+    Ok(AppearanceStream {
+      content: content.into_bytes(),
+      font_size,
+      image,
+    })
+  }
+}
+
+/// Helper function to check a size is a positive number.
+fn positive(option: &'static str, value: f64) -> Result<(), CmsError> {
+  if !value.is_finite() || value <= 0.0 {
+    return Err(CmsError::InvalidOption(
+      option,
+      String::from("expected a positive number"),
+    ));
+  }
+
+  Ok(())
+}
+
+/// Format a number for a content stream, which has no exponents, with up to three decimals.
+fn number(value: f64) -> String {
+  let formatted = format!("{:.3}", value);
+  let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+
+  match formatted {
+    "-0" | "" => String::from("0"),
+    _ => formatted.to_string(),
+  }
+}
+
+/// Escape a string in WinAnsiEncoding as a literal string, with the bytes outside of ASCII in
+/// octal so the content stream stays ASCII.
+fn escape(text: &[u8]) -> String {
+  let mut escaped = String::with_capacity(text.len());
+
+  for &byte in text {
+    match byte {
+      b'(' | b')' | b'\\' => {
+        escaped.push('\\');
+        escaped.push(char::from(byte));
+      }
+      0x20..=0x7e => escaped.push(char::from(byte)),
+      _ => escaped.push_str(&format!("\\{:03o}", byte)),
+    }
+  }
+
+  escaped
+}
+
+/// Encode text in WinAnsiEncoding, the encoding of the font, replacing the characters it
+/// doesn't have with a question mark and control characters with a space.
+fn win_ansi(text: &str) -> Vec<u8> {
+  text
+    .chars()
+    .map(|char| match char {
+      ' '..='~' | '\u{a0}'..='\u{ff}' => char as u8,
+      _ if char.is_control() => b' ',
+      _ => WIN_ANSI_HIGH
+        .iter()
+        .position(|&high| high == char)
+        .map_or(b'?', |position| 0x80 + position as u8),
+    })
+    .collect()
+}
+
+/// The width of text in WinAnsiEncoding, in thousandths of the font size.
+fn text_width_of(text: &[u8]) -> f64 {
+  text
+    .iter()
+    .map(|&byte| f64::from(HELVETICA_WIDTHS[usize::from(byte.max(b' ') - b' ')]))
+    .sum::<f64>()
+    / 1000.0
+}
+
+/// The characters of the codes 0x80 to 0x9f of WinAnsiEncoding, which differ from Latin-1, with
+/// NUL for the unused codes.
+const WIN_ANSI_HIGH: [char; 32] = [
+  '€', '\0', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\0', 'Ž', '\0', '\0', '‘',
+  '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\0', 'ž', 'Ÿ',
+];
+
+/// The widths of the glyphs of Helvetica for the codes 0x20 to 0xff of WinAnsiEncoding.
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u16; 224] = [
+  278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+  556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+  1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+  667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+  333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+  556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, 278,
+  556, 278, 222, 556, 333, 1000, 556, 556, 333, 1000, 667, 333, 1000, 278, 611, 278,
+  278, 222, 222, 333, 333, 350, 556, 1000, 333, 1000, 500, 333, 944, 278, 500, 667,
+  278, 333, 556, 556, 556, 556, 260, 556, 333, 737, 370, 556, 584, 333, 737, 333,
+  400, 584, 333, 333, 333, 556, 537, 278, 333, 333, 365, 556, 834, 834, 834, 611,
+  667, 667, 667, 667, 667, 667, 1000, 722, 667, 667, 667, 667, 278, 278, 278, 278,
+  722, 722, 778, 778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667, 611,
+  556, 556, 556, 556, 556, 556, 889, 500, 556, 556, 556, 556, 278, 278, 278, 278,
+  556, 556, 556, 556, 556, 556, 556, 584, 611, 556, 556, 556, 556, 500, 556, 500,
+];
+
+/// Helper function to read a PNG or JPEG image into an image XObject.
+fn image(data: &[u8]) -> Result<Image, CmsError> {
+  if data.starts_with(PNG_SIGNATURE) {
+    png(data)
+  } else if data.starts_with(JPEG_SIGNATURE) {
+    jpeg(data)
+  } else {
+    Err(CmsError::InvalidOption(
+      "image",
+      String::from("expected a PNG or JPEG image"),
+    ))
+  }
+}
+
+/// Helper function to read the size and components of a JPEG image, which is embedded as it is.
+fn jpeg(data: &[u8]) -> Result<Image, CmsError> {
+  let invalid = |cause: &str| CmsError::ImageParseError.caused_by(cause);
+  let truncated = || invalid("the JPEG image is truncated");
+
+  let mut adobe = false;
+  let mut position = JPEG_SIGNATURE.len();
+
+  loop {
+    // Markers may be preceded by any number of fill bytes.
+    while data.get(position) == Some(&0xff) && data.get(position + 1) == Some(&0xff) {
+      position += 1;
+    }
+
+    let &[0xff, marker] = data.get(position..position + 2).ok_or_else(truncated)? else {
+      return Err(invalid("expected a JPEG marker"));
+    };
+    position += 2;
+
+    // Markers without a segment.
+    if matches!(marker, 0x01 | 0xd0..=0xd7) {
+      continue;
+    }
+    if matches!(marker, 0xd9 | 0xda) {
+      return Err(invalid("the JPEG image has no frame header"));
+    }
+
+    let length = data
+      .get(position..position + 2)
+      .map(|length| usize::from(u16::from_be_bytes([length[0], length[1]])))
+      .filter(|&length| length >= 2)
+      .ok_or_else(truncated)?;
+    let segment = data
+      .get(position + 2..position + length)
+      .ok_or_else(truncated)?;
+    position += length;
+
+    match marker {
+      // APP14, which Adobe writes with inverted CMYK samples.
+      0xee if segment.starts_with(b"Adobe") => adobe = true,
+      // The start of frame markers, other than DHT, JPG and DAC.
+      0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+        let [precision, height_high, height_low, width_high, width_low, components, ..] = *segment
+        else {
+          return Err(truncated());
+        };
+
+        if precision != BITS_PER_COMPONENT {
+          return Err(invalid("only 8-bit JPEG images are supported"));
+        }
+
+        let width = u16::from_be_bytes([width_high, width_low]);
+        let height = u16::from_be_bytes([height_high, height_low]);
+        if width == 0 || height == 0 {
+          return Err(invalid("the JPEG image has no size"));
+        }
+
+        let color_space = match components {
+          1 => "DeviceGray",
+          3 => "DeviceRGB",
+          4 => "DeviceCMYK",
+          _ => return Err(invalid("expected a grayscale, RGB or CMYK JPEG image")),
+        };
+
+        return Ok(Image {
+          width: u32::from(width),
+          height: u32::from(height),
+          color_space,
+          filter: "DCTDecode",
+          inverted: adobe && components == 4,
+          data: data.to_vec(),
+          soft_mask: None,
+        });
+      }
+      _ => {}
+    }
+  }
+}
+
+/// Helper function to decode a PNG image into 8-bit samples and its alpha channel, compressed
+/// for FlateDecode.
+///
+/// Grayscale, RGB and palette images of any bit depth are supported, with an alpha channel or
+/// transparency chunk, but not interlaced ones.
+fn png(data: &[u8]) -> Result<Image, CmsError> {
+  let invalid = |cause: &str| CmsError::ImageParseError.caused_by(cause);
+  let truncated = || invalid("the PNG image is truncated");
+
+  let mut header = None;
+  let mut palette: &[u8] = &[];
+  let mut transparency: &[u8] = &[];
+  let mut compressed = Vec::new();
+  let mut position = PNG_SIGNATURE.len();
+
+  loop {
+    let length = data
+      .get(position..position + 4)
+      .map(|length| u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize)
+      .ok_or_else(truncated)?;
+    let chunk = data
+      .get(position + 4..position + 8 + length)
+      .ok_or_else(truncated)?;
+    let crc = data
+      .get(position + 8 + length..position + 12 + length)
+      .ok_or_else(truncated)?;
+    position += 12 + length;
+
+    if crc32fast::hash(chunk).to_be_bytes() != crc {
+      return Err(invalid("a PNG chunk has an invalid CRC"));
+    }
+
+    let (kind, chunk) = chunk.split_at(4);
+    match kind {
+      b"IHDR" => header = Some(chunk),
+      b"PLTE" => palette = chunk,
+      b"tRNS" => transparency = chunk,
+      b"IDAT" => compressed.extend_from_slice(chunk),
+      b"IEND" => break,
+      _ => {}
+    }
+  }
+
+  let Some(
+    &[w0, w1, w2, w3, h0, h1, h2, h3, bit_depth, color_type, compression, filter, interlace],
+  ) = header
+  else {
+    return Err(invalid("the PNG image has no valid header"));
+  };
+  let width = u32::from_be_bytes([w0, w1, w2, w3]);
+  let height = u32::from_be_bytes([h0, h1, h2, h3]);
+
+  if width == 0 || height == 0 {
+    return Err(invalid("the PNG image has no size"));
+  }
+  if compression != 0 || filter != 0 {
+    return Err(invalid("unknown PNG compression or filter method"));
+  }
+  if interlace != 0 {
+    return Err(invalid(
+      "interlaced PNG images are not supported, save the image without interlacing",
+    ));
+  }
+
+  let channels: usize = match (color_type, bit_depth) {
+    (0, 1 | 2 | 4 | 8 | 16) | (3, 1 | 2 | 4 | 8) => 1,
+    (4, 8 | 16) => 2,
+    (2, 8 | 16) => 3,
+    (6, 8 | 16) => 4,
+    _ => return Err(invalid("invalid PNG color type or bit depth")),
+  };
+  if color_type == 3 && (palette.is_empty() || !palette.len().is_multiple_of(3)) {
+    return Err(invalid("the PNG image has no valid palette"));
+  }
+
+  let bits_per_pixel = channels * usize::from(bit_depth);
+  let row_length = (width as usize)
+    .checked_mul(bits_per_pixel)
+    .map(|bits| bits.div_ceil(8))
+    .ok_or_else(|| invalid("the PNG image is too large"))?;
+  let expected = (row_length + 1)
+    .checked_mul(height as usize)
+    .ok_or_else(|| invalid("the PNG image is too large"))?;
+
+  // The limit guards against data that decompresses to more than the size of the image.
+  let mut filtered = Vec::with_capacity(expected);
+  ZlibDecoder::new(compressed.as_slice())
+    .take(expected as u64 + 1)
+    .read_to_end(&mut filtered)
+    .map_err(|error| CmsError::ImageParseError.caused_by(error))?;
+  if filtered.len() != expected {
+    return Err(invalid("the PNG image data doesn't match its size"));
+  }
+
+  let pixel_length = bits_per_pixel.div_ceil(8);
+  let mut rows = vec![0u8; row_length * height as usize];
+  for (index, line) in filtered.chunks_exact(row_length + 1).enumerate() {
+    let (previous, current) = rows.split_at_mut(index * row_length);
+    let previous = previous
+      .get(previous.len().saturating_sub(row_length)..)
+      .filter(|_| index > 0);
+    unfilter(
+      line[0],
+      &line[1..],
+      previous,
+      &mut current[..row_length],
+      pixel_length,
+    )
+    .ok_or_else(|| invalid("unknown PNG filter type"))?;
+  }
+
+  let sample = |row: &[u8], index: usize| -> u16 {
+    match bit_depth {
+      16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+      8 => u16::from(row[index]),
+      _ => {
+        let bit = index * usize::from(bit_depth);
+        let shift = 8 - usize::from(bit_depth) - bit % 8;
+        u16::from((row[bit / 8] >> shift) & ((1 << bit_depth) - 1))
+      }
+    }
+  };
+  // Scale a sample to 8 bits.
+  let scale = |value: u16| -> u8 {
+    match bit_depth {
+      16 => (value >> 8) as u8,
+      8 => value as u8,
+      _ => (u32::from(value) * 255 / ((1 << bit_depth) - 1)) as u8,
+    }
+  };
+  let color_key = match (color_type, transparency.len()) {
+    (0, 2) | (2, 6) => Some(
+      transparency
+        .chunks_exact(2)
+        .map(|value| u16::from_be_bytes([value[0], value[1]]))
+        .collect::<Vec<_>>(),
+    ),
+    _ => None,
+  };
+
+  let color_channels = if matches!(color_type, 0 | 4) { 1 } else { 3 };
+  let pixels = width as usize * height as usize;
+  let mut color = Vec::with_capacity(pixels * color_channels);
+  let mut alpha = Vec::with_capacity(pixels);
+
+  for row in rows.chunks_exact(row_length) {
+    for pixel in 0..width as usize {
+      let first = pixel * channels;
+
+      if color_type == 3 {
+        let index = usize::from(sample(row, pixel));
+        let entry = palette
+          .get(index * 3..index * 3 + 3)
+          .ok_or_else(|| invalid("a PNG pixel is outside of the palette"))?;
+        color.extend_from_slice(entry);
+        alpha.push(transparency.get(index).copied().unwrap_or(u8::MAX));
+        continue;
+      }
+
+      let samples = (first..first + color_channels)
+        .map(|index| sample(row, index))
+        .collect::<Vec<_>>();
+      color.extend(samples.iter().map(|&value| scale(value)));
+      alpha.push(match (&color_key, color_type) {
+        (Some(key), _) if *key == samples => 0,
+        (_, 4 | 6) => scale(sample(row, first + color_channels)),
+        _ => u8::MAX,
+      });
+    }
+  }
+
+  let soft_mask = alpha
+    .iter()
+    .any(|&value| value != u8::MAX)
+    .then(|| deflate(&alpha))
+    .transpose()?;
+
+  Ok(Image {
+    width,
+    height,
+    color_space: if color_channels == 1 {
+      "DeviceGray"
+    } else {
+      "DeviceRGB"
+    },
+    filter: "FlateDecode",
+    inverted: false,
+    data: deflate(&color)?,
+    soft_mask,
+  })
+}
+
+/// Helper function to reverse the filter of a scanline of a PNG image, returning none for an
+/// unknown filter type.
+fn unfilter(
+  filter: u8,
+  line: &[u8],
+  previous: Option<&[u8]>,
+  row: &mut [u8],
+  pixel_length: usize,
+) -> Option<()> {
+  for index in 0..line.len() {
+    let left = index.checked_sub(pixel_length).map_or(0, |left| row[left]);
+    let up = previous.map_or(0, |previous| previous[index]);
+    let up_left = match (previous, index.checked_sub(pixel_length)) {
+      (Some(previous), Some(left)) => previous[left],
+      _ => 0,
+    };
+
+    let predicted = match filter {
+      0 => 0,
+      1 => left,
+      2 => up,
+      3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+      4 => paeth(left, up, up_left),
+      _ => return None,
+    };
+
+    row[index] = line[index].wrapping_add(predicted);
+  }
+
+  Some(())
+}
+
+/// The Paeth predictor of PNG filter type 4.
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+  let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+  let (to_left, to_up, to_up_left) = (
+    (estimate - i16::from(left)).abs(),
+    (estimate - i16::from(up)).abs(),
+    (estimate - i16::from(up_left)).abs(),
+  );
+
+  if to_left <= to_up && to_left <= to_up_left {
+    left
+  } else if to_up <= to_up_left {
+    up
+  } else {
+    up_left
+  }
+}
+
+/// Helper function to compress samples for FlateDecode.
+fn deflate(data: &[u8]) -> Result<Vec<u8>, CmsError> {
+  let error = |error| CmsError::ImageParseError.caused_by(error);
+
+  let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+  encoder.write_all(data).map_err(error)?;
+  encoder.finish().map_err(error)
+}
@@ -0,0 +1,125 @@
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{Captured, Mode, OctetString};
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::rfc5280::AlgorithmIdentifier;
+use x509_certificate::rfc5652::AttributeValue;
+use x509_certificate::DigestAlgorithm;
+
+use crate::attributes;
+use crate::cms::SignedData;
+use crate::errors::CmsError;
+use crate::signing;
+
+/// Add an archive-time-stamp-v3 attribute to the signer info at `index`.
+///
+/// The timestamp covers the signed content, the signer info and, through the
+/// ats-hash-index-v3 attribute added to the timestamp token, the certificates, CRLs and
+/// unsigned attributes present at this point. The content must be given for detached
+/// signatures.
+pub fn add_archive_time_stamp_v3(
+  signed_data: &mut SignedData,
+  index: usize,
+  content: Option<&[u8]>,
+  url: &reqwest::Url,
+  digest_algorithm: DigestAlgorithm,
+) -> Result<(), CmsError> {
+  let (content_type, encapsulated_content) = signed_data.encapsulated_content()?;
+
+  let content_digest = match (encapsulated_content.as_deref(), content) {
+    (Some(content), None) | (None, Some(content)) => digest_algorithm.digest_data(content),
+    _ => return Err(CmsError::InvalidContent),
+  };
+
+  let hash = |data: &[u8]| digest_algorithm.digest_data(data);
+
+  let certificates_hash_index = signed_data
+    .certificate_values()?
+    .iter()
+    .map(|value| hash(value.as_slice()))
+    .collect::<Vec<_>>();
+
+  let crls_hash_index = signed_data
+    .crl_values()?
+    .iter()
+    .map(|value| hash(value.as_slice()))
+    .collect::<Vec<_>>();
+
+  let signer_info = signed_data.signer_info_mut(index)?;
+
+  // Each value is hashed along with the type of its attribute.
+  let unsigned_attr_values_hash_index = signer_info
+    .unsigned_attribute_values()?
+    .iter()
+    .flat_map(|(oid, values)| {
+      let attribute_type = Captured::from_values(Mode::Der, oid.encode_ref());
+
+      values
+        .iter()
+        .map(move |value| hash(&[attribute_type.as_slice(), value.as_slice()].concat()))
+    })
+    .collect::<Vec<_>>();
+
+  // ATSHashIndexV3 ::= SEQUENCE {
+  //   hashIndAlgorithm AlgorithmIdentifier DEFAULT {algorithm id-sha256},
+  //   certificatesHashIndex SEQUENCE OF OCTET STRING,
+  //   crlsHashIndex SEQUENCE OF OCTET STRING,
+  //   unsignedAttrValuesHashIndex SEQUENCE OF OCTET STRING }
+  let ats_hash_index = Captured::from_values(
+    Mode::Der,
+    encode::sequence((
+      (digest_algorithm != DigestAlgorithm::Sha256)
+        .then(|| AlgorithmIdentifier::from(digest_algorithm)),
+      octet_strings(certificates_hash_index),
+      octet_strings(crls_hash_index),
+      octet_strings(unsigned_attr_values_hash_index),
+    )),
+  );
+
+  // The message imprint is over the content type, the content digest, the signed fields of the
+  // signer info and the hash index, each as encoded.
+  let message = [
+    Captured::from_values(Mode::Der, content_type.encode_ref()).as_slice(),
+    &content_digest,
+    signer_info.version.as_slice(),
+    signer_info.sid.as_slice(),
+    signer_info.digest_algorithm.as_slice(),
+    signer_info
+      .signed_attributes
+      .as_ref()
+      .map(Captured::as_slice)
+      .unwrap_or_default(),
+    signer_info.signature_algorithm.as_slice(),
+    Captured::from_values(Mode::Der, signer_info.signature.encode_ref()).as_slice(),
+    ats_hash_index.as_slice(),
+  ]
+  .concat();
+
+  let token = signing::time_stamp_token(url, &message, digest_algorithm)?;
+
+  // The hash index is an unsigned attribute of the timestamp token.
+  let mut token = SignedData::decode(token.as_slice())?;
+
+  token.signer_info_mut(0)?.add_unsigned_attribute_value(
+    attributes::oid(attributes::OID_ATS_HASH_INDEX_V3),
+    AttributeValue::new(ats_hash_index),
+  )?;
+
+  let token = Mode::Der
+    .decode(Bytes::from(token.encode()), |cons| cons.capture_one())
+    .map_err(|error| CmsError::TimestampError.caused_by(error))?;
+
+  signer_info.add_unsigned_attribute_value(
+    attributes::oid(attributes::OID_ARCHIVE_TIME_STAMP_V3),
+    AttributeValue::new(token),
+  )
+}
+
+/// Helper function to encode hashes as a SEQUENCE OF OCTET STRING.
+fn octet_strings(hashes: Vec<Vec<u8>>) -> impl Values {
+  encode::sequence(
+    hashes
+      .into_iter()
+      .map(OctetString::encode_slice)
+      .collect::<Vec<_>>(),
+  )
+}
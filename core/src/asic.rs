@@ -0,0 +1,330 @@
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Datelike, Timelike};
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::KeyInfoSigner;
+
+use crate::attributes;
+use crate::certificates::LazyCertificate;
+use crate::errors::CmsError;
+use crate::limiter::Limiter;
+use crate::signing::{SignedDataBuilder, SignerBuilder};
+use crate::xades;
+
+const ASIC: &str = "http://uri.etsi.org/02918/v1.2.1#";
+const DS: &str = "http://www.w3.org/2000/09/xmldsig#";
+
+/// The type of an associated signature container (ETSI EN 319 162-1).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+  /// ASiC-S: one file with a CAdES signature of it.
+  Simple,
+  /// ASiC-E: any number of files listed with their digests in a manifest, which the CAdES
+  /// signature signs.
+  Extended,
+}
+
+impl Kind {
+  /// Parse a container type, `ASiC-S` or `ASiC-E`. `option` is the name of the option the type
+  /// was given for, to report errors against.
+  pub fn parse(option: &'static str, name: &str) -> Result<Self, CmsError> {
+    match name {
+      "ASiC-S" => Ok(Self::Simple),
+      "ASiC-E" => Ok(Self::Extended),
+      _ => Err(CmsError::InvalidOption(
+        option,
+        String::from("expected ASiC-S or ASiC-E"),
+      )),
+    }
+  }
+
+  fn mime_type(self) -> &'static str {
+    match self {
+      Self::Simple => "application/vnd.etsi.asic-s+zip",
+      Self::Extended => "application/vnd.etsi.asic-e+zip",
+    }
+  }
+}
+
+/// A file to package in a container.
+pub struct File<'a> {
+  /// The path of the file in the container.
+  pub name: &'a str,
+  pub content: &'a [u8],
+  pub mime_type: &'a str,
+}
+
+/// An ASiC container of files and a CAdES signature of them.
+pub struct Container<'a> {
+  pub kind: Kind,
+  pub files: &'a [File<'a>],
+  pub signing_key: &'a dyn KeyInfoSigner,
+  /// The signing certificate, followed by the rest of the chain to include.
+  pub certs: &'a [LazyCertificate],
+  pub signing_time: chrono::DateTime<chrono::Utc>,
+  /// A timestamp server to add a signature timestamp from, for CAdES-B-T rather than B-B.
+  pub time_stamp_url: Option<&'a reqwest::Url>,
+  pub limiter: Option<Arc<Limiter>>,
+}
+
+impl Container<'_> {
+  /// Sign the files and create the ZIP archive of the container.
+  ///
+  /// Files are stored rather than compressed, which every ASiC validator reads and keeps the
+  /// `mimetype` file readable at its fixed offset, as the container format requires.
+  pub fn sign(&self) -> Result<Vec<u8>, CmsError> {
+    if self.files.is_empty() {
+      return Err(CmsError::InvalidOption(
+        "files",
+        String::from("expected at least one file"),
+      ));
+    }
+
+    if self.kind == Kind::Simple && self.files.len() != 1 {
+      return Err(CmsError::InvalidOption(
+        "files",
+        format!(
+          "an ASiC-S container has exactly one file, not {}",
+          self.files.len()
+        ),
+      ));
+    }
+
+    for (index, file) in self.files.iter().enumerate() {
+      validate_name(file.name).map_err(|error| error.with_detail("index", index))?;
+
+      if self.files[..index]
+        .iter()
+        .any(|other| other.name == file.name)
+      {
+        return Err(
+          CmsError::InvalidOption("files", format!("{} is given more than once", file.name))
+            .with_detail("index", index),
+        );
+      }
+    }
+
+    let cert = self
+      .certs
+      .first()
+      .ok_or(CmsError::NoCertificate)?
+      .parsed()?;
+
+    let mut signer = SignerBuilder::new(self.signing_key, cert.clone()).signed_attribute(
+      attributes::oid(attributes::OID_SIGNING_CERTIFICATE_V2),
+      vec![attributes::signing_certificate_v2(cert)],
+    );
+
+    if let Some(url) = self.time_stamp_url {
+      signer = signer
+        .time_stamp_url(url.clone())
+        .map_err(|error| CmsError::TimestampServerParseError.caused_by(error))?;
+    }
+
+    if let Some(limiter) = &self.limiter {
+      signer = signer.limiter(limiter.clone());
+    }
+
+    let mut archive = Archive::default();
+    archive.add(
+      "mimetype",
+      self.kind.mime_type().as_bytes(),
+      self.signing_time,
+    )?;
+
+    for file in self.files {
+      archive.add(file.name, file.content, self.signing_time)?;
+    }
+
+    // ASiC-S signs the file, ASiC-E the manifest of its files.
+    let (signed, signature_name) = match self.kind {
+      Kind::Simple => (self.files[0].content.to_vec(), "META-INF/signature.p7s"),
+      Kind::Extended => {
+        let manifest = self.manifest(&signer)?.into_bytes();
+        archive.add("META-INF/ASiCManifest.xml", &manifest, self.signing_time)?;
+        (manifest, "META-INF/signature001.p7s")
+      }
+    };
+
+    let signature = SignedDataBuilder::default()
+      .content_external(Bytes::from(signed))
+      .signing_time(Some(self.signing_time.into()))
+      .certificates(self.certs.iter().cloned())
+      .signer(signer)
+      .build_der()?;
+
+    archive.add(signature_name, &signature, self.signing_time)?;
+
+    tracing::debug!(
+      files = self.files.len(),
+      timestamped = self.time_stamp_url.is_some(),
+      "Created ASiC container"
+    );
+
+    archive.finish()
+  }
+
+  /// The ASiCManifest of an ASiC-E container, with the digests of its files by the digest
+  /// algorithm of the signer.
+  fn manifest(&self, signer: &SignerBuilder) -> Result<String, CmsError> {
+    let digest_algorithm = signer.digest_algorithm()?;
+    let digest_method = xades::digest_method(digest_algorithm)?;
+
+    let references = self
+      .files
+      .iter()
+      .map(|file| {
+        format!(
+          "<asic:DataObjectReference URI=\"{}\" MimeType=\"{}\">\
+           <ds:DigestMethod Algorithm=\"{}\"/>\
+           <ds:DigestValue>{}</ds:DigestValue>\
+           </asic:DataObjectReference>",
+          uri(file.name),
+          xades::escape_attribute(file.mime_type),
+          digest_method,
+          STANDARD.encode(digest_algorithm.digest_data(file.content))
+        )
+      })
+      .collect::<String>();
+
+    Ok(format!(
+      "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+       <asic:ASiCManifest xmlns:asic=\"{ASIC}\" xmlns:ds=\"{DS}\">\
+       <asic:SigReference URI=\"META-INF/signature001.p7s\" \
+       MimeType=\"application/x-pkcs7-signature\"/>\
+       {}\
+       </asic:ASiCManifest>",
+      references
+    ))
+  }
+}
+
+/// The relative URI of a file in the container, with the bytes of its name that aren't
+/// unreserved percent-encoded (RFC 3986).
+fn uri(name: &str) -> String {
+  name
+    .bytes()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+        char::from(byte).to_string()
+      }
+      _ => format!("%{:02X}", byte),
+    })
+    .collect()
+}
+
+/// Check the name of a file is a relative path that doesn't collide with the files of the
+/// container itself.
+fn validate_name(name: &str) -> Result<(), CmsError> {
+  let reason = if name.is_empty() {
+    "a file has no name"
+  } else if name.starts_with('/') || name.contains('\\') {
+    "file names are relative paths separated by /"
+  } else if name
+    .split('/')
+    .any(|part| part.is_empty() || part == "." || part == "..")
+  {
+    "file names can't have empty, . or .. path segments"
+  } else if name == "mimetype" || name.starts_with("META-INF/") {
+    "mimetype and META-INF are reserved for the container"
+  } else {
+    return Ok(());
+  };
+
+  Err(CmsError::InvalidOption("files", String::from(reason)).with_detail("name", name))
+}
+
+/// A ZIP archive of stored files being written.
+#[derive(Default)]
+struct Archive {
+  data: Vec<u8>,
+  central_directory: Vec<u8>,
+  entries: u16,
+}
+
+impl Archive {
+  fn add(
+    &mut self,
+    name: &str,
+    content: &[u8],
+    modified: chrono::DateTime<chrono::Utc>,
+  ) -> Result<(), CmsError> {
+    let too_large = || CmsError::InvalidOption("files", String::from("ZIP64 isn't supported"));
+
+    let size = u32::try_from(content.len()).map_err(|_| too_large())?;
+    let offset = u32::try_from(self.data.len()).map_err(|_| too_large())?;
+    let name_length = u16::try_from(name.len()).map_err(|_| too_large())?;
+    self.entries = self.entries.checked_add(1).ok_or_else(too_large)?;
+
+    let (time, date) = dos_date_time(modified);
+    let crc = crc32fast::hash(content);
+    // Bit 11 marks a UTF-8 name.
+    let flags: u16 = match name.is_ascii() {
+      true => 0,
+      false => 1 << 11,
+    };
+
+    // Fields shared by the local header and the central directory entry, from the version
+    // needed to extract through the length of the extra field.
+    let mut common = Vec::with_capacity(26);
+    common.extend_from_slice(&20u16.to_le_bytes());
+    common.extend_from_slice(&flags.to_le_bytes());
+    // Stored, without compression.
+    common.extend_from_slice(&0u16.to_le_bytes());
+    common.extend_from_slice(&time.to_le_bytes());
+    common.extend_from_slice(&date.to_le_bytes());
+    common.extend_from_slice(&crc.to_le_bytes());
+    common.extend_from_slice(&size.to_le_bytes());
+    common.extend_from_slice(&size.to_le_bytes());
+    common.extend_from_slice(&name_length.to_le_bytes());
+    common.extend_from_slice(&0u16.to_le_bytes());
+
+    self.data.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    self.data.extend_from_slice(&common);
+    self.data.extend_from_slice(name.as_bytes());
+    self.data.extend_from_slice(content);
+
+    let entry = &mut self.central_directory;
+    entry.extend_from_slice(&0x02014b50u32.to_le_bytes());
+    // Version made by.
+    entry.extend_from_slice(&20u16.to_le_bytes());
+    entry.extend_from_slice(&common);
+    // File comment length, disk number, internal and external attributes.
+    entry.extend_from_slice(&[0; 10]);
+    entry.extend_from_slice(&offset.to_le_bytes());
+    entry.extend_from_slice(name.as_bytes());
+
+    Ok(())
+  }
+
+  fn finish(mut self) -> Result<Vec<u8>, CmsError> {
+    let too_large = || CmsError::InvalidOption("files", String::from("ZIP64 isn't supported"));
+
+    let offset = u32::try_from(self.data.len()).map_err(|_| too_large())?;
+    let size = u32::try_from(self.central_directory.len()).map_err(|_| too_large())?;
+
+    self.data.append(&mut self.central_directory);
+    self.data.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    // Disk numbers.
+    self.data.extend_from_slice(&[0; 4]);
+    self.data.extend_from_slice(&self.entries.to_le_bytes());
+    self.data.extend_from_slice(&self.entries.to_le_bytes());
+    self.data.extend_from_slice(&size.to_le_bytes());
+    self.data.extend_from_slice(&offset.to_le_bytes());
+    // Comment length.
+    self.data.extend_from_slice(&0u16.to_le_bytes());
+
+    Ok(self.data)
+  }
+}
+
+/// The MS-DOS time and date of a ZIP entry, clamped to the years it can represent.
+fn dos_date_time(time: chrono::DateTime<chrono::Utc>) -> (u16, u16) {
+  let year = time.year().clamp(1980, 2107) as u16;
+
+  (
+    ((time.hour() as u16) << 11) | ((time.minute() as u16) << 5) | ((time.second() as u16) / 2),
+    ((year - 1980) << 9) | ((time.month() as u16) << 5) | (time.day() as u16),
+  )
+}
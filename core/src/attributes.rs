@@ -0,0 +1,567 @@
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{Captured, ConstOid, Mode, OctetString, Oid, Tag};
+use cryptographic_message_syntax::asn1::rfc3161::OID_TIME_STAMP_TOKEN;
+use cryptographic_message_syntax::asn1::rfc5652::{
+  OID_CONTENT_TYPE, OID_COUNTER_SIGNATURE, OID_ID_DATA, OID_MESSAGE_DIGEST, OID_SIGNING_TIME,
+};
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::rfc5280::AlgorithmIdentifier;
+use x509_certificate::rfc5652::AttributeValue;
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm};
+
+use crate::errors::CmsError;
+
+/// id-aa-signingCertificateV2 (RFC 5035).
+pub const OID_SIGNING_CERTIFICATE_V2: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 47]);
+
+/// id-aa-ets-commitmentType (RFC 5126).
+pub const OID_COMMITMENT_TYPE_INDICATION: ConstOid =
+  Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 16]);
+
+/// id-aa-ets-signerLocation (RFC 5126).
+pub const OID_SIGNER_LOCATION: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 17]);
+
+/// id-aa-contentHint (RFC 2634).
+pub const OID_CONTENT_HINTS: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 4]);
+
+/// smimeCapabilities (RFC 8551).
+pub const OID_SMIME_CAPABILITIES: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 15]);
+
+/// id-aa-ets-certificateRefs (RFC 5126).
+pub const OID_CERTIFICATE_REFS: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 21]);
+
+/// id-aa-ets-revocationRefs (RFC 5126).
+pub const OID_REVOCATION_REFS: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 22]);
+
+/// id-aa-ets-certValues (RFC 5126).
+pub const OID_CERT_VALUES: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 23]);
+
+/// id-aa-ets-revocationValues (RFC 5126).
+pub const OID_REVOCATION_VALUES: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 24]);
+
+/// id-aa-ets-archiveTimestampV3 (ETSI EN 319 122-1).
+pub const OID_ARCHIVE_TIME_STAMP_V3: ConstOid = Oid(&[4, 0, 141, 69, 2, 4]);
+
+/// id-aa-ATSHashIndex-v3 (ETSI EN 319 122-1).
+pub const OID_ATS_HASH_INDEX_V3: ConstOid = Oid(&[4, 0, 129, 149, 50, 1, 5]);
+
+/// adbe-revocationInfoArchival (ISO 32000-1).
+pub const OID_REVOCATION_INFO_ARCHIVAL: ConstOid = Oid(&[42, 134, 72, 134, 247, 47, 1, 1, 8]);
+
+/// id-pkix-ocsp-basic (RFC 6960).
+const OID_OCSP_BASIC: ConstOid = Oid(&[43, 6, 1, 5, 5, 7, 48, 1, 1]);
+
+/// Signed attributes always set by the CMS builder, which cannot be provided by callers.
+pub const RESERVED_SIGNED_ATTRIBUTES: [ConstOid; 3] =
+  [OID_CONTENT_TYPE, OID_MESSAGE_DIGEST, OID_SIGNING_TIME];
+
+/// Names of the attributes known to this crate, for introspection.
+const ATTRIBUTE_NAMES: [(&str, ConstOid); 17] = [
+  ("contentType", OID_CONTENT_TYPE),
+  ("messageDigest", OID_MESSAGE_DIGEST),
+  ("signingTime", OID_SIGNING_TIME),
+  ("countersignature", OID_COUNTER_SIGNATURE),
+  ("signingCertificateV2", OID_SIGNING_CERTIFICATE_V2),
+  ("commitmentType", OID_COMMITMENT_TYPE_INDICATION),
+  ("signerLocation", OID_SIGNER_LOCATION),
+  ("contentHints", OID_CONTENT_HINTS),
+  ("smimeCapabilities", OID_SMIME_CAPABILITIES),
+  ("timeStampToken", OID_TIME_STAMP_TOKEN),
+  ("certificateRefs", OID_CERTIFICATE_REFS),
+  ("revocationRefs", OID_REVOCATION_REFS),
+  ("certValues", OID_CERT_VALUES),
+  ("revocationValues", OID_REVOCATION_VALUES),
+  ("archiveTimestampV3", OID_ARCHIVE_TIME_STAMP_V3),
+  ("atsHashIndexV3", OID_ATS_HASH_INDEX_V3),
+  ("revocationInfoArchival", OID_REVOCATION_INFO_ARCHIVAL),
+];
+
+/// The maximum number of lines in a PostalAddress.
+const MAX_POSTAL_ADDRESS_LINES: usize = 6;
+
+/// The commitment types defined by RFC 5126 (id-cti-ets-*), by the name used in the options.
+const COMMITMENT_TYPES: [(&str, ConstOid); 6] = [
+  (
+    "proofOfOrigin",
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 6, 1]),
+  ),
+  (
+    "proofOfReceipt",
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 6, 2]),
+  ),
+  (
+    "proofOfDelivery",
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 6, 3]),
+  ),
+  (
+    "proofOfSender",
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 6, 4]),
+  ),
+  (
+    "proofOfApproval",
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 6, 5]),
+  ),
+  (
+    "proofOfCreation",
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 6, 6]),
+  ),
+];
+
+/// Helper function to encode a DirectoryString, always as a UTF8String.
+fn directory_string(value: &str) -> impl Values + '_ {
+  OctetString::encode_slice_as(value.as_bytes(), Tag::UTF8_STRING)
+}
+
+/// Convert a constant OID to the owned form used by the CMS builder.
+pub fn oid(oid: ConstOid) -> Oid {
+  Oid(Bytes::from_static(oid.0))
+}
+
+/// The name of a known attribute type.
+pub fn attribute_name(attribute_oid: &Oid) -> Option<&'static str> {
+  ATTRIBUTE_NAMES
+    .iter()
+    .find(|(_, oid)| oid.as_ref() == attribute_oid.as_ref())
+    .map(|(name, _)| *name)
+}
+
+/// Helper function to capture DER encoded values as an attribute value.
+pub fn attribute_value(values: impl Values) -> AttributeValue {
+  AttributeValue::new(Captured::from_values(Mode::Der, values))
+}
+
+/// Sort attribute values by their encoding, as DER requires for a SET OF.
+pub fn sort_values(values: &mut [AttributeValue]) {
+  values.sort_by_cached_key(|value| {
+    let mut encoded = Vec::new();
+
+    value
+      .write_encoded(Mode::Der, &mut encoded)
+      .expect("writing to a Vec cannot fail");
+
+    encoded
+  });
+}
+
+/// Create a signingCertificateV2 attribute value identifying the signing certificate.
+///
+/// The certificate is identified by its SHA-256 hash, which is the default hash algorithm and
+/// therefore omitted, along with its issuer and serial number.
+pub fn signing_certificate_v2(cert: &CapturedX509Certificate) -> AttributeValue {
+  let cert_hash = DigestAlgorithm::Sha256.digest_data(cert.constructed_data());
+
+  // SigningCertificateV2 ::= SEQUENCE { certs SEQUENCE OF ESSCertIDv2 }
+  attribute_value(encode::sequence(encode::sequence(encode::sequence((
+    OctetString::encode_slice(cert_hash),
+    issuer_serial(cert),
+  )))))
+}
+
+/// Helper function to encode the IssuerSerial of a certificate.
+pub(crate) fn issuer_serial(cert: &CapturedX509Certificate) -> impl Values + '_ {
+  // IssuerSerial ::= SEQUENCE { issuer GeneralNames, serialNumber CertificateSerialNumber }
+  encode::sequence((
+    encode::sequence(encode::sequence_as(
+      Tag::CTX_4,
+      cert.issuer_name().encode_ref(),
+    )),
+    cert.serial_number_asn1().encode(),
+  ))
+}
+
+/// Helper function to encode the SHA-256 hash of data as an OtherHashAlgAndValue.
+fn other_hash(data: &[u8]) -> impl Values {
+  // OtherHashAlgAndValue ::= SEQUENCE { hashAlgorithm AlgorithmIdentifier, hashValue OCTET STRING }
+  encode::sequence((
+    AlgorithmIdentifier::from(DigestAlgorithm::Sha256),
+    OctetString::encode_slice(DigestAlgorithm::Sha256.digest_data(data)),
+  ))
+}
+
+/// Create a commitmentTypeIndication attribute value for a commitment type name.
+pub fn commitment_type_indication(commitment_type: &str) -> Result<AttributeValue, CmsError> {
+  let (_, commitment_type_id) = COMMITMENT_TYPES
+    .iter()
+    .find(|(name, _)| *name == commitment_type)
+    .ok_or(CmsError::UnknownCommitmentType)?;
+
+  // CommitmentTypeIndication ::= SEQUENCE { commitmentTypeId CommitmentTypeIdentifier, ... }
+  Ok(attribute_value(encode::sequence(
+    commitment_type_id.encode_ref(),
+  )))
+}
+
+/// Create a signerLocation attribute value.
+///
+/// At least one of the parts must be given, and the postal address is limited to six lines.
+pub fn signer_location(
+  country_name: Option<&str>,
+  locality_name: Option<&str>,
+  postal_address: Option<&[String]>,
+) -> Result<AttributeValue, CmsError> {
+  if country_name.is_none() && locality_name.is_none() && postal_address.is_none() {
+    return Err(CmsError::InvalidSignerLocation);
+  }
+
+  if let Some(postal_address) = postal_address {
+    if postal_address.is_empty() || postal_address.len() > MAX_POSTAL_ADDRESS_LINES {
+      return Err(CmsError::InvalidSignerLocation);
+    }
+  }
+
+  // SignerLocation ::= SEQUENCE {
+  //   countryName [0] DirectoryString OPTIONAL,
+  //   localityName [1] DirectoryString OPTIONAL,
+  //   postalAdddress [2] PostalAddress OPTIONAL }
+  Ok(attribute_value(encode::sequence((
+    country_name.map(|name| encode::sequence_as(Tag::CTX_0, directory_string(name))),
+    locality_name.map(|name| encode::sequence_as(Tag::CTX_1, directory_string(name))),
+    postal_address.map(|lines| {
+      encode::sequence_as(
+        Tag::CTX_2,
+        encode::sequence(
+          lines
+            .iter()
+            .map(|line| directory_string(line))
+            .collect::<Vec<_>>(),
+        ),
+      )
+    }),
+  ))))
+}
+
+/// Create a contentHints attribute value.
+///
+/// The description typically holds the MIME type of the content. The content type is a dotted
+/// OID and defaults to id-data, the type of the signed content.
+pub fn content_hints(
+  description: Option<&str>,
+  content_type: Option<&str>,
+) -> Result<AttributeValue, CmsError> {
+  if description.is_some_and(str::is_empty) {
+    return Err(CmsError::InvalidContentHints);
+  }
+
+  let content_type = match content_type {
+    Some(content_type) => content_type
+      .parse::<Oid>()
+      .map_err(|error| CmsError::InvalidContentHints.caused_by(error))?,
+    None => oid(OID_ID_DATA),
+  };
+
+  // ContentHints ::= SEQUENCE {
+  //   contentDescription UTF8String (SIZE (1..MAX)) OPTIONAL,
+  //   contentType ContentType }
+  Ok(attribute_value(encode::sequence((
+    description.map(directory_string),
+    content_type.encode(),
+  ))))
+}
+
+/// Create a smimeCapabilities attribute value from capabilities given as a dotted OID and
+/// optional DER encoded parameters, in order of preference.
+pub fn smime_capabilities<'v>(
+  capabilities: impl IntoIterator<Item = (&'v str, Option<&'v [u8]>)>,
+) -> Result<AttributeValue, CmsError> {
+  let capabilities = capabilities
+    .into_iter()
+    .map(|(capability_id, parameters)| {
+      let capability_id = capability_id
+        .parse::<Oid>()
+        .map_err(|error| CmsError::InvalidSmimeCapabilities.caused_by(error))?;
+
+      let parameters = parameters
+        .map(|parameters| der_value(parameters).ok_or(CmsError::InvalidSmimeCapabilities))
+        .transpose()?;
+
+      // SMIMECapability ::= SEQUENCE {
+      //   capabilityID OBJECT IDENTIFIER,
+      //   parameters ANY DEFINED BY capabilityID OPTIONAL }
+      Ok(encode::sequence((capability_id.encode(), parameters)))
+    })
+    .collect::<Result<Vec<_>, CmsError>>()?;
+
+  if capabilities.is_empty() {
+    return Err(CmsError::InvalidSmimeCapabilities);
+  }
+
+  // SMIMECapabilities ::= SEQUENCE OF SMIMECapability
+  Ok(attribute_value(encode::sequence(capabilities)))
+}
+
+/// Create a certValues attribute value with the certificates needed to validate the signature.
+pub fn certificate_values(certs: &[CapturedX509Certificate]) -> Result<AttributeValue, CmsError> {
+  // The certificates are written as they were encoded, rather than re-encoded.
+  let certs = certs
+    .iter()
+    .map(|cert| der_value(cert.constructed_data()).ok_or(CmsError::CertificateParseError))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  // CertificateValues ::= SEQUENCE OF Certificate
+  Ok(attribute_value(encode::sequence(certs)))
+}
+
+/// Create a revocationValues attribute value from DER encoded CRLs and OCSP responses.
+///
+/// OCSP responses may be given as an OCSPResponse, as returned by a responder, or as the
+/// BasicOCSPResponse it contains.
+pub fn revocation_values<B: AsRef<[u8]>>(
+  crls: &[B],
+  ocsp_responses: &[B],
+) -> Result<AttributeValue, CmsError> {
+  if crls.is_empty() && ocsp_responses.is_empty() {
+    return Err(CmsError::InvalidRevocationValues);
+  }
+
+  let crls = crls
+    .iter()
+    .map(|crl| der_value(crl.as_ref()).ok_or(CmsError::InvalidRevocationValues))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  let ocsp_responses = ocsp_responses
+    .iter()
+    .map(|response| basic_ocsp_response(response.as_ref()))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  // RevocationValues ::= SEQUENCE {
+  //   crlVals [0] SEQUENCE OF CertificateList OPTIONAL,
+  //   ocspVals [1] SEQUENCE OF BasicOCSPResponse OPTIONAL,
+  //   otherRevVals [2] OtherRevVals OPTIONAL }
+  Ok(attribute_value(encode::sequence((
+    (!crls.is_empty()).then(|| encode::sequence_as(Tag::CTX_0, encode::sequence(crls))),
+    (!ocsp_responses.is_empty())
+      .then(|| encode::sequence_as(Tag::CTX_1, encode::sequence(ocsp_responses))),
+  ))))
+}
+
+/// Create an adbe-revocationInfoArchival attribute value from DER encoded CRLs and OCSP
+/// responses, the signed attribute PDF validators read the revocation data of PAdES-B-LT
+/// signatures from when the document has no DSS.
+///
+/// OCSP responses may be given as an OCSPResponse or as a BasicOCSPResponse, which is wrapped in
+/// a successful OCSPResponse as the attribute holds those.
+pub fn revocation_info_archival<B: AsRef<[u8]>>(
+  crls: &[B],
+  ocsp_responses: &[B],
+) -> Result<AttributeValue, CmsError> {
+  if crls.is_empty() && ocsp_responses.is_empty() {
+    return Err(CmsError::InvalidRevocationValues);
+  }
+
+  let crls = crls
+    .iter()
+    .map(|crl| der_value(crl.as_ref()).ok_or(CmsError::InvalidRevocationValues))
+    .collect::<Result<Vec<_>, _>>()?;
+
+  // OCSPResponse ::= SEQUENCE {
+  //   responseStatus OCSPResponseStatus,
+  //   responseBytes [0] EXPLICIT SEQUENCE { responseType OBJECT IDENTIFIER, response OCTET STRING } }
+  let ocsp_responses = ocsp_responses
+    .iter()
+    .map(|response| {
+      let basic_response = basic_ocsp_response(response.as_ref())?;
+
+      let response = encode::sequence((
+        0u8.encode_as(Tag::ENUMERATED),
+        encode::sequence_as(
+          Tag::CTX_0,
+          encode::sequence((
+            OID_OCSP_BASIC.encode_ref(),
+            OctetString::encode_slice(basic_response.as_slice()),
+          )),
+        ),
+      ))
+      .to_captured(Mode::Der);
+
+      Ok(response)
+    })
+    .collect::<Result<Vec<_>, CmsError>>()?;
+
+  // RevocationInfoArchival ::= SEQUENCE {
+  //   crl [0] EXPLICIT SEQUENCE OF CRLs OPTIONAL,
+  //   ocsp [1] EXPLICIT SEQUENCE OF OCSPResponse OPTIONAL,
+  //   otherRevInfo [2] EXPLICIT SEQUENCE OF OtherRevInfo OPTIONAL }
+  Ok(attribute_value(encode::sequence((
+    (!crls.is_empty()).then(|| encode::sequence_as(Tag::CTX_0, encode::sequence(crls))),
+    (!ocsp_responses.is_empty())
+      .then(|| encode::sequence_as(Tag::CTX_1, encode::sequence(ocsp_responses))),
+  ))))
+}
+
+/// Create a certificateRefs attribute value referencing the certificates needed to validate the
+/// signature, which are the CA certificates, by their SHA-256 hash and issuer and serial number.
+pub fn complete_certificate_refs(certs: &[CapturedX509Certificate]) -> AttributeValue {
+  // CompleteCertificateRefs ::= SEQUENCE OF OtherCertID
+  // OtherCertID ::= SEQUENCE { otherCertHash OtherHash, issuerSerial IssuerSerial OPTIONAL }
+  attribute_value(encode::sequence(
+    certs
+      .iter()
+      .map(|cert| encode::sequence((other_hash(cert.constructed_data()), issuer_serial(cert))))
+      .collect::<Vec<_>>(),
+  ))
+}
+
+/// Create a revocationRefs attribute value referencing DER encoded CRLs and OCSP responses by
+/// their SHA-256 hash, in a single CrlOcspRef.
+pub fn complete_revocation_refs<B: AsRef<[u8]>>(
+  crls: &[B],
+  ocsp_responses: &[B],
+) -> Result<AttributeValue, CmsError> {
+  if crls.is_empty() && ocsp_responses.is_empty() {
+    return Err(CmsError::InvalidRevocationValues);
+  }
+
+  // CrlValidatedID ::= SEQUENCE { crlHash OtherHash, crlIdentifier CrlIdentifier OPTIONAL }
+  let crl_ids = crls
+    .iter()
+    .map(|crl| {
+      let crl = der_value(crl.as_ref()).ok_or(CmsError::InvalidRevocationValues)?;
+
+      Ok(encode::sequence(other_hash(crl.as_slice())))
+    })
+    .collect::<Result<Vec<_>, CmsError>>()?;
+
+  // OcspResponsesID ::= SEQUENCE { ocspIdentifier OcspIdentifier, ocspRepHash OtherHash OPTIONAL }
+  // OcspIdentifier ::= SEQUENCE { ocspResponderID ResponderID, producedAt GeneralizedTime }
+  let ocsp_ids = ocsp_responses
+    .iter()
+    .map(|response| {
+      let response = basic_ocsp_response(response.as_ref())?;
+      let (responder_id, produced_at) = ocsp_identifier(&response)?;
+
+      Ok(encode::sequence((
+        encode::sequence((responder_id, produced_at)),
+        other_hash(response.as_slice()),
+      )))
+    })
+    .collect::<Result<Vec<_>, CmsError>>()?;
+
+  // CompleteRevocationRefs ::= SEQUENCE OF CrlOcspRef
+  // CrlOcspRef ::= SEQUENCE {
+  //   crlids [0] CRLListID OPTIONAL,
+  //   ocspids [1] OcspListID OPTIONAL,
+  //   otherRev [2] OtherRevRefs OPTIONAL }
+  // CRLListID ::= SEQUENCE { crls SEQUENCE OF CrlValidatedID }
+  // OcspListID ::= SEQUENCE { ocspResponses SEQUENCE OF OcspResponsesID }
+  Ok(attribute_value(encode::sequence(encode::sequence((
+    (!crl_ids.is_empty())
+      .then(|| encode::sequence_as(Tag::CTX_0, encode::sequence(encode::sequence(crl_ids)))),
+    (!ocsp_ids.is_empty())
+      .then(|| encode::sequence_as(Tag::CTX_1, encode::sequence(encode::sequence(ocsp_ids)))),
+  )))))
+}
+
+/// Helper function to take the responderID and producedAt of a BasicOCSPResponse.
+fn ocsp_identifier(basic_response: &Captured) -> Result<(Captured, Captured), CmsError> {
+  // BasicOCSPResponse ::= SEQUENCE { tbsResponseData ResponseData, ... }
+  // ResponseData ::= SEQUENCE {
+  //   version [0] EXPLICIT Version DEFAULT v1,
+  //   responderID ResponderID,
+  //   producedAt GeneralizedTime, ... }
+  Mode::Der
+    .decode(basic_response.as_slice(), |cons| {
+      cons.take_sequence(|cons| {
+        let identifier = cons.take_sequence(|cons| {
+          cons.take_opt_constructed_if(Tag::CTX_0, |cons| cons.skip_all())?;
+
+          let responder_id = cons.capture_one()?;
+          let produced_at = cons.capture_one()?;
+          cons.skip_all()?;
+
+          Ok((responder_id, produced_at))
+        })?;
+
+        cons.skip_all()?;
+
+        Ok(identifier)
+      })
+    })
+    .map_err(|error| CmsError::InvalidRevocationValues.caused_by(error))
+}
+
+/// Helper function to take the BasicOCSPResponse out of an OCSPResponse, or pass one through.
+fn basic_ocsp_response(response: &[u8]) -> Result<Captured, CmsError> {
+  let captured = der_value(response).ok_or(CmsError::InvalidRevocationValues)?;
+
+  // OCSPResponse ::= SEQUENCE {
+  //   responseStatus OCSPResponseStatus,
+  //   responseBytes [0] EXPLICIT SEQUENCE { responseType OBJECT IDENTIFIER, response OCTET STRING } }
+  // A BasicOCSPResponse starts with a SEQUENCE rather than the ENUMERATED status instead.
+  let basic_response = Mode::Der
+    .decode(captured.as_slice(), |cons| {
+      cons.take_sequence(|cons| {
+        match cons.take_opt_primitive_if(Tag::ENUMERATED, |prim| prim.to_u8())? {
+          None => {
+            cons.skip_all()?;
+            Ok(None)
+          }
+          Some(0) => cons.take_constructed_if(Tag::CTX_0, |cons| {
+            cons.take_sequence(|cons| {
+              if Oid::take_from(cons)? != OID_OCSP_BASIC {
+                return Err(cons.content_err("expected a basic OCSP response"));
+              }
+
+              OctetString::take_from(cons).map(|response| Some(response.into_bytes()))
+            })
+          }),
+          Some(_) => Err(cons.content_err("unsuccessful OCSP response")),
+        }
+      })
+    })
+    .map_err(|error| CmsError::InvalidRevocationValues.caused_by(error))?;
+
+  match basic_response {
+    Some(basic_response) => der_value(&basic_response).ok_or(CmsError::InvalidRevocationValues),
+    None => Ok(captured),
+  }
+}
+
+/// Helper function to capture a value that must be exactly one DER encoded element.
+fn der_value(value: &[u8]) -> Option<Captured> {
+  let captured = Mode::Der
+    .decode(Bytes::copy_from_slice(value), |cons| cons.capture_one())
+    .ok()?;
+
+  (captured.len() == value.len()).then_some(captured)
+}
+
+/// Add caller provided attributes as (dotted OID, DER encoded value) pairs.
+///
+/// Values with the same OID are grouped into a single attribute. OIDs in `reserved`, or of
+/// attributes already in `attributes`, are rejected so an attribute never appears twice.
+pub fn add_custom_attributes<'v>(
+  attributes: &mut Vec<(Oid, Vec<AttributeValue>)>,
+  custom: impl IntoIterator<Item = (&'v str, &'v [u8])>,
+  reserved: &[ConstOid],
+) -> Result<(), CmsError> {
+  let existing = attributes.len();
+
+  for (attribute_oid, value) in custom {
+    let attribute_oid = attribute_oid
+      .parse::<Oid>()
+      .map_err(|error| CmsError::InvalidCustomAttribute.caused_by(error))?;
+
+    let is_taken = reserved
+      .iter()
+      .any(|reserved| reserved.as_ref() == attribute_oid.as_ref())
+      || attributes[..existing]
+        .iter()
+        .any(|(oid, _)| *oid == attribute_oid);
+
+    if is_taken {
+      return Err(CmsError::InvalidCustomAttribute);
+    }
+
+    let value = AttributeValue::new(der_value(value).ok_or(CmsError::InvalidCustomAttribute)?);
+
+    match attributes[existing..]
+      .iter_mut()
+      .find(|(oid, _)| *oid == attribute_oid)
+    {
+      Some((_, values)) => values.push(value),
+      None => attributes.push((attribute_oid, vec![value])),
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,152 @@
+use std::sync::Mutex;
+
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::{
+  CapturedX509Certificate, DigestAlgorithm, KeyAlgorithm, KeyInfoSigner, Sign, Signature,
+  SignatureAlgorithm, Signer, X509CertificateError,
+};
+use zeroize::Zeroizing;
+
+use crate::errors::CmsError;
+
+/// Signers for signing a batch of messages in one request to a remote signer, such as a CSC
+/// signHash call authorized once for all the documents of a batch.
+///
+/// The signed data of each document is built twice with a fixed signing time: first with a
+/// [BatchSigner::Collect] to learn the messages to sign, then, once the remote signer has
+/// signed them all, with a [BatchSigner::Signed] holding the signature of its message.
+pub enum BatchSigner {
+  /// Records the messages it's asked to sign, returning an empty signature.
+  Collect {
+    key: KeyInfo,
+    messages: Mutex<Vec<Vec<u8>>>,
+  },
+  /// Returns the signature of the message it was created with, failing for any other message.
+  Signed {
+    key: KeyInfo,
+    message: Vec<u8>,
+    signature: Vec<u8>,
+  },
+}
+
+/// The key of the signing certificate, for the signer infos.
+pub struct KeyInfo {
+  key_algorithm: KeyAlgorithm,
+  signature_algorithm: SignatureAlgorithm,
+  public_key: Bytes,
+}
+
+impl KeyInfo {
+  /// The key of a certificate, signing with SHA-256.
+  pub fn new(cert: &CapturedX509Certificate) -> Result<Self, CmsError> {
+    let key_algorithm = cert
+      .key_algorithm()
+      .ok_or(CmsError::UnsupportedKeyAlgorithm)?;
+
+    let signature_algorithm = SignatureAlgorithm::from_oid_and_digest_algorithm(
+      cert.key_algorithm_oid(),
+      DigestAlgorithm::Sha256,
+    )
+    .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+    Ok(Self {
+      key_algorithm,
+      signature_algorithm,
+      public_key: cert.public_key_data(),
+    })
+  }
+}
+
+impl BatchSigner {
+  pub fn collect(cert: &CapturedX509Certificate) -> Result<Self, CmsError> {
+    Ok(Self::Collect {
+      key: KeyInfo::new(cert)?,
+      messages: Mutex::new(Vec::new()),
+    })
+  }
+
+  pub fn signed(
+    cert: &CapturedX509Certificate,
+    message: Vec<u8>,
+    signature: Vec<u8>,
+  ) -> Result<Self, CmsError> {
+    Ok(Self::Signed {
+      key: KeyInfo::new(cert)?,
+      message,
+      signature,
+    })
+  }
+
+  /// The messages a [BatchSigner::Collect] was asked to sign, in order.
+  pub fn into_messages(self) -> Vec<Vec<u8>> {
+    match self {
+      Self::Collect { messages, .. } => {
+        messages.into_inner().unwrap_or_else(|err| err.into_inner())
+      }
+      Self::Signed { .. } => Vec::new(),
+    }
+  }
+
+  fn key(&self) -> &KeyInfo {
+    match self {
+      Self::Collect { key, .. } | Self::Signed { key, .. } => key,
+    }
+  }
+}
+
+impl KeyInfoSigner for BatchSigner {}
+
+impl Sign for BatchSigner {
+  fn sign(&self, message: &[u8]) -> Result<(Vec<u8>, SignatureAlgorithm), X509CertificateError> {
+    let signature = self.try_sign(message)?;
+    let algorithm = self.signature_algorithm()?;
+
+    Ok((signature.into(), algorithm))
+  }
+
+  fn key_algorithm(&self) -> Option<KeyAlgorithm> {
+    Some(self.key().key_algorithm)
+  }
+
+  fn signature_algorithm(&self) -> Result<SignatureAlgorithm, X509CertificateError> {
+    Ok(self.key().signature_algorithm)
+  }
+
+  fn private_key_data(&self) -> Option<Zeroizing<Vec<u8>>> {
+    None
+  }
+
+  fn public_key_data(&self) -> Bytes {
+    self.key().public_key.clone()
+  }
+
+  fn rsa_primes(
+    &self,
+  ) -> Result<Option<(Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>)>, X509CertificateError> {
+    Ok(None)
+  }
+}
+
+impl Signer<Signature> for BatchSigner {
+  fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+    match self {
+      Self::Collect { messages, .. } => {
+        messages
+          .lock()
+          .unwrap_or_else(|err| err.into_inner())
+          .push(msg.to_vec());
+
+        Ok(Signature::from(Vec::new()))
+      }
+      // The signed attributes only change if the options do, which would be a bug.
+      Self::Signed {
+        message, signature, ..
+      } => match message.as_slice() == msg {
+        true => Ok(Signature::from(signature.clone())),
+        false => Err(signature::Error::from_source(
+          "the message to sign changed after it was signed",
+        )),
+      },
+    }
+  }
+}
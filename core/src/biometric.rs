@@ -0,0 +1,163 @@
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{Captured, ConstOid, Mode, OctetString, Oid, Tag};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use rsa::pkcs1::DecodeRsaPublicKey;
+use x509_certificate::rfc5280::AlgorithmIdentifier;
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm, KeyAlgorithm};
+use zeroize::Zeroizing;
+
+use crate::errors::CmsError;
+use crate::rsa_pss::OID_MGF1;
+
+/// id-ct-authEnvelopedData (RFC 5083).
+const OID_AUTH_ENVELOPED_DATA: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 23]);
+
+/// id-data (RFC 5652).
+const OID_DATA: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 7, 1]);
+
+/// id-aes256-GCM (RFC 5084).
+const OID_AES_256_GCM: ConstOid = Oid(&[96, 134, 72, 1, 101, 3, 4, 1, 46]);
+
+/// id-RSAES-OAEP (RFC 8017).
+const OID_RSAES_OAEP: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 1, 7]);
+
+/// The length of the GCM authentication tag, the largest it can be.
+const TAG_LENGTH: u8 = 16;
+
+/// Encrypt biometric data, such as the ISO/IEC 19794-7 pen strokes of a signature pad, to the
+/// certificates of the trustees allowed to read it, as a CMS AuthEnvelopedData (RFC 5083).
+///
+/// The data is encrypted with a random AES-256-GCM key, which is encrypted to each RSA
+/// recipient with RSAES-OAEP and SHA-256. Whoever holds the key of a recipient, typically a
+/// notary or escrow service rather than the signer, can decrypt it, e.g. with `openssl cms`.
+pub fn encrypt(data: &[u8], recipients: &[CapturedX509Certificate]) -> Result<Vec<u8>, CmsError> {
+  if recipients.is_empty() {
+    return Err(CmsError::InvalidOption(
+      "recipients",
+      String::from("expected at least one certificate"),
+    ));
+  }
+
+  let random = SystemRandom::new();
+  let random_error = |_| CmsError::SigningError.caused_by("failed to generate a content key");
+
+  let mut key = Zeroizing::new([0u8; 32]);
+  random.fill(key.as_mut()).map_err(random_error)?;
+  let mut nonce = [0u8; NONCE_LEN];
+  random.fill(&mut nonce).map_err(random_error)?;
+
+  let mut recipient_infos = recipients
+    .iter()
+    .enumerate()
+    .map(|(index, cert)| {
+      recipient_info(cert, key.as_ref()).map_err(|error| error.with_detail("index", index))
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  // DER sorts the values of a SET OF by their encoding.
+  recipient_infos.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+
+  let mut encrypted = data.to_vec();
+  let tag = LessSafeKey::new(
+    UnboundKey::new(&AES_256_GCM, key.as_ref())
+      .map_err(|_| CmsError::SigningError.caused_by("invalid content key"))?,
+  )
+  .seal_in_place_separate_tag(
+    Nonce::assume_unique_for_key(nonce),
+    Aad::empty(),
+    &mut encrypted,
+  )
+  .map_err(|_| CmsError::SigningError.caused_by("failed to encrypt the data"))?;
+
+  // EncryptedContentInfo ::= SEQUENCE {
+  //   contentType ContentType,
+  //   contentEncryptionAlgorithm ContentEncryptionAlgorithmIdentifier,
+  //   encryptedContent [0] IMPLICIT EncryptedContent OPTIONAL }
+  //
+  // GCMParameters ::= SEQUENCE { aes-nonce OCTET STRING, aes-ICVlen AES-GCM-ICVlen DEFAULT 12 }
+  let encrypted_content_info = encode::sequence((
+    OID_DATA.encode_ref(),
+    encode::sequence((
+      OID_AES_256_GCM.encode_ref(),
+      encode::sequence((OctetString::encode_slice(nonce), TAG_LENGTH.encode())),
+    )),
+    OctetString::encode_slice_as(&encrypted, Tag::CTX_0),
+  ));
+
+  // AuthEnvelopedData ::= SEQUENCE {
+  //   version CMSVersion,
+  //   originatorInfo [0] IMPLICIT OriginatorInfo OPTIONAL,
+  //   recipientInfos RecipientInfos,
+  //   authEncryptedContentInfo EncryptedContentInfo,
+  //   authAttrs [1] IMPLICIT AuthAttributes OPTIONAL,
+  //   mac MessageAuthenticationCode,
+  //   unauthAttrs [2] IMPLICIT UnauthAttributes OPTIONAL }
+  let auth_enveloped_data = encode::sequence((
+    0u8.encode(),
+    encode::set(recipient_infos),
+    encrypted_content_info,
+    OctetString::encode_slice(tag.as_ref()),
+  ));
+
+  let content_info = encode::sequence((
+    OID_AUTH_ENVELOPED_DATA.encode_ref(),
+    encode::sequence_as(Tag::CTX_0, auth_enveloped_data),
+  ));
+
+  tracing::debug!(
+    size = data.len(),
+    recipients = recipients.len(),
+    "Encrypted biometric data"
+  );
+
+  Ok(content_info.to_captured(Mode::Der).into_bytes().to_vec())
+}
+
+/// Helper function to encrypt the content key to a recipient, as a KeyTransRecipientInfo.
+fn recipient_info(cert: &CapturedX509Certificate, key: &[u8]) -> Result<Captured, CmsError> {
+  if cert.key_algorithm() != Some(KeyAlgorithm::Rsa) {
+    return Err(CmsError::UnsupportedKeyAlgorithm.caused_by("recipients need RSA keys"));
+  }
+
+  let public_key = rsa::RsaPublicKey::from_pkcs1_der(cert.public_key_data().as_ref())
+    .map_err(|error| CmsError::PublicKeyError.caused_by(error))?;
+
+  let encrypted_key = public_key
+    .encrypt(&mut rand_core::OsRng, rsa::Oaep::new::<sha2::Sha256>(), key)
+    .map_err(|error| CmsError::SigningError.caused_by(error))?;
+
+  // RSAES-OAEP-params ::= SEQUENCE {
+  //   hashFunc [0] AlgorithmIdentifier DEFAULT sha1Identifier,
+  //   maskGenFunc [1] AlgorithmIdentifier DEFAULT mgf1SHA1Identifier,
+  //   pSourceFunc [2] AlgorithmIdentifier DEFAULT pSpecifiedEmptyIdentifier }
+  let oaep_parameters = encode::sequence((
+    encode::sequence_as(
+      Tag::CTX_0,
+      AlgorithmIdentifier::from(DigestAlgorithm::Sha256),
+    ),
+    encode::sequence_as(
+      Tag::CTX_1,
+      encode::sequence((
+        OID_MGF1.encode_ref(),
+        AlgorithmIdentifier::from(DigestAlgorithm::Sha256),
+      )),
+    ),
+  ));
+
+  // KeyTransRecipientInfo ::= SEQUENCE {
+  //   version CMSVersion,
+  //   rid RecipientIdentifier,
+  //   keyEncryptionAlgorithm KeyEncryptionAlgorithmIdentifier,
+  //   encryptedKey EncryptedKey }
+  let info = encode::sequence((
+    0u8.encode(),
+    encode::sequence((
+      cert.issuer_name().encode_ref(),
+      cert.serial_number_asn1().encode(),
+    )),
+    encode::sequence((OID_RSAES_OAEP.encode_ref(), oaep_parameters)),
+    OctetString::encode_slice(encrypted_key),
+  ));
+
+  Ok(info.to_captured(Mode::Der))
+}
@@ -0,0 +1,251 @@
+use std::cmp::Ordering;
+use std::io::Write;
+use std::sync::{Arc, OnceLock};
+
+use bcder::decode::{Constructed, DecodeError, Source};
+use bcder::encode::Values;
+use bcder::{Captured, Mode, Tag};
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::rfc3280::Name;
+use x509_certificate::CapturedX509Certificate;
+
+use crate::errors::CmsError;
+use crate::{keys, pkcs8};
+
+/// A DER encoded certificate that is only parsed once something needs its contents.
+///
+/// Fully parsing a certificate is the most expensive part of embedding it in a signature, and
+/// callers often pass whole CA bundles of which only the signing certificate has to be looked
+/// into. The others are embedded as they were given and ordered by their issuer and subject
+/// names, which are read without parsing the rest.
+#[derive(Clone)]
+pub struct LazyCertificate {
+  der: Bytes,
+  /// The encoded issuer and subject names.
+  issuer: Captured,
+  subject: Captured,
+  parsed: Arc<OnceLock<CapturedX509Certificate>>,
+}
+
+impl LazyCertificate {
+  /// Read the names of a DER encoded certificate, leaving the rest to be parsed when needed.
+  pub fn from_der(der: impl Into<Bytes>) -> Result<Self, CmsError> {
+    let der = der.into();
+    let (issuer, subject) = Mode::Ber
+      .decode(der.as_ref(), take_names)
+      .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+    Ok(Self {
+      der,
+      issuer,
+      subject,
+      parsed: Arc::new(OnceLock::new()),
+    })
+  }
+
+  /// The certificate as it was given.
+  pub fn der(&self) -> &[u8] {
+    &self.der
+  }
+
+  /// The parsed certificate, parsed on the first call.
+  pub fn parsed(&self) -> Result<&CapturedX509Certificate, CmsError> {
+    if let Some(cert) = self.parsed.get() {
+      return Ok(cert);
+    }
+
+    let cert = CapturedX509Certificate::from_der(self.der.to_vec())
+      .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+    Ok(self.parsed.get_or_init(|| cert))
+  }
+
+  /// The subject name in the format of `Name::user_friendly_str`, read without parsing the
+  /// certificate, for logging.
+  pub fn subject_str(&self) -> Option<String> {
+    Mode::Der
+      .decode(self.subject.as_slice(), Name::take_from)
+      .ok()
+      .and_then(|name| name.user_friendly_str().ok())
+  }
+
+  /// Order certificates so the issuer comes before the certificates it issued, as
+  /// `CapturedX509Certificate::compare_issuer` does.
+  pub fn compare_issuer(&self, other: &Self) -> Ordering {
+    let is = |a: &Captured, b: &Captured| a.as_slice() == b.as_slice();
+
+    if is(&self.subject, &self.issuer) {
+      Ordering::Equal
+    } else if is(&self.issuer, &other.subject) {
+      Ordering::Greater
+    } else if is(&self.subject, &other.issuer) {
+      Ordering::Less
+    } else {
+      Ordering::Equal
+    }
+  }
+}
+
+impl From<CapturedX509Certificate> for LazyCertificate {
+  fn from(cert: CapturedX509Certificate) -> Self {
+    let der = Bytes::copy_from_slice(cert.constructed_data());
+
+    // A parsed certificate has names, but they are re-encoded should reading them from the
+    // original encoding fail.
+    let (issuer, subject) = Mode::Ber
+      .decode(der.as_ref(), take_names)
+      .unwrap_or_else(|_| {
+        (
+          Captured::from_values(Mode::Der, cert.issuer_name().encode_ref()),
+          Captured::from_values(Mode::Der, cert.subject_name().encode_ref()),
+        )
+      });
+
+    Self {
+      der,
+      issuer,
+      subject,
+      parsed: Arc::new(OnceLock::from(cert)),
+    }
+  }
+}
+
+impl PartialEq for LazyCertificate {
+  fn eq(&self, other: &Self) -> bool {
+    self.der == other.der
+  }
+}
+
+impl Eq for LazyCertificate {}
+
+/// Embeds the certificate as it was given, which is also the only encoding that keeps the
+/// signature of a certificate that isn't strictly DER valid.
+impl Values for LazyCertificate {
+  fn encoded_len(&self, _mode: Mode) -> usize {
+    self.der.len()
+  }
+
+  fn write_encoded<W: Write>(&self, _mode: Mode, target: &mut W) -> Result<(), std::io::Error> {
+    target.write_all(&self.der)
+  }
+}
+
+/// Read the certificates of PEM data, parsing only the first, which is the signing certificate
+/// of the signing functions.
+///
+/// `option` is the name of the option the PEM data was given for, to report errors against.
+pub fn from_pem(option: &'static str, pem: &[u8]) -> Result<Vec<LazyCertificate>, CmsError> {
+  read_pem(pem).map_err(|error| error.with_detail("option", option))
+}
+
+fn read_pem(pem: &[u8]) -> Result<Vec<LazyCertificate>, CmsError> {
+  let pem = std::str::from_utf8(pem)
+    .map_err(|_| CmsError::CertificateParseError.caused_by("the PEM data is not valid UTF-8"))?;
+
+  let mut certs = Vec::new();
+  let mut rest = pem;
+
+  while let Some((_, block)) = rest.split_once("-----BEGIN CERTIFICATE-----") {
+    let (data, after) = block
+      .split_once("-----END CERTIFICATE-----")
+      .ok_or_else(|| CmsError::CertificateParseError.caused_by("missing END line"))?;
+
+    let mut der = keys::decode_base64(data)
+      .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+    // Certificates aren't secret, so they are moved out of the buffer that would be wiped.
+    certs.push(LazyCertificate::from_der(std::mem::take(&mut *der))?);
+    rest = after;
+  }
+
+  let Some(first) = certs.first() else {
+    return Err(match pem.contains("PRIVATE KEY-----") {
+      true => CmsError::NoCertificate.caused_by(
+        "expected a certificate, not a private key; were the certificate and key swapped?",
+      ),
+      false => CmsError::NoCertificate,
+    });
+  };
+
+  first.parsed()?;
+
+  Ok(certs)
+}
+
+/// Read one or more concatenated DER encoded certificates, such as the `.cer` files Windows
+/// exports, parsing only the first, as [from_pem] does.
+///
+/// `option` is the name of the option the DER data was given for, to report errors against.
+pub fn from_der(option: &'static str, der: &[u8]) -> Result<Vec<LazyCertificate>, CmsError> {
+  read_der(der).map_err(|error| error.with_detail("option", option))
+}
+
+fn read_der(der: &[u8]) -> Result<Vec<LazyCertificate>, CmsError> {
+  let values = Mode::Ber
+    .decode(der, |cons| {
+      let mut values = Vec::new();
+
+      // Certificates are SEQUENCEs, and only optional values end without an error at the end
+      // of the data.
+      loop {
+        let value =
+          cons.capture(|cons| cons.take_opt_sequence(|cons| cons.skip_all()).map(|_| ()))?;
+        if value.as_slice().is_empty() {
+          return Ok(values);
+        }
+
+        values.push(value);
+      }
+    })
+    .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+  if values
+    .iter()
+    .map(|value| value.as_slice().len())
+    .sum::<usize>()
+    != der.len()
+  {
+    return Err(CmsError::CertificateParseError.caused_by("trailing data after the certificates"));
+  }
+
+  let certs = values
+    .into_iter()
+    .map(|value| LazyCertificate::from_der(value.into_bytes()))
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(
+      |error| match keys::private_key_from_der(der).is_ok() || pkcs8::is_encrypted(der) {
+        true => CmsError::NoCertificate.caused_by(
+          "expected a certificate, not a private key; were the certificate and key swapped?",
+        ),
+        false => error,
+      },
+    )?;
+
+  certs.first().ok_or(CmsError::NoCertificate)?.parsed()?;
+
+  Ok(certs)
+}
+
+/// Take the issuer and subject of a Certificate.
+fn take_names<S: Source>(
+  cons: &mut Constructed<S>,
+) -> Result<(Captured, Captured), DecodeError<S::Error>> {
+  cons.take_sequence(|cons| {
+    let names = cons.take_sequence(|cons| {
+      // version [0] EXPLICIT, serialNumber and signature precede the issuer.
+      cons.take_opt_constructed_if(Tag::CTX_0, |cons| cons.skip_all())?;
+      cons.skip_one()?;
+      cons.skip_one()?;
+
+      let issuer = cons.capture_one()?;
+      cons.skip_one()?;
+      let subject = cons.capture_one()?;
+
+      cons.skip_all()?;
+      Ok((issuer, subject))
+    })?;
+
+    cons.skip_all()?;
+    Ok(names)
+  })
+}
@@ -0,0 +1,56 @@
+use crate::errors::CmsError;
+
+/// The transform method of the signature reference of a certification signature.
+pub const TRANSFORM_METHOD: &str = "DocMDP";
+
+/// The version of the DocMDP transform parameters, the only one there is (ISO 32000-1, 12.8.2.2).
+pub const TRANSFORM_PARAMS_VERSION: &str = "1.2";
+
+/// The changes a certification signature allows to the document after it, the `/P` entry of
+/// its DocMDP transform parameters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Permissions {
+  /// No changes at all, including other signatures.
+  NoChanges,
+  /// Filling in forms, instantiating page templates and signing.
+  #[default]
+  FormFilling,
+  /// What form filling allows, and creating, deleting and changing annotations.
+  Annotations,
+}
+
+impl Permissions {
+  /// Parse permissions, `noChanges`, `formFilling` or `annotations`. `option` is the name of the
+  /// option they were given for, to report errors against.
+  pub fn parse(option: &'static str, name: &str) -> Result<Self, CmsError> {
+    match name {
+      "noChanges" => Ok(Self::NoChanges),
+      "formFilling" => Ok(Self::FormFilling),
+      "annotations" => Ok(Self::Annotations),
+      _ => Err(CmsError::InvalidOption(
+        option,
+        String::from("expected noChanges, formFilling or annotations"),
+      )),
+    }
+  }
+
+  /// The access permissions value, `/P`.
+  pub fn value(self) -> u8 {
+    match self {
+      Self::NoChanges => 1,
+      Self::FormFilling => 2,
+      Self::Annotations => 3,
+    }
+  }
+}
+
+/// The `/Reference` array of the signature dictionary of a certification signature, with the
+/// signature reference dictionary of its DocMDP transform, in PDF syntax.
+pub fn reference(permissions: Permissions) -> String {
+  format!(
+    "[<< /Type /SigRef /TransformMethod /{} /TransformParams << /Type /TransformParams /P {} /V /{} >> >>]",
+    TRANSFORM_METHOD,
+    permissions.value(),
+    TRANSFORM_PARAMS_VERSION
+  )
+}
@@ -0,0 +1,703 @@
+use std::convert::Infallible;
+
+use bcder::decode::{Constructed, DecodeError, SliceSource, Source};
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{Captured, ConstOid, Mode, OctetString, Oid, Tag};
+use cryptographic_message_syntax::asn1::rfc5652::{
+  IssuerAndSerialNumber, SignerIdentifier, OID_ID_SIGNED_DATA, OID_MESSAGE_DIGEST,
+};
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::rfc5652::AttributeValue;
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm, SignatureAlgorithm};
+
+use crate::attributes;
+use crate::certificates::LazyCertificate;
+use crate::errors::CmsError;
+use crate::rsa_pss;
+
+/// id-ce-subjectKeyIdentifier (RFC 5280).
+const OID_SUBJECT_KEY_IDENTIFIER: ConstOid = Oid(&[85, 29, 14]);
+
+/// A SignedData structure that keeps every field in its original encoding.
+///
+/// Unlike the parsed structures of the CMS crate, decoding and encoding this round-trips
+/// losslessly, so it can be used to amend an existing CMS without invalidating signatures.
+pub struct SignedData {
+  pub version: Captured,
+  pub digest_algorithms: Captured,
+  pub encapsulated_content_info: Captured,
+  /// The `[0] IMPLICIT CertificateSet`, including its tag.
+  pub certificates: Option<Captured>,
+  /// The `[1] IMPLICIT RevocationInfoChoices`, including its tag.
+  pub crls: Option<Captured>,
+  pub signer_infos: Vec<SignerInfo>,
+}
+
+pub struct SignerInfo {
+  pub version: Captured,
+  pub sid: Captured,
+  pub digest_algorithm: Captured,
+  /// The `[0] IMPLICIT SignedAttributes`, including its tag.
+  pub signed_attributes: Option<Captured>,
+  pub signature_algorithm: Captured,
+  pub signature: OctetString,
+  pub unsigned_attributes: Vec<Attribute>,
+}
+
+/// An encoded attribute along with its type.
+pub struct Attribute {
+  pub oid: Oid,
+  pub encoded: Captured,
+}
+
+impl SignedData {
+  /// Decode a ContentInfo containing SignedData, in BER or DER.
+  pub fn decode(data: &[u8]) -> Result<Self, CmsError> {
+    Mode::Ber
+      .decode(data, |cons| {
+        cons.take_sequence(|cons| {
+          let content_type = Oid::take_from(cons)?;
+
+          if content_type != OID_ID_SIGNED_DATA {
+            return Err(cons.content_err("expected signed data"));
+          }
+
+          cons.take_constructed_if(Tag::CTX_0, |cons| cons.take_sequence(Self::take_from))
+        })
+      })
+      .map_err(|error| CmsError::SignedDataParseError.caused_by(error))
+  }
+
+  fn take_from<S: Source>(cons: &mut Constructed<S>) -> Result<Self, DecodeError<S::Error>> {
+    let version = cons.capture_one()?;
+    let digest_algorithms = cons.capture_one()?;
+    let encapsulated_content_info = cons.capture_one()?;
+    let certificates = take_opt_captured(cons, Tag::CTX_0)?;
+    let crls = take_opt_captured(cons, Tag::CTX_1)?;
+
+    let signer_infos = cons.take_set(|cons| {
+      let mut signer_infos = Vec::new();
+
+      while let Some(signer_info) = cons.take_opt_sequence(SignerInfo::take_from)? {
+        signer_infos.push(signer_info);
+      }
+
+      Ok(signer_infos)
+    })?;
+
+    Ok(Self {
+      version,
+      digest_algorithms,
+      encapsulated_content_info,
+      certificates,
+      crls,
+      signer_infos,
+    })
+  }
+
+  /// Encode as a ContentInfo.
+  ///
+  /// Parts taken from a decoded structure are written as they were, so the result is only DER
+  /// if the input was. What was added is inserted in the DER order of its SET OF, and BER mode
+  /// writes definite lengths as DER does, so amending DER keeps it DER.
+  pub fn encode(&self) -> Vec<u8> {
+    let signer_infos = self
+      .signer_infos
+      .iter()
+      .map(SignerInfo::encode_ref)
+      .collect::<Vec<_>>();
+
+    let values = encode::sequence((
+      OID_ID_SIGNED_DATA.encode_ref(),
+      encode::sequence_as(
+        Tag::CTX_0,
+        encode::sequence((
+          &self.version,
+          &self.digest_algorithms,
+          &self.encapsulated_content_info,
+          self.certificates.as_ref(),
+          self.crls.as_ref(),
+          encode::set(signer_infos),
+        )),
+      ),
+    ));
+
+    let mut encoded = Vec::with_capacity(values.encoded_len(Mode::Ber));
+
+    values
+      .write_encoded(Mode::Ber, &mut encoded)
+      .expect("writing to a Vec cannot fail");
+
+    encoded
+  }
+
+  /// Add certificates to the certificate set, skipping any that are already present, in the
+  /// DER order of the set.
+  pub fn add_certificates(&mut self, certs: &[LazyCertificate]) -> Result<(), CmsError> {
+    let mut values = self.certificate_values()?;
+
+    let existing = values.len();
+
+    for cert in certs {
+      let der = cert.der();
+
+      if !values.iter().any(|value| value.as_slice() == der) {
+        values.push(
+          Mode::Ber
+            .decode(der, |cons| cons.capture_one())
+            .map_err(|error| CmsError::CertificateParseError.caused_by(error))?,
+        );
+      }
+    }
+
+    if values.len() > existing {
+      sort_set(&mut values);
+
+      self.certificates = Some(Captured::from_values(
+        Mode::Ber,
+        encode::set_as(Tag::CTX_0, values),
+      ));
+    }
+
+    Ok(())
+  }
+
+  /// Remove NULL parameters from the digest and signature algorithm identifiers.
+  ///
+  /// Algorithm identifiers aren't covered by the signature, so this keeps signatures valid. It
+  /// is for validators that reject the NULL parameters written by the CMS crate.
+  pub fn omit_null_parameters(&mut self) -> Result<(), CmsError> {
+    let mut digest_algorithms = Mode::Ber
+      .decode(self.digest_algorithms.as_slice(), |cons| {
+        cons.take_set(take_all_values)
+      })
+      .map_err(|error| CmsError::SignedDataParseError.caused_by(error))?
+      .iter()
+      .map(omit_null_parameters)
+      .collect::<Result<Vec<_>, _>>()?;
+
+    sort_set(&mut digest_algorithms);
+
+    self.digest_algorithms = Captured::from_values(Mode::Ber, encode::set(digest_algorithms));
+
+    for signer_info in &mut self.signer_infos {
+      signer_info.digest_algorithm = omit_null_parameters(&signer_info.digest_algorithm)?;
+      signer_info.signature_algorithm = omit_null_parameters(&signer_info.signature_algorithm)?;
+    }
+
+    Ok(())
+  }
+
+  /// Remove NULL parameters from the ECDSA and EdDSA signature algorithm identifiers, which
+  /// must not have any (RFC 5758, RFC 8410), unlike RSA, whose parameters are NULL.
+  pub fn omit_elliptic_curve_null_parameters(&mut self) -> Result<(), CmsError> {
+    for signer_info in &mut self.signer_infos {
+      let algorithm = SignatureAlgorithm::try_from(&signer_info.signature_algorithm_oid()?);
+
+      if matches!(
+        algorithm,
+        Ok(
+          SignatureAlgorithm::EcdsaSha256
+            | SignatureAlgorithm::EcdsaSha384
+            | SignatureAlgorithm::Ed25519
+        )
+      ) {
+        signer_info.signature_algorithm = omit_null_parameters(&signer_info.signature_algorithm)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn version(&self) -> Result<u8, CmsError> {
+    decode(&self.version, |cons| cons.take_u8())
+  }
+
+  pub fn digest_algorithm_oids(&self) -> Result<Vec<Oid>, CmsError> {
+    decode(&self.digest_algorithms, |cons| {
+      cons.take_set(|cons| {
+        let mut oids = Vec::new();
+
+        while let Some(oid) = cons.take_opt_sequence(take_algorithm_oid)? {
+          oids.push(oid);
+        }
+
+        Ok(oids)
+      })
+    })
+  }
+
+  /// The eContentType and, for attached signatures, the eContent.
+  pub fn encapsulated_content(&self) -> Result<(Oid, Option<Bytes>), CmsError> {
+    decode(&self.encapsulated_content_info, |cons| {
+      cons.take_sequence(|cons| {
+        let content_type = Oid::take_from(cons)?;
+        let content = cons.take_opt_constructed_if(Tag::CTX_0, |cons| {
+          OctetString::take_from(cons).map(|content| content.into_bytes())
+        })?;
+
+        Ok((content_type, content))
+      })
+    })
+  }
+
+  /// The encoded CertificateChoices values of the certificate set.
+  pub fn certificate_values(&self) -> Result<Vec<Captured>, CmsError> {
+    take_set_values(self.certificates.as_ref(), Tag::CTX_0)
+  }
+
+  /// The encoded RevocationInfoChoice values of the crls set.
+  pub fn crl_values(&self) -> Result<Vec<Captured>, CmsError> {
+    take_set_values(self.crls.as_ref(), Tag::CTX_1)
+  }
+
+  /// The certificates in the certificate set, skipping other certificate formats.
+  pub fn x509_certificates(&self) -> Result<Vec<CapturedX509Certificate>, CmsError> {
+    self
+      .certificate_values()?
+      .iter()
+      .filter(|value| value.as_slice().first() == Some(&0x30))
+      .map(|value| {
+        CapturedX509Certificate::from_der(value.as_slice().to_vec())
+          .map_err(|error| CmsError::CertificateParseError.caused_by(error))
+      })
+      .collect()
+  }
+
+  /// Get the signer info at `index`.
+  pub fn signer_info_mut(&mut self, index: usize) -> Result<&mut SignerInfo, CmsError> {
+    self
+      .signer_infos
+      .get_mut(index)
+      .ok_or_else(|| CmsError::SignerInfoNotFound.with_detail("index", index))
+  }
+}
+
+impl SignerInfo {
+  fn take_from<S: Source>(cons: &mut Constructed<S>) -> Result<Self, DecodeError<S::Error>> {
+    let version = cons.capture_one()?;
+    let sid = cons.capture_one()?;
+    let digest_algorithm = cons.capture_one()?;
+    let signed_attributes = take_opt_captured(cons, Tag::CTX_0)?;
+    let signature_algorithm = cons.capture_one()?;
+    let signature = OctetString::take_from(cons)?;
+
+    let unsigned_attributes = cons
+      .take_opt_constructed_if(Tag::CTX_1, |cons| {
+        let mut attributes = Vec::new();
+
+        loop {
+          let mut attribute_oid = None;
+
+          let encoded = cons.capture(|cons| {
+            cons.take_opt_sequence(|cons| {
+              attribute_oid = Some(Oid::take_from(cons)?);
+              cons.skip_all()
+            })?;
+
+            Ok(())
+          })?;
+
+          match attribute_oid {
+            Some(oid) => attributes.push(Attribute { oid, encoded }),
+            None => break,
+          }
+        }
+
+        Ok(attributes)
+      })?
+      .unwrap_or_default();
+
+    Ok(Self {
+      version,
+      sid,
+      digest_algorithm,
+      signed_attributes,
+      signature_algorithm,
+      signature,
+      unsigned_attributes,
+    })
+  }
+
+  pub fn encode_ref(&self) -> impl Values + '_ {
+    encode::sequence((
+      &self.version,
+      &self.sid,
+      &self.digest_algorithm,
+      self.signed_attributes.as_ref(),
+      &self.signature_algorithm,
+      self.signature.encode_ref(),
+      (!self.unsigned_attributes.is_empty()).then(|| {
+        encode::set_as(
+          Tag::CTX_1,
+          self
+            .unsigned_attributes
+            .iter()
+            .map(|attribute| &attribute.encoded)
+            .collect::<Vec<_>>(),
+        )
+      }),
+    ))
+  }
+
+  pub fn version(&self) -> Result<u8, CmsError> {
+    decode(&self.version, |cons| cons.take_u8())
+  }
+
+  pub fn sid(&self) -> Result<SignerIdentifier, CmsError> {
+    decode(&self.sid, |cons| {
+      if let Some(key_identifier) = cons.take_opt_value_if(Tag::CTX_0, OctetString::from_content)? {
+        return Ok(SignerIdentifier::SubjectKeyIdentifier(key_identifier));
+      }
+
+      Ok(SignerIdentifier::IssuerAndSerialNumber(
+        IssuerAndSerialNumber::take_from(cons)?,
+      ))
+    })
+  }
+
+  /// Find the certificate of the signer among `certs`, by issuer and serial number or by the
+  /// subject key identifier extension, whichever the signer is identified by.
+  pub fn find_certificate<'a>(
+    &self,
+    certs: &'a [CapturedX509Certificate],
+  ) -> Result<Option<&'a CapturedX509Certificate>, CmsError> {
+    Ok(match self.sid()? {
+      SignerIdentifier::IssuerAndSerialNumber(issuer_and_serial_number) => {
+        certs.iter().find(|cert| {
+          cert.issuer_name() == &issuer_and_serial_number.issuer
+            && cert.serial_number_asn1() == &issuer_and_serial_number.serial_number
+        })
+      }
+      SignerIdentifier::SubjectKeyIdentifier(key_identifier) => certs.iter().find(|cert| {
+        subject_key_identifier(cert).is_some_and(|id| id == key_identifier.to_bytes())
+      }),
+    })
+  }
+
+  pub fn digest_algorithm_oid(&self) -> Result<Oid, CmsError> {
+    decode(&self.digest_algorithm, |cons| {
+      cons.take_sequence(take_algorithm_oid)
+    })
+  }
+
+  pub fn signature_algorithm_oid(&self) -> Result<Oid, CmsError> {
+    decode(&self.signature_algorithm, |cons| {
+      cons.take_sequence(take_algorithm_oid)
+    })
+  }
+
+  /// The signed attributes as their types and encoded values, in encoded order.
+  pub fn signed_attribute_values(&self) -> Result<Vec<(Oid, Vec<Captured>)>, CmsError> {
+    let Some(signed_attributes) = &self.signed_attributes else {
+      return Ok(Vec::new());
+    };
+
+    decode(signed_attributes, |cons| {
+      cons.take_constructed_if(Tag::CTX_0, |cons| {
+        let mut attributes = Vec::new();
+
+        while let Some(attribute) = cons.take_opt_sequence(take_attribute_values)? {
+          attributes.push(attribute);
+        }
+
+        Ok(attributes)
+      })
+    })
+  }
+
+  /// The signed attributes as the signature was computed over them, which is their encoding with
+  /// the SET OF tag in place of the implicit `[0]` tag (RFC 5652, section 5.4).
+  pub fn signed_attributes_der(&self) -> Option<Vec<u8>> {
+    let mut signed_attributes = self.signed_attributes.as_ref()?.to_vec();
+    *signed_attributes.first_mut()? = 0x31;
+
+    Some(signed_attributes)
+  }
+
+  /// Whether the signature verifies with the public key of a certificate, over the signed
+  /// attributes or, for a signer info without them, the content.
+  ///
+  /// Unlike the CMS crate, this verifies RSASSA-PSS signatures, with the parameters
+  /// [rsa_pss::algorithm_identifier] writes for the digest algorithm of the signer info.
+  pub fn signature_valid(
+    &self,
+    cert: &CapturedX509Certificate,
+    content: Option<&[u8]>,
+  ) -> Result<bool, CmsError> {
+    let signed_attributes = self.signed_attributes_der();
+    let Some(message) = signed_attributes.as_deref().or(content) else {
+      return Ok(false);
+    };
+
+    let signature = self.signature.to_bytes();
+    let signature_algorithm = self.signature_algorithm_oid()?;
+    let digest_algorithm = DigestAlgorithm::try_from(&self.digest_algorithm_oid()?)
+      .map_err(|_| CmsError::UnsupportedDigestAlgorithm)?;
+
+    if signature_algorithm == rsa_pss::OID_RSASSA_PSS {
+      return Ok(rsa_pss::verify(cert, digest_algorithm, message, &signature));
+    }
+
+    let key_algorithm = cert
+      .key_algorithm()
+      .ok_or(CmsError::UnsupportedKeyAlgorithm)?;
+
+    let verification_algorithm =
+      SignatureAlgorithm::from_oid_and_digest_algorithm(&signature_algorithm, digest_algorithm)
+        .and_then(|algorithm| algorithm.resolve_verification_algorithm(key_algorithm))
+        .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+    Ok(
+      cert
+        .verify_signed_data_with_algorithm(message, &signature, verification_algorithm)
+        .is_ok(),
+    )
+  }
+
+  /// The content of the messageDigest signed attribute, if present.
+  pub fn message_digest(&self) -> Result<Option<Bytes>, CmsError> {
+    let message_digest = self
+      .signed_attribute_values()?
+      .into_iter()
+      .find(|(oid, _)| *oid == OID_MESSAGE_DIGEST)
+      .and_then(|(_, values)| values.into_iter().next());
+
+    message_digest
+      .map(|value| {
+        decode(&value, |cons| {
+          OctetString::take_from(cons).map(OctetString::into_bytes)
+        })
+      })
+      .transpose()
+  }
+
+  /// The unsigned attributes as their types and encoded values, in encoded order.
+  pub fn unsigned_attribute_values(&self) -> Result<Vec<(Oid, Vec<Captured>)>, CmsError> {
+    self
+      .unsigned_attributes
+      .iter()
+      .map(|attribute| {
+        decode(&attribute.encoded, |cons| {
+          cons.take_sequence(take_attribute_values)
+        })
+      })
+      .collect()
+  }
+
+  /// Add a value to the unsigned attribute of type `oid`, adding the attribute if needed.
+  pub fn add_unsigned_attribute_value(
+    &mut self,
+    oid: Oid,
+    value: AttributeValue,
+  ) -> Result<(), CmsError> {
+    let existing = self
+      .unsigned_attributes
+      .iter()
+      .position(|attribute| attribute.oid == oid);
+
+    match existing {
+      Some(index) => {
+        let attribute = &mut self.unsigned_attributes[index];
+
+        let mut values = Mode::Ber
+          .decode(attribute.encoded.as_slice(), |cons| {
+            cons.take_sequence(|cons| {
+              Oid::skip_in(cons)?;
+              cons.take_set(take_all_values)
+            })
+          })
+          .map_err(|error| CmsError::SignedDataParseError.caused_by(error))?;
+
+        values.push(Captured::from_values(Mode::Der, value));
+        sort_set(&mut values);
+
+        attribute.encoded = Captured::from_values(
+          Mode::Ber,
+          encode::sequence((oid.encode_ref(), encode::set(values))),
+        );
+      }
+      None => self
+        .unsigned_attributes
+        .push(Attribute::new(oid, vec![value])),
+    }
+
+    self.sort_unsigned_attributes();
+
+    Ok(())
+  }
+
+  /// Add unsigned attributes, followed by caller provided ones as (dotted OID, DER encoded
+  /// value) pairs.
+  ///
+  /// Values of custom attributes with the same OID are grouped into a single attribute. OIDs of
+  /// unsigned attributes already present, such as a timestamp token, are rejected.
+  pub fn add_unsigned_attributes<'v>(
+    &mut self,
+    attributes: &[(Oid, Vec<AttributeValue>)],
+    custom: impl IntoIterator<Item = (&'v str, &'v [u8])>,
+  ) -> Result<(), CmsError> {
+    let existing = self.unsigned_attributes.len();
+
+    let mut attribute_values = self
+      .unsigned_attributes
+      .iter()
+      .map(|attribute| (attribute.oid.clone(), Vec::new()))
+      .collect::<Vec<_>>();
+
+    for (oid, values) in attributes {
+      if attribute_values.iter().any(|(existing, _)| existing == oid) {
+        return Err(CmsError::InvalidCustomAttribute);
+      }
+
+      attribute_values.push((oid.clone(), values.clone()));
+    }
+
+    attributes::add_custom_attributes(&mut attribute_values, custom, &[])?;
+
+    for (oid, values) in attribute_values.into_iter().skip(existing) {
+      self.unsigned_attributes.push(Attribute::new(oid, values));
+    }
+
+    self.sort_unsigned_attributes();
+
+    Ok(())
+  }
+
+  /// Sort the unsigned attributes by their encoding, as DER requires for a SET OF. Those of a
+  /// DER input were already sorted, so they keep their order.
+  fn sort_unsigned_attributes(&mut self) {
+    self
+      .unsigned_attributes
+      .sort_by(|a, b| a.encoded.as_slice().cmp(b.encoded.as_slice()));
+  }
+}
+
+impl Attribute {
+  pub fn new(oid: Oid, mut values: Vec<AttributeValue>) -> Self {
+    attributes::sort_values(&mut values);
+
+    // Attribute ::= SEQUENCE { attrType OBJECT IDENTIFIER, attrValues SET OF AttributeValue }
+    let encoded = Captured::from_values(
+      Mode::Der,
+      encode::sequence((oid.encode_ref(), encode::set(values))),
+    );
+
+    Self { oid, encoded }
+  }
+}
+
+/// Helper function to decode a captured value.
+fn decode<F, T>(captured: &Captured, op: F) -> Result<T, CmsError>
+where
+  F: FnOnce(&mut Constructed<SliceSource>) -> Result<T, DecodeError<Infallible>>,
+{
+  Mode::Ber
+    .decode(captured.as_slice(), op)
+    .map_err(|error| CmsError::SignedDataParseError.caused_by(error))
+}
+
+/// Helper function to sort the values of a SET OF by their encoding, as DER requires.
+///
+/// DER compares encodings padded with trailing zeros, but as the encoding of a value starts
+/// with its tag and length, one can't be a prefix of another and comparing them as they are is
+/// the same.
+fn sort_set(values: &mut [Captured]) {
+  values.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+}
+
+/// Helper function to take the values of an optional implicitly tagged SET OF.
+fn take_set_values(captured: Option<&Captured>, tag: Tag) -> Result<Vec<Captured>, CmsError> {
+  match captured {
+    Some(captured) => decode(captured, |cons| {
+      cons.take_constructed_if(tag, take_all_values)
+    }),
+    None => Ok(Vec::new()),
+  }
+}
+
+/// Helper function to take the algorithm of an AlgorithmIdentifier, ignoring its parameters.
+fn take_algorithm_oid<S: Source>(cons: &mut Constructed<S>) -> Result<Oid, DecodeError<S::Error>> {
+  let oid = Oid::take_from(cons)?;
+  cons.skip_all()?;
+
+  Ok(oid)
+}
+
+/// Helper function to take the type and values of an Attribute.
+fn take_attribute_values<S: Source>(
+  cons: &mut Constructed<S>,
+) -> Result<(Oid, Vec<Captured>), DecodeError<S::Error>> {
+  let oid = Oid::take_from(cons)?;
+  let values = cons.take_set(take_all_values)?;
+
+  Ok((oid, values))
+}
+
+/// Helper function to remove NULL parameters from an AlgorithmIdentifier.
+fn omit_null_parameters(algorithm: &Captured) -> Result<Captured, CmsError> {
+  let (oid, parameters) = Mode::Ber
+    .decode(algorithm.as_slice(), |cons| {
+      cons.take_sequence(|cons| Ok((Oid::take_from(cons)?, cons.capture_all()?)))
+    })
+    .map_err(|error| CmsError::SignedDataParseError.caused_by(error))?;
+
+  let is_null = parameters.as_slice() == [5, 0];
+
+  // AlgorithmIdentifier ::= SEQUENCE { algorithm OBJECT IDENTIFIER, parameters ANY OPTIONAL }
+  Ok(Captured::from_values(
+    Mode::Ber,
+    encode::sequence((oid.encode(), (!is_null).then_some(parameters))),
+  ))
+}
+
+/// Helper function to capture each of the remaining values.
+fn take_all_values<S: Source>(
+  cons: &mut Constructed<S>,
+) -> Result<Vec<Captured>, DecodeError<S::Error>> {
+  let mut values = Vec::new();
+
+  loop {
+    let mut found = false;
+
+    let captured = cons.capture(|cons| {
+      found = cons.skip_opt(|_, _, _| Ok(()))?.is_some();
+
+      Ok(())
+    })?;
+
+    if !found {
+      return Ok(values);
+    }
+
+    values.push(captured);
+  }
+}
+
+/// Helper function to capture an optional implicitly tagged value, including its tag.
+fn take_opt_captured<S: Source>(
+  cons: &mut Constructed<S>,
+  tag: Tag,
+) -> Result<Option<Captured>, DecodeError<S::Error>> {
+  let captured = cons.capture(|cons| {
+    cons.take_opt_constructed_if(tag, |cons| cons.skip_all())?;
+
+    Ok(())
+  })?;
+
+  Ok((!captured.is_empty()).then_some(captured))
+}
+
+/// Helper function to decode the subject key identifier extension of a certificate, if it has
+/// one.
+fn subject_key_identifier(cert: &CapturedX509Certificate) -> Option<Bytes> {
+  let extension = cert
+    .iter_extensions()
+    .find(|extension| extension.id == OID_SUBJECT_KEY_IDENTIFIER)?;
+
+  // SubjectKeyIdentifier ::= KeyIdentifier ::= OCTET STRING
+  Mode::Der
+    .decode(extension.value.to_bytes(), OctetString::take_from)
+    .ok()
+    .map(|key_identifier| key_identifier.to_bytes())
+}
@@ -0,0 +1,363 @@
+use bcder::{BitString, Captured, ConstOid, Mode, Oid, PrintableString};
+use cryptographic_message_syntax::asn1::rfc3161::OID_TIME_STAMP_TOKEN;
+use cryptographic_message_syntax::asn1::rfc5652::{
+  OID_CONTENT_TYPE, OID_ID_DATA, OID_MESSAGE_DIGEST, OID_SIGNING_TIME,
+};
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::CapturedX509Certificate;
+
+use crate::attributes;
+use crate::cms::SignedData;
+use crate::errors::CmsError;
+
+/// id-aa-signingCertificate (RFC 2634), which baseline signatures may use for SHA-1.
+const OID_SIGNING_CERTIFICATE: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 2, 12]);
+
+/// id-pe-qcStatements (RFC 3739).
+const OID_QC_STATEMENTS: ConstOid = Oid(&[43, 6, 1, 5, 5, 7, 1, 3]);
+
+/// id-ce-keyUsage (RFC 5280).
+const OID_KEY_USAGE: ConstOid = Oid(&[85, 29, 15]);
+
+/// id-etsi-qcs-QcCompliance (ETSI EN 319 412-5).
+const OID_QC_COMPLIANCE: ConstOid = Oid(&[4, 0, 142, 70, 1, 1]);
+
+/// id-etsi-qcs-QcSSCD (ETSI EN 319 412-5).
+const OID_QC_SSCD: ConstOid = Oid(&[4, 0, 142, 70, 1, 4]);
+
+/// id-etsi-qcs-QcCClegislation (ETSI EN 319 412-5), the countries whose law a certificate is
+/// qualified under outside the EU.
+const OID_QC_CC_LEGISLATION: ConstOid = Oid(&[4, 0, 142, 70, 1, 7]);
+
+/// id-etsi-qcs-QcType (ETSI EN 319 412-5).
+const OID_QC_TYPE: ConstOid = Oid(&[4, 0, 142, 70, 1, 6]);
+
+/// id-etsi-qct-esign (ETSI EN 319 412-5).
+const OID_QC_TYPE_ESIGN: ConstOid = Oid(&[4, 0, 142, 70, 1, 6, 1]);
+
+/// id-etsi-qct-eseal (ETSI EN 319 412-5).
+const OID_QC_TYPE_ESEAL: ConstOid = Oid(&[4, 0, 142, 70, 1, 6, 2]);
+
+/// The bit of nonRepudiation, also called contentCommitment, in KeyUsage.
+const NON_REPUDIATION: usize = 1;
+
+/// A profile whose requirements on the CMS of a signature [check] evaluates.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+  /// The baseline levels of ETSI EN 319 122-1.
+  CadesBB,
+  CadesBT,
+  CadesBLt,
+  CadesBLta,
+  /// The levels of ETSI EN 319 142-1 whose requirements are all in the CMS.
+  PadesBB,
+  PadesBT,
+  /// The prerequisites of a qualified electronic signature under eIDAS that are in the CMS: an
+  /// AdES-B-B signature with a qualified certificate for electronic signatures whose key is in a
+  /// qualified signature creation device.
+  Qes,
+  /// The prerequisites of a qualified electronic signature under the Swiss ZertES that are in
+  /// the CMS: a timestamped signature, as the Code of Obligations requires for the equivalence
+  /// to a handwritten one, with a certificate qualified under Swiss law whose key is in a QSCD.
+  ZertEs,
+  /// The prerequisites of a qualified electronic seal under eIDAS that are in the CMS: an
+  /// AdES-B-B signature with a qualified certificate for electronic seals, of a legal person,
+  /// whose key is in a qualified signature creation device.
+  QSeal,
+}
+
+/// The names of the profiles [Profile::parse] accepts.
+pub const PROFILES: [&str; 9] = [
+  "CAdES-B-B",
+  "CAdES-B-T",
+  "CAdES-B-LT",
+  "CAdES-B-LTA",
+  "PAdES-B-B",
+  "PAdES-B-T",
+  "QES",
+  "ZertES",
+  "QSeal",
+];
+
+impl Profile {
+  /// Parse a profile name, such as `PAdES-B-T`. `option` is the name of the option the profile
+  /// was given for, to report errors against.
+  pub fn parse(option: &'static str, name: &str) -> Result<Self, CmsError> {
+    match name {
+      "CAdES-B-B" => Ok(Self::CadesBB),
+      "CAdES-B-T" => Ok(Self::CadesBT),
+      "CAdES-B-LT" => Ok(Self::CadesBLt),
+      "CAdES-B-LTA" => Ok(Self::CadesBLta),
+      "PAdES-B-B" => Ok(Self::PadesBB),
+      "PAdES-B-T" => Ok(Self::PadesBT),
+      "QES" => Ok(Self::Qes),
+      "ZertES" => Ok(Self::ZertEs),
+      "QSeal" => Ok(Self::QSeal),
+      // The validation data and document timestamps of these levels are in the PDF.
+      "PAdES-B-LT" | "PAdES-B-LTA" => Err(CmsError::InvalidOption(
+        option,
+        format!(
+          "{} is met by the DSS and document timestamps of the PDF, which isn't parsed; check \
+           PAdES-B-T for the signature",
+          name
+        ),
+      )),
+      _ => Err(CmsError::InvalidOption(
+        option,
+        format!("expected one of {}", PROFILES.join(", ")),
+      )),
+    }
+  }
+
+  fn level(self) -> u8 {
+    match self {
+      Self::CadesBB | Self::PadesBB | Self::Qes | Self::QSeal => 0,
+      Self::CadesBT | Self::PadesBT | Self::ZertEs => 1,
+      Self::CadesBLt => 2,
+      Self::CadesBLta => 3,
+    }
+  }
+}
+
+/// The elements the signer info at `index` is missing for a profile, empty if it meets it.
+///
+/// Only the presence of the elements is checked. Whether signatures, timestamps and certificate
+/// chains are valid is left to a validator.
+pub fn check(
+  signed_data: &SignedData,
+  index: usize,
+  profile: Profile,
+) -> Result<Vec<String>, CmsError> {
+  let signer_info = signed_data
+    .signer_infos
+    .get(index)
+    .ok_or_else(|| CmsError::SignerInfoNotFound.with_detail("index", index))?;
+
+  let signed_attributes = signer_info.signed_attribute_values()?;
+  let unsigned_attributes = signer_info.unsigned_attribute_values()?;
+  let signed = |oid: ConstOid| signed_attributes.iter().any(|(other, _)| *other == oid);
+  let unsigned = |oid: ConstOid| unsigned_attributes.iter().any(|(other, _)| *other == oid);
+
+  let mut missing = Vec::new();
+
+  if !signed(OID_CONTENT_TYPE) {
+    missing.push(String::from("content-type signed attribute"));
+  }
+
+  if !signed(OID_MESSAGE_DIGEST) {
+    missing.push(String::from("message-digest signed attribute"));
+  }
+
+  if !signed(attributes::OID_SIGNING_CERTIFICATE_V2) && !signed(OID_SIGNING_CERTIFICATE) {
+    missing.push(String::from("signing-certificate-v2 signed attribute"));
+  }
+
+  let certs = signed_data.x509_certificates()?;
+  let cert = signer_info.find_certificate(&certs)?;
+
+  if cert.is_none() {
+    missing.push(String::from("signing certificate in the certificates"));
+  }
+
+  match profile {
+    Profile::CadesBB | Profile::CadesBT | Profile::CadesBLt | Profile::CadesBLta => {
+      if !signed(OID_SIGNING_TIME) {
+        missing.push(String::from("signing-time signed attribute"));
+      }
+    }
+    Profile::PadesBB | Profile::PadesBT => {
+      // PAdES signatures are detached, and the signing time belongs in the signature dictionary.
+      if signed(OID_SIGNING_TIME) {
+        missing.push(String::from(
+          "absence of the signing-time signed attribute, which PAdES takes from /M",
+        ));
+      }
+
+      match signed_data.encapsulated_content()? {
+        (content_type, None) if content_type == OID_ID_DATA => {}
+        _ => missing.push(String::from("detached id-data content")),
+      }
+    }
+    Profile::Qes | Profile::ZertEs | Profile::QSeal => {
+      if let Some(cert) = &cert {
+        missing.extend(qualified_certificate_missing(cert, profile)?);
+      }
+    }
+  }
+
+  if profile.level() >= 1 && !unsigned(OID_TIME_STAMP_TOKEN) {
+    missing.push(String::from("signature-time-stamp unsigned attribute"));
+  }
+
+  if profile.level() >= 2 {
+    // The validation data may be in the attributes or in the certificates and crls of SignedData.
+    if !unsigned(attributes::OID_CERT_VALUES) && certs.len() < 2 {
+      missing.push(String::from(
+        "certificate-values unsigned attribute or CA certificates",
+      ));
+    }
+
+    if !unsigned(attributes::OID_REVOCATION_VALUES) && signed_data.crl_values()?.is_empty() {
+      missing.push(String::from(
+        "revocation-values unsigned attribute or revocation data",
+      ));
+    }
+  }
+
+  if profile.level() >= 3 && !unsigned(attributes::OID_ARCHIVE_TIME_STAMP_V3) {
+    missing.push(String::from("archive-time-stamp-v3 unsigned attribute"));
+  }
+
+  Ok(missing)
+}
+
+/// Whether a certificate is one for electronic seals, created by a legal person rather than a
+/// natural one, by the eseal QcType of its QCStatements.
+pub fn is_seal_certificate(cert: &CapturedX509Certificate) -> Result<bool, CmsError> {
+  let statements = qc_statements(cert)?;
+
+  Ok(
+    qc_types(&statements)?
+      .is_some_and(|types| types.iter().any(|qc_type| *qc_type == OID_QC_TYPE_ESEAL)),
+  )
+}
+
+/// What a signing certificate is missing to be a qualified certificate for electronic
+/// signatures, or seals for [Profile::QSeal], with its key in a QSCD, by its QCStatements and
+/// key usage. Certificates are qualified under eIDAS by QcCompliance and under ZertES by a
+/// QcCClegislation of CH.
+fn qualified_certificate_missing(
+  cert: &CapturedX509Certificate,
+  profile: Profile,
+) -> Result<Vec<String>, CmsError> {
+  let statements = qc_statements(cert)?;
+  let statement = |oid: ConstOid| statements.iter().find(|(id, _)| *id == oid);
+
+  let mut missing = Vec::new();
+
+  match profile {
+    Profile::ZertEs => {
+      let countries = statement(OID_QC_CC_LEGISLATION)
+        .map(|(_, info)| {
+          Mode::Der
+            .decode(info.as_slice(), |cons| {
+              cons.take_sequence(|cons| {
+                let mut countries = Vec::new();
+
+                while let Some(country) = PrintableString::take_opt_from(cons)? {
+                  countries.push(country.to_string());
+                }
+
+                Ok(countries)
+              })
+            })
+            .map_err(|error| CmsError::CertificateParseError.caused_by(error))
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+      if !countries.iter().any(|country| country == "CH") {
+        missing.push(String::from(
+          "QcCClegislation statement of CH of the certificate",
+        ));
+      }
+    }
+    _ => {
+      if statement(OID_QC_COMPLIANCE).is_none() {
+        missing.push(String::from("QcCompliance statement of the certificate"));
+      }
+    }
+  }
+
+  if statement(OID_QC_SSCD).is_none() {
+    missing.push(String::from("QcSSCD statement of the certificate"));
+  }
+
+  // Without a QcType, a qualified certificate is one for electronic signatures.
+  let types = qc_types(&statements)?;
+
+  match profile {
+    Profile::QSeal => {
+      if !types.is_some_and(|types| types.iter().any(|qc_type| *qc_type == OID_QC_TYPE_ESEAL)) {
+        missing.push(String::from("eseal QcType of the certificate"));
+      }
+    }
+    _ => {
+      if types.is_some_and(|types| !types.iter().any(|qc_type| *qc_type == OID_QC_TYPE_ESIGN)) {
+        missing.push(String::from("esign QcType of the certificate"));
+      }
+    }
+  }
+
+  let non_repudiation = extension(cert, OID_KEY_USAGE)
+    .map(|value| {
+      Mode::Der
+        .decode(value.as_ref(), BitString::take_from)
+        .map_err(|error| CmsError::CertificateParseError.caused_by(error))
+    })
+    .transpose()?
+    .is_some_and(|key_usage| key_usage.bit(NON_REPUDIATION));
+
+  if !non_repudiation {
+    missing.push(String::from("nonRepudiation key usage of the certificate"));
+  }
+
+  Ok(missing)
+}
+
+/// Helper function to find the value of an extension of a certificate.
+fn extension(cert: &CapturedX509Certificate, oid: ConstOid) -> Option<Bytes> {
+  cert
+    .iter_extensions()
+    .find(|extension| extension.id == oid)
+    .map(|extension| extension.value.to_bytes())
+}
+
+/// Helper function to parse the QCStatements of a certificate, empty if it has none.
+fn qc_statements(cert: &CapturedX509Certificate) -> Result<Vec<(Oid, Captured)>, CmsError> {
+  let Some(value) = extension(cert, OID_QC_STATEMENTS) else {
+    return Ok(Vec::new());
+  };
+
+  // QCStatements ::= SEQUENCE OF SEQUENCE { statementId OID, statementInfo ANY OPTIONAL }
+  Mode::Der
+    .decode(value.as_ref(), |cons| {
+      cons.take_sequence(|cons| {
+        let mut statements = Vec::new();
+
+        while let Some(statement) = cons.take_opt_sequence(|cons| {
+          let id = Oid::take_from(cons)?;
+          let info = cons.capture_all()?;
+          Ok((id, info))
+        })? {
+          statements.push(statement);
+        }
+
+        Ok(statements)
+      })
+    })
+    .map_err(|error| CmsError::CertificateParseError.caused_by(error))
+}
+
+/// Helper function to parse the QcType statement of QCStatements, if there is one.
+fn qc_types(statements: &[(Oid, Captured)]) -> Result<Option<Vec<Oid>>, CmsError> {
+  statements
+    .iter()
+    .find(|(id, _)| *id == OID_QC_TYPE)
+    .map(|(_, info)| {
+      Mode::Der
+        .decode(info.as_slice(), |cons| {
+          cons.take_sequence(|cons| {
+            let mut types = Vec::new();
+
+            while let Some(qc_type) = Oid::take_opt_from(cons)? {
+              types.push(qc_type);
+            }
+
+            Ok(types)
+          })
+        })
+        .map_err(|error| CmsError::CertificateParseError.caused_by(error))
+    })
+    .transpose()
+}
@@ -0,0 +1,104 @@
+use zeroize::Zeroizing;
+
+use crate::errors::CmsError;
+use crate::keys;
+
+/// The environment variable holding a base64 encoded P12 container, used when no P12 is given.
+pub const P12_BASE64_VAR: &str = "PDF_SIGN_P12_BASE64";
+
+/// The environment variable holding the password for the P12 container of [P12_BASE64_VAR].
+pub const P12_PASSWORD_VAR: &str = "PDF_SIGN_P12_PASSWORD";
+
+/// Read a secret from where a source points to, so it never passes through JavaScript or the
+/// command line:
+///
+/// - `env:NAME`, the value of an environment variable
+/// - `env-base64:NAME`, the base64 decoded value of an environment variable, for binary secrets
+///   such as P12 containers
+/// - `fd:N`, everything readable from an open file descriptor, e.g. a pipe or a mounted secret
+///
+/// `option` is the name of the option the source was given for, to report errors against.
+pub fn read(option: &'static str, source: &str) -> Result<Zeroizing<Vec<u8>>, CmsError> {
+  let invalid = |reason: String| CmsError::InvalidOption(option, reason);
+
+  match source.split_once(':') {
+    Some(("env", name)) => Ok(Zeroizing::new(var(option, name)?.as_bytes().to_vec())),
+    Some(("env-base64", name)) => decode(option, name, &var(option, name)?),
+    Some(("fd", fd)) => {
+      let fd = fd
+        .parse::<u32>()
+        .map_err(|_| invalid(format!("{} is not a file descriptor", fd)))?;
+
+      read_fd(fd).map(Zeroizing::new)
+    }
+    _ => Err(invalid(String::from(
+      "expected env:NAME, env-base64:NAME or fd:N",
+    ))),
+  }
+}
+
+/// Read a secret that is text, such as a password, from a source as for [read].
+pub fn read_string(option: &'static str, source: &str) -> Result<Zeroizing<String>, CmsError> {
+  let secret = read(option, source)?;
+
+  let secret = std::str::from_utf8(&secret)
+    .map_err(|_| CmsError::InvalidOption(option, String::from("not valid UTF-8")))?;
+
+  // Secrets read from files and pipes usually end with a newline.
+  Ok(Zeroizing::new(
+    secret.strip_suffix('\n').unwrap_or(secret).to_string(),
+  ))
+}
+
+/// The P12 container of the [P12_BASE64_VAR] environment variable, if it's set.
+pub fn p12_from_env() -> Result<Option<Zeroizing<Vec<u8>>>, CmsError> {
+  if std::env::var_os(P12_BASE64_VAR).is_none() {
+    return Ok(None);
+  }
+
+  let p12 = var(P12_BASE64_VAR, P12_BASE64_VAR)?;
+  decode(P12_BASE64_VAR, P12_BASE64_VAR, &p12).map(Some)
+}
+
+/// The password of the [P12_PASSWORD_VAR] environment variable, empty if it's not set.
+pub fn p12_password_from_env() -> Result<Zeroizing<String>, CmsError> {
+  match std::env::var_os(P12_PASSWORD_VAR) {
+    Some(_) => var(P12_PASSWORD_VAR, P12_PASSWORD_VAR),
+    None => Ok(Zeroizing::new(String::new())),
+  }
+}
+
+fn var(option: &'static str, name: &str) -> Result<Zeroizing<String>, CmsError> {
+  std::env::var(name).map(Zeroizing::new).map_err(|error| {
+    let reason = match error {
+      std::env::VarError::NotPresent => "is not set",
+      std::env::VarError::NotUnicode(_) => "is not valid UTF-8",
+    };
+
+    CmsError::InvalidOption(option, format!("environment variable {} {}", name, reason))
+  })
+}
+
+fn decode(option: &'static str, name: &str, value: &str) -> Result<Zeroizing<Vec<u8>>, CmsError> {
+  // Line breaks are allowed, as in the output of `base64` without `-w 0`.
+  keys::decode_base64(value).map_err(|error| {
+    CmsError::InvalidOption(option, format!("environment variable {}: {}", name, error))
+  })
+}
+
+#[cfg(unix)]
+fn read_fd(fd: u32) -> Result<Vec<u8>, CmsError> {
+  // Opening /dev/fd/N rather than taking over N leaves the descriptor open for its owner.
+  let path = format!("/dev/fd/{}", fd);
+
+  std::fs::read(&path).map_err(|error| {
+    CmsError::FileReadError
+      .caused_by(format!("{}: {}", path, error))
+      .with_detail("path", &path)
+  })
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: u32) -> Result<Vec<u8>, CmsError> {
+  Err(CmsError::FileReadError.caused_by("file descriptors are only supported on Unix"))
+}
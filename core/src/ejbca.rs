@@ -0,0 +1,163 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::blocking::Client;
+use reqwest::{Certificate, Identity};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde::{Deserialize, Serialize};
+use x509_certificate::{CapturedX509Certificate, InMemorySigningKeyPair, X509CertificateBuilder};
+use zeroize::Zeroizing;
+
+use crate::certificates::LazyCertificate;
+use crate::errors::CmsError;
+use crate::{http, keys};
+
+/// Path of the PKCS#10 enrollment endpoint relative to the EJBCA base URL.
+const PKCS10_ENROLL_PATH: &str = "/ejbca/ejbca-rest-api/v1/certificate/pkcs10enroll";
+
+#[derive(Serialize)]
+struct Pkcs10EnrollRequest<'a> {
+  certificate_request: &'a str,
+  certificate_profile_name: &'a str,
+  end_entity_profile_name: &'a str,
+  certificate_authority_name: &'a str,
+  username: &'a str,
+  password: &'a str,
+  include_chain: bool,
+  response_format: &'a str,
+}
+
+#[derive(Deserialize)]
+struct Pkcs10EnrollResponse {
+  certificate: String,
+  #[serde(default)]
+  certificate_chain: Vec<String>,
+}
+
+pub struct EjbcaEnrollment<'a> {
+  pub certificate_request: &'a str,
+  pub certificate_profile_name: &'a str,
+  pub end_entity_profile_name: &'a str,
+  pub certificate_authority_name: &'a str,
+  pub username: &'a str,
+  pub enrollment_code: &'a str,
+}
+
+/// Client for the EJBCA REST API, authenticated with a client certificate.
+pub struct EjbcaClient {
+  client: Client,
+  url: String,
+}
+
+impl EjbcaClient {
+  /// Create a client for the EJBCA instance at `url`.
+  ///
+  /// `certs` are the client certificate followed by its chain and `private_key` its unencrypted
+  /// PKCS#8 key, as read by [crate::input]. `ca_certs` trust in-house CAs for the server's TLS
+  /// certificate.
+  pub fn new(
+    url: String,
+    certs: &[LazyCertificate],
+    private_key: &[u8],
+    ca_certs: &[LazyCertificate],
+  ) -> Result<Self, CmsError> {
+    if certs.is_empty() {
+      return Err(CmsError::NoCertificate.with_detail("option", "clientCert"));
+    }
+
+    // reqwest reads the credential from a PEM bundle, whatever encoding it was given in.
+    let mut identity = keys::encode_private_key_pem(private_key);
+
+    for cert in certs {
+      identity.push_str(&cert.parsed()?.encode_pem());
+    }
+
+    let identity = Identity::from_pem(identity.as_bytes())
+      .map_err(|error| CmsError::ClientCertificateError.caused_by(error))?;
+
+    let mut builder = http::client_builder().identity(identity);
+
+    for ca_cert in ca_certs {
+      let ca_cert = Certificate::from_der(ca_cert.der())
+        .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+      builder = builder.add_root_certificate(ca_cert);
+    }
+
+    let client = builder
+      .build()
+      .map_err(|error| CmsError::ClientCertificateError.caused_by(error))?;
+
+    Ok(Self {
+      client,
+      url: url.trim_end_matches('/').to_string(),
+    })
+  }
+
+  /// Enroll a PKCS#10 certificate request, returning the issued certificate followed by its chain.
+  pub fn enroll(
+    &self,
+    enrollment: EjbcaEnrollment,
+  ) -> Result<Vec<CapturedX509Certificate>, CmsError> {
+    let request = Pkcs10EnrollRequest {
+      certificate_request: enrollment.certificate_request,
+      certificate_profile_name: enrollment.certificate_profile_name,
+      end_entity_profile_name: enrollment.end_entity_profile_name,
+      certificate_authority_name: enrollment.certificate_authority_name,
+      username: enrollment.username,
+      password: enrollment.enrollment_code,
+      include_chain: true,
+      response_format: "DER",
+    };
+
+    tracing::debug!(url = self.url, "Enrolling certificate with EJBCA");
+
+    let response = self
+      .client
+      .post(format!("{}{}", self.url, PKCS10_ENROLL_PATH))
+      .json(&request)
+      .send()
+      .and_then(|response| response.error_for_status())
+      .map_err(|error| CmsError::EnrollmentError.caused_by(error))?
+      .json::<Pkcs10EnrollResponse>()
+      .map_err(|error| CmsError::EnrollmentError.caused_by(error))?;
+
+    std::iter::once(&response.certificate)
+      .chain(response.certificate_chain.iter())
+      .map(|cert| {
+        let der = STANDARD
+          .decode(cert)
+          .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+        CapturedX509Certificate::from_der(der)
+          .map_err(|error| CmsError::CertificateParseError.caused_by(error))
+      })
+      .collect()
+  }
+}
+
+/// Generate a P-256 private key for enrollment, returned as PKCS#8 DER.
+pub fn generate_private_key() -> Result<Zeroizing<Vec<u8>>, CmsError> {
+  let document =
+    EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &SystemRandom::new())
+      .map_err(|error| CmsError::KeyGenerationError.caused_by(error))?;
+
+  Ok(Zeroizing::new(document.as_ref().to_vec()))
+}
+
+/// Create a PEM encoded PKCS#10 certificate request for the key.
+pub fn certificate_request(
+  private_key: &InMemorySigningKeyPair,
+  common_name: &str,
+) -> Result<String, CmsError> {
+  let mut builder = X509CertificateBuilder::default();
+
+  builder
+    .subject()
+    .append_common_name_utf8_string(common_name)
+    .map_err(|_| CmsError::CertificateRequestError.caused_by("invalid common name"))?;
+
+  builder
+    .create_certificate_signing_request(private_key)
+    .and_then(|request| Ok(request.encode_pem()?))
+    .map_err(|error| CmsError::CertificateRequestError.caused_by(error))
+}
@@ -0,0 +1,272 @@
+use std::fmt;
+
+#[derive(Clone, Debug)]
+pub enum CmsError {
+  CertificateParseError,
+  PrivateKeyParseError,
+  WrongPrivateKeyPassword,
+  UnsupportedPrivateKeyEncryption,
+  P12ParseError,
+  WrongP12Password,
+  UnsupportedP12Encryption,
+  PrivateKeyBagError,
+  NoPrivateKey,
+  CertBagError,
+  NoCertificate,
+  P12EntryNotFound,
+  TimestampServerParseError,
+  BuildSignedDataError,
+  DigestError,
+  PublicKeyError,
+  UnsupportedKeyAlgorithm,
+  SigningError,
+  CertificateKeyMismatch,
+  NotSealCertificate,
+  ClientCertificateError,
+  KeyGenerationError,
+  CertificateRequestError,
+  EnrollmentError,
+  UnknownSigner,
+  MissingSignerOption,
+  SignerRegistrationError,
+  EmptySignerPool,
+  UnknownSignerPool,
+  UnknownKeyHandle,
+  UnknownCommitmentType,
+  InvalidSignerLocation,
+  InvalidContentHints,
+  InvalidCustomAttribute,
+  SignedDataParseError,
+  VerificationError,
+  SignerInfoNotFound,
+  TimestampError,
+  RevocationInfoError,
+  UnsupportedDigestAlgorithm,
+  InvalidContent,
+  InvalidDigest,
+  NoSigners,
+  /// Signed data larger than the space reserved for it, with its size and the reserved size in
+  /// bytes.
+  SignatureTooLarge(usize, usize),
+  InvalidCmsVersion,
+  InvalidRevocationValues,
+  InvalidSmimeCapabilities,
+  ImageParseError,
+  FileReadError,
+  FileWriteError,
+  InvalidLogLevel,
+  GCloudClientError,
+  /// A Google Cloud KMS request that failed, with the key path and the gRPC status code, e.g.
+  /// `PERMISSION_DENIED`. The status message is the cause.
+  GCloudRequestError(String, &'static str),
+  RuntimeError,
+  /// An option that is missing something or has the wrong format, with the reason.
+  InvalidOption(&'static str, String),
+  /// An error along with the message of the underlying error that caused it.
+  WithCause(Box<CmsError>, String),
+  /// An error along with what it concerns, such as the option, URL or certificate subject, as a
+  /// name in the camel case of the JavaScript options and its value.
+  WithDetail(Box<CmsError>, &'static str, String),
+}
+
+impl CmsError {
+  /// A stable code for the error, which callers can branch on rather than the message.
+  pub fn code(&self) -> &'static str {
+    match self {
+      CmsError::CertificateParseError => "ERR_CERTIFICATE_PARSE",
+      CmsError::PrivateKeyParseError => "ERR_PRIVATE_KEY_PARSE",
+      CmsError::WrongPrivateKeyPassword => "ERR_PRIVATE_KEY_WRONG_PASSWORD",
+      CmsError::UnsupportedPrivateKeyEncryption => "ERR_PRIVATE_KEY_UNSUPPORTED_ENCRYPTION",
+      CmsError::P12ParseError => "ERR_P12_PARSE",
+      CmsError::WrongP12Password => "ERR_P12_WRONG_PASSWORD",
+      CmsError::UnsupportedP12Encryption => "ERR_P12_UNSUPPORTED_ENCRYPTION",
+      CmsError::PrivateKeyBagError => "ERR_P12_PRIVATE_KEY_BAGS",
+      CmsError::NoPrivateKey => "ERR_P12_NO_PRIVATE_KEY",
+      CmsError::CertBagError => "ERR_P12_CERT_BAGS",
+      CmsError::NoCertificate => "ERR_NO_CERTIFICATE",
+      CmsError::P12EntryNotFound => "ERR_P12_ENTRY_NOT_FOUND",
+      CmsError::TimestampServerParseError => "ERR_TSA_URL_INVALID",
+      CmsError::BuildSignedDataError => "ERR_BUILD_SIGNED_DATA",
+      CmsError::DigestError => "ERR_DIGEST",
+      CmsError::PublicKeyError => "ERR_PUBLIC_KEY",
+      CmsError::UnsupportedKeyAlgorithm => "ERR_UNSUPPORTED_KEY_ALGORITHM",
+      CmsError::SigningError => "ERR_SIGNING",
+      CmsError::CertificateKeyMismatch => "ERR_CERTIFICATE_KEY_MISMATCH",
+      CmsError::NotSealCertificate => "ERR_NOT_SEAL_CERTIFICATE",
+      CmsError::ClientCertificateError => "ERR_CLIENT_CERTIFICATE",
+      CmsError::KeyGenerationError => "ERR_KEY_GENERATION",
+      CmsError::CertificateRequestError => "ERR_CERTIFICATE_REQUEST",
+      CmsError::EnrollmentError => "ERR_ENROLLMENT",
+      CmsError::UnknownSigner => "ERR_UNKNOWN_SIGNER",
+      CmsError::MissingSignerOption => "ERR_MISSING_SIGNER_OPTION",
+      CmsError::SignerRegistrationError => "ERR_SIGNER_REGISTRATION",
+      CmsError::EmptySignerPool => "ERR_EMPTY_SIGNER_POOL",
+      CmsError::UnknownSignerPool => "ERR_UNKNOWN_SIGNER_POOL",
+      CmsError::UnknownKeyHandle => "ERR_UNKNOWN_KEY_HANDLE",
+      CmsError::UnknownCommitmentType => "ERR_UNKNOWN_COMMITMENT_TYPE",
+      CmsError::InvalidSignerLocation => "ERR_INVALID_SIGNER_LOCATION",
+      CmsError::InvalidContentHints => "ERR_INVALID_CONTENT_HINTS",
+      CmsError::InvalidCustomAttribute => "ERR_INVALID_CUSTOM_ATTRIBUTE",
+      CmsError::SignedDataParseError => "ERR_SIGNED_DATA_PARSE",
+      CmsError::VerificationError => "ERR_VERIFICATION",
+      CmsError::SignerInfoNotFound => "ERR_SIGNER_INFO_NOT_FOUND",
+      CmsError::TimestampError => "ERR_TIMESTAMP",
+      CmsError::RevocationInfoError => "ERR_REVOCATION_INFO",
+      CmsError::UnsupportedDigestAlgorithm => "ERR_UNSUPPORTED_DIGEST_ALGORITHM",
+      CmsError::InvalidContent => "ERR_INVALID_CONTENT",
+      CmsError::InvalidDigest => "ERR_INVALID_DIGEST",
+      CmsError::NoSigners => "ERR_NO_SIGNERS",
+      CmsError::SignatureTooLarge(..) => "ERR_SIGNATURE_TOO_LARGE",
+      CmsError::InvalidCmsVersion => "ERR_INVALID_CMS_VERSION",
+      CmsError::InvalidRevocationValues => "ERR_INVALID_REVOCATION_VALUES",
+      CmsError::InvalidSmimeCapabilities => "ERR_INVALID_SMIME_CAPABILITIES",
+      CmsError::ImageParseError => "ERR_IMAGE_PARSE",
+      CmsError::FileReadError => "ERR_FILE_READ",
+      CmsError::FileWriteError => "ERR_FILE_WRITE",
+      CmsError::InvalidLogLevel => "ERR_INVALID_LOG_LEVEL",
+      CmsError::GCloudClientError => "ERR_GCLOUD_CLIENT",
+      CmsError::GCloudRequestError(..) => "ERR_GCLOUD_REQUEST",
+      CmsError::RuntimeError => "ERR_RUNTIME",
+      CmsError::InvalidOption(..) => "ERR_INVALID_OPTION",
+      CmsError::WithCause(error, _) | CmsError::WithDetail(error, ..) => error.code(),
+    }
+  }
+
+  /// Attach the underlying error, whose message is otherwise lost when mapping it to this one.
+  pub fn caused_by(self, cause: impl fmt::Display) -> Self {
+    CmsError::WithCause(Box::new(self), cause.to_string())
+  }
+
+  /// Attach what the error concerns, e.g. `("url", url)` for a timestamp server that failed.
+  pub fn with_detail(self, name: &'static str, value: impl fmt::Display) -> Self {
+    CmsError::WithDetail(Box::new(self), name, value.to_string())
+  }
+
+  /// A [CmsError::SigningError] caused by the error of a signer, which is the source of the
+  /// signature error as that only reads "signature error". Errors of the signers of this crate,
+  /// such as a rejected Google Cloud KMS request, are returned as they are.
+  pub fn signing_failed(error: signature::Error) -> Self {
+    match std::error::Error::source(&error) {
+      Some(source) => match source.downcast_ref::<CmsError>() {
+        Some(error) => error.clone(),
+        None => CmsError::SigningError.caused_by(source),
+      },
+      None => CmsError::SigningError.caused_by(error),
+    }
+  }
+
+  /// What the error concerns, as names and values, with those attached last first.
+  pub fn details(&self) -> Vec<(&'static str, String)> {
+    match self {
+      CmsError::InvalidOption(option, _) => vec![("option", option.to_string())],
+      CmsError::GCloudRequestError(key_path, status) => {
+        vec![
+          ("keyPath", key_path.clone()),
+          ("grpcCode", status.to_string()),
+        ]
+      }
+      CmsError::SignatureTooLarge(size, max_size) => {
+        vec![
+          ("size", size.to_string()),
+          ("maxSize", max_size.to_string()),
+        ]
+      }
+      CmsError::WithCause(error, _) => error.details(),
+      CmsError::WithDetail(error, name, value) => {
+        let mut details = vec![(*name, value.clone())];
+        details.extend(error.details());
+        details
+      }
+      _ => Vec::new(),
+    }
+  }
+
+  /// The message of the underlying error, if known.
+  pub fn cause(&self) -> Option<&str> {
+    match self {
+      CmsError::WithCause(_, cause) => Some(cause),
+      CmsError::WithDetail(error, ..) => error.cause(),
+      _ => None,
+    }
+  }
+}
+
+impl std::error::Error for CmsError {}
+
+impl fmt::Display for CmsError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CmsError::CertificateParseError => write!(f, "Failed to parse certificate"),
+      CmsError::PrivateKeyParseError => write!(f, "Failed to parse private key"),
+      CmsError::WrongPrivateKeyPassword => write!(f, "Wrong private key password"),
+      CmsError::UnsupportedPrivateKeyEncryption => {
+        write!(f, "Unsupported private key encryption")
+      }
+      CmsError::P12ParseError => write!(f, "Failed to parse p12"),
+      CmsError::WrongP12Password => write!(f, "Wrong p12 password"),
+      CmsError::UnsupportedP12Encryption => write!(f, "Unsupported p12 encryption"),
+      CmsError::PrivateKeyBagError => write!(f, "Failed to get private key bags"),
+      CmsError::NoPrivateKey => write!(f, "No private key bags"),
+      CmsError::CertBagError => write!(f, "Failed to get cert bags"),
+      CmsError::NoCertificate => write!(f, "No cert bags"),
+      CmsError::P12EntryNotFound => write!(f, "No P12 entry matches"),
+      CmsError::TimestampServerParseError => write!(f, "Failed to parse timestamp server"),
+      CmsError::BuildSignedDataError => write!(f, "Failed to build signed data"),
+      CmsError::DigestError => write!(f, "Failed to get digest"),
+      CmsError::PublicKeyError => write!(f, "Failed to get public key"),
+      CmsError::UnsupportedKeyAlgorithm => write!(f, "Unsupported key algorithm"),
+      CmsError::SigningError => write!(f, "Failed to sign digest"),
+      CmsError::CertificateKeyMismatch => write!(f, "Certificate does not match signing key"),
+      CmsError::NotSealCertificate => {
+        write!(f, "Certificate is not a certificate for electronic seals")
+      }
+      CmsError::ClientCertificateError => write!(f, "Failed to load client certificate"),
+      CmsError::KeyGenerationError => write!(f, "Failed to generate private key"),
+      CmsError::CertificateRequestError => write!(f, "Failed to create certificate request"),
+      CmsError::EnrollmentError => write!(f, "Failed to enroll certificate"),
+      CmsError::UnknownSigner => write!(f, "Unknown signer"),
+      CmsError::MissingSignerOption => write!(f, "Missing signer option"),
+      CmsError::SignerRegistrationError => write!(f, "Failed to register signer"),
+      CmsError::EmptySignerPool => write!(f, "Signer pool has no members"),
+      CmsError::UnknownSignerPool => write!(f, "Unknown signer pool"),
+      CmsError::UnknownKeyHandle => write!(f, "Unknown key handle"),
+      CmsError::UnknownCommitmentType => write!(f, "Unknown commitment type"),
+      CmsError::InvalidSignerLocation => write!(f, "Invalid signer location"),
+      CmsError::InvalidContentHints => write!(f, "Invalid content hints"),
+      CmsError::InvalidCustomAttribute => write!(f, "Invalid custom attribute"),
+      CmsError::SignedDataParseError => write!(f, "Failed to parse signed data"),
+      CmsError::VerificationError => write!(f, "Failed to verify signature"),
+      CmsError::SignerInfoNotFound => write!(f, "Signer info not found"),
+      CmsError::TimestampError => write!(f, "Failed to get timestamp"),
+      CmsError::RevocationInfoError => write!(f, "Failed to get revocation info"),
+      CmsError::UnsupportedDigestAlgorithm => write!(
+        f,
+        "Unsupported digest algorithm, expected sha256, sha384 or sha512"
+      ),
+      CmsError::InvalidContent => write!(f, "Either content or digest must be given"),
+      CmsError::InvalidDigest => write!(f, "Digest does not match the digest algorithm"),
+      CmsError::NoSigners => write!(f, "No signers"),
+      CmsError::SignatureTooLarge(size, max_size) => write!(
+        f,
+        "Signed data of {} bytes exceeds the {} bytes reserved for it",
+        size, max_size
+      ),
+      CmsError::InvalidCmsVersion => write!(f, "Invalid CMS version"),
+      CmsError::InvalidRevocationValues => write!(f, "Invalid revocation values"),
+      CmsError::InvalidSmimeCapabilities => write!(f, "Invalid S/MIME capabilities"),
+      CmsError::ImageParseError => write!(f, "Failed to parse image"),
+      CmsError::FileReadError => write!(f, "Failed to read file"),
+      CmsError::FileWriteError => write!(f, "Failed to write file"),
+      CmsError::InvalidLogLevel => write!(f, "Invalid log level"),
+      CmsError::GCloudClientError => write!(f, "Failed to create Google Cloud client"),
+      CmsError::GCloudRequestError(key_path, status) => write!(
+        f,
+        "Google Cloud KMS request for {} failed with {}",
+        key_path, status
+      ),
+      CmsError::RuntimeError => write!(f, "Failed to run async runtime"),
+      CmsError::InvalidOption(option, reason) => write!(f, "Invalid {} option: {}", option, reason),
+      CmsError::WithCause(error, _) | CmsError::WithDetail(error, ..) => error.fmt(f),
+    }
+  }
+}
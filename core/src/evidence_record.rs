@@ -0,0 +1,108 @@
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{Captured, Mode, OctetString, Tag};
+use x509_certificate::rfc5280::AlgorithmIdentifier;
+use x509_certificate::DigestAlgorithm;
+
+use crate::errors::CmsError;
+use crate::signing;
+
+/// Create an Evidence Record (RFC 4998) of a data object group, such as a signed PDF and the
+/// certificates and revocation data to validate it, with an archive timestamp over all of them.
+///
+/// The group is protected as a whole: the reduced hash tree lists the hashes of all its data
+/// objects, whose binary sorted concatenation is hashed for the timestamp. A single data object
+/// is timestamped by its own hash, without a hash tree.
+pub fn create(
+  data: &[&[u8]],
+  url: &reqwest::Url,
+  digest_algorithm: DigestAlgorithm,
+) -> Result<Vec<u8>, CmsError> {
+  let hash_tree = HashTree::new(data, digest_algorithm)?;
+
+  let token = signing::time_stamp_token_for_digest(url, &hash_tree.root, digest_algorithm)?;
+
+  tracing::debug!(data_objects = data.len(), "Created evidence record");
+
+  Ok(hash_tree.evidence_record(&token))
+}
+
+/// The reduced hash tree of a data object group, whose root is timestamped.
+pub struct HashTree {
+  digest_algorithm: DigestAlgorithm,
+  /// The hashes of the data objects, in binary order.
+  pub hashes: Vec<Vec<u8>>,
+  /// The hash of the concatenated hashes, or of the data object itself if there is only one.
+  pub root: Vec<u8>,
+}
+
+impl HashTree {
+  /// Hash the data objects of a group, of which there must be at least one.
+  pub fn new(data: &[&[u8]], digest_algorithm: DigestAlgorithm) -> Result<Self, CmsError> {
+    if data.is_empty() {
+      return Err(CmsError::InvalidOption(
+        "data",
+        String::from("expected at least one data object"),
+      ));
+    }
+
+    let mut hashes = data
+      .iter()
+      .map(|data| digest_algorithm.digest_data(data))
+      .collect::<Vec<_>>();
+    hashes.sort();
+
+    let root = match hashes.as_slice() {
+      [hash] => hash.clone(),
+      _ => digest_algorithm.digest_data(&hashes.concat()),
+    };
+
+    Ok(Self {
+      digest_algorithm,
+      hashes,
+      root,
+    })
+  }
+
+  /// Encode the evidence record with the timestamp token of the root, a ContentInfo containing
+  /// SignedData.
+  pub fn evidence_record(&self, token: &Captured) -> Vec<u8> {
+    // ArchiveTimeStamp ::= SEQUENCE {
+    //   digestAlgorithm [0] AlgorithmIdentifier OPTIONAL,
+    //   attributes [1] Attributes OPTIONAL,
+    //   reducedHashtree [2] SEQUENCE OF PartialHashtree OPTIONAL,
+    //   timeStamp ContentInfo }
+    //
+    // PartialHashtree ::= SEQUENCE OF OCTET STRING
+    let reduced_hash_tree = (self.hashes.len() > 1).then(|| {
+      encode::sequence_as(
+        Tag::CTX_2,
+        encode::sequence(
+          self
+            .hashes
+            .iter()
+            .map(|hash| OctetString::encode_slice(hash.as_slice()))
+            .collect::<Vec<_>>(),
+        ),
+      )
+    });
+
+    let archive_time_stamp = encode::sequence((reduced_hash_tree, token));
+
+    // EvidenceRecord ::= SEQUENCE {
+    //   version INTEGER { v1(1) },
+    //   digestAlgorithms SEQUENCE OF AlgorithmIdentifier,
+    //   cryptoInfos [0] CryptoInfos OPTIONAL,
+    //   encryptionInfo [1] EncryptionInfo OPTIONAL,
+    //   archiveTimeStampSequence ArchiveTimeStampSequence }
+    //
+    // ArchiveTimeStampSequence ::= SEQUENCE OF ArchiveTimeStampChain
+    // ArchiveTimeStampChain ::= SEQUENCE OF ArchiveTimeStamp
+    let evidence_record = encode::sequence((
+      1u8.encode(),
+      encode::sequence(AlgorithmIdentifier::from(self.digest_algorithm)),
+      encode::sequence(encode::sequence(archive_time_stamp)),
+    ));
+
+    evidence_record.to_captured(Mode::Der).into_bytes().to_vec()
+  }
+}
@@ -0,0 +1,193 @@
+use cryptographic_message_syntax::Bytes;
+use gcloud_sdk::{
+  google::cloud::kms::{
+    self,
+    v1::{
+      crypto_key_version::CryptoKeyVersionAlgorithm,
+      key_management_service_client::KeyManagementServiceClient, AsymmetricSignRequest,
+      GetPublicKeyRequest,
+    },
+  },
+  GoogleApi, GoogleAuthMiddleware,
+};
+use rsa::pkcs8::der::zeroize::Zeroizing;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::{Code, Request, Status};
+use x509_certificate::{
+  algorithm, KeyInfoSigner, Sign, Signature, SignatureAlgorithm, Signer, X509CertificateError,
+};
+
+use crate::errors::CmsError;
+use crate::runtime;
+
+/// Signer for a Google Cloud KMS key version.
+///
+/// The signer is `Send` and `Sync`, so one signer can serve concurrent signing operations from
+/// several threads, e.g. the libuv thread pool or Node.js worker threads, which share its gRPC
+/// channel. All signers share one runtime.
+pub struct GCloudSigner {
+  client: GoogleApi<KeyManagementServiceClient<GoogleAuthMiddleware>>,
+  key_path: String,
+  /// The `x-goog-request-params` routing header for the key.
+  request_params: AsciiMetadataValue,
+}
+
+impl GCloudSigner {
+  /// Create a signer for a key version, failing if no Google Cloud credentials are found.
+  pub fn new(key_path: String) -> Result<Self, CmsError> {
+    let request_params = AsciiMetadataValue::try_from(format!("name={}", key_path))
+      .map_err(|_| CmsError::InvalidOption("keyPath", String::from("not a valid key path")))?;
+
+    let client = runtime::block_on(GoogleApi::from_function(
+      KeyManagementServiceClient::new,
+      "https://cloudkms.googleapis.com",
+      None,
+    ))?
+    .map_err(|error| CmsError::GCloudClientError.caused_by(error))?;
+
+    Ok(Self {
+      client,
+      key_path,
+      request_params,
+    })
+  }
+
+  /// Fetch the algorithm of the configured key version.
+  ///
+  /// This requires the `cloudkms.cryptoKeyVersions.viewPublicKey` permission.
+  pub fn algorithm(&self) -> Result<CryptoKeyVersionAlgorithm, CmsError> {
+    let request = self.request(GetPublicKeyRequest {
+      name: self.key_path.clone(),
+    });
+
+    tracing::debug!(
+      key_path = self.key_path,
+      "Fetching Google Cloud KMS public key"
+    );
+
+    let result = runtime::block_on(self.client.get().get_public_key(request))?
+      .map_err(|status| self.request_error(status))?;
+
+    Ok(
+      CryptoKeyVersionAlgorithm::try_from(result.into_inner().algorithm)
+        .unwrap_or(CryptoKeyVersionAlgorithm::Unspecified),
+    )
+  }
+
+  /// Whether signatures from the given key algorithm match what we declare to the CMS builder.
+  pub fn supports_algorithm(algorithm: CryptoKeyVersionAlgorithm) -> bool {
+    matches!(
+      algorithm,
+      CryptoKeyVersionAlgorithm::RsaSignPkcs12048Sha256
+        | CryptoKeyVersionAlgorithm::RsaSignPkcs13072Sha256
+        | CryptoKeyVersionAlgorithm::RsaSignPkcs14096Sha256
+    )
+  }
+
+  /// The error for a request Cloud KMS rejected, e.g. for a missing IAM permission or a disabled
+  /// key version.
+  fn request_error(&self, status: Status) -> CmsError {
+    tracing::warn!(
+      key_path = self.key_path,
+      code = grpc_code(status.code()),
+      message = status.message(),
+      "Google Cloud KMS request failed"
+    );
+
+    CmsError::GCloudRequestError(self.key_path.clone(), grpc_code(status.code()))
+      .caused_by(status.message())
+  }
+
+  /// Wrap a message in a request carrying the routing header for the key.
+  fn request<T>(&self, message: T) -> Request<T> {
+    let mut request = Request::new(message);
+
+    request
+      .metadata_mut()
+      .insert("x-goog-request-params", self.request_params.clone());
+
+    request
+  }
+}
+
+impl KeyInfoSigner for GCloudSigner {}
+
+impl Sign for GCloudSigner {
+  fn sign(&self, message: &[u8]) -> Result<(Vec<u8>, SignatureAlgorithm), X509CertificateError> {
+    let signature = self.try_sign(message)?;
+    let algorithm = self.signature_algorithm()?;
+
+    Ok((signature.into(), algorithm))
+  }
+
+  fn key_algorithm(&self) -> Option<x509_certificate::KeyAlgorithm> {
+    Some(algorithm::KeyAlgorithm::Rsa)
+  }
+
+  fn signature_algorithm(&self) -> Result<SignatureAlgorithm, X509CertificateError> {
+    Ok(SignatureAlgorithm::RsaSha256)
+  }
+
+  fn private_key_data(&self) -> Option<Zeroizing<Vec<u8>>> {
+    None
+  }
+
+  fn public_key_data(&self) -> Bytes {
+    Bytes::new()
+  }
+
+  fn rsa_primes(
+    &self,
+  ) -> Result<Option<(Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>)>, X509CertificateError> {
+    Ok(None)
+  }
+}
+
+impl Signer<Signature> for GCloudSigner {
+  fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+    let digest = ring::digest::digest(&ring::digest::SHA256, msg);
+
+    let request = AsymmetricSignRequest {
+      name: self.key_path.clone(),
+      digest: Some(kms::v1::Digest {
+        digest: Some(kms::v1::digest::Digest::Sha256(digest.as_ref().to_vec())),
+      }),
+      ..Default::default()
+    };
+
+    let request = self.request(request);
+
+    tracing::debug!(key_path = self.key_path, "Signing with Google Cloud KMS");
+
+    let result = runtime::block_on(self.client.get().asymmetric_sign(request))
+      .and_then(|result| result.map_err(|status| self.request_error(status)))
+      .map_err(signature::Error::from_source)?;
+
+    let signature = result.into_inner().signature;
+
+    Ok(Signature::from(signature))
+  }
+}
+
+/// The canonical name of a gRPC status code, as Google Cloud documents it.
+fn grpc_code(code: Code) -> &'static str {
+  match code {
+    Code::Ok => "OK",
+    Code::Cancelled => "CANCELLED",
+    Code::Unknown => "UNKNOWN",
+    Code::InvalidArgument => "INVALID_ARGUMENT",
+    Code::DeadlineExceeded => "DEADLINE_EXCEEDED",
+    Code::NotFound => "NOT_FOUND",
+    Code::AlreadyExists => "ALREADY_EXISTS",
+    Code::PermissionDenied => "PERMISSION_DENIED",
+    Code::ResourceExhausted => "RESOURCE_EXHAUSTED",
+    Code::FailedPrecondition => "FAILED_PRECONDITION",
+    Code::Aborted => "ABORTED",
+    Code::OutOfRange => "OUT_OF_RANGE",
+    Code::Unimplemented => "UNIMPLEMENTED",
+    Code::Internal => "INTERNAL",
+    Code::Unavailable => "UNAVAILABLE",
+    Code::DataLoss => "DATA_LOSS",
+    Code::Unauthenticated => "UNAUTHENTICATED",
+  }
+}
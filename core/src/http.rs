@@ -0,0 +1,65 @@
+use std::sync::RwLock;
+
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::Proxy;
+
+use crate::errors::CmsError;
+
+/// Proxy for the requests to timestamp servers and EJBCA, set with [set_proxy].
+static PROXY: RwLock<Option<Proxy>> = RwLock::new(None);
+
+/// The client shared by all requests to timestamp servers, created by [client].
+static CLIENT: RwLock<Option<Client>> = RwLock::new(None);
+
+/// Send HTTP requests through a proxy, e.g. `http://proxy.internal:3128`.
+///
+/// Without one, the proxy set by the `HTTP_PROXY` and `HTTPS_PROXY` environment variables is
+/// used, if any.
+pub fn set_proxy(url: Option<&str>) -> Result<(), CmsError> {
+  let proxy = url
+    .map(Proxy::all)
+    .transpose()
+    .map_err(|error| CmsError::InvalidOption("proxy", error.to_string()))?;
+
+  *PROXY.write().unwrap_or_else(|error| error.into_inner()) = proxy;
+
+  // The shared client still uses the previous proxy, so a new one is created on the next request.
+  *CLIENT.write().unwrap_or_else(|error| error.into_inner()) = None;
+
+  Ok(())
+}
+
+/// A client builder with the proxy set by [set_proxy].
+pub fn client_builder() -> ClientBuilder {
+  let builder = Client::builder();
+
+  match PROXY
+    .read()
+    .unwrap_or_else(|error| error.into_inner())
+    .clone()
+  {
+    Some(proxy) => builder.proxy(proxy),
+    None => builder,
+  }
+}
+
+/// The client shared by all requests to timestamp servers, with the proxy set by [set_proxy].
+///
+/// Its connections are kept open and reused, so signing many documents in a row doesn't make a
+/// new TLS handshake with the same server each time.
+pub fn client() -> Result<Client, reqwest::Error> {
+  if let Some(client) = CLIENT
+    .read()
+    .unwrap_or_else(|error| error.into_inner())
+    .as_ref()
+  {
+    return Ok(client.clone());
+  }
+
+  let mut shared = CLIENT.write().unwrap_or_else(|error| error.into_inner());
+
+  match shared.as_ref() {
+    Some(client) => Ok(client.clone()),
+    None => Ok(shared.insert(client_builder().build()?).clone()),
+  }
+}
@@ -0,0 +1,100 @@
+use x509_certificate::{CapturedX509Certificate, InMemorySigningKeyPair};
+use zeroize::Zeroizing;
+
+use crate::certificates::{self, LazyCertificate};
+use crate::errors::CmsError;
+use crate::{keys, validation};
+
+/// The encoding of the certificates and keys given to an option.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputFormat {
+  /// PEM or DER, whichever the data is in.
+  #[default]
+  Auto,
+  Pem,
+  /// DER, such as certificates exported from Windows or returned by KMS APIs.
+  Der,
+}
+
+impl InputFormat {
+  /// Parse a format, `pem` or `der`, which callers default to [InputFormat::Auto]. `option` is
+  /// the name of the option the format was given for, to report errors against.
+  pub fn parse(option: &'static str, name: &str) -> Result<Self, CmsError> {
+    match name {
+      "pem" => Ok(Self::Pem),
+      "der" => Ok(Self::Der),
+      _ => Err(CmsError::InvalidOption(
+        option,
+        String::from("expected pem or der"),
+      )),
+    }
+  }
+
+  /// The format data is in, which is DER if it looks like DER for [InputFormat::Auto].
+  fn of(self, value: &[u8]) -> Self {
+    match self {
+      Self::Auto if validation::is_der(value) => Self::Der,
+      Self::Auto => Self::Pem,
+      format => format,
+    }
+  }
+}
+
+/// Read the certificates given to an option, PEM certificates or one or more concatenated DER
+/// certificates, parsing only the first, which is the signing certificate of the signing
+/// functions.
+///
+/// Check the buffer with [validation::encoded] first, for errors that hint at the mistake.
+pub fn certificates(
+  option: &'static str,
+  value: &[u8],
+  format: InputFormat,
+) -> Result<Vec<LazyCertificate>, CmsError> {
+  match format.of(value) {
+    InputFormat::Der => certificates::from_der(option, value),
+    InputFormat::Pem | InputFormat::Auto => certificates::from_pem(option, value),
+  }
+}
+
+/// Read and parse all the certificates given to an option, such as trusted roots.
+pub fn parsed_certificates(
+  option: &'static str,
+  value: &[u8],
+  format: InputFormat,
+) -> Result<Vec<CapturedX509Certificate>, CmsError> {
+  certificates(option, value, format)?
+    .iter()
+    .map(|cert| cert.parsed().cloned())
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|error| error.with_detail("option", option))
+}
+
+/// Read the PKCS#8 private key given to an option, in PEM or DER, decrypting it with `password`
+/// if it's encrypted.
+pub fn private_key(
+  option: &'static str,
+  value: &[u8],
+  password: Option<&str>,
+  format: InputFormat,
+) -> Result<InMemorySigningKeyPair, CmsError> {
+  match format.of(value) {
+    InputFormat::Der => keys::private_key_from_der_with_password(option, value, password),
+    InputFormat::Pem | InputFormat::Auto => {
+      keys::private_key_from_pem_with_password(option, value, password)
+    }
+  }
+}
+
+/// Read the private key given to an option like [private_key], as the DER of an unencrypted
+/// PKCS#8 PrivateKeyInfo, which is wiped from memory when dropped. The key isn't parsed.
+pub fn private_key_der(
+  option: &'static str,
+  value: &[u8],
+  password: Option<&str>,
+  format: InputFormat,
+) -> Result<Zeroizing<Vec<u8>>, CmsError> {
+  match format.of(value) {
+    InputFormat::Der => keys::private_key_der_from_der(option, value, password),
+    InputFormat::Pem | InputFormat::Auto => keys::private_key_der_from_pem(option, value, password),
+  }
+}
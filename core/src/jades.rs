@@ -0,0 +1,188 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::Serialize;
+use x509_certificate::{
+  CapturedX509Certificate, DigestAlgorithm, KeyInfoSigner, SignatureAlgorithm,
+};
+
+use crate::errors::CmsError;
+use crate::limiter::Limiter;
+use crate::signing;
+
+/// A JAdES baseline signature of a payload, a JWS with the qualifying properties of ETSI TS
+/// 119 182-1 as header parameters.
+pub struct Signature<'a> {
+  pub signing_key: &'a dyn KeyInfoSigner,
+  /// The signing certificate, followed by the rest of the chain to include.
+  pub certs: &'a [CapturedX509Certificate],
+  pub payload: &'a [u8],
+  /// The media type of the payload, for the `cty` header parameter.
+  pub content_type: Option<&'a str>,
+  pub signing_time: chrono::DateTime<chrono::Utc>,
+  /// A timestamp server to add a signature timestamp from, for JAdES-B-T rather than B-B.
+  pub time_stamp_url: Option<&'a reqwest::Url>,
+  /// Whether to leave the payload out of the JWS, which the validator is given separately
+  /// (RFC 7515, Appendix F).
+  pub detached: bool,
+  /// Whether to use the JWS Compact Serialization instead of the flattened JSON one, which
+  /// can't carry the unprotected header a signature timestamp goes in.
+  pub compact: bool,
+  pub limiter: Option<&'a Limiter>,
+}
+
+#[derive(Serialize)]
+struct ProtectedHeader<'a> {
+  alg: &'static str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  cty: Option<&'a str>,
+  #[serde(rename = "x5t#S256")]
+  x5t_s256: String,
+  x5c: Vec<String>,
+  #[serde(rename = "sigT")]
+  sig_t: String,
+  crit: [&'static str; 1],
+}
+
+#[derive(Serialize)]
+struct FlattenedJws<'a> {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  payload: Option<&'a str>,
+  protected: &'a str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  header: Option<UnprotectedHeader>,
+  signature: &'a str,
+}
+
+#[derive(Serialize)]
+struct UnprotectedHeader {
+  #[serde(rename = "etsiU")]
+  etsi_u: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignatureTimeStamp {
+  sig_tst: TimeStampTokens,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimeStampTokens {
+  tst_tokens: Vec<TimeStampToken>,
+}
+
+#[derive(Serialize)]
+struct TimeStampToken {
+  val: String,
+}
+
+impl Signature<'_> {
+  /// Create the JWS, in the JSON or compact serialization.
+  pub fn sign(&self) -> Result<String, CmsError> {
+    let cert = self.certs.first().ok_or(CmsError::NoCertificate)?;
+
+    if self.compact && self.time_stamp_url.is_some() {
+      return Err(CmsError::InvalidOption(
+        "compact",
+        String::from("a signature timestamp needs the JSON serialization"),
+      ));
+    }
+
+    let signature_algorithm = self
+      .signing_key
+      .signature_algorithm()
+      .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+    let header = ProtectedHeader {
+      alg: algorithm(signature_algorithm)?,
+      cty: self.content_type,
+      x5t_s256: URL_SAFE_NO_PAD
+        .encode(DigestAlgorithm::Sha256.digest_data(cert.constructed_data())),
+      x5c: self
+        .certs
+        .iter()
+        .map(|cert| STANDARD.encode(cert.constructed_data()))
+        .collect(),
+      sig_t: self
+        .signing_time
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+      crit: ["sigT"],
+    };
+
+    let protected = URL_SAFE_NO_PAD.encode(to_json(&header)?);
+    let payload = URL_SAFE_NO_PAD.encode(self.payload);
+    let signing_input = format!("{}.{}", protected, payload);
+
+    let permit = self.limiter.map(Limiter::acquire);
+
+    let signature = self
+      .signing_key
+      .try_sign(signing_input.as_bytes())
+      .map_err(CmsError::signing_failed)?;
+
+    drop(permit);
+
+    let signature = URL_SAFE_NO_PAD.encode(signing::raw_signature(
+      signature_algorithm,
+      signature.as_ref(),
+    )?);
+
+    tracing::debug!(
+      alg = header.alg,
+      timestamped = self.time_stamp_url.is_some(),
+      "Created JAdES signature"
+    );
+
+    if self.compact {
+      return Ok(match self.detached {
+        true => format!("{}..{}", protected, signature),
+        false => format!("{}.{}", signing_input, signature),
+      });
+    }
+
+    // The signature timestamp is over the base64url encoded signature value.
+    let header = self
+      .time_stamp_url
+      .map(|url| {
+        let token = signing::time_stamp_token(url, signature.as_bytes(), DigestAlgorithm::Sha256)?;
+
+        let property = SignatureTimeStamp {
+          sig_tst: TimeStampTokens {
+            tst_tokens: vec![TimeStampToken {
+              val: STANDARD.encode(token.as_slice()),
+            }],
+          },
+        };
+
+        Ok::<_, CmsError>(UnprotectedHeader {
+          etsi_u: vec![URL_SAFE_NO_PAD.encode(to_json(&property)?)],
+        })
+      })
+      .transpose()?;
+
+    let jws = FlattenedJws {
+      payload: (!self.detached).then_some(payload.as_str()),
+      protected: &protected,
+      header,
+      signature: &signature,
+    };
+
+    to_json(&jws)
+  }
+}
+
+/// The JWS `alg` of a signature algorithm (RFC 7518).
+fn algorithm(signature_algorithm: SignatureAlgorithm) -> Result<&'static str, CmsError> {
+  match signature_algorithm {
+    SignatureAlgorithm::RsaSha256 => Ok("RS256"),
+    SignatureAlgorithm::RsaSha384 => Ok("RS384"),
+    SignatureAlgorithm::RsaSha512 => Ok("RS512"),
+    SignatureAlgorithm::EcdsaSha256 => Ok("ES256"),
+    SignatureAlgorithm::EcdsaSha384 => Ok("ES384"),
+    _ => Err(CmsError::UnsupportedKeyAlgorithm),
+  }
+}
+
+fn to_json(value: &impl Serialize) -> Result<String, CmsError> {
+  serde_json::to_string(value).map_err(|error| CmsError::SigningError.caused_by(error))
+}
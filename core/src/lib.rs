@@ -0,0 +1,99 @@
+//! CMS signing for PDF documents, without the Node-API bindings.
+//!
+//! The `@documenso/pdf-sign` Node.js package is a thin layer over these modules, so Rust
+//! services can create exactly the same signatures: [signing::SignedDataBuilder] and
+//! [signing::SignerBuilder] create SignedData for content or a precomputed digest, [cms] parses
+//! and re-encodes it to add unsigned attributes, [archive] adds archive timestamps,
+//! [evidence_record] creates evidence records and [attributes] encodes the signed and unsigned
+//! attributes the options map to. [xades] and [jades] sign XML and JSON workflows with the same
+//! keys, [asic] packages other files with their signatures, [smime] signed emails and
+//! [biometric] encrypts the pen data captured with a signature. [rsa_pss] signs with RSASSA-PSS
+//! for certificates restricted to it and [revocation] fetches the OCSP responses and CRLs of a
+//! chain for long-term validation. [appearance] builds the visible stamp of a signature and
+//! [certification] the DocMDP reference of a certification signature. [signer_backend] creates
+//! the keys of the signing functions by name, including those of providers added by embedders.
+
+pub mod appearance;
+pub mod archive;
+pub mod asic;
+pub mod attributes;
+pub mod batch_signer;
+pub mod biometric;
+pub mod certificates;
+pub mod certification;
+pub mod cms;
+pub mod compliance;
+pub mod credentials;
+pub mod ejbca;
+pub mod errors;
+pub mod evidence_record;
+#[cfg(feature = "gcloud")]
+pub mod gcloud_signer;
+pub mod http;
+pub mod input;
+pub mod jades;
+pub mod keys;
+pub mod limiter;
+pub mod pkcs8;
+pub mod placeholder_signer;
+pub mod progress;
+pub mod revocation;
+pub mod rsa_pss;
+#[cfg(feature = "gcloud")]
+pub mod runtime;
+pub mod self_test;
+pub mod signer_backend;
+pub mod signing;
+pub mod smime;
+pub mod validation;
+pub mod xades;
+
+use cryptographic_message_syntax::asn1::rfc5652::CmsVersion;
+use x509_certificate::DigestAlgorithm;
+
+use errors::CmsError;
+
+/// Parse an ISO 8601 signing time, defaulting to now.
+pub fn parse_signing_time(signing_time: Option<String>) -> chrono::DateTime<chrono::Utc> {
+  signing_time
+    .and_then(|time| time.parse::<chrono::DateTime<chrono::Utc>>().ok())
+    .unwrap_or(chrono::Utc::now())
+}
+
+/// Parse a CMSVersion number.
+pub fn parse_cms_version(version: Option<u32>) -> Result<Option<CmsVersion>, CmsError> {
+  version
+    .map(|version| match version {
+      0 => Ok(CmsVersion::V0),
+      1 => Ok(CmsVersion::V1),
+      2 => Ok(CmsVersion::V2),
+      3 => Ok(CmsVersion::V3),
+      4 => Ok(CmsVersion::V4),
+      5 => Ok(CmsVersion::V5),
+      _ => Err(CmsError::InvalidCmsVersion),
+    })
+    .transpose()
+}
+
+/// The names of the digest algorithms [parse_digest_algorithm] accepts.
+pub const DIGEST_ALGORITHMS: [&str; 3] = ["sha256", "sha384", "sha512"];
+
+/// The name of a digest algorithm, as [parse_digest_algorithm] accepts it.
+pub fn digest_algorithm_name(digest_algorithm: DigestAlgorithm) -> &'static str {
+  match digest_algorithm {
+    DigestAlgorithm::Sha1 => "sha1",
+    DigestAlgorithm::Sha256 => "sha256",
+    DigestAlgorithm::Sha384 => "sha384",
+    DigestAlgorithm::Sha512 => "sha512",
+  }
+}
+
+/// Parse a digest algorithm name, defaulting to SHA-256.
+pub fn parse_digest_algorithm(digest_algorithm: Option<&str>) -> Result<DigestAlgorithm, CmsError> {
+  match digest_algorithm {
+    None | Some("sha256") => Ok(DigestAlgorithm::Sha256),
+    Some("sha384") => Ok(DigestAlgorithm::Sha384),
+    Some("sha512") => Ok(DigestAlgorithm::Sha512),
+    Some(_) => Err(CmsError::UnsupportedDigestAlgorithm),
+  }
+}
@@ -0,0 +1,109 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::errors::CmsError;
+
+/// Limits the requests a signer makes to its key and timestamp server, so batch jobs stay within
+/// the quotas of providers such as Google Cloud KMS instead of getting the key throttled.
+///
+/// Requests beyond the concurrency limit wait for a running one to finish, and requests beyond
+/// the rate wait for their turn, blocking the calling thread.
+pub struct Limiter {
+  max_concurrent: Option<u32>,
+  interval: Option<Duration>,
+  state: Mutex<State>,
+  finished: Condvar,
+}
+
+struct State {
+  running: u32,
+  /// The earliest time the next request may start at.
+  next_start: Instant,
+}
+
+impl Limiter {
+  /// A limiter for at most `max_concurrent` requests at once and `max_per_second` requests
+  /// started per second, either of which may be unlimited.
+  pub fn new(max_concurrent: Option<u32>, max_per_second: Option<f64>) -> Result<Self, CmsError> {
+    if max_concurrent == Some(0) {
+      return Err(CmsError::InvalidOption(
+        "maxConcurrentRequests",
+        String::from("expected at least 1"),
+      ));
+    }
+
+    let interval = max_per_second
+      .map(|max_per_second| {
+        (max_per_second.is_finite() && max_per_second > 0.0)
+          .then(|| Duration::from_secs_f64(1.0 / max_per_second))
+          .ok_or_else(|| {
+            CmsError::InvalidOption(
+              "maxRequestsPerSecond",
+              String::from("expected a positive number"),
+            )
+          })
+      })
+      .transpose()?;
+
+    Ok(Self {
+      max_concurrent,
+      interval,
+      state: Mutex::new(State {
+        running: 0,
+        next_start: Instant::now(),
+      }),
+      finished: Condvar::new(),
+    })
+  }
+
+  /// Wait until a request may start, returning a permit that ends it when dropped.
+  pub fn acquire(&self) -> Permit<'_> {
+    let mut state = self.lock();
+
+    while self
+      .max_concurrent
+      .is_some_and(|max_concurrent| state.running >= max_concurrent)
+    {
+      state = self
+        .finished
+        .wait(state)
+        .unwrap_or_else(|error| error.into_inner());
+    }
+
+    state.running += 1;
+
+    // Each request reserves the next slot, so waiting requests start one interval apart.
+    let start = state.next_start.max(Instant::now());
+
+    if let Some(interval) = self.interval {
+      state.next_start = start + interval;
+    }
+
+    drop(state);
+
+    let wait = start.saturating_duration_since(Instant::now());
+
+    if !wait.is_zero() {
+      tracing::debug!(wait_ms = wait.as_millis() as u64, "Rate limiting request");
+      std::thread::sleep(wait);
+    }
+
+    Permit { limiter: self }
+  }
+
+  fn lock(&self) -> MutexGuard<'_, State> {
+    self.state.lock().unwrap_or_else(|error| error.into_inner())
+  }
+}
+
+/// A request let through by a [Limiter], which is done when the permit is dropped.
+pub struct Permit<'a> {
+  limiter: &'a Limiter,
+}
+
+impl Drop for Permit<'_> {
+  fn drop(&mut self) {
+    self.limiter.lock().running -= 1;
+    self.limiter.finished.notify_one();
+  }
+}
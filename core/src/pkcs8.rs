@@ -0,0 +1,423 @@
+use std::num::NonZeroU32;
+
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockCipher, BlockDecryptMut, KeyInit, KeyIvInit};
+use md5::{Digest, Md5};
+use p12::{AlgorithmIdentifier, EncryptedPrivateKeyInfo};
+use ring::pbkdf2;
+use yasna::models::ObjectIdentifier;
+use zeroize::Zeroizing;
+
+use crate::errors::CmsError;
+use crate::keys;
+
+/// id-PBES2 (RFC 8018).
+const OID_PBES2: &[u64] = &[1, 2, 840, 113549, 1, 5, 13];
+
+/// id-PBKDF2 (RFC 8018).
+const OID_PBKDF2: &[u64] = &[1, 2, 840, 113549, 1, 5, 12];
+
+/// id-scrypt (RFC 7914).
+const OID_SCRYPT: &[u64] = &[1, 3, 6, 1, 4, 1, 11591, 4, 11];
+
+/// The pseudorandom functions of PBKDF2 (RFC 8018).
+const OID_HMAC_WITH_SHA1: &[u64] = &[1, 2, 840, 113549, 2, 7];
+const OID_HMAC_WITH_SHA256: &[u64] = &[1, 2, 840, 113549, 2, 9];
+const OID_HMAC_WITH_SHA384: &[u64] = &[1, 2, 840, 113549, 2, 10];
+const OID_HMAC_WITH_SHA512: &[u64] = &[1, 2, 840, 113549, 2, 11];
+
+/// des-EDE3-CBC (RFC 8018).
+const OID_DES_EDE3_CBC: &[u64] = &[1, 2, 840, 113549, 3, 7];
+
+/// aes128-CBC-PAD, aes192-CBC-PAD and aes256-CBC-PAD (RFC 8018).
+const OID_AES_128_CBC: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 2];
+const OID_AES_192_CBC: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 22];
+const OID_AES_256_CBC: &[u64] = &[2, 16, 840, 1, 101, 3, 4, 1, 42];
+
+/// The AlgorithmIdentifier of rsaEncryption, with its NULL parameters (RFC 8017).
+const RSA_ENCRYPTION: &[u8] = &[
+  0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05, 0x00,
+];
+
+/// The DER encoded id-ecPublicKey (RFC 5480).
+const ID_EC_PUBLIC_KEY: &[u8] = &[0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+
+/// The DER tags of the elements of traditional and PKCS#8 keys.
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_CONTEXT_0: u8 = 0xa0;
+
+/// A CBC cipher that private keys are encrypted with.
+#[derive(Clone, Copy)]
+enum Cipher {
+  DesEde3,
+  Aes128,
+  Aes192,
+  Aes256,
+}
+
+impl Cipher {
+  /// The cipher of a PBES2 encryption scheme.
+  fn from_oid(oid: &ObjectIdentifier) -> Option<Self> {
+    match oid.components().as_slice() {
+      OID_DES_EDE3_CBC => Some(Self::DesEde3),
+      OID_AES_128_CBC => Some(Self::Aes128),
+      OID_AES_192_CBC => Some(Self::Aes192),
+      OID_AES_256_CBC => Some(Self::Aes256),
+      _ => None,
+    }
+  }
+
+  /// The cipher of a `DEK-Info` header, named as OpenSSL names it.
+  fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "DES-EDE3-CBC" => Some(Self::DesEde3),
+      "AES-128-CBC" => Some(Self::Aes128),
+      "AES-192-CBC" => Some(Self::Aes192),
+      "AES-256-CBC" => Some(Self::Aes256),
+      _ => None,
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Self::DesEde3 => "DES-EDE3-CBC",
+      Self::Aes128 => "AES-128-CBC",
+      Self::Aes192 => "AES-192-CBC",
+      Self::Aes256 => "AES-256-CBC",
+    }
+  }
+
+  fn key_length(self) -> usize {
+    match self {
+      Self::DesEde3 | Self::Aes192 => 24,
+      Self::Aes128 => 16,
+      Self::Aes256 => 32,
+    }
+  }
+
+  fn iv_length(self) -> usize {
+    match self {
+      Self::DesEde3 => 8,
+      Self::Aes128 | Self::Aes192 | Self::Aes256 => 16,
+    }
+  }
+
+  /// Decrypt data and remove its PKCS#7 padding. A wrong key almost always leaves invalid
+  /// padding, so that is reported as a wrong password.
+  fn decrypt(
+    self,
+    key: &[u8],
+    iv: &[u8],
+    encrypted: &[u8],
+  ) -> Result<Zeroizing<Vec<u8>>, CmsError> {
+    match self {
+      Self::DesEde3 => decrypt_cbc::<des::TdesEde3>(key, iv, encrypted),
+      Self::Aes128 => decrypt_cbc::<aes::Aes128>(key, iv, encrypted),
+      Self::Aes192 => decrypt_cbc::<aes::Aes192>(key, iv, encrypted),
+      Self::Aes256 => decrypt_cbc::<aes::Aes256>(key, iv, encrypted),
+    }
+  }
+}
+
+/// Decrypt a DER encoded PKCS#8 EncryptedPrivateKeyInfo (RFC 5958) with its password, returning
+/// the PrivateKeyInfo, which is wiped from memory when dropped.
+///
+/// Supported are PBES2 with PBKDF2 and DES-EDE3-CBC or AES-CBC, as `openssl pkcs8 -topk8`
+/// writes them, and the PKCS#12 schemes with 3DES or 40-bit RC2, the default of OpenSSL before
+/// 1.1.0. Keys encrypted with scrypt fail with an unsupported encryption error.
+pub fn decrypt(der: &[u8], password: &str) -> Result<Zeroizing<Vec<u8>>, CmsError> {
+  let info = yasna::parse_der(der, EncryptedPrivateKeyInfo::parse)
+    .map_err(|error| CmsError::PrivateKeyParseError.caused_by(error))?;
+
+  let decrypted = match &info.encryption_algorithm {
+    AlgorithmIdentifier::OtherAlg(other) if other.algorithm_type.components() == OID_PBES2 => {
+      pbes2(other.params.as_deref(), &info.encrypted_data, password)?
+    }
+    AlgorithmIdentifier::OtherAlg(other) => {
+      return Err(unsupported(&other.algorithm_type));
+    }
+    AlgorithmIdentifier::Sha1 => {
+      return Err(CmsError::PrivateKeyParseError.caused_by("SHA-1 is not an encryption scheme"));
+    }
+    // Like P12 containers, these derive keys from the password as a BMPString, which OpenSSL
+    // before 1.1.0 encodes byte by byte.
+    _ => [keys::bmp_string(password), keys::widened_utf8(password)]
+      .iter()
+      .find_map(|encoded| info.decrypt(encoded))
+      .map(Zeroizing::new)
+      .ok_or(CmsError::WrongPrivateKeyPassword)?,
+  };
+
+  tracing::debug!("Decrypted PKCS#8 private key");
+
+  Ok(decrypted)
+}
+
+/// Decrypt the DER of a traditional PEM key encrypted by OpenSSL, with the `DEK-Info` header of
+/// its block, returning the key, which is wiped from memory when dropped.
+///
+/// The header names the cipher, DES-EDE3-CBC or AES-CBC, and its IV. The key is derived from
+/// the password and the first 8 bytes of the IV with MD5, as `EVP_BytesToKey` does it with one
+/// iteration.
+pub fn decrypt_pem(
+  dek_info: &str,
+  encrypted: &[u8],
+  password: &str,
+) -> Result<Zeroizing<Vec<u8>>, CmsError> {
+  let (name, iv) = dek_info.split_once(',').unwrap_or((dek_info, ""));
+  let name = name.trim();
+
+  let cipher = Cipher::from_name(name).ok_or_else(|| {
+    CmsError::UnsupportedPrivateKeyEncryption.caused_by(format!(
+      "legacy PEM encryption with {}; {}",
+      name, REENCRYPT_HINT
+    ))
+  })?;
+
+  let iv = hex::decode(iv.trim())
+    .ok()
+    .filter(|iv| iv.len() == cipher.iv_length())
+    .ok_or_else(|| {
+      CmsError::PrivateKeyParseError.caused_by(format!("invalid {} IV", cipher.name()))
+    })?;
+
+  // D_i = MD5(D_i-1 || password || salt), concatenated until there are enough bytes for the key.
+  let mut key = Zeroizing::new(Vec::with_capacity(cipher.key_length() + 16));
+
+  while key.len() < cipher.key_length() {
+    let mut md5 = Md5::new();
+
+    if let Some(previous) = key.len().checked_sub(16).map(|start| &key[start..]) {
+      md5.update(previous);
+    }
+
+    md5.update(password.as_bytes());
+    md5.update(&iv[..8]);
+
+    key.extend_from_slice(&md5.finalize());
+  }
+
+  key.truncate(cipher.key_length());
+
+  let decrypted = cipher.decrypt(&key, &iv, encrypted)?;
+
+  tracing::debug!(
+    cipher = cipher.name(),
+    "Decrypted traditional PEM private key"
+  );
+
+  Ok(decrypted)
+}
+
+/// Wrap a DER encoded traditional private key, a PKCS#1 RSAPrivateKey (RFC 8017) or a SEC1
+/// ECPrivateKey with its curve (RFC 5915), as OpenSSL writes `RSA PRIVATE KEY` and
+/// `EC PRIVATE KEY` blocks, in a PKCS#8 PrivateKeyInfo, which is wiped from memory when dropped.
+///
+/// Anything else, such as a PKCS#8 key, gives `None`.
+pub fn wrap_traditional(der: &[u8]) -> Option<Zeroizing<Vec<u8>>> {
+  let (TAG_SEQUENCE, key, []) = split_element(der)? else {
+    return None;
+  };
+  let (TAG_INTEGER, _, rest) = split_element(key)? else {
+    return None;
+  };
+
+  let algorithm = match split_element(rest)? {
+    // RSAPrivateKey ::= SEQUENCE { version INTEGER, modulus INTEGER, ... }
+    (TAG_INTEGER, ..) => RSA_ENCRYPTION.to_vec(),
+    // ECPrivateKey ::= SEQUENCE {
+    //   version INTEGER, privateKey OCTET STRING,
+    //   parameters [0] ECParameters OPTIONAL, publicKey [1] BIT STRING OPTIONAL }
+    (TAG_OCTET_STRING, _, rest) => match split_element(rest)? {
+      (TAG_CONTEXT_0, curve, _) => {
+        let parameters = [ID_EC_PUBLIC_KEY, curve].concat();
+
+        [header(TAG_SEQUENCE, parameters.len()), parameters].concat()
+      }
+      _ => return None,
+    },
+    _ => return None,
+  };
+
+  // PrivateKeyInfo ::= SEQUENCE {
+  //   version INTEGER, privateKeyAlgorithm AlgorithmIdentifier, privateKey OCTET STRING }
+  let private_key = header(TAG_OCTET_STRING, der.len());
+  let length = 3 + algorithm.len() + private_key.len() + der.len();
+  let info = header(TAG_SEQUENCE, length);
+
+  let mut wrapped = Zeroizing::new(Vec::with_capacity(info.len() + length));
+  wrapped.extend_from_slice(&info);
+  wrapped.extend_from_slice(&[TAG_INTEGER, 1, 0]);
+  wrapped.extend_from_slice(&algorithm);
+  wrapped.extend_from_slice(&private_key);
+  wrapped.extend_from_slice(der);
+
+  Some(wrapped)
+}
+
+/// Whether a DER encoded PKCS#8 key is an EncryptedPrivateKeyInfo rather than a PrivateKeyInfo.
+pub fn is_encrypted(der: &[u8]) -> bool {
+  yasna::parse_der(der, EncryptedPrivateKeyInfo::parse).is_ok()
+}
+
+/// Helper function to decrypt data encrypted with PBES2, with the DER encoded PBES2-params.
+fn pbes2(
+  params: Option<&[u8]>,
+  encrypted: &[u8],
+  password: &str,
+) -> Result<Zeroizing<Vec<u8>>, CmsError> {
+  let invalid = |error| CmsError::PrivateKeyParseError.caused_by(error);
+
+  // PBES2-params ::= SEQUENCE {
+  //   keyDerivationFunc AlgorithmIdentifier {{PBES2-KDFs}},
+  //   encryptionScheme AlgorithmIdentifier {{PBES2-Encs}} }
+  let ((kdf, kdf_params), (scheme, scheme_params)) = yasna::parse_der(
+    params.ok_or_else(|| invalid(String::from("missing PBES2 parameters")))?,
+    |r| {
+      r.read_sequence(|r| {
+        let kdf = r
+          .next()
+          .read_sequence(|r| Ok((r.next().read_oid()?, r.read_optional(|r| r.read_der())?)))?;
+        let scheme = r
+          .next()
+          .read_sequence(|r| Ok((r.next().read_oid()?, r.read_optional(|r| r.read_der())?)))?;
+
+        Ok((kdf, scheme))
+      })
+    },
+  )
+  .map_err(|error| invalid(error.to_string()))?;
+
+  if kdf.components() != OID_PBKDF2 {
+    return Err(unsupported(&kdf));
+  }
+  let cipher = Cipher::from_oid(&scheme).ok_or_else(|| unsupported(&scheme))?;
+
+  // PBKDF2-params ::= SEQUENCE {
+  //   salt CHOICE { specified OCTET STRING, otherSource AlgorithmIdentifier },
+  //   iterationCount INTEGER (1..MAX),
+  //   keyLength INTEGER (1..MAX) OPTIONAL,
+  //   prf AlgorithmIdentifier {{PBKDF2-PRFs}} DEFAULT algid-hmacWithSHA1 }
+  let (salt, iterations, key_length, prf) =
+    yasna::parse_der(kdf_params.as_deref().unwrap_or_default(), |r| {
+      r.read_sequence(|r| {
+        let salt = r.next().read_bytes()?;
+        let iterations = r.next().read_u32()?;
+        let key_length = r.read_optional(|r| r.read_u64())?;
+        let prf = r.read_optional(|r| {
+          r.read_sequence(|r| {
+            let oid = r.next().read_oid()?;
+            r.read_optional(|r| r.read_null())?;
+
+            Ok(oid)
+          })
+        })?;
+
+        Ok((salt, iterations, key_length, prf))
+      })
+    })
+    .map_err(|error| invalid(error.to_string()))?;
+
+  let algorithm = match &prf {
+    None => pbkdf2::PBKDF2_HMAC_SHA1,
+    Some(prf) => match prf.components().as_slice() {
+      OID_HMAC_WITH_SHA1 => pbkdf2::PBKDF2_HMAC_SHA1,
+      OID_HMAC_WITH_SHA256 => pbkdf2::PBKDF2_HMAC_SHA256,
+      OID_HMAC_WITH_SHA384 => pbkdf2::PBKDF2_HMAC_SHA384,
+      OID_HMAC_WITH_SHA512 => pbkdf2::PBKDF2_HMAC_SHA512,
+      _ => return Err(unsupported(prf)),
+    },
+  };
+
+  let iterations =
+    NonZeroU32::new(iterations).ok_or_else(|| invalid(String::from("zero PBKDF2 iterations")))?;
+
+  if key_length.is_some_and(|length| length != cipher.key_length() as u64) {
+    return Err(invalid(String::from(
+      "the PBKDF2 key length doesn't match the cipher",
+    )));
+  }
+
+  let iv = yasna::parse_der(scheme_params.as_deref().unwrap_or_default(), |r| {
+    r.read_bytes()
+  })
+  .ok()
+  .filter(|iv| iv.len() == cipher.iv_length())
+  .ok_or_else(|| invalid(format!("invalid {} IV", cipher.name())))?;
+
+  let mut key = Zeroizing::new(vec![0u8; cipher.key_length()]);
+  pbkdf2::derive(
+    algorithm,
+    iterations,
+    &salt,
+    password.as_bytes(),
+    key.as_mut(),
+  );
+
+  cipher.decrypt(&key, &iv, encrypted)
+}
+
+/// Helper function to decrypt data with a block cipher in CBC mode and remove its padding.
+fn decrypt_cbc<C>(key: &[u8], iv: &[u8], encrypted: &[u8]) -> Result<Zeroizing<Vec<u8>>, CmsError>
+where
+  C: BlockCipher + BlockDecryptMut + KeyInit,
+{
+  cbc::Decryptor::<C>::new_from_slices(key, iv)
+    .map_err(|error| CmsError::PrivateKeyParseError.caused_by(error.to_string()))?
+    .decrypt_padded_vec_mut::<Pkcs7>(encrypted)
+    .map(Zeroizing::new)
+    .map_err(|_| CmsError::WrongPrivateKeyPassword)
+}
+
+/// Helper function to split the first DER element off `input`, into its tag, its contents and
+/// what follows it.
+fn split_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+  let (&tag, rest) = input.split_first()?;
+  let (&first, rest) = rest.split_first()?;
+
+  let (length, rest) = match first {
+    0..=0x7f => (usize::from(first), rest),
+    0x81..=0x84 => {
+      let (bytes, rest) = rest.split_at_checked(usize::from(first & 0x7f))?;
+
+      (
+        bytes
+          .iter()
+          .fold(0, |length, byte| length << 8 | usize::from(*byte)),
+        rest,
+      )
+    }
+    _ => return None,
+  };
+
+  let (contents, rest) = rest.split_at_checked(length)?;
+
+  Some((tag, contents, rest))
+}
+
+/// Helper function to encode the tag and definite length of a DER element.
+fn header(tag: u8, length: usize) -> Vec<u8> {
+  let bytes = length.to_be_bytes();
+  let significant = &bytes[bytes.iter().take_while(|byte| **byte == 0).count()..];
+
+  match length {
+    0..=0x7f => vec![tag, length as u8],
+    _ => [&[tag, 0x80 | significant.len() as u8], significant].concat(),
+  }
+}
+
+/// How to re-encrypt a key with an unsupported encryption.
+const REENCRYPT_HINT: &str =
+  "re-encrypt the key with PBKDF2, as `openssl pkcs8 -topk8 -v2 aes-256-cbc` does";
+
+/// Helper function to report an algorithm that can't be used to decrypt a key.
+fn unsupported(algorithm: &ObjectIdentifier) -> CmsError {
+  let name = match algorithm.components().as_slice() {
+    OID_SCRYPT => String::from("scrypt"),
+    _ => format!("algorithm {}", algorithm),
+  };
+
+  CmsError::UnsupportedPrivateKeyEncryption.caused_by(format!("{}; {}", name, REENCRYPT_HINT))
+}
@@ -0,0 +1,95 @@
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::{
+  CapturedX509Certificate, DigestAlgorithm, EcdsaCurve, KeyAlgorithm, KeyInfoSigner, Sign,
+  Signature, SignatureAlgorithm, Signer, X509CertificateError,
+};
+use zeroize::Zeroizing;
+
+use crate::errors::CmsError;
+
+/// Signer producing a zero filled signature of the size the key of a certificate would produce.
+///
+/// Used for dry runs, so the size of the signed data is known without reaching a remote key.
+/// The signature never verifies.
+pub struct PlaceholderSigner {
+  key_algorithm: KeyAlgorithm,
+  signature_algorithm: SignatureAlgorithm,
+  public_key: Bytes,
+  signature_size: usize,
+}
+
+impl PlaceholderSigner {
+  pub fn new(cert: &CapturedX509Certificate) -> Result<Self, CmsError> {
+    let key_algorithm = cert
+      .key_algorithm()
+      .ok_or(CmsError::UnsupportedKeyAlgorithm)?;
+
+    let signature_algorithm = SignatureAlgorithm::from_oid_and_digest_algorithm(
+      cert.key_algorithm_oid(),
+      DigestAlgorithm::Sha256,
+    )
+    .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+    let signature_size = match key_algorithm {
+      KeyAlgorithm::Rsa => {
+        let public_key = cert
+          .rsa_public_key_data()
+          .map_err(|error| CmsError::PublicKeyError.caused_by(error))?;
+
+        // The modulus is a DER integer with a leading zero byte.
+        let modulus = public_key.modulus.as_slice();
+        modulus.len() - modulus.iter().take_while(|byte| **byte == 0).count()
+      }
+      // DER encoded ECDSA signatures are at most this long, with both integers padded.
+      KeyAlgorithm::Ecdsa(EcdsaCurve::Secp256r1) => 72,
+      KeyAlgorithm::Ecdsa(EcdsaCurve::Secp384r1) => 104,
+      KeyAlgorithm::Ed25519 => 64,
+    };
+
+    Ok(Self {
+      key_algorithm,
+      signature_algorithm,
+      public_key: cert.public_key_data(),
+      signature_size,
+    })
+  }
+}
+
+impl KeyInfoSigner for PlaceholderSigner {}
+
+impl Sign for PlaceholderSigner {
+  fn sign(&self, message: &[u8]) -> Result<(Vec<u8>, SignatureAlgorithm), X509CertificateError> {
+    let signature = self.try_sign(message)?;
+    let algorithm = self.signature_algorithm()?;
+
+    Ok((signature.into(), algorithm))
+  }
+
+  fn key_algorithm(&self) -> Option<KeyAlgorithm> {
+    Some(self.key_algorithm)
+  }
+
+  fn signature_algorithm(&self) -> Result<SignatureAlgorithm, X509CertificateError> {
+    Ok(self.signature_algorithm)
+  }
+
+  fn private_key_data(&self) -> Option<Zeroizing<Vec<u8>>> {
+    None
+  }
+
+  fn public_key_data(&self) -> Bytes {
+    self.public_key.clone()
+  }
+
+  fn rsa_primes(
+    &self,
+  ) -> Result<Option<(Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>)>, X509CertificateError> {
+    Ok(None)
+  }
+}
+
+impl Signer<Signature> for PlaceholderSigner {
+  fn try_sign(&self, _msg: &[u8]) -> Result<Signature, signature::Error> {
+    Ok(Signature::from(vec![0; self.signature_size]))
+  }
+}
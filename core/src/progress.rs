@@ -0,0 +1,121 @@
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+/// A stage of a signing operation, reported as it begins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+  Hashing,
+  Signing,
+  Timestamping,
+  Writing,
+}
+
+impl Stage {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Stage::Hashing => "hashing",
+      Stage::Signing => "signing",
+      Stage::Timestamping => "timestamping",
+      Stage::Writing => "writing",
+    }
+  }
+}
+
+/// How long the stages of a signing operation took, summed over its signers.
+///
+/// A stage lasts until the next one begins, so `hashing` includes building the signed attributes
+/// and `writing` includes writing the output file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timings {
+  /// Reading and parsing the options, keys, certificates and content, before hashing.
+  pub parsing: Duration,
+  pub hashing: Duration,
+  /// Signing with the key, the round trip for remote keys such as Google Cloud KMS.
+  pub signing: Duration,
+  /// The round trip to the timestamp server.
+  pub timestamping: Duration,
+  pub writing: Duration,
+}
+
+impl Timings {
+  fn add(&mut self, stage: Option<Stage>, duration: Duration) {
+    let total = match stage {
+      None => &mut self.parsing,
+      Some(Stage::Hashing) => &mut self.hashing,
+      Some(Stage::Signing) => &mut self.signing,
+      Some(Stage::Timestamping) => &mut self.timestamping,
+      Some(Stage::Writing) => &mut self.writing,
+    };
+
+    *total += duration;
+  }
+}
+
+type Reporter = Box<dyn Fn(Stage)>;
+
+/// The timings of the current operation, with the stage in progress and when it began.
+struct Timer {
+  timings: Timings,
+  stage: Option<Stage>,
+  started: Instant,
+}
+
+thread_local! {
+  static REPORTER: RefCell<Option<Reporter>> = RefCell::new(None);
+  static TIMER: Cell<Option<Timer>> = const { Cell::new(None) };
+}
+
+/// Run an operation, passing the stages it reports on this thread to a reporter.
+pub fn with_reporter<T>(reporter: impl Fn(Stage) + 'static, op: impl FnOnce() -> T) -> T {
+  let previous = REPORTER.with(|current| current.replace(Some(Box::new(reporter))));
+
+  let result = op();
+
+  REPORTER.with(|current| current.replace(previous));
+
+  result
+}
+
+/// Run an operation, timing the stages it reports on this thread.
+pub fn with_timings<T>(op: impl FnOnce() -> T) -> (T, Timings) {
+  let previous = TIMER.with(|timer| {
+    timer.replace(Some(Timer {
+      timings: Timings::default(),
+      stage: None,
+      started: Instant::now(),
+    }))
+  });
+
+  let result = op();
+
+  let timer = TIMER.with(|timer| timer.replace(previous));
+  let mut timings = Timings::default();
+
+  if let Some(timer) = timer {
+    timings = timer.timings;
+    timings.add(timer.stage, timer.started.elapsed());
+  }
+
+  (result, timings)
+}
+
+/// Report the beginning of a stage to the reporter of the current operation, if any.
+pub fn report(stage: Stage) {
+  tracing::debug!(stage = stage.as_str(), "Started stage");
+
+  TIMER.with(|timer| {
+    if let Some(mut current) = timer.take() {
+      let now = Instant::now();
+      current.timings.add(current.stage, now - current.started);
+      current.stage = Some(stage);
+      current.started = now;
+      timer.set(Some(current));
+    }
+  });
+
+  REPORTER.with(|current| {
+    if let Some(reporter) = current.borrow().as_ref() {
+      reporter(stage);
+    }
+  });
+}
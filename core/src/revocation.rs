@@ -0,0 +1,302 @@
+use bcder::decode::{Constructed, DecodeError, Source};
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{ConstOid, Mode, OctetString, Oid, Tag};
+use cryptographic_message_syntax::Bytes;
+use reqwest::header::CONTENT_TYPE;
+use x509_certificate::rfc5280::AlgorithmIdentifier;
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm};
+
+use crate::attributes;
+use crate::errors::CmsError;
+use crate::http;
+
+/// id-pe-authorityInfoAccess (RFC 5280).
+const OID_AUTHORITY_INFO_ACCESS: ConstOid = Oid(&[43, 6, 1, 5, 5, 7, 1, 1]);
+
+/// id-ad-ocsp (RFC 5280).
+const OID_AD_OCSP: ConstOid = Oid(&[43, 6, 1, 5, 5, 7, 48, 1]);
+
+/// id-ce-cRLDistributionPoints (RFC 5280).
+const OID_CRL_DISTRIBUTION_POINTS: ConstOid = Oid(&[85, 29, 31]);
+
+/// Media types of OCSP requests and responses over HTTP (RFC 6960).
+const HTTP_CONTENT_TYPE_OCSP_REQUEST: &str = "application/ocsp-request";
+const HTTP_CONTENT_TYPE_OCSP_RESPONSE: &str = "application/ocsp-response";
+
+/// The DER encoded CRLs and OCSP responses of a certificate chain.
+#[derive(Default)]
+pub struct RevocationInfo {
+  pub crls: Vec<Vec<u8>>,
+  /// OCSPResponses, as returned by the responders.
+  pub ocsp_responses: Vec<Vec<u8>>,
+}
+
+/// Fetch the revocation data of a certificate and the chain it's issued by, for long-term
+/// validation (PAdES-B-LT).
+///
+/// The chain is followed through `issuers`, which may also hold unrelated certificates, up to a
+/// self-issued certificate or one whose issuer isn't among them. Each certificate but a
+/// self-issued root is checked with the OCSP responder of its authority information access
+/// extension, or with the CRL of its distribution points if there's no responder or it fails.
+/// Only HTTP URLs are used, which is all a PDF validator fetches too.
+pub fn fetch(
+  cert: &CapturedX509Certificate,
+  issuers: &[CapturedX509Certificate],
+) -> Result<RevocationInfo, CmsError> {
+  let mut info = RevocationInfo::default();
+  let mut current = cert;
+
+  while !current.subject_is_issuer() {
+    let issuer = issuers.iter().find(|issuer| {
+      issuer.subject_name() == current.issuer_name()
+        && issuer.constructed_data() != current.constructed_data()
+    });
+
+    fetch_one(current, issuer, &mut info).map_err(|error| {
+      match current.subject_name().user_friendly_str() {
+        Ok(subject) => error.with_detail("subject", subject),
+        Err(_) => error,
+      }
+    })?;
+
+    match issuer {
+      Some(issuer) => current = issuer,
+      None => break,
+    }
+  }
+
+  tracing::debug!(
+    crls = info.crls.len(),
+    ocsp_responses = info.ocsp_responses.len(),
+    "Fetched revocation info"
+  );
+
+  Ok(info)
+}
+
+/// Helper function to fetch the OCSP response or, failing that, the CRL of a certificate.
+fn fetch_one(
+  cert: &CapturedX509Certificate,
+  issuer: Option<&CapturedX509Certificate>,
+  info: &mut RevocationInfo,
+) -> Result<(), CmsError> {
+  let mut last_error = None;
+
+  // The request identifies the certificate by the hash of its issuer's key.
+  if let Some(issuer) = issuer {
+    for url in ocsp_urls(cert)? {
+      match ocsp_response(&url, cert, issuer) {
+        Ok(response) => {
+          info.ocsp_responses.push(response);
+          return Ok(());
+        }
+        Err(error) => {
+          tracing::warn!(url, cause = error.cause(), "OCSP request failed: {}", error);
+          last_error = Some(error.with_detail("url", url));
+        }
+      }
+    }
+  }
+
+  for url in crl_urls(cert)? {
+    match crl(&url) {
+      Ok(crl) => {
+        if !info.crls.contains(&crl) {
+          info.crls.push(crl);
+        }
+        return Ok(());
+      }
+      Err(error) => {
+        tracing::warn!(url, cause = error.cause(), "CRL download failed: {}", error);
+        last_error = Some(error.with_detail("url", url));
+      }
+    }
+  }
+
+  Err(last_error.unwrap_or_else(|| {
+    CmsError::RevocationInfoError.caused_by("the certificate has no OCSP responder or CRL")
+  }))
+}
+
+/// Helper function to request the OCSP response of a certificate.
+fn ocsp_response(
+  url: &str,
+  cert: &CapturedX509Certificate,
+  issuer: &CapturedX509Certificate,
+) -> Result<Vec<u8>, CmsError> {
+  let issuer_name = issuer.subject_name().encode_ref().to_captured(Mode::Der);
+
+  // OCSPRequest ::= SEQUENCE { tbsRequest TBSRequest, ... }
+  // TBSRequest ::= SEQUENCE { version [0] EXPLICIT Version DEFAULT v1, ...,
+  //   requestList SEQUENCE OF Request, ... }
+  // Request ::= SEQUENCE { reqCert CertID, ... }
+  // CertID ::= SEQUENCE { hashAlgorithm AlgorithmIdentifier, issuerNameHash OCTET STRING,
+  //   issuerKeyHash OCTET STRING, serialNumber CertificateSerialNumber }
+  //
+  // SHA-1 is the hash every responder supports, and only identifies the certificate.
+  let request = encode::sequence(encode::sequence(encode::sequence(encode::sequence(
+    encode::sequence((
+      AlgorithmIdentifier::from(DigestAlgorithm::Sha1),
+      OctetString::encode_slice(DigestAlgorithm::Sha1.digest_data(issuer_name.as_slice())),
+      OctetString::encode_slice(DigestAlgorithm::Sha1.digest_data(&issuer.public_key_data())),
+      cert.serial_number_asn1().encode(),
+    )),
+  ))))
+  .to_captured(Mode::Der);
+
+  let response = http::client()
+    .and_then(|client| {
+      client
+        .post(url)
+        .header(CONTENT_TYPE, HTTP_CONTENT_TYPE_OCSP_REQUEST)
+        .body(request.as_slice().to_vec())
+        .send()
+    })
+    .and_then(|response| response.error_for_status())
+    .map_err(|error| CmsError::RevocationInfoError.caused_by(error))?;
+
+  if response
+    .headers()
+    .get(CONTENT_TYPE)
+    .map(|value| value.as_bytes())
+    != Some(HTTP_CONTENT_TYPE_OCSP_RESPONSE.as_bytes())
+  {
+    return Err(CmsError::RevocationInfoError.caused_by("the response is not an OCSP response"));
+  }
+
+  let response = response
+    .bytes()
+    .map_err(|error| CmsError::RevocationInfoError.caused_by(error))?
+    .to_vec();
+
+  // Responses that aren't successful, e.g. for a certificate the responder doesn't know, are
+  // rejected here rather than embedded.
+  attributes::revocation_values::<&[u8]>(&[], &[&response])
+    .map_err(|error| CmsError::RevocationInfoError.caused_by(error))?;
+
+  Ok(response)
+}
+
+/// Helper function to download a CRL.
+fn crl(url: &str) -> Result<Vec<u8>, CmsError> {
+  let crl = http::client()
+    .and_then(|client| client.get(url).send())
+    .and_then(|response| response.error_for_status())
+    .and_then(|response| response.bytes())
+    .map_err(|error| CmsError::RevocationInfoError.caused_by(error))?
+    .to_vec();
+
+  attributes::revocation_values::<&[u8]>(&[&crl], &[]).map_err(|_| {
+    CmsError::RevocationInfoError.caused_by("the response is not a DER encoded CRL")
+  })?;
+
+  Ok(crl)
+}
+
+/// Helper function to find the HTTP URLs of the OCSP responders of a certificate.
+fn ocsp_urls(cert: &CapturedX509Certificate) -> Result<Vec<String>, CmsError> {
+  let Some(value) = extension(cert, OID_AUTHORITY_INFO_ACCESS) else {
+    return Ok(Vec::new());
+  };
+
+  // AuthorityInfoAccessSyntax ::= SEQUENCE OF AccessDescription
+  // AccessDescription ::= SEQUENCE { accessMethod OBJECT IDENTIFIER, accessLocation GeneralName }
+  let urls = Mode::Der
+    .decode(value.as_ref(), |cons| {
+      cons.take_sequence(|cons| {
+        let mut urls = Vec::new();
+
+        while let Some(url) = cons.take_opt_sequence(|cons| {
+          let method = Oid::take_from(cons)?;
+          let location = take_uris(cons)?;
+
+          Ok(location.filter(|_| method == OID_AD_OCSP))
+        })? {
+          urls.extend(url);
+        }
+
+        Ok(urls)
+      })
+    })
+    .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+  Ok(http_urls(urls))
+}
+
+/// Helper function to find the HTTP URLs of the CRL distribution points of a certificate.
+fn crl_urls(cert: &CapturedX509Certificate) -> Result<Vec<String>, CmsError> {
+  let Some(value) = extension(cert, OID_CRL_DISTRIBUTION_POINTS) else {
+    return Ok(Vec::new());
+  };
+
+  // CRLDistributionPoints ::= SEQUENCE OF DistributionPoint
+  // DistributionPoint ::= SEQUENCE {
+  //   distributionPoint [0] DistributionPointName OPTIONAL,
+  //   reasons [1] ReasonFlags OPTIONAL,
+  //   cRLIssuer [2] GeneralNames OPTIONAL }
+  // DistributionPointName ::= CHOICE {
+  //   fullName [0] GeneralNames,
+  //   nameRelativeToCRLIssuer [1] RelativeDistinguishedName }
+  let urls = Mode::Der
+    .decode(value.as_ref(), |cons| {
+      cons.take_sequence(|cons| {
+        let mut urls = Vec::new();
+
+        while let Some(point) = cons.take_opt_sequence(|cons| {
+          let names = cons.take_opt_constructed_if(Tag::CTX_0, |cons| {
+            let names = cons.take_opt_constructed_if(Tag::CTX_0, |cons| {
+              let mut names = Vec::new();
+
+              while let Some(name) = take_uris(cons)? {
+                names.push(name);
+              }
+
+              Ok(names)
+            })?;
+            cons.skip_all()?;
+
+            Ok(names.unwrap_or_default())
+          })?;
+          cons.skip_all()?;
+
+          Ok(names.unwrap_or_default())
+        })? {
+          urls.extend(point);
+        }
+
+        Ok(urls)
+      })
+    })
+    .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+  Ok(http_urls(urls))
+}
+
+/// Helper function to take a GeneralName, returning the URI of a uniformResourceIdentifier and
+/// an empty string for other names, or `None` at the end of the content.
+fn take_uris<S: Source>(
+  cons: &mut Constructed<S>,
+) -> Result<Option<String>, DecodeError<S::Error>> {
+  // uniformResourceIdentifier [6] IA5String
+  if let Some(uri) = cons.take_opt_value_if(Tag::CTX_6, OctetString::from_content)? {
+    return Ok(Some(String::from_utf8_lossy(&uri.to_bytes()).into_owned()));
+  }
+
+  Ok(cons.skip_one()?.map(|_| String::new()))
+}
+
+/// Helper function to keep the HTTP URLs, which are the ones that are fetched.
+fn http_urls(urls: Vec<String>) -> Vec<String> {
+  urls
+    .into_iter()
+    .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+    .collect()
+}
+
+/// Helper function to find the value of an extension of a certificate.
+fn extension(cert: &CapturedX509Certificate, oid: ConstOid) -> Option<Bytes> {
+  cert
+    .iter_extensions()
+    .find(|extension| extension.id == oid)
+    .map(|extension| extension.value.to_bytes())
+}
@@ -0,0 +1,109 @@
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{ConstOid, Mode, Oid, Tag};
+use ring::rand::SystemRandom;
+use ring::signature::{self as ring_signature, RsaEncoding, RsaKeyPair, UnparsedPublicKey};
+use x509_certificate::rfc5280::{AlgorithmIdentifier, AlgorithmParameter};
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm, KeyAlgorithm, KeyInfoSigner};
+
+use crate::errors::CmsError;
+
+/// id-RSASSA-PSS (RFC 8017).
+pub const OID_RSASSA_PSS: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 1, 10]);
+
+/// id-mgf1 (RFC 8017).
+pub(crate) const OID_MGF1: ConstOid = Oid(&[42, 134, 72, 134, 247, 13, 1, 1, 8]);
+
+/// The signature algorithm identifier of RSASSA-PSS with a digest algorithm, which is also used
+/// for MGF1, and a salt as long as the digest, as RFC 4056 recommends for CMS.
+pub fn algorithm_identifier(digest_algorithm: DigestAlgorithm) -> AlgorithmIdentifier {
+  let salt_length = digest_algorithm.digester().finish().as_ref().len() as u8;
+
+  // RSASSA-PSS-params ::= SEQUENCE {
+  //   hashAlgorithm [0] HashAlgorithm DEFAULT sha1,
+  //   maskGenAlgorithm [1] MaskGenAlgorithm DEFAULT mgf1SHA1,
+  //   saltLength [2] INTEGER DEFAULT 20,
+  //   trailerField [3] TrailerField DEFAULT trailerFieldBC }
+  let parameters = encode::sequence((
+    encode::sequence_as(Tag::CTX_0, AlgorithmIdentifier::from(digest_algorithm)),
+    encode::sequence_as(
+      Tag::CTX_1,
+      encode::sequence((
+        OID_MGF1.encode_ref(),
+        AlgorithmIdentifier::from(digest_algorithm),
+      )),
+    ),
+    encode::sequence_as(Tag::CTX_2, salt_length.encode()),
+  ));
+
+  AlgorithmIdentifier {
+    algorithm: Oid(OID_RSASSA_PSS.0.into()),
+    parameters: Some(AlgorithmParameter::from_captured(
+      parameters.to_captured(Mode::Der),
+    )),
+  }
+}
+
+/// Sign a message with RSASSA-PSS, with the parameters of [algorithm_identifier].
+///
+/// PKCS#1 v1.5 is the only padding of the signing key traits, so this signs with the private key
+/// of the signing key, which must be an RSA key held in memory rather than in an HSM or a cloud
+/// KMS.
+pub fn sign(
+  signing_key: &dyn KeyInfoSigner,
+  digest_algorithm: DigestAlgorithm,
+  message: &[u8],
+) -> Result<Vec<u8>, CmsError> {
+  if signing_key.key_algorithm() != Some(KeyAlgorithm::Rsa) {
+    return Err(CmsError::InvalidOption(
+      "signatureScheme",
+      String::from("RSASSA-PSS needs an RSA key"),
+    ));
+  }
+
+  let private_key = signing_key.private_key_data().ok_or_else(|| {
+    CmsError::InvalidOption(
+      "signatureScheme",
+      String::from("RSASSA-PSS needs a private key held in memory"),
+    )
+  })?;
+
+  // The private key data of RSA keys is the PKCS#1 RSAPrivateKey.
+  let key_pair = RsaKeyPair::from_der(&private_key)
+    .map_err(|error| CmsError::PrivateKeyParseError.caused_by(error))?;
+
+  let padding: &'static dyn RsaEncoding = match digest_algorithm {
+    DigestAlgorithm::Sha256 => &ring_signature::RSA_PSS_SHA256,
+    DigestAlgorithm::Sha384 => &ring_signature::RSA_PSS_SHA384,
+    DigestAlgorithm::Sha512 => &ring_signature::RSA_PSS_SHA512,
+    DigestAlgorithm::Sha1 => return Err(CmsError::UnsupportedDigestAlgorithm),
+  };
+
+  let mut signature = vec![0; key_pair.public().modulus_len()];
+
+  key_pair
+    .sign(padding, &SystemRandom::new(), message, &mut signature)
+    .map_err(|_| CmsError::SigningError.caused_by("RSASSA-PSS signing failed"))?;
+
+  Ok(signature)
+}
+
+/// Verify an RSASSA-PSS signature of a message with the public key of a certificate, with the
+/// parameters of [algorithm_identifier] for the digest algorithm.
+pub fn verify(
+  cert: &CapturedX509Certificate,
+  digest_algorithm: DigestAlgorithm,
+  message: &[u8],
+  signature: &[u8],
+) -> bool {
+  let parameters = match digest_algorithm {
+    DigestAlgorithm::Sha256 => &ring_signature::RSA_PSS_2048_8192_SHA256,
+    DigestAlgorithm::Sha384 => &ring_signature::RSA_PSS_2048_8192_SHA384,
+    DigestAlgorithm::Sha512 => &ring_signature::RSA_PSS_2048_8192_SHA512,
+    DigestAlgorithm::Sha1 => return false,
+  };
+
+  cert.key_algorithm() == Some(KeyAlgorithm::Rsa)
+    && UnparsedPublicKey::new(parameters, cert.public_key_data())
+      .verify(message, signature)
+      .is_ok()
+}
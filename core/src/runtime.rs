@@ -0,0 +1,48 @@
+use std::future::Future;
+use std::sync::OnceLock;
+
+use tokio::runtime::{self, Handle, Runtime};
+
+use crate::errors::CmsError;
+
+/// Threads of the shared runtime, which only drive the I/O of requests while callers block.
+const WORKER_THREADS: usize = 2;
+
+/// The Tokio runtime shared by the signers making network requests, such as Google Cloud KMS,
+/// started on first use.
+fn shared() -> Result<&'static Runtime, CmsError> {
+  static RUNTIME: OnceLock<Result<Runtime, String>> = OnceLock::new();
+
+  RUNTIME
+    .get_or_init(|| {
+      runtime::Builder::new_multi_thread()
+        .worker_threads(WORKER_THREADS)
+        .thread_name("pdf-sign-runtime")
+        .enable_all()
+        .build()
+        .map_err(|error| error.to_string())
+    })
+    .as_ref()
+    .map_err(|error| CmsError::RuntimeError.caused_by(error))
+}
+
+/// Run a future to completion on the shared runtime, blocking the calling thread.
+///
+/// Any number of threads can block on requests at once.
+pub fn block_on<F>(future: F) -> Result<F::Output, CmsError>
+where
+  F: Future + Send,
+  F::Output: Send,
+{
+  let runtime = shared()?;
+
+  if Handle::try_current().is_err() {
+    return Ok(runtime.block_on(future));
+  }
+
+  // Tokio panics when blocking a thread that is running a runtime, and a thread that has entered
+  // one, like the main thread of Node.js with napi-rs, may be running it. The future is run on
+  // a thread of its own instead.
+  std::thread::scope(|scope| scope.spawn(|| runtime.block_on(future)).join())
+    .map_err(|_| CmsError::RuntimeError.caused_by("the request panicked"))
+}
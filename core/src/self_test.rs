@@ -0,0 +1,154 @@
+use std::time::{Duration, Instant};
+
+use cryptographic_message_syntax::Bytes;
+use x509_certificate::{
+  CapturedX509Certificate, EcdsaCurve, InMemorySigningKeyPair, KeyAlgorithm, X509CertificateBuilder,
+};
+
+use crate::certificates;
+use crate::errors::CmsError;
+use crate::keys;
+use crate::signing::{SignedDataBuilder, SignerBuilder};
+
+/// A self-signed RSA certificate and its key, for the self-test only. The key is public, so
+/// nothing signed with it can be trusted.
+const RSA_FIXTURE: &[u8] = include_bytes!("../fixtures/self-test-rsa.pem");
+
+/// The document signed by the self-test.
+const DOCUMENT: &[u8] = b"%PDF-1.7\n% documenso/pdf-sign self-test document\n%%EOF\n";
+
+/// The outcome of one capability of [run].
+pub struct Check {
+  pub capability: &'static str,
+  pub result: Result<(), CmsError>,
+  pub duration: Duration,
+}
+
+/// Sign the embedded fixture document with each supported key algorithm, attached and detached,
+/// and verify the signatures, so a deployment can tell the addon works on its platform.
+///
+/// Signatures are timestamped by `timestamp_server` if given, which checks that the server is
+/// reachable and its tokens verify. Nothing is written and no other service is contacted.
+pub fn run(timestamp_server: Option<&reqwest::Url>) -> Vec<Check> {
+  let mut checks = vec![
+    check("rsa", || sign_and_verify(&rsa_fixture()?, false, None)),
+    check("ecdsa-p256", || {
+      sign_and_verify(&generated(EcdsaCurve::Secp256r1)?, false, None)
+    }),
+    check("ecdsa-p384", || {
+      sign_and_verify(&generated(EcdsaCurve::Secp384r1)?, false, None)
+    }),
+    check("attached", || sign_and_verify(&rsa_fixture()?, true, None)),
+  ];
+
+  if let Some(url) = timestamp_server {
+    checks.push(check("timestamp", || {
+      sign_and_verify(&rsa_fixture()?, false, Some(url))
+    }));
+  }
+
+  checks
+}
+
+fn check(capability: &'static str, test: impl FnOnce() -> Result<(), CmsError>) -> Check {
+  let started = Instant::now();
+  let result = test();
+
+  match &result {
+    Ok(()) => tracing::debug!(capability, "Self-test passed"),
+    Err(error) => tracing::warn!(
+      capability,
+      code = error.code(),
+      "Self-test failed: {}",
+      error
+    ),
+  }
+
+  Check {
+    capability,
+    result,
+    duration: started.elapsed(),
+  }
+}
+
+fn rsa_fixture() -> Result<(InMemorySigningKeyPair, CapturedX509Certificate), CmsError> {
+  let key = keys::private_key_from_pem("fixture", RSA_FIXTURE)?;
+  let certs = certificates::from_pem("fixture", RSA_FIXTURE)?;
+  let cert = certs
+    .first()
+    .ok_or(CmsError::NoCertificate)?
+    .parsed()?
+    .clone();
+
+  Ok((key, cert))
+}
+
+/// A key generated for the self-test, with a self-signed certificate.
+fn generated(
+  curve: EcdsaCurve,
+) -> Result<(InMemorySigningKeyPair, CapturedX509Certificate), CmsError> {
+  let mut builder = X509CertificateBuilder::default();
+
+  builder
+    .subject()
+    .append_common_name_utf8_string("pdf-sign self-test")
+    .map_err(|_| CmsError::KeyGenerationError)?;
+
+  let (cert, key) = builder
+    .create_with_random_keypair(KeyAlgorithm::Ecdsa(curve))
+    .map_err(|_| CmsError::KeyGenerationError)?;
+
+  Ok((key, cert))
+}
+
+fn sign_and_verify(
+  (key, cert): &(InMemorySigningKeyPair, CapturedX509Certificate),
+  attached: bool,
+  timestamp_server: Option<&reqwest::Url>,
+) -> Result<(), CmsError> {
+  let mut signer = SignerBuilder::new(key, cert.clone());
+
+  if let Some(url) = timestamp_server {
+    signer = signer
+      .time_stamp_url(url.clone())
+      .map_err(|error| CmsError::TimestampServerParseError.caused_by(error))?;
+  }
+
+  let builder = match attached {
+    true => SignedDataBuilder::default().content_inline(Bytes::from_static(DOCUMENT)),
+    false => SignedDataBuilder::default().content_external(Bytes::from_static(DOCUMENT)),
+  };
+
+  let der = builder.signer(signer).build_der()?;
+
+  let signed_data = cryptographic_message_syntax::SignedData::parse_ber(&der)
+    .map_err(|error| CmsError::SignedDataParseError.caused_by(error))?;
+
+  let signer = signed_data
+    .signers()
+    .next()
+    .ok_or(CmsError::SignerInfoNotFound)?;
+
+  let verification = |result: Result<(), cryptographic_message_syntax::CmsError>| {
+    result.map_err(|error| CmsError::VerificationError.caused_by(error))
+  };
+
+  verification(signer.verify_signature_with_signed_data(&signed_data))?;
+
+  verification(match attached {
+    true => signer.verify_message_digest_with_signed_data(&signed_data),
+    false => signer.verify_message_digest_with_content(DOCUMENT),
+  })?;
+
+  if timestamp_server.is_some() {
+    match signer.verify_time_stamp_token() {
+      Ok(Some(())) => {}
+      Ok(None) => {
+        return Err(CmsError::TimestampError.caused_by("the signature has no timestamp token"))
+      }
+      Err(error) => return Err(CmsError::VerificationError.caused_by(error)),
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,221 @@
+//! Named signing backends, which create the key of a signature from its options.
+//!
+//! The private key, P12 and Google Cloud KMS keys of the signing functions are created by the
+//! backends registered here, under `privateKey`, `p12` and `gcloud`. Crates embedding the core
+//! add their own providers with [register_backend], which `signWithSigner` then signs with by
+//! name.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use x509_certificate::{CapturedX509Certificate, KeyInfoSigner};
+use zeroize::Zeroizing;
+
+use crate::certificates::LazyCertificate;
+use crate::errors::CmsError;
+#[cfg(feature = "gcloud")]
+use crate::gcloud_signer::GCloudSigner;
+use crate::input::{self, InputFormat};
+use crate::keys::{self, P12Contents, P12Selector};
+use crate::validation;
+
+/// Options for a signer backend, as passed by the caller of `signWithSigner`.
+pub type SignerOptions = HashMap<String, String>;
+
+/// What a backend creates a key from.
+#[derive(Clone, Default)]
+pub struct SignerInput {
+  /// Text options, such as the `signerOptions` of `signWithSigner` or a key path.
+  pub options: SignerOptions,
+  /// Binary options, such as a private key or a P12 container, which are zeroized when dropped.
+  pub data: HashMap<&'static str, Zeroizing<Vec<u8>>>,
+  /// The certificates given with the key, the signing certificate first.
+  pub certs: Vec<LazyCertificate>,
+}
+
+impl SignerInput {
+  /// A text option.
+  pub fn option(&self, name: &str) -> Option<&str> {
+    self.options.get(name).map(String::as_str)
+  }
+
+  /// A binary option, or the bytes of the text option of the same name, such as a PEM key
+  /// given in `signerOptions`.
+  pub fn data(&self, name: &str) -> Option<&[u8]> {
+    self
+      .data
+      .get(name)
+      .map(|data| data.as_slice())
+      .or_else(|| self.option(name).map(str::as_bytes))
+  }
+
+  /// A binary or text option the backend can't do without.
+  pub fn required_data(&self, name: &'static str) -> Result<&[u8], CmsError> {
+    self
+      .data(name)
+      .ok_or_else(|| CmsError::MissingSignerOption.with_detail("option", name))
+  }
+
+  /// The signing certificate given with the key.
+  pub fn signing_certificate(&self) -> Result<&CapturedX509Certificate, CmsError> {
+    self.certs.first().ok_or(CmsError::NoCertificate)?.parsed()
+  }
+}
+
+/// A key created by a backend, with the certificates it signs for.
+pub struct SignerKey {
+  pub signing_key: Box<dyn KeyInfoSigner + Send + Sync>,
+  /// The certificates of the signature, the signing certificate first: those of the input, or
+  /// those the key came with, such as the certificates of a P12 container.
+  pub certs: Vec<LazyCertificate>,
+}
+
+/// A named signing backend that creates a key for each signing operation.
+///
+/// Implement this to add a provider and make it available to `signWithSigner` with
+/// [register_backend], without adding a dedicated `sign_with_*` function.
+pub trait SignerBackend: Send + Sync {
+  /// Create a key for the input.
+  fn create(&self, input: SignerInput) -> Result<SignerKey, CmsError>;
+}
+
+type Backends = RwLock<HashMap<String, Arc<dyn SignerBackend>>>;
+
+fn backends() -> &'static Backends {
+  static BACKENDS: OnceLock<Backends> = OnceLock::new();
+
+  BACKENDS.get_or_init(|| {
+    let mut backends: HashMap<String, Arc<dyn SignerBackend>> = HashMap::new();
+
+    backends.insert(String::from("privateKey"), Arc::new(PrivateKeyBackend));
+    backends.insert(String::from("p12"), Arc::new(P12Backend));
+    #[cfg(feature = "gcloud")]
+    backends.insert(String::from("gcloud"), Arc::new(GCloudBackend));
+
+    RwLock::new(backends)
+  })
+}
+
+/// Register a backend under `name`, replacing any backend previously registered with it.
+pub fn register_backend(name: impl Into<String>, backend: Arc<dyn SignerBackend>) {
+  backends()
+    .write()
+    .unwrap_or_else(|err| err.into_inner())
+    .insert(name.into(), backend);
+}
+
+/// The names of the registered backends, sorted.
+pub fn backend_names() -> Vec<String> {
+  let mut names = backends()
+    .read()
+    .unwrap_or_else(|err| err.into_inner())
+    .keys()
+    .cloned()
+    .collect::<Vec<_>>();
+
+  names.sort();
+  names
+}
+
+/// The backend registered under `name`.
+pub fn find_backend(name: &str) -> Option<Arc<dyn SignerBackend>> {
+  backends()
+    .read()
+    .unwrap_or_else(|err| err.into_inner())
+    .get(name)
+    .cloned()
+}
+
+/// Create a key with the backend registered under `name`.
+///
+/// The registry isn't locked while the key is created, which may take a network request.
+pub fn create_signer(name: &str, input: SignerInput) -> Result<SignerKey, CmsError> {
+  let backend =
+    find_backend(name).ok_or_else(|| CmsError::UnknownSigner.with_detail("signer", name))?;
+
+  backend.create(input)
+}
+
+/// A PKCS#8 private key, in the `privateKey` option, encrypted with the `privateKeyPassword`
+/// option if at all, and read as the `format` option says.
+pub struct PrivateKeyBackend;
+
+impl SignerBackend for PrivateKeyBackend {
+  fn create(&self, input: SignerInput) -> Result<SignerKey, CmsError> {
+    let format = input
+      .option("format")
+      .map(|name| InputFormat::parse("format", name))
+      .transpose()?
+      .unwrap_or_default();
+
+    let private_key = input.required_data("privateKey")?;
+    validation::encoded("privateKey", private_key, "a private key", format)?;
+
+    let signing_key = input::private_key(
+      "privateKey",
+      private_key,
+      input.option("privateKeyPassword"),
+      format,
+    )?;
+
+    Ok(SignerKey {
+      signing_key: Box::new(signing_key),
+      certs: input.certs,
+    })
+  }
+}
+
+/// A key of the P12 container in the `p12` option, with the `password` option, selected by the
+/// `friendlyName` or `thumbprint` option. The key comes with the certificates of the container.
+pub struct P12Backend;
+
+impl SignerBackend for P12Backend {
+  fn create(&self, input: SignerInput) -> Result<SignerKey, CmsError> {
+    let p12 = input.required_data("p12")?;
+    let password = std::str::from_utf8(input.data("password").unwrap_or_default())
+      .map_err(|_| CmsError::InvalidOption("password", String::from("expected UTF-8")))?;
+    let selector = P12Selector::new(
+      input.option("friendlyName").map(String::from),
+      input.option("thumbprint"),
+    )?;
+
+    let P12Contents {
+      private_key,
+      certificates,
+    } = keys::read_p12_entry(p12, password, &selector)?;
+
+    let signing_key = keys::private_key_from_der(&private_key)?;
+
+    let certs = certificates
+      .into_iter()
+      .map(LazyCertificate::from_der)
+      .collect::<Result<Vec<_>, _>>()?;
+
+    // Only the signing certificate is parsed up front, as for PEM certificates.
+    certs.first().ok_or(CmsError::NoCertificate)?.parsed()?;
+
+    Ok(SignerKey {
+      signing_key: Box::new(signing_key),
+      certs,
+    })
+  }
+}
+
+/// Signer backend for Google Cloud KMS, configured with the `keyPath` option.
+#[cfg(feature = "gcloud")]
+pub struct GCloudBackend;
+
+#[cfg(feature = "gcloud")]
+impl SignerBackend for GCloudBackend {
+  fn create(&self, input: SignerInput) -> Result<SignerKey, CmsError> {
+    let key_path = input
+      .option("keyPath")
+      .ok_or_else(|| CmsError::MissingSignerOption.with_detail("option", "keyPath"))?;
+    validation::key_path("keyPath", key_path)?;
+
+    Ok(SignerKey {
+      signing_key: Box::new(GCloudSigner::new(key_path.to_string())?),
+      certs: input.certs,
+    })
+  }
+}
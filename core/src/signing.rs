@@ -0,0 +1,987 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bcder::encode::{self, PrimitiveContent, Values};
+use bcder::{Captured, Integer, Mode, OctetString, Oid, Tag, Utf8String};
+use cryptographic_message_syntax::asn1::rfc3161::{
+  MessageImprint, PkiStatusInfo, TimeStampReq, TimeStampResp, OID_TIME_STAMP_TOKEN,
+};
+use cryptographic_message_syntax::asn1::rfc5652::{
+  CmsVersion, DigestAlgorithmIdentifiers, EncapsulatedContentInfo, IssuerAndSerialNumber,
+  SignatureValue, SignedAttributes, SignedData, SignerIdentifier, SignerInfo, SignerInfos,
+  UnsignedAttributes, OID_CONTENT_TYPE, OID_ID_DATA, OID_ID_SIGNED_DATA, OID_MESSAGE_DIGEST,
+  OID_SIGNING_TIME,
+};
+use cryptographic_message_syntax::{Bytes, TimeStampResponse};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::IntoUrl;
+use ring::rand::{SecureRandom, SystemRandom};
+use x509_certificate::asn1time::UtcTime;
+use x509_certificate::rfc5652::{Attribute, AttributeValue};
+use x509_certificate::{
+  CapturedX509Certificate, DigestAlgorithm, KeyInfoSigner, SignatureAlgorithm,
+};
+
+use crate::attributes::{self, attribute_value};
+use crate::certificates::LazyCertificate;
+use crate::cms;
+use crate::errors::CmsError;
+use crate::http;
+use crate::limiter::Limiter;
+use crate::progress::{self, Stage};
+use crate::rsa_pss;
+
+/// Media types of RFC 3161 timestamp requests and replies over HTTP.
+const HTTP_CONTENT_TYPE_REQUEST: &str = "application/timestamp-query";
+const HTTP_CONTENT_TYPE_RESPONSE: &str = "application/timestamp-reply";
+
+/// How much of a file is read at a time when digesting it.
+const DIGEST_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The padding of RSA signatures.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignatureScheme {
+  /// RSASSA-PKCS1-v1_5, which every validator accepts.
+  #[default]
+  Pkcs1,
+  /// RSASSA-PSS, for certificates restricted to it.
+  Pss,
+}
+
+impl SignatureScheme {
+  /// Parse a signature scheme, `pkcs1` or `pss`. `option` is the name of the option the scheme
+  /// was given for, to report errors against.
+  pub fn parse(option: &'static str, name: &str) -> Result<Self, CmsError> {
+    match name {
+      "pkcs1" => Ok(Self::Pkcs1),
+      "pss" => Ok(Self::Pss),
+      _ => Err(CmsError::InvalidOption(
+        option,
+        String::from("expected pkcs1 or pss"),
+      )),
+    }
+  }
+}
+
+/// A signer to add to a [SignedDataBuilder].
+///
+/// This mirrors the builder of the CMS crate, which hashes the content itself and therefore
+/// can't sign a precomputed digest.
+pub struct SignerBuilder<'a> {
+  signing_key: &'a dyn KeyInfoSigner,
+  signing_certificate: CapturedX509Certificate,
+  extra_signed_attributes: Vec<Attribute>,
+  time_stamp_url: Option<reqwest::Url>,
+  limiter: Option<Arc<Limiter>>,
+  signature_scheme: SignatureScheme,
+}
+
+impl<'a> SignerBuilder<'a> {
+  pub fn new(
+    signing_key: &'a dyn KeyInfoSigner,
+    signing_certificate: CapturedX509Certificate,
+  ) -> Self {
+    Self {
+      signing_key,
+      signing_certificate,
+      extra_signed_attributes: Vec::new(),
+      time_stamp_url: None,
+      limiter: None,
+      signature_scheme: SignatureScheme::Pkcs1,
+    }
+  }
+
+  /// Add a signed attribute, in addition to content-type, message-digest and signing-time.
+  #[must_use]
+  pub fn signed_attribute(mut self, typ: Oid, mut values: Vec<AttributeValue>) -> Self {
+    attributes::sort_values(&mut values);
+
+    self.extra_signed_attributes.push(Attribute { typ, values });
+    self
+  }
+
+  /// Add a timestamp token from an RFC 3161 server as an unsigned attribute.
+  pub fn time_stamp_url(mut self, url: impl IntoUrl) -> Result<Self, reqwest::Error> {
+    self.time_stamp_url = Some(url.into_url()?);
+    Ok(self)
+  }
+
+  /// Limit the requests to the key and the timestamp server, together with the other signers
+  /// sharing the limiter.
+  #[must_use]
+  pub fn limiter(mut self, limiter: Arc<Limiter>) -> Self {
+    self.limiter = Some(limiter);
+    self
+  }
+
+  /// Set the padding of RSA signatures, PKCS#1 v1.5 by default. RSASSA-PSS needs an RSA key
+  /// held in memory.
+  #[must_use]
+  pub fn signature_scheme(mut self, signature_scheme: SignatureScheme) -> Self {
+    self.signature_scheme = signature_scheme;
+    self
+  }
+
+  pub fn signing_certificate(&self) -> &CapturedX509Certificate {
+    &self.signing_certificate
+  }
+
+  /// The digest algorithm of the signature, which is also used for the content.
+  ///
+  /// Ed25519 signs the signed attributes themselves rather than a digest of them, and the
+  /// content is digested with SHA-512 (RFC 8419).
+  pub fn digest_algorithm(&self) -> Result<DigestAlgorithm, CmsError> {
+    match self
+      .signing_key
+      .signature_algorithm()
+      .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?
+    {
+      SignatureAlgorithm::Ed25519 => Ok(DigestAlgorithm::Sha512),
+      algorithm => algorithm
+        .digest_algorithm()
+        .ok_or(CmsError::UnsupportedKeyAlgorithm),
+    }
+  }
+
+  /// Build a counter-signature over the signature value of another signer info.
+  ///
+  /// As required by RFC 5652 section 11.4, the content-type attribute is omitted.
+  pub fn build_counter_signature(
+    &self,
+    signature: &[u8],
+    signing_time: &UtcTime,
+  ) -> Result<SignerInfo, CmsError> {
+    let digest = self.digest_algorithm()?.digest_data(signature);
+
+    let mut signer_info = self.sign(None, &digest, Some(signing_time))?;
+
+    if let Some(request) = self.time_stamp_request()? {
+      progress::report(Stage::Timestamping);
+      request.add_to(&mut signer_info)?;
+    }
+
+    Ok(signer_info)
+  }
+
+  /// Build a signer info without its timestamp, which is added from [Self::time_stamp_request].
+  fn sign(
+    &self,
+    content_type: Option<&Oid>,
+    digest: &[u8],
+    signing_time: Option<&UtcTime>,
+  ) -> Result<SignerInfo, CmsError> {
+    let digest_algorithm = self.digest_algorithm()?;
+
+    let mut signed_attributes = SignedAttributes::default();
+
+    if let Some(content_type) = content_type {
+      signed_attributes.push(Attribute {
+        typ: Oid(Bytes::from_static(OID_CONTENT_TYPE.0)),
+        values: vec![attribute_value(content_type.encode_ref())],
+      });
+    }
+
+    signed_attributes.push(Attribute {
+      typ: Oid(Bytes::from_static(OID_MESSAGE_DIGEST.0)),
+      values: vec![attribute_value(OctetString::encode_slice(digest))],
+    });
+
+    if let Some(signing_time) = signing_time {
+      signed_attributes.push(Attribute {
+        typ: Oid(Bytes::from_static(OID_SIGNING_TIME.0)),
+        values: vec![attribute_value(signing_time.clone().encode())],
+      });
+    }
+
+    signed_attributes.extend(self.extra_signed_attributes.iter().cloned());
+
+    // Signed attributes are DER encoded, so the SET OF must be sorted by the encoding of each
+    // attribute. The values of each attribute were sorted when it was added.
+    let signed_attributes = signed_attributes
+      .as_sorted()
+      .map_err(|error| CmsError::BuildSignedDataError.caused_by(error))?;
+
+    let mut signer_info = SignerInfo {
+      version: CmsVersion::V1,
+      sid: SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+        issuer: self.signing_certificate.issuer_name().clone(),
+        serial_number: self.signing_certificate.serial_number_asn1().clone(),
+      }),
+      digest_algorithm: digest_algorithm.into(),
+      signed_attributes: Some(signed_attributes),
+      signature_algorithm: match self.signature_scheme {
+        SignatureScheme::Pkcs1 => self
+          .signing_key
+          .signature_algorithm()
+          .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?
+          .into(),
+        SignatureScheme::Pss => rsa_pss::algorithm_identifier(digest_algorithm),
+      },
+      signature: SignatureValue::new(Bytes::new()),
+      unsigned_attributes: None,
+      signed_attributes_data: None,
+    };
+
+    // The signature is over the DER encoded signed attributes, not the content.
+    let signed_content = signer_info
+      .signed_attributes_digested_content()
+      .map_err(|error| CmsError::BuildSignedDataError.caused_by(error))?
+      .ok_or(CmsError::BuildSignedDataError)?;
+
+    progress::report(Stage::Signing);
+
+    let permit = self.limiter.as_deref().map(Limiter::acquire);
+
+    let signature = match self.signature_scheme {
+      SignatureScheme::Pkcs1 => self
+        .signing_key
+        .try_sign(&signed_content)
+        .map(Vec::from)
+        .map_err(CmsError::signing_failed),
+      SignatureScheme::Pss => rsa_pss::sign(self.signing_key, digest_algorithm, &signed_content),
+    }
+    .map_err(
+      |error| match self.signing_certificate.subject_name().user_friendly_str() {
+        Ok(subject) => error.with_detail("subject", subject),
+        Err(_) => error,
+      },
+    )?;
+
+    drop(permit);
+
+    signer_info.signature = SignatureValue::new(Bytes::from(signature));
+
+    Ok(signer_info)
+  }
+
+  /// The timestamp to add to the signer info built by this signer, if any.
+  fn time_stamp_request(&self) -> Result<Option<TimeStampRequest>, CmsError> {
+    let Some(url) = &self.time_stamp_url else {
+      return Ok(None);
+    };
+
+    Ok(Some(TimeStampRequest {
+      url: url.clone(),
+      digest_algorithm: self.digest_algorithm()?,
+      limiter: self.limiter.clone(),
+    }))
+  }
+}
+
+/// A timestamp to request for a signer info once it's signed.
+///
+/// Unlike a [SignerBuilder], which may hold a key that must stay on the calling thread, this can
+/// be sent to another thread, so the timestamps of several signers can be requested at once.
+struct TimeStampRequest {
+  url: reqwest::Url,
+  digest_algorithm: DigestAlgorithm,
+  limiter: Option<Arc<Limiter>>,
+}
+
+impl TimeStampRequest {
+  fn add_to(&self, signer_info: &mut SignerInfo) -> Result<(), CmsError> {
+    let permit = self.limiter.as_deref().map(Limiter::acquire);
+
+    // The timestamp is over the signature, binding it to a point in time.
+    let time_stamp_token = time_stamp_token(
+      &self.url,
+      signer_info.signature.to_bytes().as_ref(),
+      self.digest_algorithm,
+    )?;
+
+    drop(permit);
+
+    let mut unsigned_attributes = UnsignedAttributes::default();
+
+    unsigned_attributes.push(Attribute {
+      typ: Oid(Bytes::from_static(OID_TIME_STAMP_TOKEN.0)),
+      values: vec![AttributeValue::new(time_stamp_token)],
+    });
+
+    signer_info.unsigned_attributes = Some(unsigned_attributes);
+
+    Ok(())
+  }
+}
+
+/// Add the timestamps of several signer infos, requesting them concurrently as each is a round
+/// trip to a server.
+fn add_time_stamps(
+  signer_infos: &mut [(SignerInfo, Option<TimeStampRequest>)],
+) -> Result<(), CmsError> {
+  let mut pending = signer_infos
+    .iter_mut()
+    .filter_map(|(signer_info, request)| Some((signer_info, request.as_ref()?)))
+    .collect::<Vec<_>>();
+
+  if pending.is_empty() {
+    return Ok(());
+  }
+
+  progress::report(Stage::Timestamping);
+
+  if let [(signer_info, request)] = pending.as_mut_slice() {
+    return request.add_to(signer_info);
+  }
+
+  // Logs of the requests belong to the operation that made them.
+  let span = tracing::Span::current();
+
+  std::thread::scope(|scope| {
+    let handles = pending
+      .into_iter()
+      .map(|(signer_info, request)| {
+        let span = &span;
+        scope.spawn(move || span.in_scope(|| request.add_to(signer_info)))
+      })
+      .collect::<Vec<_>>();
+
+    // Threads still running after an error are joined when the scope ends.
+    handles.into_iter().try_for_each(|handle| {
+      handle
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+  })
+}
+
+/// Request a timestamp token for a message from an RFC 3161 server.
+///
+/// The token is returned as encoded by the server, as a ContentInfo containing SignedData.
+pub fn time_stamp_token(
+  url: &reqwest::Url,
+  message: &[u8],
+  digest_algorithm: DigestAlgorithm,
+) -> Result<Captured, CmsError> {
+  time_stamp_token_for_digest(
+    url,
+    digest_algorithm.digest_data(message).as_ref(),
+    digest_algorithm,
+  )
+}
+
+/// Request a timestamp token for the digest of a message, e.g. of the byte range of a PDF for a
+/// document timestamp, from an RFC 3161 server.
+pub fn time_stamp_token_for_digest(
+  url: &reqwest::Url,
+  digest: &[u8],
+  digest_algorithm: DigestAlgorithm,
+) -> Result<Captured, CmsError> {
+  check_digest(digest, digest_algorithm)?;
+
+  request_time_stamp_token(url, digest, digest_algorithm)
+    .map_err(|error| error.with_detail("url", public_url(url)))
+}
+
+/// Check that a precomputed digest is as long as the digests of its algorithm.
+pub fn check_digest(digest: &[u8], digest_algorithm: DigestAlgorithm) -> Result<(), CmsError> {
+  let length = digest_algorithm.digester().finish().as_ref().len();
+
+  if digest.len() != length {
+    return Err(CmsError::InvalidDigest.caused_by(format!(
+      "expected {} bytes for {}, got {}",
+      length,
+      digest_algorithm,
+      digest.len()
+    )));
+  }
+
+  Ok(())
+}
+
+/// A URL without the username and password it may carry, to report errors against.
+fn public_url(url: &reqwest::Url) -> reqwest::Url {
+  let mut url = url.clone();
+  let _ = url.set_username("");
+  let _ = url.set_password(None);
+  url
+}
+
+fn request_time_stamp_token(
+  url: &reqwest::Url,
+  digest: &[u8],
+  digest_algorithm: DigestAlgorithm,
+) -> Result<Captured, CmsError> {
+  tracing::debug!(url = %url, "Requesting timestamp token");
+
+  let response = time_stamp_request(url, digest, digest_algorithm)?;
+
+  if !response.is_success() {
+    tracing::warn!(status = ?response.status.status, "Timestamp server rejected the request");
+    return Err(CmsError::TimestampError.caused_by(rejection(&response.status)));
+  }
+
+  let token = response
+    .time_stamp_token
+    .as_ref()
+    .ok_or_else(|| CmsError::TimestampError.caused_by("the response has no timestamp token"))?;
+
+  if token.content_type != OID_ID_SIGNED_DATA {
+    return Err(CmsError::TimestampError.caused_by("the timestamp token is not SignedData"));
+  }
+
+  // The ContentInfo encoding of the CMS crate drops the [0] tag of the content, so the token is
+  // encoded here. The content is kept as it was received.
+  Ok(Captured::from_values(
+    Mode::Der,
+    encode::sequence((
+      token.content_type.encode_ref(),
+      encode::sequence_as(Tag::CTX_0, &token.content),
+    )),
+  ))
+}
+
+/// Send a timestamp request for the digest of a message, with a random nonce that the response
+/// must echo.
+///
+/// This mirrors `time_stamp_message_http` of the CMS crate, which can't use a proxy.
+fn time_stamp_request(
+  url: &reqwest::Url,
+  digest: &[u8],
+  digest_algorithm: DigestAlgorithm,
+) -> Result<TimeStampResponse, CmsError> {
+  let mut nonce = [0u8; 8];
+  SystemRandom::new()
+    .fill(&mut nonce)
+    .map_err(|_| CmsError::TimestampError.caused_by("failed to generate a nonce"))?;
+
+  let request = TimeStampReq {
+    version: Integer::from(1),
+    message_imprint: MessageImprint {
+      hash_algorithm: digest_algorithm.into(),
+      hashed_message: OctetString::new(Bytes::copy_from_slice(digest)),
+    },
+    req_policy: None,
+    nonce: Some(Integer::from(u64::from_le_bytes(nonce))),
+    cert_req: Some(true),
+    extensions: None,
+  };
+
+  let mut body = Vec::new();
+  request
+    .encode_ref()
+    .write_encoded(Mode::Der, &mut body)
+    .map_err(|error| CmsError::TimestampError.caused_by(error))?;
+
+  let response = http::client()
+    .and_then(|client| {
+      client
+        .post(url.clone())
+        .header(CONTENT_TYPE, HTTP_CONTENT_TYPE_REQUEST)
+        .body(body)
+        .send()
+    })
+    .and_then(|response| response.error_for_status())
+    .map_err(|error| CmsError::TimestampError.caused_by(error))?;
+
+  if response
+    .headers()
+    .get(CONTENT_TYPE)
+    .map(|value| value.as_bytes())
+    != Some(HTTP_CONTENT_TYPE_RESPONSE.as_bytes())
+  {
+    return Err(CmsError::TimestampError.caused_by("the response is not a timestamp reply"));
+  }
+
+  let response = response
+    .bytes()
+    .map_err(|error| CmsError::TimestampError.caused_by(error))?;
+
+  let response = TimeStampResponse::from(
+    Mode::Der
+      .decode(response.as_ref(), TimeStampResp::take_from)
+      .map_err(|error| CmsError::TimestampError.caused_by(error))?,
+  );
+
+  if response.is_success() {
+    let tst_info = response
+      .tst_info()
+      .map_err(|error| CmsError::TimestampError.caused_by(error))?;
+
+    if let Some(tst_info) = tst_info {
+      if tst_info.nonce != request.nonce {
+        return Err(CmsError::TimestampError.caused_by("the response nonce doesn't match"));
+      }
+
+      // A token for another message would be embedded without complaint, and only fail
+      // validation.
+      if tst_info.message_imprint.hashed_message.to_bytes() != digest {
+        return Err(
+          CmsError::TimestampError.caused_by("the response message imprint doesn't match"),
+        );
+      }
+    }
+  }
+
+  Ok(response)
+}
+
+/// Helper function to describe why a timestamp server rejected a request, from the status,
+/// failure info and text of its response, e.g. `Rejection (BadAlg): unsupported algorithm`.
+fn rejection(status: &PkiStatusInfo) -> String {
+  let mut reason = format!("{:?}", status.status);
+
+  if let Some(fail_info) = status.fail_info {
+    reason.push_str(&format!(" ({:?})", fail_info));
+  }
+
+  // PKIFreeText ::= SEQUENCE SIZE (1..MAX) OF UTF8String
+  let text = status.status_string.as_ref().and_then(|text| {
+    let encoded = text.encode_ref().to_captured(Mode::Der);
+
+    Mode::Der
+      .decode(encoded.as_slice(), |cons| {
+        cons.take_sequence(|cons| {
+          let mut lines = Vec::new();
+
+          while let Some(line) =
+            cons.take_opt_value_if(Tag::UTF8_STRING, Utf8String::from_content)?
+          {
+            lines.push(line.to_string());
+          }
+
+          Ok(lines.join(" "))
+        })
+      })
+      .ok()
+  });
+
+  if let Some(text) = text.filter(|text| !text.is_empty()) {
+    reason.push_str(": ");
+    reason.push_str(&text);
+  }
+
+  format!("the server responded with status {}", reason)
+}
+
+/// The content to sign.
+enum SignedContent {
+  /// Content embedded in the signature as eContent.
+  Inline(Bytes),
+  /// Content that is digested but not embedded.
+  External(Bytes),
+  /// The digest of content that is not embedded, computed by the caller.
+  Digest(DigestAlgorithm, Vec<u8>),
+  /// A file that is digested as it's read but not embedded.
+  File(PathBuf),
+}
+
+impl SignedContent {
+  /// The digest of the content for each of the algorithms.
+  fn digests(&self, algorithms: &[DigestAlgorithm]) -> Result<Vec<Vec<u8>>, CmsError> {
+    match self {
+      Self::Inline(content) | Self::External(content) => Ok(
+        algorithms
+          .iter()
+          .map(|algorithm| algorithm.digest_data(content))
+          .collect(),
+      ),
+      Self::Digest(digest_algorithm, digest) => algorithms
+        .iter()
+        .map(|algorithm| {
+          (algorithm == digest_algorithm)
+            .then(|| digest.clone())
+            .ok_or(CmsError::UnsupportedDigestAlgorithm)
+        })
+        .collect(),
+      Self::File(path) => digest_file(path, algorithms),
+    }
+  }
+}
+
+/// Digest a file with several algorithms in a single pass, reading it in chunks so it's never
+/// held in memory as a whole.
+pub fn digest_file(path: &Path, algorithms: &[DigestAlgorithm]) -> Result<Vec<Vec<u8>>, CmsError> {
+  let error = |error: io::Error| {
+    CmsError::FileReadError
+      .caused_by(format!("{}: {}", path.display(), error))
+      .with_detail("path", path.display())
+  };
+
+  let mut file = File::open(path).map_err(error)?;
+  let mut digesters = algorithms
+    .iter()
+    .map(DigestAlgorithm::digester)
+    .collect::<Vec<_>>();
+  let mut chunk = vec![0; DIGEST_CHUNK_SIZE];
+
+  loop {
+    let read = match file.read(&mut chunk) {
+      Ok(0) => break,
+      Ok(read) => read,
+      Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+      Err(err) => return Err(error(err)),
+    };
+
+    for digester in &mut digesters {
+      digester.update(&chunk[..read]);
+    }
+  }
+
+  Ok(
+    digesters
+      .into_iter()
+      .map(|digester| digester.finish().as_ref().to_vec())
+      .collect(),
+  )
+}
+
+/// Builds a DER encoded SignedData structure.
+pub struct SignedDataBuilder<'a> {
+  signed_content: SignedContent,
+  signers: Vec<SignerBuilder<'a>>,
+  certificates: Vec<LazyCertificate>,
+  content_type: Oid,
+  signing_time: Option<UtcTime>,
+  null_parameters: bool,
+  version: Option<CmsVersion>,
+  signer_info_version: Option<CmsVersion>,
+  unsigned_attributes: Vec<(Oid, Vec<AttributeValue>)>,
+}
+
+impl Default for SignedDataBuilder<'_> {
+  fn default() -> Self {
+    Self {
+      signed_content: SignedContent::External(Bytes::new()),
+      signers: Vec::new(),
+      certificates: Vec::new(),
+      content_type: Oid(Bytes::from_static(OID_ID_DATA.0)),
+      signing_time: Some(UtcTime::now()),
+      null_parameters: true,
+      version: None,
+      signer_info_version: None,
+      unsigned_attributes: Vec::new(),
+    }
+  }
+}
+
+impl<'a> SignedDataBuilder<'a> {
+  /// Embed the content in the signature.
+  ///
+  /// The content is shared rather than copied, e.g. a `Bytes::from_owner` of a buffer owned by
+  /// the caller.
+  #[must_use]
+  pub fn content_inline(mut self, content: impl Into<Bytes>) -> Self {
+    self.signed_content = SignedContent::Inline(content.into());
+    self
+  }
+
+  /// Sign the content without embedding it, as for PDF signatures.
+  #[must_use]
+  pub fn content_external(mut self, content: impl Into<Bytes>) -> Self {
+    self.signed_content = SignedContent::External(content.into());
+    self
+  }
+
+  /// Sign a file without embedding it, hashing it as it's read rather than loading it.
+  #[must_use]
+  pub fn content_file(mut self, path: impl Into<PathBuf>) -> Self {
+    self.signed_content = SignedContent::File(path.into());
+    self
+  }
+
+  /// Sign content that is not embedded by its digest.
+  ///
+  /// The digest algorithm must be the one used by the signature algorithm of every signer.
+  #[must_use]
+  pub fn content_digest(mut self, digest_algorithm: DigestAlgorithm, digest: Vec<u8>) -> Self {
+    self.signed_content = SignedContent::Digest(digest_algorithm, digest);
+    self
+  }
+
+  #[must_use]
+  pub fn signer(mut self, signer: SignerBuilder<'a>) -> Self {
+    self.signers.push(signer);
+    self
+  }
+
+  /// Add certificates, skipping any that were already added.
+  ///
+  /// [LazyCertificate]s are embedded without being parsed.
+  #[must_use]
+  pub fn certificates<C: Into<LazyCertificate>>(mut self, certs: impl Iterator<Item = C>) -> Self {
+    for cert in certs {
+      let cert = cert.into();
+
+      if !self.certificates.contains(&cert) {
+        self.certificates.push(cert);
+      }
+    }
+
+    self
+  }
+
+  /// Set the type of the signed content, id-data by default.
+  #[must_use]
+  pub fn content_type(mut self, oid: Oid) -> Self {
+    self.content_type = oid;
+    self
+  }
+
+  /// Set the signing time, the current time by default, or omit the signing-time attribute.
+  #[must_use]
+  pub fn signing_time(mut self, time: Option<UtcTime>) -> Self {
+    self.signing_time = time;
+    self
+  }
+
+  /// Whether algorithm identifiers carry NULL parameters, which is the default.
+  #[must_use]
+  pub fn null_parameters(mut self, null_parameters: bool) -> Self {
+    self.null_parameters = null_parameters;
+    self
+  }
+
+  /// Override the SignedData version, which is otherwise the lowest RFC 5652 allows.
+  #[must_use]
+  pub fn version(mut self, version: Option<CmsVersion>) -> Self {
+    self.version = version;
+    self
+  }
+
+  /// Override the version of every signer info, which is otherwise v1 for the issuer and serial
+  /// number signer identifier.
+  #[must_use]
+  pub fn signer_info_version(mut self, version: Option<CmsVersion>) -> Self {
+    self.signer_info_version = version;
+    self
+  }
+
+  /// Add unsigned attributes to every signer info, after its timestamp token.
+  ///
+  /// The result is the same as adding them to the encoded signed data with
+  /// [cms::SignerInfo::add_unsigned_attributes], without decoding and encoding it again.
+  #[must_use]
+  pub fn unsigned_attributes(mut self, attributes: Vec<(Oid, Vec<AttributeValue>)>) -> Self {
+    self.unsigned_attributes = attributes;
+    self
+  }
+
+  pub fn build_der(&self) -> Result<Vec<u8>, CmsError> {
+    let mut signed = Vec::with_capacity(self.signers.len());
+    let mut digest_algorithms = DigestAlgorithmIdentifiers::default();
+    let mut certificates = self.certificates.clone();
+
+    let mut signer_digest_algorithms = Vec::new();
+
+    for signer in &self.signers {
+      let digest_algorithm = signer.digest_algorithm()?;
+
+      if !signer_digest_algorithms.contains(&digest_algorithm) {
+        signer_digest_algorithms.push(digest_algorithm);
+      }
+    }
+
+    progress::report(Stage::Hashing);
+
+    // The content is digested once for each algorithm, however many signers use it.
+    let digests = self.signed_content.digests(&signer_digest_algorithms)?;
+
+    for signer in &self.signers {
+      let digest_algorithm = signer.digest_algorithm()?;
+
+      let digest = signer_digest_algorithms
+        .iter()
+        .position(|algorithm| *algorithm == digest_algorithm)
+        .map(|index| &digests[index])
+        .ok_or(CmsError::UnsupportedDigestAlgorithm)?;
+
+      // Keys are used one after another, as they may not be usable from other threads.
+      tracing::debug!(
+        subject = %signer
+          .signing_certificate
+          .subject_name()
+          .user_friendly_str()
+          .unwrap_or_default(),
+        digest_algorithm = ?digest_algorithm,
+        "Signing with certificate"
+      );
+
+      let mut signer_info =
+        signer.sign(Some(&self.content_type), digest, self.signing_time.as_ref())?;
+
+      if let Some(version) = self.signer_info_version {
+        signer_info.version = version;
+      }
+
+      signed.push((signer_info, signer.time_stamp_request()?));
+
+      if !digest_algorithms
+        .iter()
+        .any(|algorithm| algorithm.algorithm == Oid::from(digest_algorithm))
+      {
+        digest_algorithms.push(digest_algorithm.into());
+      }
+
+      if !certificates
+        .iter()
+        .any(|cert| cert.der() == signer.signing_certificate.constructed_data())
+      {
+        certificates.push(signer.signing_certificate.clone().into());
+      }
+    }
+
+    add_time_stamps(&mut signed)?;
+
+    if !self.unsigned_attributes.is_empty() {
+      for (signer_info, _) in &mut signed {
+        let unsigned_attributes = signer_info
+          .unsigned_attributes
+          .get_or_insert_with(UnsignedAttributes::default);
+
+        // An attribute may not appear twice, e.g. a custom one with the timestamp token's type.
+        if self.unsigned_attributes.iter().any(|(oid, _)| {
+          unsigned_attributes
+            .iter()
+            .any(|attribute| attribute.typ == *oid)
+        }) {
+          return Err(CmsError::InvalidCustomAttribute);
+        }
+
+        unsigned_attributes.extend(self.unsigned_attributes.iter().map(|(typ, values)| {
+          Attribute {
+            typ: typ.clone(),
+            values: values.clone(),
+          }
+        }));
+      }
+    }
+
+    let mut signer_infos = SignerInfos::default();
+    signer_infos.extend(signed.into_iter().map(|(signer_info, _)| signer_info));
+
+    // Log the certificates as a chain, with issuing certificates before those they issued.
+    let mut chain = certificates.iter().collect::<Vec<_>>();
+
+    chain.sort_by(|a, b| a.compare_issuer(b));
+
+    tracing::debug!(
+      certificates = ?chain
+        .iter()
+        .map(|cert| cert.subject_str().unwrap_or_default())
+        .collect::<Vec<_>>(),
+      "Embedded certificates"
+    );
+
+    // The certificate set is a SET OF, which DER orders by encoding.
+    certificates.sort_by(|a, b| a.der().cmp(b.der()));
+
+    // RFC 5652 section 5.1, for signed data with only X.509 certificates and no CRLs of other
+    // formats.
+    let version = self.version.unwrap_or_else(|| {
+      let is_v3 = self.content_type != OID_ID_DATA
+        || signer_infos
+          .iter()
+          .any(|signer_info| signer_info.version == CmsVersion::V3);
+
+      if is_v3 {
+        CmsVersion::V3
+      } else {
+        CmsVersion::V1
+      }
+    });
+
+    let signed_data = SignedData {
+      version,
+      digest_algorithms,
+      content_info: EncapsulatedContentInfo {
+        content_type: self.content_type.clone(),
+        content: match &self.signed_content {
+          SignedContent::Inline(content) => Some(OctetString::new(content.clone())),
+          SignedContent::External(_) | SignedContent::Digest(..) | SignedContent::File(_) => None,
+        },
+      },
+      certificates: None,
+      crls: None,
+      signer_infos,
+    };
+
+    progress::report(Stage::Writing);
+
+    // This is `SignedData::encode_ref` with the certificates embedded as they were given, so
+    // they don't have to be parsed. The signed data is encoded once, into a buffer of its exact
+    // size.
+    let encoded = encode::sequence((
+      OID_ID_SIGNED_DATA.encode_ref(),
+      encode::sequence_as(
+        Tag::CTX_0,
+        encode::sequence((
+          signed_data.version.encode(),
+          signed_data.digest_algorithms.encode_ref(),
+          signed_data.content_info.encode_ref(),
+          (!certificates.is_empty()).then(|| encode::set_as(Tag::CTX_0, &certificates)),
+          signed_data.signer_infos.encode_ref(),
+        )),
+      ),
+    ));
+    let mut der = Vec::with_capacity(encoded.encoded_len(Mode::Der));
+
+    encoded
+      .write_encoded(Mode::Der, &mut der)
+      .map_err(|error| CmsError::BuildSignedDataError.caused_by(error))?;
+
+    // The CMS crate writes NULL parameters for every algorithm, which elliptic curve signature
+    // algorithms must not have.
+    let elliptic_curve = self.signers.iter().any(|signer| {
+      matches!(
+        signer.signing_key.signature_algorithm(),
+        Ok(
+          SignatureAlgorithm::EcdsaSha256
+            | SignatureAlgorithm::EcdsaSha384
+            | SignatureAlgorithm::Ed25519
+        )
+      )
+    });
+
+    if !self.null_parameters || elliptic_curve {
+      let mut signed_data = cms::SignedData::decode(&der)?;
+
+      match self.null_parameters {
+        true => signed_data.omit_elliptic_curve_null_parameters()?,
+        false => signed_data.omit_null_parameters()?,
+      }
+      der = signed_data.encode();
+    }
+
+    tracing::debug!(size = der.len(), "Encoded signed data");
+
+    Ok(der)
+  }
+}
+
+/// Convert a signature to the encoding of XML-DSig and JWS, which for ECDSA is the concatenation
+/// of r and s rather than their DER encoded SEQUENCE (RFC 4050, RFC 7518).
+pub(crate) fn raw_signature(
+  signature_algorithm: SignatureAlgorithm,
+  signature: &[u8],
+) -> Result<Vec<u8>, CmsError> {
+  let size = match signature_algorithm {
+    SignatureAlgorithm::EcdsaSha256 => 32,
+    SignatureAlgorithm::EcdsaSha384 => 48,
+    _ => return Ok(signature.to_vec()),
+  };
+
+  let (r, s) = Mode::Der
+    .decode(signature, |cons| {
+      cons.take_sequence(|cons| Ok((Integer::take_from(cons)?, Integer::take_from(cons)?)))
+    })
+    .map_err(|error| CmsError::SigningError.caused_by(error))?;
+
+  let mut value = Vec::with_capacity(2 * size);
+
+  for integer in [r, s] {
+    let bytes = integer.as_slice();
+    let bytes = &bytes[bytes.iter().take_while(|byte| **byte == 0).count()..];
+
+    if bytes.len() > size {
+      return Err(CmsError::SigningError.caused_by("ECDSA signature is larger than the curve"));
+    }
+
+    value.resize(value.len() + size - bytes.len(), 0);
+    value.extend_from_slice(bytes);
+  }
+
+  Ok(value)
+}
@@ -0,0 +1,133 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::rand::{SecureRandom, SystemRandom};
+use x509_certificate::DigestAlgorithm;
+
+use crate::cms::SignedData;
+use crate::errors::CmsError;
+
+/// The length of the lines of the base64 encoded signature (RFC 2045).
+const LINE_LENGTH: usize = 76;
+
+/// Wrap a MIME entity and a detached CMS signature of it in a multipart/signed message
+/// (RFC 8551). The caller adds the envelope headers, such as `From` and `Subject`.
+///
+/// The body is embedded as it is, so it must be the canonical form that was signed, with CRLF
+/// line endings. It is checked against the message-digest of the first signer info, so a body
+/// that was changed, e.g. by line ending conversion, fails here rather than at the recipient.
+pub fn multipart_signed(body: &[u8], signature: &[u8]) -> Result<Vec<u8>, CmsError> {
+  let bare_line_feed = body
+    .iter()
+    .enumerate()
+    .any(|(index, byte)| *byte == b'\n' && (index == 0 || body[index - 1] != b'\r'));
+
+  if bare_line_feed {
+    return Err(CmsError::InvalidOption(
+      "body",
+      String::from("expected CRLF line endings, the canonical form S/MIME signs"),
+    ));
+  }
+
+  let signed_data = SignedData::decode(signature)?;
+
+  if signed_data.encapsulated_content()?.1.is_some() {
+    return Err(CmsError::InvalidOption(
+      "signature",
+      String::from("expected a detached signature, created without attached"),
+    ));
+  }
+
+  let signer_info = signed_data
+    .signer_infos
+    .first()
+    .ok_or_else(|| CmsError::SignerInfoNotFound.with_detail("index", 0))?;
+
+  let digest_algorithm = DigestAlgorithm::try_from(&signer_info.digest_algorithm_oid()?)
+    .map_err(|_| CmsError::UnsupportedDigestAlgorithm)?;
+
+  if let Some(message_digest) = signer_info.message_digest()? {
+    if digest_algorithm.digest_data(body) != message_digest.as_ref() {
+      return Err(CmsError::InvalidOption(
+        "signature",
+        String::from("expected a signature of the body, which has a different digest"),
+      ));
+    }
+  }
+
+  let boundary = boundary(body)?;
+
+  let encoded = STANDARD.encode(signature);
+  let lines = encoded
+    .as_bytes()
+    .chunks(LINE_LENGTH)
+    .map(|line| std::str::from_utf8(line).expect("base64 is ASCII"))
+    .collect::<Vec<_>>()
+    .join("\r\n");
+
+  let mut message = format!(
+    "MIME-Version: 1.0\r\n\
+     Content-Type: multipart/signed; protocol=\"application/pkcs7-signature\"; micalg={}; \
+     boundary=\"{}\"\r\n\
+     \r\n\
+     This is an S/MIME signed message\r\n\
+     \r\n\
+     --{}\r\n",
+    micalg(digest_algorithm),
+    boundary,
+    boundary
+  )
+  .into_bytes();
+
+  message.extend_from_slice(body);
+  message.extend_from_slice(
+    format!(
+      "\r\n--{}\r\n\
+       Content-Type: application/pkcs7-signature; name=\"smime.p7s\"\r\n\
+       Content-Transfer-Encoding: base64\r\n\
+       Content-Disposition: attachment; filename=\"smime.p7s\"\r\n\
+       \r\n\
+       {}\r\n\
+       --{}--\r\n",
+      boundary, lines, boundary
+    )
+    .as_bytes(),
+  );
+
+  tracing::debug!(size = message.len(), "Created S/MIME message");
+
+  Ok(message)
+}
+
+/// The micalg parameter of a digest algorithm (RFC 8551).
+fn micalg(digest_algorithm: DigestAlgorithm) -> &'static str {
+  match digest_algorithm {
+    DigestAlgorithm::Sha1 => "sha-1",
+    DigestAlgorithm::Sha256 => "sha-256",
+    DigestAlgorithm::Sha384 => "sha-384",
+    DigestAlgorithm::Sha512 => "sha-512",
+  }
+}
+
+/// A random multipart boundary that doesn't occur in the body.
+fn boundary(body: &[u8]) -> Result<String, CmsError> {
+  loop {
+    let mut random = [0u8; 12];
+    SystemRandom::new()
+      .fill(&mut random)
+      .map_err(|_| CmsError::SigningError.caused_by("failed to generate a boundary"))?;
+
+    let boundary = format!(
+      "----=_pdf-sign_{}",
+      random
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>()
+    );
+
+    if !body
+      .windows(boundary.len())
+      .any(|window| window == boundary.as_bytes())
+    {
+      return Ok(boundary);
+    }
+  }
+}
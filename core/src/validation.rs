@@ -0,0 +1,221 @@
+use crate::errors::CmsError;
+use crate::input::InputFormat;
+
+/// Beginning of every PEM block, whatever its label.
+const PEM_BEGIN: &[u8] = b"-----BEGIN ";
+
+/// Check the options shared by the signing functions before any work is done, so mistakes are
+/// reported for the option that caused them rather than by whichever step fails first.
+pub fn signing_options(
+  content: Option<&[u8]>,
+  content_path: Option<&str>,
+  digest: Option<&[u8]>,
+  digest_algorithm: Option<&str>,
+  timestamp_server: Option<&str>,
+) -> Result<(), CmsError> {
+  if let Some(content) = content {
+    non_empty("content", content)?;
+  }
+
+  if content_path.is_some_and(str::is_empty) {
+    return Err(invalid("contentPath", "the path is empty"));
+  }
+
+  if let Some(digest) = digest {
+    non_empty("digest", digest)?;
+  }
+
+  crate::parse_digest_algorithm(digest_algorithm)?;
+
+  if let Some(timestamp_server) = timestamp_server {
+    self::timestamp_server("timestampServer", timestamp_server)?;
+  }
+
+  Ok(())
+}
+
+/// Check that a timestamp server is an http or https URL with a host, returning the parsed URL,
+/// so a typo is reported before the key is used rather than by the request at the end.
+pub fn timestamp_server(option: &'static str, value: &str) -> Result<reqwest::Url, CmsError> {
+  let url = reqwest::Url::parse(value)
+    .map_err(|error| invalid(option, format!("not a valid URL: {}", error)))?;
+
+  if !matches!(url.scheme(), "http" | "https") {
+    return Err(invalid(option, "expected an http or https URL"));
+  }
+
+  if url.host_str().is_none_or(str::is_empty) {
+    return Err(invalid(option, "the URL has no host"));
+  }
+
+  Ok(url)
+}
+
+/// Check that a buffer isn't empty, which is usually a file that failed to load.
+pub fn non_empty(option: &'static str, value: &[u8]) -> Result<(), CmsError> {
+  if value.is_empty() {
+    return Err(invalid(option, "the buffer is empty"));
+  }
+
+  Ok(())
+}
+
+/// Check that a buffer looks like PEM, e.g. `what` is "a certificate".
+pub fn pem(option: &'static str, value: &[u8], what: &str) -> Result<(), CmsError> {
+  pem_or(option, value, what, "PEM")
+}
+
+/// Helper function to check that a buffer looks like PEM, where `formats` are the formats the
+/// option takes.
+fn pem_or(option: &'static str, value: &[u8], what: &str, formats: &str) -> Result<(), CmsError> {
+  non_empty(option, value)?;
+
+  if contains_pem(value) {
+    return Ok(());
+  }
+
+  let hint = match sniff(value) {
+    Some(Format::P12) => ", not a P12 container, which signWithP12 takes",
+    Some(Format::Der) => ", not DER",
+    Some(Format::Base64Pem) => ", not base64 encoded PEM, which has to be decoded first",
+    Some(Format::Base64Der) => ", not base64 without the BEGIN and END lines of PEM",
+    Some(Format::Pem) | None => "",
+  };
+
+  Err(invalid(
+    option,
+    format!("expected {} in {} format{}", what, formats, hint),
+  ))
+}
+
+/// Check that a buffer looks like DER other than a P12 container, e.g. `what` is "a
+/// certificate".
+pub fn der(option: &'static str, value: &[u8], what: &str) -> Result<(), CmsError> {
+  non_empty(option, value)?;
+
+  let hint = match sniff(value) {
+    Some(Format::Der) => return Ok(()),
+    Some(Format::P12) => ", not a P12 container, which signWithP12 takes",
+    Some(Format::Pem) => ", not PEM",
+    Some(Format::Base64Pem | Format::Base64Der) => {
+      ", not base64 text, which has to be decoded first"
+    }
+    None => "",
+  };
+
+  Err(invalid(
+    option,
+    format!("expected {} in DER format{}", what, hint),
+  ))
+}
+
+/// Check that a buffer looks like PEM or DER, as `format` says or, for [InputFormat::Auto],
+/// whichever it is.
+pub fn encoded(
+  option: &'static str,
+  value: &[u8],
+  what: &str,
+  format: InputFormat,
+) -> Result<(), CmsError> {
+  match format {
+    InputFormat::Der => der(option, value, what),
+    InputFormat::Auto if is_der(value) => Ok(()),
+    InputFormat::Auto => pem_or(option, value, what, "PEM or DER"),
+    InputFormat::Pem => pem(option, value, what),
+  }
+}
+
+/// Check that a buffer looks like a DER encoded P12 container.
+pub fn p12(option: &'static str, value: &[u8]) -> Result<(), CmsError> {
+  non_empty(option, value)?;
+
+  match sniff(value) {
+    Some(Format::P12) => Ok(()),
+    // Whether DER other than a P12 container is corrupt or something else is left to the parser.
+    Some(Format::Der) => Ok(()),
+    Some(Format::Pem) => Err(invalid(option, "expected a P12 container, not PEM")),
+    Some(Format::Base64Pem | Format::Base64Der) => Err(invalid(
+      option,
+      "expected a P12 container, not base64 text, which has to be decoded first",
+    )),
+    None => Err(invalid(option, "expected a P12 container")),
+  }
+}
+
+/// Check that a Google Cloud KMS key path is the full name of a key version.
+pub fn key_path(option: &'static str, value: &str) -> Result<(), CmsError> {
+  if !value.starts_with("projects/") || !value.contains("/cryptoKeyVersions/") {
+    return Err(invalid(
+      option,
+      "expected a key version name like projects/<project>/locations/<location>/keyRings/<key ring>/cryptoKeys/<key>/cryptoKeyVersions/<version>",
+    ));
+  }
+
+  Ok(())
+}
+
+/// What a buffer given for a key or certificate looks like, to hint at the likely mistake when
+/// it's not what the option takes.
+enum Format {
+  Pem,
+  P12,
+  /// DER other than a P12 container, such as a certificate or PKCS#8 key.
+  Der,
+  /// Base64 text of PEM or DER, e.g. a file that was encoded for an environment variable.
+  Base64Pem,
+  Base64Der,
+}
+
+fn sniff(value: &[u8]) -> Option<Format> {
+  if contains_pem(value) {
+    return Some(Format::Pem);
+  }
+
+  // DER always starts with a SEQUENCE for the structures passed in. A PFX starts with version 3,
+  // while certificates start with a SEQUENCE and PKCS#8 keys with version 0 or 1.
+  if value.first() == Some(&0x30) {
+    let content = match value.get(1) {
+      Some(length @ 0x81..=0x84) => value.get(2 + (length - 0x80) as usize..),
+      Some(_) => value.get(2..),
+      None => None,
+    };
+
+    return match content {
+      Some([0x02, 0x01, 0x03, ..]) => Some(Format::P12),
+      _ => Some(Format::Der),
+    };
+  }
+
+  // Line breaks are allowed, and the start is enough to tell the format.
+  let start = value
+    .iter()
+    .filter(|byte| !byte.is_ascii_whitespace())
+    .take(64)
+    .copied()
+    .collect::<Vec<_>>();
+
+  let start = std::str::from_utf8(&start[..start.len() / 4 * 4]).ok()?;
+  let decoded = crate::keys::decode_base64(start).ok()?;
+
+  match sniff(&decoded)? {
+    Format::Pem => Some(Format::Base64Pem),
+    Format::P12 | Format::Der => Some(Format::Base64Der),
+    Format::Base64Pem | Format::Base64Der => None,
+  }
+}
+
+/// Whether a buffer looks like DER other than a P12 container, such as a certificate or PKCS#8
+/// key.
+pub(crate) fn is_der(value: &[u8]) -> bool {
+  matches!(sniff(value), Some(Format::Der))
+}
+
+fn contains_pem(value: &[u8]) -> bool {
+  value
+    .windows(PEM_BEGIN.len())
+    .any(|window| window == PEM_BEGIN)
+}
+
+fn invalid(option: &'static str, reason: impl Into<String>) -> CmsError {
+  CmsError::InvalidOption(option, reason.into())
+}
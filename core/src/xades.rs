@@ -0,0 +1,258 @@
+use std::fmt::Write;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bcder::{Captured, Mode};
+use ring::rand::{SecureRandom, SystemRandom};
+use x509_certificate::{
+  CapturedX509Certificate, DigestAlgorithm, KeyInfoSigner, SignatureAlgorithm,
+};
+
+use crate::attributes;
+use crate::errors::CmsError;
+use crate::limiter::Limiter;
+use crate::signing;
+
+const DS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const XADES: &str = "http://uri.etsi.org/01903/v1.3.2#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const SIGNED_PROPERTIES_TYPE: &str = "http://uri.etsi.org/01903#SignedProperties";
+
+/// A detached XAdES signature of content that is referenced by a URI rather than embedded.
+pub struct DetachedSignature<'a> {
+  pub signing_key: &'a dyn KeyInfoSigner,
+  /// The signing certificate, followed by the rest of the chain to include.
+  pub certs: &'a [CapturedX509Certificate],
+  pub content: &'a [u8],
+  /// The URI the signature references the content by, typically its file name.
+  pub uri: &'a str,
+  pub mime_type: &'a str,
+  pub signing_time: chrono::DateTime<chrono::Utc>,
+  /// A timestamp server to add a signature timestamp from, for XAdES-T rather than XAdES-BES.
+  pub time_stamp_url: Option<&'a reqwest::Url>,
+  pub limiter: Option<&'a Limiter>,
+}
+
+impl DetachedSignature<'_> {
+  /// Create the ds:Signature document.
+  ///
+  /// The SignedInfo and SignedProperties are written in the form exclusive XML canonicalization
+  /// gives them, so what is signed is exactly what a validator canonicalizes. The content is
+  /// digested as the octets it is, as a reference without transforms requires.
+  pub fn sign(&self) -> Result<String, CmsError> {
+    let cert = self.certs.first().ok_or(CmsError::NoCertificate)?;
+
+    let signature_algorithm = self
+      .signing_key
+      .signature_algorithm()
+      .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+    let digest_algorithm = signature_algorithm
+      .digest_algorithm()
+      .ok_or(CmsError::UnsupportedKeyAlgorithm)?;
+
+    let id = random_id()?;
+    let signature_id = format!("id-{}", id);
+    let reference_id = format!("r-{}", id);
+    let properties_id = format!("xades-{}", id);
+    let value_id = format!("value-{}", id);
+
+    let digest_method = digest_method(digest_algorithm)?;
+
+    let mut signed_properties = String::new();
+    write!(
+      signed_properties,
+      "<xades:SignedProperties xmlns:xades=\"{XADES}\" Id=\"{}\">\
+       <xades:SignedSignatureProperties>\
+       <xades:SigningTime>{}</xades:SigningTime>\
+       <xades:SigningCertificateV2><xades:Cert><xades:CertDigest>\
+       <ds:DigestMethod xmlns:ds=\"{DS}\" Algorithm=\"{}\"></ds:DigestMethod>\
+       <ds:DigestValue xmlns:ds=\"{DS}\">{}</ds:DigestValue>\
+       </xades:CertDigest>\
+       <xades:IssuerSerialV2>{}</xades:IssuerSerialV2>\
+       </xades:Cert></xades:SigningCertificateV2>\
+       </xades:SignedSignatureProperties>\
+       <xades:SignedDataObjectProperties>\
+       <xades:DataObjectFormat ObjectReference=\"#{}\">\
+       <xades:MimeType>{}</xades:MimeType>\
+       </xades:DataObjectFormat>\
+       </xades:SignedDataObjectProperties>\
+       </xades:SignedProperties>",
+      properties_id,
+      self
+        .signing_time
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+      digest_method,
+      STANDARD.encode(digest_algorithm.digest_data(cert.constructed_data())),
+      STANDARD.encode(Captured::from_values(Mode::Der, attributes::issuer_serial(cert)).as_slice()),
+      reference_id,
+      escape_text(self.mime_type),
+    )
+    .expect("writing to a String cannot fail");
+
+    let reference = |attributes: &str, transforms: &str, data: &[u8]| {
+      format!(
+        "<ds:Reference {}>{}\
+         <ds:DigestMethod Algorithm=\"{}\"></ds:DigestMethod>\
+         <ds:DigestValue>{}</ds:DigestValue>\
+         </ds:Reference>",
+        attributes,
+        transforms,
+        digest_method,
+        STANDARD.encode(digest_algorithm.digest_data(data))
+      )
+    };
+
+    // Canonical attribute order is by name: Id, Type, URI.
+    let signed_info = format!(
+      "<ds:SignedInfo xmlns:ds=\"{DS}\">\
+       <ds:CanonicalizationMethod Algorithm=\"{EXC_C14N}\"></ds:CanonicalizationMethod>\
+       <ds:SignatureMethod Algorithm=\"{}\"></ds:SignatureMethod>\
+       {}{}\
+       </ds:SignedInfo>",
+      signature_method(signature_algorithm)?,
+      reference(
+        &format!(
+          "Id=\"{}\" URI=\"{}\"",
+          reference_id,
+          escape_attribute(self.uri)
+        ),
+        "",
+        self.content,
+      ),
+      reference(
+        &format!(
+          "Type=\"{SIGNED_PROPERTIES_TYPE}\" URI=\"#{}\"",
+          properties_id
+        ),
+        &format!(
+          "<ds:Transforms><ds:Transform Algorithm=\"{EXC_C14N}\"></ds:Transform></ds:Transforms>"
+        ),
+        signed_properties.as_bytes(),
+      ),
+    );
+
+    let permit = self.limiter.map(Limiter::acquire);
+
+    let signature = self
+      .signing_key
+      .try_sign(signed_info.as_bytes())
+      .map_err(CmsError::signing_failed)?;
+
+    drop(permit);
+
+    let signature = signing::raw_signature(signature_algorithm, signature.as_ref())?;
+
+    let signature_value = format!(
+      "<ds:SignatureValue xmlns:ds=\"{DS}\" Id=\"{}\">{}</ds:SignatureValue>",
+      value_id,
+      STANDARD.encode(signature)
+    );
+
+    // The signature timestamp is over the canonicalized SignatureValue element.
+    let unsigned_properties = self
+      .time_stamp_url
+      .map(|url| {
+        let token =
+          signing::time_stamp_token(url, signature_value.as_bytes(), DigestAlgorithm::Sha256)?;
+
+        Ok::<_, CmsError>(format!(
+          "<xades:UnsignedProperties><xades:UnsignedSignatureProperties>\
+           <xades:SignatureTimeStamp Id=\"ts-{}\">\
+           <ds:CanonicalizationMethod Algorithm=\"{EXC_C14N}\"></ds:CanonicalizationMethod>\
+           <xades:EncapsulatedTimeStamp>{}</xades:EncapsulatedTimeStamp>\
+           </xades:SignatureTimeStamp>\
+           </xades:UnsignedSignatureProperties></xades:UnsignedProperties>",
+          id,
+          STANDARD.encode(token.as_slice())
+        ))
+      })
+      .transpose()?
+      .unwrap_or_default();
+
+    let key_info = self
+      .certs
+      .iter()
+      .map(|cert| {
+        format!(
+          "<ds:X509Certificate>{}</ds:X509Certificate>",
+          STANDARD.encode(cert.constructed_data())
+        )
+      })
+      .collect::<String>();
+
+    tracing::debug!(
+      uri = self.uri,
+      timestamped = self.time_stamp_url.is_some(),
+      "Created XAdES signature"
+    );
+
+    Ok(format!(
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+       <ds:Signature xmlns:ds=\"{DS}\" Id=\"{}\">\
+       {}{}\
+       <ds:KeyInfo><ds:X509Data>{}</ds:X509Data></ds:KeyInfo>\
+       <ds:Object><xades:QualifyingProperties xmlns:xades=\"{XADES}\" Target=\"#{}\">\
+       {}{}\
+       </xades:QualifyingProperties></ds:Object>\
+       </ds:Signature>",
+      signature_id,
+      signed_info,
+      signature_value,
+      key_info,
+      signature_id,
+      signed_properties,
+      unsigned_properties,
+    ))
+  }
+}
+
+/// The XML-DSig URI of a digest algorithm.
+pub(crate) fn digest_method(digest_algorithm: DigestAlgorithm) -> Result<&'static str, CmsError> {
+  match digest_algorithm {
+    DigestAlgorithm::Sha256 => Ok("http://www.w3.org/2001/04/xmlenc#sha256"),
+    DigestAlgorithm::Sha384 => Ok("http://www.w3.org/2001/04/xmldsig-more#sha384"),
+    DigestAlgorithm::Sha512 => Ok("http://www.w3.org/2001/04/xmlenc#sha512"),
+    _ => Err(CmsError::UnsupportedDigestAlgorithm),
+  }
+}
+
+/// The XML-DSig URI of a signature algorithm.
+fn signature_method(signature_algorithm: SignatureAlgorithm) -> Result<&'static str, CmsError> {
+  match signature_algorithm {
+    SignatureAlgorithm::RsaSha256 => Ok("http://www.w3.org/2001/04/xmldsig-more#rsa-sha256"),
+    SignatureAlgorithm::RsaSha384 => Ok("http://www.w3.org/2001/04/xmldsig-more#rsa-sha384"),
+    SignatureAlgorithm::RsaSha512 => Ok("http://www.w3.org/2001/04/xmldsig-more#rsa-sha512"),
+    SignatureAlgorithm::EcdsaSha256 => Ok("http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha256"),
+    SignatureAlgorithm::EcdsaSha384 => Ok("http://www.w3.org/2001/04/xmldsig-more#ecdsa-sha384"),
+    _ => Err(CmsError::UnsupportedKeyAlgorithm),
+  }
+}
+
+/// A random identifier for the elements of a signature, unique within the document.
+fn random_id() -> Result<String, CmsError> {
+  let mut id = [0u8; 8];
+  SystemRandom::new()
+    .fill(&mut id)
+    .map_err(|_| CmsError::SigningError.caused_by("failed to generate an identifier"))?;
+
+  Ok(id.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Escape text content as canonical XML does.
+fn escape_text(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('\r', "&#xD;")
+}
+
+/// Escape an attribute value as canonical XML does.
+pub(crate) fn escape_attribute(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('"', "&quot;")
+    .replace('\t', "&#x9;")
+    .replace('\n', "&#xA;")
+    .replace('\r', "&#xD;")
+}
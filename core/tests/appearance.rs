@@ -0,0 +1,110 @@
+//! Building the appearance stream of a visible signature, with a PNG image written by the test.
+
+use std::io::{Read, Write};
+
+use chrono::TimeZone;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pdf_sign_core::appearance::{Appearance, FONT_RESOURCE, IMAGE_RESOURCE};
+
+/// A PNG chunk with its length and CRC.
+fn chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+  let body = [kind, data].concat();
+
+  [
+    &(data.len() as u32).to_be_bytes()[..],
+    &body,
+    &crc32fast::hash(&body).to_be_bytes(),
+  ]
+  .concat()
+}
+
+/// An 8-bit RGBA PNG image of `width` by `height` pixels of one color, unfiltered.
+fn png(width: u32, height: u32, rgba: [u8; 4]) -> Vec<u8> {
+  let header = [
+    &width.to_be_bytes()[..],
+    &height.to_be_bytes(),
+    // 8 bits, RGBA, deflate, no filter method, no interlacing.
+    &[8, 6, 0, 0, 0],
+  ]
+  .concat();
+
+  let row = [&[0][..], &rgba.repeat(width as usize)].concat();
+
+  let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+  encoder.write_all(&row.repeat(height as usize)).unwrap();
+
+  [
+    &b"\x89PNG\r\n\x1a\n"[..],
+    &chunk(b"IHDR", &header),
+    &chunk(b"IDAT", &encoder.finish().unwrap()),
+    &chunk(b"IEND", &[]),
+  ]
+  .concat()
+}
+
+fn inflate(data: &[u8]) -> Vec<u8> {
+  let mut inflated = Vec::new();
+  ZlibDecoder::new(data).read_to_end(&mut inflated).unwrap();
+
+  inflated
+}
+
+fn appearance(image: Option<&[u8]>) -> Appearance<'_> {
+  Appearance {
+    width: 200.0,
+    height: 50.0,
+    name: Some("Jane Doe"),
+    reason: Some("Approved"),
+    location: None,
+    signing_time: chrono::Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+    image,
+    font_size: None,
+  }
+}
+
+#[test]
+fn renders_text_and_image() {
+  let image = png(4, 2, [255, 0, 0, 128]);
+
+  let stream = appearance(Some(&image)).build().unwrap();
+  let content = String::from_utf8(stream.content).unwrap();
+
+  assert!(content.starts_with("q\n"));
+  assert!(content.ends_with("ET\nQ\n"));
+  assert!(content.contains(&format!("/{} Do", IMAGE_RESOURCE)));
+  assert!(content.contains(&format!("BT\n/{} ", FONT_RESOURCE)));
+  assert!(content.contains("(Jane Doe) Tj\nT*\n(Reason: Approved) Tj\nT*\n"));
+  assert!(content.contains("(Date: 2026-01-02 03:04:05 UTC) Tj"));
+  // Three lines of text fitted to the 46 points of height inside the padding.
+  assert!(stream.font_size > 0.0 && stream.font_size <= 46.0 / (3.0 * 1.2));
+
+  let image = stream.image.unwrap();
+
+  assert_eq!((image.width, image.height), (4, 2));
+  assert_eq!(image.color_space, "DeviceRGB");
+  assert_eq!(image.filter, "FlateDecode");
+  assert_eq!(inflate(&image.data), [255, 0, 0].repeat(8));
+  assert_eq!(inflate(&image.soft_mask.unwrap()), [128].repeat(8));
+}
+
+#[test]
+fn rejects_invalid_images() {
+  let code = |image: &[u8]| {
+    appearance(Some(image))
+      .build()
+      .err()
+      .map(|error| error.code())
+  };
+
+  let image = png(4, 2, [0, 0, 0, 255]);
+  let mut corrupted = image.clone();
+  // A byte of the IHDR chunk, which no longer matches its CRC.
+  corrupted[20] ^= 0xff;
+
+  assert_eq!(code(&image[..image.len() - 6]), Some("ERR_IMAGE_PARSE"));
+  assert_eq!(code(&corrupted), Some("ERR_IMAGE_PARSE"));
+  assert_eq!(code(b"\xff\xd8\xff"), Some("ERR_IMAGE_PARSE"));
+  assert_eq!(code(b"GIF89a"), Some("ERR_INVALID_OPTION"));
+}
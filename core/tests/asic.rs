@@ -0,0 +1,174 @@
+//! Creating ASiC containers and verifying their files and CAdES signatures.
+
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pdf_sign_core::asic::{Container, File, Kind};
+use pdf_sign_core::certificates::LazyCertificate;
+use pdf_sign_core::cms;
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm};
+
+const FILES: &[File] = &[
+  File {
+    name: "invoice.xml",
+    content: b"<Invoice><ID>2026-0042</ID></Invoice>",
+    mime_type: "application/xml",
+  },
+  File {
+    name: "attachments/terms & conditions.txt",
+    content: b"Payable within 30 days.",
+    mime_type: "text/plain",
+  },
+];
+
+/// The names and contents of the stored entries of a ZIP archive, in the order of the archive.
+fn entries(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+  let u16_at = |offset: usize| u16::from_le_bytes([zip[offset], zip[offset + 1]]);
+  let u32_at = |offset: usize| u32::from_le_bytes(zip[offset..offset + 4].try_into().unwrap());
+
+  let mut entries = Vec::new();
+  let mut offset = 0;
+
+  // Local file headers, up to the central directory.
+  while u32_at(offset) == 0x04034b50 {
+    assert_eq!(u16_at(offset + 8), 0, "entries are stored");
+
+    let crc = u32_at(offset + 14);
+    let size = u32_at(offset + 18) as usize;
+    let name_length = u16_at(offset + 26) as usize;
+    let extra_length = u16_at(offset + 28) as usize;
+
+    let name = &zip[offset + 30..offset + 30 + name_length];
+    let start = offset + 30 + name_length + extra_length;
+    let content = &zip[start..start + size];
+
+    assert_eq!(crc32fast::hash(content), crc);
+
+    entries.push((String::from_utf8(name.to_vec()).unwrap(), content.to_vec()));
+    offset = start + size;
+  }
+
+  entries
+}
+
+fn sign(kind: Kind, files: &[File]) -> (Vec<(String, Vec<u8>)>, CapturedX509Certificate) {
+  let (key, cert) = common::rsa();
+
+  let zip = Container {
+    kind,
+    files,
+    signing_key: &key,
+    certs: &[LazyCertificate::from(cert.clone())],
+    signing_time: chrono::Utc::now(),
+    time_stamp_url: None,
+    limiter: None,
+  }
+  .sign()
+  .unwrap();
+
+  // The mimetype comes first, so it can be read at a fixed offset.
+  assert_eq!(&zip[30..38], b"mimetype");
+
+  (entries(&zip), cert)
+}
+
+/// Verify a detached CAdES signature of content.
+fn verify(signature: &[u8], cert: &CapturedX509Certificate, content: &[u8]) {
+  let signed_data = cms::SignedData::decode(signature).unwrap();
+  let signer_info = &signed_data.signer_infos[0];
+
+  assert!(signer_info.signature_valid(cert, Some(content)).unwrap());
+  assert_eq!(
+    signer_info.message_digest().unwrap().unwrap().as_ref(),
+    DigestAlgorithm::Sha256.digest_data(content)
+  );
+}
+
+#[test]
+fn asic_s_signs_its_file() {
+  let (entries, cert) = sign(Kind::Simple, &FILES[..1]);
+
+  let names = entries
+    .iter()
+    .map(|(name, _)| name.as_str())
+    .collect::<Vec<_>>();
+
+  assert_eq!(names, ["mimetype", "invoice.xml", "META-INF/signature.p7s"]);
+  assert_eq!(entries[0].1, b"application/vnd.etsi.asic-s+zip");
+  assert_eq!(entries[1].1, FILES[0].content);
+
+  verify(&entries[2].1, &cert, FILES[0].content);
+}
+
+#[test]
+fn asic_e_signs_its_manifest() {
+  let (entries, cert) = sign(Kind::Extended, FILES);
+
+  let names = entries
+    .iter()
+    .map(|(name, _)| name.as_str())
+    .collect::<Vec<_>>();
+
+  assert_eq!(
+    names,
+    [
+      "mimetype",
+      "invoice.xml",
+      "attachments/terms & conditions.txt",
+      "META-INF/ASiCManifest.xml",
+      "META-INF/signature001.p7s",
+    ]
+  );
+  assert_eq!(entries[0].1, b"application/vnd.etsi.asic-e+zip");
+
+  let manifest = std::str::from_utf8(&entries[3].1).unwrap();
+
+  // Each file is referenced by its URI with its digest.
+  for (file, uri) in FILES
+    .iter()
+    .zip(["invoice.xml", "attachments/terms%20%26%20conditions.txt"])
+  {
+    let reference = format!(
+      "URI=\"{}\" MimeType=\"{}\"><ds:DigestMethod Algorithm=\"http://www.w3.org/2001/04/xmlenc#sha256\"/><ds:DigestValue>{}</ds:DigestValue>",
+      uri,
+      file.mime_type,
+      STANDARD.encode(DigestAlgorithm::Sha256.digest_data(file.content))
+    );
+
+    assert!(manifest.contains(&reference), "{}", manifest);
+  }
+
+  verify(&entries[4].1, &cert, &entries[3].1);
+}
+
+#[test]
+fn rejects_invalid_files() {
+  let (key, cert) = common::rsa();
+  let certs = [LazyCertificate::from(cert)];
+
+  let error = |kind, files: &[File]| {
+    Container {
+      kind,
+      files,
+      signing_key: &key,
+      certs: &certs,
+      signing_time: chrono::Utc::now(),
+      time_stamp_url: None,
+      limiter: None,
+    }
+    .sign()
+    .err()
+    .map(|error| error.to_string())
+  };
+
+  let reserved = File {
+    name: "META-INF/manifest.xml",
+    content: b"",
+    mime_type: "text/xml",
+  };
+
+  assert!(error(Kind::Simple, FILES).is_some_and(|error| error.contains("exactly one file")));
+  assert!(error(Kind::Extended, &[]).is_some_and(|error| error.contains("at least one file")));
+  assert!(error(Kind::Extended, &[reserved])
+    .is_some_and(|error| error.contains("reserved for the container")));
+}
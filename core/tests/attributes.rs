@@ -0,0 +1,133 @@
+//! The DER encoding of the signed attributes of a signature with caller provided attributes,
+//! with the RSA key of the self-test.
+
+mod common;
+
+use bcder::encode::Values;
+use bcder::{Captured, Mode, Tag};
+use cryptographic_message_syntax::asn1::rfc5652;
+use cryptographic_message_syntax::Bytes;
+use pdf_sign_core::attributes::{
+  self, OID_COMMITMENT_TYPE_INDICATION, OID_CONTENT_HINTS, OID_SIGNER_LOCATION,
+};
+use pdf_sign_core::cms;
+use pdf_sign_core::signing::{SignedDataBuilder, SignerBuilder};
+use x509_certificate::rfc5652::AttributeValue;
+
+const CONTENT: &[u8] = b"%PDF-1.7\n% Signed attributes test document\n%%EOF\n";
+
+/// A DER value captured as an attribute value.
+fn value(der: &[u8]) -> AttributeValue {
+  AttributeValue::new(
+    Mode::Der
+      .decode(Bytes::copy_from_slice(der), |cons| cons.capture_one())
+      .unwrap(),
+  )
+}
+
+/// Encode values decoded in BER mode, which writes definite lengths as DER does.
+fn encode(values: impl Values) -> Vec<u8> {
+  let mut encoded = Vec::new();
+
+  values.write_encoded(Mode::Ber, &mut encoded).unwrap();
+
+  encoded
+}
+
+/// The DER encoding of each signed attribute and of the values of each, in encoded order.
+fn signed_attributes(signer_info: &cms::SignerInfo) -> Vec<(Captured, Vec<Captured>)> {
+  let signed_attributes = signer_info.signed_attributes.as_ref().unwrap();
+
+  let encodings = Mode::Der
+    .decode(signed_attributes.as_slice(), |cons| {
+      cons.take_constructed_if(Tag::CTX_0, |cons| {
+        let mut encodings = Vec::new();
+
+        loop {
+          let encoded =
+            cons.capture(|cons| cons.take_opt_sequence(|cons| cons.skip_all()).map(drop))?;
+
+          if encoded.as_slice().is_empty() {
+            return Ok(encodings);
+          }
+
+          encodings.push(encoded);
+        }
+      })
+    })
+    .unwrap();
+
+  let values = signer_info.signed_attribute_values().unwrap();
+
+  encodings
+    .into_iter()
+    .zip(values.into_iter().map(|(_, values)| values))
+    .collect()
+}
+
+fn is_sorted(encodings: &[&[u8]]) -> bool {
+  encodings.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+#[test]
+fn signed_attributes_are_canonical_der() {
+  let (key, cert) = common::rsa();
+
+  // UTF8Strings given out of DER order, so sorting them changes the encoding.
+  let custom = vec![
+    value(b"\x0c\x02zz"),
+    value(b"\x0c\x01a"),
+    value(b"\x0c\x02ab"),
+  ];
+
+  let signer = SignerBuilder::new(&key, cert.clone())
+    .signed_attribute("1.2.3.4".parse().unwrap(), custom)
+    .signed_attribute(
+      attributes::oid(OID_SIGNER_LOCATION),
+      vec![attributes::signer_location(Some("NL"), Some("Amsterdam"), None).unwrap()],
+    )
+    .signed_attribute(
+      attributes::oid(OID_CONTENT_HINTS),
+      vec![attributes::content_hints(Some("application/pdf"), None).unwrap()],
+    )
+    .signed_attribute(
+      attributes::oid(OID_COMMITMENT_TYPE_INDICATION),
+      vec![attributes::commitment_type_indication("proofOfApproval").unwrap()],
+    );
+
+  let der_signed_data = SignedDataBuilder::default()
+    .content_external(Bytes::from_static(CONTENT))
+    .signer(signer)
+    .build_der()
+    .unwrap();
+
+  let signed_data = cms::SignedData::decode(&der_signed_data).unwrap();
+  let signer_info = &signed_data.signer_infos[0];
+  let signed_attributes = signed_attributes(signer_info);
+
+  // contentType, messageDigest, signingTime and the four added above.
+  assert_eq!(signed_attributes.len(), 7);
+  assert!(is_sorted(
+    &signed_attributes
+      .iter()
+      .map(|(attribute, _)| attribute.as_slice())
+      .collect::<Vec<_>>()
+  ));
+
+  for (_, values) in &signed_attributes {
+    assert!(is_sorted(
+      &values.iter().map(Captured::as_slice).collect::<Vec<_>>()
+    ));
+  }
+
+  // The CMS crate sorts the signed attributes again when it encodes a signer info, which
+  // changes nothing for a canonical encoding.
+  let parsed = rfc5652::SignedData::decode_ber(&der_signed_data).unwrap();
+  let reencoded = parsed.signer_infos.iter().next().unwrap();
+
+  assert_eq!(
+    encode(reencoded.encode_ref()),
+    encode(signer_info.encode_ref())
+  );
+  assert!(signer_info.signature_valid(&cert, Some(CONTENT)).unwrap());
+}
@@ -0,0 +1,39 @@
+//! The DocMDP transform of certification signatures for each permission level.
+
+use pdf_sign_core::certification::{self, Permissions};
+
+#[test]
+fn encodes_each_permission_level() {
+  for (name, permissions, value) in [
+    ("noChanges", Permissions::NoChanges, 1),
+    ("formFilling", Permissions::FormFilling, 2),
+    ("annotations", Permissions::Annotations, 3),
+  ] {
+    assert_eq!(
+      Permissions::parse("permissions", name).unwrap(),
+      permissions
+    );
+    assert_eq!(permissions.value(), value);
+    assert_eq!(
+      certification::reference(permissions),
+      format!(
+        "[<< /Type /SigRef /TransformMethod /DocMDP /TransformParams << /Type /TransformParams \
+         /P {} /V /1.2 >> >>]",
+        value
+      )
+    );
+  }
+}
+
+#[test]
+fn defaults_to_form_filling() {
+  assert_eq!(Permissions::default(), Permissions::FormFilling);
+}
+
+#[test]
+fn rejects_unknown_permissions() {
+  assert_eq!(
+    Permissions::parse("permissions", "everything").map_err(|error| error.code()),
+    Err("ERR_INVALID_OPTION")
+  );
+}
@@ -0,0 +1,143 @@
+//! Amending an existing signature through the lossless SignedData of the cms module, with the
+//! RSA key of the self-test.
+
+mod common;
+
+use bcder::{Mode, Oid};
+use cryptographic_message_syntax::Bytes;
+use pdf_sign_core::attributes::{self, OID_CONTENT_HINTS};
+use pdf_sign_core::cms;
+use pdf_sign_core::signing::{SignedDataBuilder, SignerBuilder};
+use x509_certificate::DigestAlgorithm;
+
+const CONTENT: &[u8] = b"%PDF-1.7\n% Amended signature test document\n%%EOF\n";
+
+/// The subject key identifier extension of the self-test certificate.
+const SUBJECT_KEY_IDENTIFIER: &str = "44f0a58990cd331bfcac05f0647cff93c8bc4642";
+
+/// A UTF8String, as the DER value of a custom attribute.
+fn utf8_string(value: &str) -> Vec<u8> {
+  [&[0x0c, value.len() as u8], value.as_bytes()].concat()
+}
+
+fn signed_data() -> Vec<u8> {
+  let (key, cert) = common::rsa();
+
+  SignedDataBuilder::default()
+    .content_external(Bytes::from_static(CONTENT))
+    .signer(SignerBuilder::new(&key, cert))
+    .build_der()
+    .unwrap()
+}
+
+#[test]
+fn decodes_and_encodes_losslessly() {
+  let der = signed_data();
+
+  assert_eq!(cms::SignedData::decode(&der).unwrap().encode(), der);
+}
+
+#[test]
+fn amended_signature_still_verifies() {
+  let (_, cert) = common::rsa();
+  let der = signed_data();
+
+  let mut signed_data = cms::SignedData::decode(&der).unwrap();
+  let signer_info = signed_data.signer_info_mut(0).unwrap();
+
+  let second = utf8_string("second");
+  let first = utf8_string("first");
+
+  signer_info
+    .add_unsigned_attributes(
+      &[(
+        attributes::oid(OID_CONTENT_HINTS),
+        vec![attributes::content_hints(Some("application/pdf"), None).unwrap()],
+      )],
+      [
+        ("1.2.3.4", second.as_slice()),
+        ("1.2.3.4", first.as_slice()),
+      ],
+    )
+    .unwrap();
+
+  let amended = signed_data.encode();
+  let signed_data = cms::SignedData::decode(&amended).unwrap();
+  let signer_info = &signed_data.signer_infos[0];
+
+  // What was signed is unchanged, so the signature and messageDigest still verify.
+  assert!(signer_info.signature_valid(&cert, Some(CONTENT)).unwrap());
+  assert_eq!(
+    signer_info.message_digest().unwrap().unwrap().as_ref(),
+    DigestAlgorithm::Sha256.digest_data(CONTENT)
+  );
+
+  let unsigned_attributes = signer_info.unsigned_attribute_values().unwrap();
+  let custom = "1.2.3.4".parse::<Oid>().unwrap();
+
+  assert_eq!(unsigned_attributes.len(), 2);
+  assert!(signer_info
+    .unsigned_attributes
+    .windows(2)
+    .all(|pair| pair[0].encoded.as_slice() < pair[1].encoded.as_slice()));
+
+  let (_, values) = unsigned_attributes
+    .iter()
+    .find(|(oid, _)| *oid == custom)
+    .unwrap();
+
+  // The values of the attribute are in DER SET OF order, not the order they were given in.
+  assert_eq!(values[0].as_slice(), first.as_slice());
+  assert_eq!(values[1].as_slice(), second.as_slice());
+
+  // Encoding the amended structure again gives the same bytes, as it's DER.
+  assert_eq!(signed_data.encode(), amended);
+}
+
+#[test]
+fn rejects_an_attribute_already_present() {
+  let der = signed_data();
+
+  let mut signed_data = cms::SignedData::decode(&der).unwrap();
+  let signer_info = signed_data.signer_info_mut(0).unwrap();
+  let value = utf8_string("value");
+
+  signer_info
+    .add_unsigned_attributes(&[], [("1.2.3.4", value.as_slice())])
+    .unwrap();
+
+  assert_eq!(
+    signer_info
+      .add_unsigned_attributes(&[], [("1.2.3.4", value.as_slice())])
+      .map_err(|error| error.code()),
+    Err("ERR_INVALID_CUSTOM_ATTRIBUTE")
+  );
+}
+
+#[test]
+fn finds_the_certificate_by_either_signer_identifier() {
+  let (_, cert) = common::rsa();
+  let (_, other) = common::ecdsa_p256();
+  let certs = [other, cert.clone()];
+  let der = signed_data();
+
+  let mut signed_data = cms::SignedData::decode(&der).unwrap();
+  let signer_info = signed_data.signer_info_mut(0).unwrap();
+
+  assert_eq!(signer_info.find_certificate(&certs).unwrap(), Some(&cert));
+
+  // A signer identifier of `[0] SubjectKeyIdentifier`, as version 3 signer infos use.
+  let sid = |key_identifier: &[u8]| {
+    let encoded = [&[0x80, key_identifier.len() as u8], key_identifier].concat();
+
+    Mode::Der
+      .decode(Bytes::from(encoded), |cons| cons.capture_one())
+      .unwrap()
+  };
+
+  signer_info.sid = sid(&hex::decode(SUBJECT_KEY_IDENTIFIER).unwrap());
+  assert_eq!(signer_info.find_certificate(&certs).unwrap(), Some(&cert));
+
+  signer_info.sid = sid(&[0; 20]);
+  assert_eq!(signer_info.find_certificate(&certs).unwrap(), None);
+}
@@ -0,0 +1,33 @@
+//! Keys and certificates shared by the tests.
+
+use pdf_sign_core::{certificates, keys};
+use x509_certificate::{
+  CapturedX509Certificate, EcdsaCurve, InMemorySigningKeyPair, KeyAlgorithm, X509CertificateBuilder,
+};
+
+/// The self-signed RSA certificate and key of the self-test.
+const RSA_FIXTURE: &[u8] = include_bytes!("../../fixtures/self-test-rsa.pem");
+
+pub fn rsa() -> (InMemorySigningKeyPair, CapturedX509Certificate) {
+  let key = keys::private_key_from_pem("fixture", RSA_FIXTURE).unwrap();
+  let certs = certificates::from_pem("fixture", RSA_FIXTURE).unwrap();
+
+  (key, certs[0].parsed().unwrap().clone())
+}
+
+/// A generated ECDSA P-256 key with a self-signed certificate.
+#[allow(dead_code)]
+pub fn ecdsa_p256() -> (InMemorySigningKeyPair, CapturedX509Certificate) {
+  let mut builder = X509CertificateBuilder::default();
+
+  builder
+    .subject()
+    .append_common_name_utf8_string("pdf-sign test")
+    .unwrap();
+
+  let (cert, key) = builder
+    .create_with_random_keypair(KeyAlgorithm::Ecdsa(EcdsaCurve::Secp256r1))
+    .unwrap();
+
+  (key, cert)
+}
@@ -0,0 +1,101 @@
+//! The reduced hash tree of an evidence record and its encoding, with a stand-in for the
+//! timestamp token of the root.
+
+use bcder::{Captured, Mode, OctetString, Tag};
+use pdf_sign_core::evidence_record::HashTree;
+use x509_certificate::DigestAlgorithm;
+
+const DATA: [&[u8]; 3] = [b"signed.pdf", b"certificates", b"revocation"];
+
+/// The SHA-256 hash of the binary sorted concatenation of the SHA-256 hashes of [DATA].
+const ROOT: &str = "14e00a12fe32aafa2315456df99a9ebfa37277b16155168625b213e20868a89f";
+
+/// An empty SEQUENCE, standing in for the ContentInfo of a timestamp token.
+fn token() -> Captured {
+  Mode::Der
+    .decode(&[0x30, 0x00][..], |cons| cons.capture_one())
+    .unwrap()
+}
+
+/// The hashes of the reduced hash tree of an encoded evidence record, if it has one.
+fn reduced_hash_tree(evidence_record: &[u8]) -> Option<Vec<Vec<u8>>> {
+  Mode::Der
+    .decode(evidence_record, |cons| {
+      cons.take_sequence(|cons| {
+        cons.skip_one()?;
+        cons.skip_one()?;
+
+        cons.take_sequence(|cons| {
+          cons.take_sequence(|cons| {
+            cons.take_sequence(|cons| {
+              let hashes = cons.take_opt_constructed_if(Tag::CTX_2, |cons| {
+                cons.take_sequence(|cons| {
+                  let mut hashes = Vec::new();
+
+                  while let Some(hash) = OctetString::take_opt_from(cons)? {
+                    hashes.push(hash.to_bytes().to_vec());
+                  }
+
+                  Ok(hashes)
+                })
+              })?;
+
+              cons.skip_one()?;
+
+              Ok(hashes)
+            })
+          })
+        })
+      })
+    })
+    .unwrap()
+}
+
+#[test]
+fn root_is_the_hash_of_the_sorted_hashes() {
+  let hash_tree = HashTree::new(&DATA, DigestAlgorithm::Sha256).unwrap();
+
+  let mut hashes = DATA
+    .iter()
+    .map(|data| DigestAlgorithm::Sha256.digest_data(data))
+    .collect::<Vec<_>>();
+  hashes.sort();
+
+  assert_eq!(hash_tree.hashes, hashes);
+  assert_eq!(hex::encode(&hash_tree.root), ROOT);
+
+  // The order the data objects are given in doesn't matter.
+  let reversed = [DATA[2], DATA[1], DATA[0]];
+
+  assert_eq!(
+    HashTree::new(&reversed, DigestAlgorithm::Sha256)
+      .unwrap()
+      .root,
+    hash_tree.root
+  );
+
+  let evidence_record = hash_tree.evidence_record(&token());
+
+  assert_eq!(reduced_hash_tree(&evidence_record), Some(hashes));
+}
+
+#[test]
+fn single_data_object_has_no_hash_tree() {
+  let hash_tree = HashTree::new(&DATA[..1], DigestAlgorithm::Sha256).unwrap();
+
+  assert_eq!(hash_tree.root, DigestAlgorithm::Sha256.digest_data(DATA[0]));
+  assert_eq!(
+    reduced_hash_tree(&hash_tree.evidence_record(&token())),
+    None
+  );
+}
+
+#[test]
+fn rejects_an_empty_group() {
+  assert_eq!(
+    HashTree::new(&[], DigestAlgorithm::Sha256)
+      .err()
+      .map(|error| error.code()),
+    Some("ERR_INVALID_OPTION")
+  );
+}
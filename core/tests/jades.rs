@@ -0,0 +1,141 @@
+//! Creating JAdES signatures and verifying them as a JWS validator would.
+
+mod common;
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use pdf_sign_core::jades::Signature;
+use ring::signature::{
+  UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_FIXED, RSA_PKCS1_2048_8192_SHA256,
+};
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm, InMemorySigningKeyPair};
+
+const PAYLOAD: &[u8] = br#"{"approved":true,"document":"contract.pdf"}"#;
+
+fn sign(
+  (key, cert): &(InMemorySigningKeyPair, CapturedX509Certificate),
+  detached: bool,
+  compact: bool,
+) -> String {
+  Signature {
+    signing_key: key,
+    certs: std::slice::from_ref(cert),
+    payload: PAYLOAD,
+    content_type: Some("application/json"),
+    signing_time: chrono::Utc::now(),
+    time_stamp_url: None,
+    detached,
+    compact,
+    limiter: None,
+  }
+  .sign()
+  .unwrap()
+}
+
+/// Verify a JWS over the protected header and the payload, returning the protected header.
+fn verify(
+  cert: &CapturedX509Certificate,
+  algorithm: &'static dyn VerificationAlgorithm,
+  protected: &str,
+  payload: &str,
+  signature: &str,
+) -> serde_json::Value {
+  UnparsedPublicKey::new(algorithm, cert.public_key_data())
+    .verify(
+      format!("{}.{}", protected, payload).as_bytes(),
+      &URL_SAFE_NO_PAD.decode(signature).unwrap(),
+    )
+    .unwrap();
+
+  serde_json::from_slice(&URL_SAFE_NO_PAD.decode(protected).unwrap()).unwrap()
+}
+
+fn sign_and_verify(
+  fixture: (InMemorySigningKeyPair, CapturedX509Certificate),
+  alg: &str,
+  algorithm: &'static dyn VerificationAlgorithm,
+) {
+  let cert = &fixture.1;
+  let payload = URL_SAFE_NO_PAD.encode(PAYLOAD);
+
+  let jws: serde_json::Value = serde_json::from_str(&sign(&fixture, false, false)).unwrap();
+
+  assert_eq!(jws["payload"], payload.as_str());
+
+  let header = verify(
+    cert,
+    algorithm,
+    jws["protected"].as_str().unwrap(),
+    &payload,
+    jws["signature"].as_str().unwrap(),
+  );
+
+  assert_eq!(header["alg"], alg);
+  assert_eq!(header["cty"], "application/json");
+  assert_eq!(header["crit"], serde_json::json!(["sigT"]));
+  assert_eq!(
+    header["x5c"],
+    serde_json::json!([STANDARD.encode(cert.constructed_data())])
+  );
+  assert_eq!(
+    header["x5t#S256"],
+    URL_SAFE_NO_PAD.encode(DigestAlgorithm::Sha256.digest_data(cert.constructed_data()))
+  );
+
+  // A detached JWS is verified with the payload given separately.
+  let jws: serde_json::Value = serde_json::from_str(&sign(&fixture, true, false)).unwrap();
+
+  assert!(jws.get("payload").is_none());
+
+  verify(
+    cert,
+    algorithm,
+    jws["protected"].as_str().unwrap(),
+    &payload,
+    jws["signature"].as_str().unwrap(),
+  );
+
+  let compact = sign(&fixture, false, true);
+  let [protected, compact_payload, signature] = compact.split('.').collect::<Vec<_>>()[..] else {
+    panic!("expected three parts: {}", compact);
+  };
+
+  assert_eq!(compact_payload, payload);
+
+  verify(cert, algorithm, protected, &payload, signature);
+}
+
+#[test]
+fn rsa_signature_verifies() {
+  sign_and_verify(common::rsa(), "RS256", &RSA_PKCS1_2048_8192_SHA256);
+}
+
+#[test]
+fn ecdsa_signature_verifies() {
+  // JWS ECDSA signatures are r and s concatenated rather than DER encoded.
+  sign_and_verify(common::ecdsa_p256(), "ES256", &ECDSA_P256_SHA256_FIXED);
+}
+
+#[test]
+fn compact_serialization_rejects_a_timestamp() {
+  let (key, cert) = common::rsa();
+  let url = reqwest::Url::parse("http://timestamp.example").unwrap();
+
+  let result = Signature {
+    signing_key: &key,
+    certs: std::slice::from_ref(&cert),
+    payload: PAYLOAD,
+    content_type: None,
+    signing_time: chrono::Utc::now(),
+    time_stamp_url: Some(&url),
+    detached: false,
+    compact: true,
+    limiter: None,
+  }
+  .sign();
+
+  assert_eq!(
+    result.map_err(|error| error.code()),
+    Err("ERR_INVALID_OPTION")
+  );
+}
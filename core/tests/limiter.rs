@@ -0,0 +1,68 @@
+//! Limiting the concurrency and rate of requests, with threads standing in for signing jobs.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pdf_sign_core::limiter::Limiter;
+
+#[test]
+fn never_exceeds_the_concurrency_limit() {
+  const LIMIT: u32 = 3;
+  const JOBS: usize = LIMIT as usize + 1;
+
+  let limiter = Limiter::new(Some(LIMIT), None).unwrap();
+  let running = AtomicU32::new(0);
+  let most_running = AtomicU32::new(0);
+  let finished = AtomicU32::new(0);
+  let start = Barrier::new(JOBS);
+
+  thread::scope(|scope| {
+    for _ in 0..JOBS {
+      scope.spawn(|| {
+        // Every job asks for a permit at once, so one of them has to wait.
+        start.wait();
+
+        let _permit = limiter.acquire();
+        let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+        most_running.fetch_max(now_running, Ordering::SeqCst);
+
+        thread::sleep(Duration::from_millis(50));
+        running.fetch_sub(1, Ordering::SeqCst);
+        finished.fetch_add(1, Ordering::SeqCst);
+      });
+    }
+  });
+
+  assert!(most_running.load(Ordering::SeqCst) <= LIMIT);
+  // The job beyond the limit ran once a permit was released.
+  assert_eq!(finished.load(Ordering::SeqCst), JOBS as u32);
+}
+
+#[test]
+fn spaces_requests_by_the_rate() {
+  let limiter = Limiter::new(None, Some(20.0)).unwrap();
+  let started = Instant::now();
+
+  for _ in 0..3 {
+    drop(limiter.acquire());
+  }
+
+  // The first request starts at once and the others one 50 ms interval apart.
+  assert!(started.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn rejects_invalid_limits() {
+  let code = |max_concurrent, max_per_second| {
+    Limiter::new(max_concurrent, max_per_second)
+      .err()
+      .map(|error| error.code())
+  };
+
+  assert_eq!(code(Some(0), None), Some("ERR_INVALID_OPTION"));
+  assert_eq!(code(None, Some(0.0)), Some("ERR_INVALID_OPTION"));
+  assert_eq!(code(None, Some(f64::NAN)), Some("ERR_INVALID_OPTION"));
+  assert_eq!(code(Some(1), Some(1.0)), None);
+}
@@ -0,0 +1,141 @@
+//! Decrypting encrypted private keys and wrapping traditional ones, with keys written by
+//! OpenSSL 3. The encrypted keys are the EC P-256 key `key.der` or the RSA key `rsa.der`,
+//! encrypted with the password `secret` unless their name says otherwise.
+
+use pdf_sign_core::input::{self, InputFormat};
+use pdf_sign_core::{keys, pkcs8};
+use x509_certificate::Sign;
+
+const KEY: &[u8] = include_bytes!("../fixtures/pkcs8/key.der");
+const RSA_KEY: &[u8] = include_bytes!("../fixtures/pkcs8/rsa.der");
+
+/// Decrypt a PEM encoded EncryptedPrivateKeyInfo.
+fn decrypt(pem: &[u8], password: &str) -> Result<Vec<u8>, &'static str> {
+  let der = keys::decode_pem(pem).unwrap();
+
+  pkcs8::decrypt(&der, password)
+    .map(|key| key.to_vec())
+    .map_err(|error| error.code())
+}
+
+/// The public key of a DER encoded private key, to compare keys that are encoded differently.
+fn public_key(der: &[u8]) -> Vec<u8> {
+  keys::private_key_from_der(der)
+    .unwrap()
+    .public_key_data()
+    .to_vec()
+}
+
+#[test]
+fn decrypts_pbes2_with_des_ede3() {
+  let pem = include_bytes!("../fixtures/pkcs8/pbes2-des3.pem");
+
+  assert_eq!(decrypt(pem, "secret").unwrap(), KEY);
+}
+
+#[test]
+fn decrypts_pbes2_with_aes() {
+  let aes_128 = include_bytes!("../fixtures/pkcs8/pbes2-aes-128.pem");
+  // With hmacWithSHA512 as the PBKDF2 pseudorandom function.
+  let aes_256 = include_bytes!("../fixtures/pkcs8/pbes2-aes-256.pem");
+
+  assert_eq!(decrypt(aes_128, "secret").unwrap(), KEY);
+  assert_eq!(decrypt(aes_256, "secret").unwrap(), KEY);
+}
+
+#[test]
+fn decrypts_pkcs12_pbe() {
+  let pem = include_bytes!("../fixtures/pkcs8/pkcs12-pbe.pem");
+  let utf8 = include_bytes!("../fixtures/pkcs8/pkcs12-pbe-utf8.pem");
+
+  assert_eq!(decrypt(pem, "secret").unwrap(), KEY);
+  assert_eq!(decrypt(utf8, "grüß").unwrap(), KEY);
+}
+
+#[test]
+fn rejects_a_wrong_password() {
+  for pem in [
+    &include_bytes!("../fixtures/pkcs8/pbes2-des3.pem")[..],
+    include_bytes!("../fixtures/pkcs8/pbes2-aes-256.pem"),
+    include_bytes!("../fixtures/pkcs8/pkcs12-pbe.pem"),
+  ] {
+    assert_eq!(decrypt(pem, "wrong"), Err("ERR_PRIVATE_KEY_WRONG_PASSWORD"));
+  }
+}
+
+#[test]
+fn rejects_scrypt() {
+  let pem = include_bytes!("../fixtures/pkcs8/scrypt.pem");
+
+  assert_eq!(
+    decrypt(pem, "secret"),
+    Err("ERR_PRIVATE_KEY_UNSUPPORTED_ENCRYPTION")
+  );
+}
+
+#[test]
+fn wraps_traditional_keys() {
+  let pkcs1 = keys::decode_pem(include_bytes!("../fixtures/pkcs8/pkcs1.pem")).unwrap();
+  let sec1 = keys::decode_pem(include_bytes!("../fixtures/pkcs8/sec1.pem")).unwrap();
+
+  assert_eq!(pkcs8::wrap_traditional(&pkcs1).unwrap().as_slice(), RSA_KEY);
+  // OpenSSL leaves the curve out of the ECPrivateKey in PKCS#8, so only the keys match.
+  assert_eq!(
+    public_key(&pkcs8::wrap_traditional(&sec1).unwrap()),
+    public_key(KEY)
+  );
+  assert!(pkcs8::wrap_traditional(KEY).is_none());
+}
+
+#[test]
+fn decrypts_dek_info_keys() {
+  let pkcs1 = include_bytes!("../fixtures/pkcs8/pkcs1-des3.pem");
+  let sec1 = include_bytes!("../fixtures/pkcs8/sec1-aes-256.pem");
+
+  let decrypted = |pem, password| {
+    keys::private_key_from_pem_with_password("privateKey", pem, password)
+      .map(|key| key.public_key_data().to_vec())
+      .map_err(|error| error.code())
+  };
+
+  assert_eq!(decrypted(pkcs1, Some("secret")), Ok(public_key(RSA_KEY)));
+  assert_eq!(decrypted(sec1, Some("secret")), Ok(public_key(KEY)));
+  assert_eq!(
+    decrypted(sec1, Some("wrong")),
+    Err("ERR_PRIVATE_KEY_WRONG_PASSWORD")
+  );
+  assert_eq!(decrypted(sec1, None), Err("ERR_INVALID_OPTION"));
+}
+
+#[test]
+fn decodes_keys_to_unencrypted_pkcs8() {
+  let der = |value: &[u8], password| {
+    input::private_key_der("privateKey", value, password, InputFormat::Auto)
+      .map(|der| der.to_vec())
+      .map_err(|error| error.code())
+  };
+
+  assert_eq!(der(KEY, None), Ok(KEY.to_vec()));
+  assert_eq!(
+    der(
+      include_bytes!("../fixtures/pkcs8/pbes2-aes-128.pem"),
+      Some("secret")
+    ),
+    Ok(KEY.to_vec())
+  );
+  assert_eq!(
+    der(
+      include_bytes!("../fixtures/pkcs8/pkcs1-des3.pem"),
+      Some("secret")
+    ),
+    Ok(RSA_KEY.to_vec())
+  );
+  assert_eq!(
+    der(include_bytes!("../fixtures/pkcs8/pkcs1.pem"), None),
+    Ok(RSA_KEY.to_vec())
+  );
+  assert_eq!(
+    der(include_bytes!("../fixtures/pkcs8/pbes2-des3.pem"), None),
+    Err("ERR_INVALID_OPTION")
+  );
+}
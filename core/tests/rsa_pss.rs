@@ -0,0 +1,121 @@
+//! Signing with RSASSA-PSS and verifying the signatures, with the RSA key of the self-test.
+
+mod common;
+
+use cryptographic_message_syntax::Bytes;
+use pdf_sign_core::signing::{SignatureScheme, SignedDataBuilder, SignerBuilder};
+use pdf_sign_core::{cms, rsa_pss};
+use x509_certificate::DigestAlgorithm;
+
+const CONTENT: &[u8] = b"%PDF-1.7\n% RSASSA-PSS test document\n%%EOF\n";
+
+#[test]
+fn signed_data_round_trip() {
+  let (key, cert) = common::rsa();
+
+  let signer = SignerBuilder::new(&key, cert.clone()).signature_scheme(SignatureScheme::Pss);
+  let der = SignedDataBuilder::default()
+    .content_external(Bytes::from_static(CONTENT))
+    .signer(signer)
+    .build_der()
+    .unwrap();
+
+  let signed_data = cms::SignedData::decode(&der).unwrap();
+  let signer_info = &signed_data.signer_infos[0];
+
+  // RSA keys sign with SHA-256, so the salt is 32 bytes long.
+  let parameters = rsa_pss::algorithm_identifier(DigestAlgorithm::Sha256)
+    .parameters
+    .unwrap();
+
+  assert_eq!(
+    signer_info.signature_algorithm_oid().unwrap(),
+    rsa_pss::OID_RSASSA_PSS
+  );
+  assert!(signer_info
+    .signature_algorithm
+    .as_slice()
+    .ends_with(parameters.as_slice()));
+  assert!(signer_info.signature_valid(&cert, Some(CONTENT)).unwrap());
+  assert_eq!(
+    signer_info.message_digest().unwrap().unwrap().as_ref(),
+    DigestAlgorithm::Sha256.digest_data(CONTENT)
+  );
+
+  // A PKCS#1 v1.5 signature doesn't verify as RSASSA-PSS.
+  let pkcs1 = SignedDataBuilder::default()
+    .content_external(Bytes::from_static(CONTENT))
+    .signer(SignerBuilder::new(&key, cert.clone()))
+    .build_der()
+    .unwrap();
+  let mut pkcs1 = cms::SignedData::decode(&pkcs1).unwrap();
+
+  pkcs1.signer_infos[0].signature_algorithm = signer_info.signature_algorithm.clone();
+
+  assert!(!pkcs1.signer_infos[0]
+    .signature_valid(&cert, Some(CONTENT))
+    .unwrap());
+}
+
+#[test]
+fn signs_and_verifies_each_digest() {
+  let (key, cert) = common::rsa();
+
+  for (digest_algorithm, salt_length) in [
+    (DigestAlgorithm::Sha256, 32),
+    (DigestAlgorithm::Sha384, 48),
+    (DigestAlgorithm::Sha512, 64),
+  ] {
+    let parameters = rsa_pss::algorithm_identifier(digest_algorithm)
+      .parameters
+      .unwrap();
+
+    // saltLength [2] INTEGER, the last of the parameters.
+    assert!(parameters
+      .as_slice()
+      .ends_with(&[0xa2, 0x03, 0x02, 0x01, salt_length]));
+
+    let signature = rsa_pss::sign(&key, digest_algorithm, CONTENT).unwrap();
+
+    assert!(rsa_pss::verify(
+      &cert,
+      digest_algorithm,
+      CONTENT,
+      &signature
+    ));
+    assert!(!rsa_pss::verify(
+      &cert,
+      digest_algorithm,
+      b"other",
+      &signature
+    ));
+  }
+
+  // The salt is as long as the digest, so a signature only verifies with its own digest.
+  let signature = rsa_pss::sign(&key, DigestAlgorithm::Sha384, CONTENT).unwrap();
+
+  assert!(!rsa_pss::verify(
+    &cert,
+    DigestAlgorithm::Sha256,
+    CONTENT,
+    &signature
+  ));
+}
+
+#[test]
+fn rejects_sha1() {
+  let (key, cert) = common::rsa();
+
+  assert_eq!(
+    rsa_pss::sign(&key, DigestAlgorithm::Sha1, CONTENT)
+      .map_err(|error| error.code())
+      .err(),
+    Some("ERR_UNSUPPORTED_DIGEST_ALGORITHM")
+  );
+  assert!(!rsa_pss::verify(
+    &cert,
+    DigestAlgorithm::Sha1,
+    CONTENT,
+    &[0; 256]
+  ));
+}
@@ -0,0 +1,96 @@
+//! Creating detached XAdES signatures and verifying them as a validator would.
+//!
+//! The SignedInfo and SignedProperties are written in their canonical form, so canonicalizing
+//! them is taking them out of the document as they are.
+
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pdf_sign_core::xades::DetachedSignature;
+use ring::signature::{
+  UnparsedPublicKey, VerificationAlgorithm, ECDSA_P256_SHA256_FIXED, RSA_PKCS1_2048_8192_SHA256,
+};
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm, InMemorySigningKeyPair};
+
+const CONTENT: &[u8] = b"<Invoice><ID>2026-0042</ID></Invoice>";
+
+/// The element of a signature from its start tag through its end tag.
+fn element<'a>(xml: &'a str, start: &str, end: &str) -> &'a str {
+  let from = xml.find(start).unwrap();
+  let to = from + xml[from..].find(end).unwrap() + end.len();
+
+  &xml[from..to]
+}
+
+/// The text content of the first element with a start tag.
+fn text<'a>(xml: &'a str, start: &str) -> &'a str {
+  let from = xml.find(start).unwrap();
+  let from = from + xml[from..].find('>').unwrap() + 1;
+
+  &xml[from..from + xml[from..].find('<').unwrap()]
+}
+
+fn sign_and_verify(
+  (key, cert): (InMemorySigningKeyPair, CapturedX509Certificate),
+  algorithm: &'static dyn VerificationAlgorithm,
+) {
+  let xml = DetachedSignature {
+    signing_key: &key,
+    certs: std::slice::from_ref(&cert),
+    content: CONTENT,
+    uri: "invoice.xml",
+    mime_type: "application/xml",
+    signing_time: chrono::Utc::now(),
+    time_stamp_url: None,
+    limiter: None,
+  }
+  .sign()
+  .unwrap();
+
+  let signed_info = element(&xml, "<ds:SignedInfo", "</ds:SignedInfo>");
+  let signed_properties = element(&xml, "<xades:SignedProperties", "</xades:SignedProperties>");
+  let digest = |data: &[u8]| STANDARD.encode(DigestAlgorithm::Sha256.digest_data(data));
+
+  // The references to the content and to the signed properties, in that order.
+  let (content_reference, properties_reference) =
+    signed_info.split_once("</ds:Reference>").unwrap();
+
+  assert!(content_reference.contains("URI=\"invoice.xml\""));
+  assert_eq!(text(content_reference, "<ds:DigestValue"), digest(CONTENT));
+  assert_eq!(
+    text(properties_reference, "<ds:DigestValue"),
+    digest(signed_properties.as_bytes())
+  );
+  assert_eq!(
+    text(signed_properties, "<ds:DigestValue"),
+    digest(cert.constructed_data())
+  );
+  assert_eq!(
+    text(&xml, "<ds:X509Certificate"),
+    STANDARD.encode(cert.constructed_data())
+  );
+
+  let signature = STANDARD.decode(text(&xml, "<ds:SignatureValue")).unwrap();
+
+  UnparsedPublicKey::new(algorithm, cert.public_key_data())
+    .verify(signed_info.as_bytes(), &signature)
+    .unwrap();
+
+  // Changing what is signed breaks the signature.
+  let tampered = signed_info.replace("invoice.xml", "invoice.xmk");
+
+  assert!(UnparsedPublicKey::new(algorithm, cert.public_key_data())
+    .verify(tampered.as_bytes(), &signature)
+    .is_err());
+}
+
+#[test]
+fn rsa_signature_verifies() {
+  sign_and_verify(common::rsa(), &RSA_PKCS1_2048_8192_SHA256);
+}
+
+#[test]
+fn ecdsa_signature_verifies() {
+  // XML-DSig ECDSA signatures are r and s concatenated rather than DER encoded.
+  sign_and_verify(common::ecdsa_p256(), &ECDSA_P256_SHA256_FIXED);
+}
@@ -3,30 +3,1061 @@
 
 /* auto-generated by NAPI-RS */
 
-export interface SignWithPrivateKeyOptions {
-  content: Buffer;
-  cert: Buffer;
-  privateKey: Buffer;
+/** Digest algorithm of the signature, and of a precomputed `digest`. */
+export type DigestAlgorithm = 'sha256' | 'sha384' | 'sha512';
+/** Padding of RSA signatures, RSASSA-PKCS1-v1_5 or RSASSA-PSS. */
+export type SignatureScheme = 'pkcs1' | 'pss';
+/** Encoding of certificates and private keys, detected from the data if not given. */
+export type InputFormat = 'pem' | 'der';
+/** The changes a certification signature allows after it. */
+export type DocMdpPermissions = 'noChanges' | 'formFilling' | 'annotations';
+/** What a signature means, for the commitment-type-indication attribute. */
+export type CommitmentType =
+  | 'proofOfOrigin'
+  | 'proofOfReceipt'
+  | 'proofOfDelivery'
+  | 'proofOfSender'
+  | 'proofOfApproval'
+  | 'proofOfCreation';
+/** A stage of a signing operation, as reported to `onProgress`. */
+export type SigningStage = 'hashing' | 'signing' | 'timestamping' | 'writing';
+/** The level of log events passed to `setLogger`. */
+export type LogLevel = 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace';
+/** Where the signer claims to be, for the signerLocation attribute. */
+export interface SignerLocation {
+  countryName?: string;
+  localityName?: string;
+  postalAddress?: Array<string>;
+}
+/** A description of the signed content, for the contentHints attribute. */
+export interface ContentHints {
+  description?: string;
+  contentType?: string;
+}
+/**
+ * A capability for the smimeCapabilities attribute, as a dotted OID and optional DER encoded
+ * parameters.
+ */
+export interface SmimeCapability {
+  oid: string;
+  parameters?: Buffer;
+}
+/** An attribute given as a dotted OID and a DER encoded value. */
+export interface CustomAttribute {
+  oid: string;
+  value: Buffer;
+}
+/** DER encoded CRLs and OCSP responses, for the revocationValues and revocationRefs attributes. */
+export interface RevocationValues {
+  crls?: Array<Buffer>;
+  ocspResponses?: Array<Buffer>;
+}
+/**
+ * The audit record of a signing operation, also logged as JSON in the `record` field of an `info`
+ * event with the `documenso_pdf_sign::audit` target.
+ *
+ * `documentDigest` is the hex digest of the signed content and `signerFingerprint` the hex
+ * SHA-256 fingerprint of the signing certificate. Failed operations have their `errorCode` and
+ * only the details known before they failed.
+ */
+export interface AuditRecord {
+  operation: string;
+  backend: string;
+  dryRun: boolean;
+  outcome: 'success' | 'failure';
+  errorCode?: string;
+  startedAt: string;
+  duration: number;
+  documentDigest?: string;
+  digestAlgorithm?: string;
+  signerFingerprint?: string;
+  signerSubject?: string;
+  signingTime?: string;
+  timestampTime?: string;
+  timestampAuthority?: string;
+}
+/**
+ * Signed data with the details of its first signer, returned with the `detailed` option.
+ *
+ * Times are RFC 3339 strings, `signingTime` is missing if the signing time attribute was left
+ * out and `timestampTime` if the signature wasn't timestamped. `warnings` lists what is likely
+ * to cause trouble with validators without making the signature invalid, such as a certificate
+ * about to expire.
+ */
+export interface SigningResult {
+  signature: Buffer;
+  size: number;
+  signingTime?: string;
+  timestampTime?: string;
+  signerSubject: string;
+  serialNumber: string;
+  timings: SigningTimings;
+  warnings: Array<string>;
+  audit: AuditRecord;
+  trace?: Array<TraceEvent>;
+}
+/** The options that make the signing functions return a `SigningResult`. */
+export type DetailedOutput = { detailed: true } | { debugTrace: true };
+/**
+ * An event logged while signing, returned in the `trace` of a result with the `debugTrace`
+ * option. `elapsed` is the number of milliseconds since the signing operation started.
+ */
+export interface TraceEvent extends LogEvent {
+  time: string;
+  elapsed: number;
+}
+/** How many milliseconds the stages of a signing operation took. */
+export interface SigningTimings {
+  parsing: number;
+  hashing: number;
+  signing: number;
+  timestamping: number;
+  writing: number;
+  total: number;
+}
+/**
+ * What a signing operation would produce, returned with the `dryRun` option.
+ *
+ * `keyMatchesCertificate` is missing if the key wasn't used, and `problems` lists what would
+ * make the signature invalid, such as an expired certificate, while `warnings` lists what
+ * wouldn't, as for `SigningResult`.
+ */
+export interface DryRunReport {
+  signerSubject: string;
+  serialNumber: string;
+  notBefore: string;
+  notAfter: string;
+  certificateCount: number;
+  signatureSize: number;
+  timestamped: boolean;
+  keyMatchesCertificate?: boolean;
+  problems: Array<string>;
+  warnings: Array<string>;
+  trace?: Array<TraceEvent>;
+}
+/**
+ * The options of a signature and its output, shared by the signing functions, which take them
+ * in the same object as the options of their key.
+ */
+export interface SigningOptions {
+  content?: Uint8Array;
+  contentPath?: string;
+  /**
+   * The precomputed digest of the content, computed with the digest algorithm the key signs
+   * with: SHA-256 for RSA and ECDSA P-256 keys, SHA-384 for P-384 and SHA-512 for Ed25519.
+   */
+  digest?: Buffer;
+  digestAlgorithm?: DigestAlgorithm;
   signingTime?: string;
   timestampServer?: string;
+  commitmentType?: CommitmentType;
+  signerLocation?: SignerLocation;
+  contentHints?: ContentHints;
+  seal?: boolean;
+  smimeCapabilities?: Array<SmimeCapability>;
+  signedAttributes?: Array<CustomAttribute>;
+  unsignedAttributes?: Array<CustomAttribute>;
+  attached?: boolean;
+  includeSigningTime?: boolean;
+  nullParameters?: boolean;
+  signedDataVersion?: number;
+  signerInfoVersion?: number;
+  certValues?: Buffer;
+  revocationValues?: RevocationValues;
+  embedRevocationInfo?: boolean;
+  certificateRefs?: Buffer;
+  revocationRefs?: RevocationValues;
+  outputPath?: string;
+  maxSize?: number;
+  detailed?: boolean;
+  debugTrace?: boolean;
+}
+export interface SignWithPrivateKeyOptions extends SigningOptions {
+  cert: Buffer;
+  privateKey: Buffer;
+  privateKeyPassword?: string;
+  format?: InputFormat;
+  signatureScheme?: SignatureScheme;
+  dryRun?: boolean;
+  checkReachability?: boolean;
 }
 /** Sign data with the private key. */
+export function signWithPrivateKey(
+  options: SignWithPrivateKeyOptions & { dryRun: true },
+): DryRunReport;
+export function signWithPrivateKey(
+  options: SignWithPrivateKeyOptions & DetailedOutput,
+): SigningResult;
 export function signWithPrivateKey(options: SignWithPrivateKeyOptions): Buffer;
-export interface SignWithP12Options {
-  content: Buffer;
+export interface SignWithP12Options extends SigningOptions {
+  cert?: Buffer;
+  certPath?: string;
+  certSource?: string;
+  password?: string;
+  passwordSource?: string;
+  friendlyName?: string;
+  thumbprint?: string;
+  signatureScheme?: SignatureScheme;
+  dryRun?: boolean;
+  checkReachability?: boolean;
+}
+/** Sign data with a P12 container. */
+export function signWithP12(options: SignWithP12Options & { dryRun: true }): DryRunReport;
+export function signWithP12(options: SignWithP12Options & DetailedOutput): SigningResult;
+export function signWithP12(options: SignWithP12Options): Buffer;
+/** A certificate of a P12 container, returned by `inspectP12`. */
+export interface P12EntryInfo {
+  friendlyName?: string;
+  hasPrivateKey: boolean;
+  /** The hex SHA-1 thumbprint of the certificate, as Windows shows it. */
+  thumbprint: string;
+  /** The hex SHA-256 fingerprint of the certificate. */
+  fingerprint: string;
+  certificate: ParsedCertificate;
+}
+/**
+ * List the certificates of a P12 container with their friendly names and whether it has their
+ * private key, to choose the `friendlyName` or `thumbprint` to sign with.
+ */
+export function inspectP12(p12: Buffer, password?: string): Array<P12EntryInfo>;
+export interface SignWithGCloudOptions extends SigningOptions {
   cert: Buffer;
+  keyPath: string;
+  format?: InputFormat;
+  dryRun?: boolean;
+  checkReachability?: boolean;
+}
+/** Sign data with Google Cloud. */
+export function signWithGCloud(options: SignWithGCloudOptions & { dryRun: true }): DryRunReport;
+export function signWithGCloud(options: SignWithGCloudOptions & DetailedOutput): SigningResult;
+export function signWithGCloud(options: SignWithGCloudOptions): Buffer;
+/** Sign data with the private key without blocking the event loop. */
+export function signWithPrivateKeyAsync(
+  options: SignWithPrivateKeyOptions & { dryRun: true },
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<DryRunReport>;
+export function signWithPrivateKeyAsync(
+  options: SignWithPrivateKeyOptions & DetailedOutput,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<SigningResult>;
+export function signWithPrivateKeyAsync(
+  options: SignWithPrivateKeyOptions,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<Buffer>;
+/** Sign data with a P12 container without blocking the event loop. */
+export function signWithP12Async(
+  options: SignWithP12Options & { dryRun: true },
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<DryRunReport>;
+export function signWithP12Async(
+  options: SignWithP12Options & DetailedOutput,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<SigningResult>;
+export function signWithP12Async(
+  options: SignWithP12Options,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<Buffer>;
+/** Sign data with Google Cloud without blocking the event loop. */
+export function signWithGCloudAsync(
+  options: SignWithGCloudOptions & { dryRun: true },
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<DryRunReport>;
+export function signWithGCloudAsync(
+  options: SignWithGCloudOptions & DetailedOutput,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<SigningResult>;
+export function signWithGCloudAsync(
+  options: SignWithGCloudOptions,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<Buffer>;
+export interface SignerKeyOptions {
+  p12?: Buffer;
+  p12Path?: string;
+  p12Source?: string;
   password?: string;
+  passwordSource?: string;
+  friendlyName?: string;
+  thumbprint?: string;
+  cert?: Buffer;
+  privateKey?: Buffer;
+  privateKeyPassword?: string;
+  format?: InputFormat;
+  keyPath?: string;
+  maxConcurrentRequests?: number;
+  maxRequestsPerSecond?: number;
+}
+/** The options of `Signer.sign` and `signWithKey`, which are the `SigningOptions`. */
+export type SignerSignOptions = SigningOptions;
+/**
+ * A key and its certificates, parsed once and reused for any number of signatures.
+ *
+ * The key is a P12 container, a PEM encoded private key or a Google Cloud KMS key, whose
+ * client and connection are kept for later signatures.
+ */
+export class Signer {
+  constructor(options: SignerKeyOptions);
+  /** Sign data with the key. */
+  sign(options: SigningOptions & DetailedOutput): SigningResult;
+  sign(options: SigningOptions): Buffer;
+  /** Sign data with the key without blocking the event loop. */
+  signAsync(
+    options: SigningOptions & DetailedOutput,
+    signal?: AbortSignal,
+    onProgress?: (stage: SigningStage) => void,
+  ): Promise<SigningResult>;
+  signAsync(
+    options: SigningOptions,
+    signal?: AbortSignal,
+    onProgress?: (stage: SigningStage) => void,
+  ): Promise<Buffer>;
+  /** Create a detached XAdES signature of content with the key. */
+  signXades(options: XadesSignOptions): Buffer;
+  /** Create a JAdES signature of a payload with the key. */
+  signJades(options: JadesSignOptions): Buffer;
+  /** Package files and a CAdES signature of them in an ASiC container with the key. */
+  signAsic(options: AsicSignOptions): Buffer;
+  /** Create a detached XAdES signature of content with the key without blocking the event loop. */
+  signXadesAsync(options: XadesSignOptions, signal?: AbortSignal): Promise<Buffer>;
+  /** Create a JAdES signature of a payload with the key without blocking the event loop. */
+  signJadesAsync(options: JadesSignOptions, signal?: AbortSignal): Promise<Buffer>;
+  /**
+   * Package files and a CAdES signature of them in an ASiC container with the key without
+   * blocking the event loop.
+   */
+  signAsicAsync(options: AsicSignOptions, signal?: AbortSignal): Promise<Buffer>;
+}
+export interface XadesSignOptions {
+  content: Buffer;
+  uri: string;
+  mimeType?: string;
   signingTime?: string;
   timestampServer?: string;
 }
-/** Sign data with a P12 container. */
-export function signWithP12(options: SignWithP12Options): Buffer;
-export interface SignWithGCloudOptions {
+export interface JadesSignOptions {
+  payload: Buffer;
+  contentType?: string;
+  signingTime?: string;
+  timestampServer?: string;
+  detached?: boolean;
+  compact?: boolean;
+}
+export interface AsicFile {
+  name: string;
   content: Buffer;
+  mimeType?: string;
+}
+export interface AsicSignOptions {
+  files: Array<AsicFile>;
+  containerType?: 'ASiC-S' | 'ASiC-E';
+  signingTime?: string;
+  timestampServer?: string;
+}
+/**
+ * Load a key and its certificates once, returning a handle to sign with any number of times.
+ *
+ * Unlike a `Signer`, the handle is a number that can be passed to other worker threads, which
+ * all share the loaded key.
+ */
+export function loadKey(options: SignerKeyOptions): number;
+/** Drop a loaded key, returning whether the handle was loaded. */
+export function unloadKey(handle: number): boolean;
+/** Sign data with a loaded key. */
+export function signWithKey(
+  handle: number,
+  options: SigningOptions & DetailedOutput,
+): SigningResult;
+export function signWithKey(handle: number, options: SigningOptions): Buffer;
+/** Sign data with a loaded key without blocking the event loop. */
+export function signWithKeyAsync(
+  handle: number,
+  options: SigningOptions & DetailedOutput,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<SigningResult>;
+export function signWithKeyAsync(
+  handle: number,
+  options: SigningOptions,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<Buffer>;
+/**
+ * Computes the digest of content incrementally, e.g. while reading it from a stream, for the
+ * `digest` option of the signing functions.
+ */
+export class ContentDigest {
+  constructor(digestAlgorithm?: DigestAlgorithm);
+  /** Add a chunk of the content. */
+  update(chunk: Uint8Array): void;
+  /** Finish the digest, after which no more chunks can be added. */
+  digest(): Buffer;
+}
+export interface LogEvent {
+  level: Exclude<LogLevel, 'off'>;
+  target: string;
+  message: string;
+  fields: Record<string, string>;
+}
+/**
+ * Pass log events to a callback instead of logging them to stderr.
+ *
+ * Events up to the level, `info` by default, are passed to the callback. Without a callback,
+ * events are logged to stderr again, up to the level or the one set by `PDF_SIGN_LOG`, as they
+ * are when the worker thread that set the callback exits.
+ */
+export function setLogger(callback?: (event: LogEvent) => void, level?: LogLevel): void;
+export interface ConfigureDefaultsOptions {
+  timestampServer?: string;
+  digestAlgorithm?: DigestAlgorithm;
+  trustStore?: Buffer;
+  proxy?: string;
+}
+/**
+ * Set defaults for options left out of later calls, replacing any defaults set before.
+ *
+ * The digest algorithm is checked against the key by the signing functions, like one given
+ * for the call. The trust store is a PEM bundle of the certificates that dry runs expect the
+ * signing certificate to chain up to. The proxy is used for requests to timestamp servers and
+ * EJBCA.
+ */
+export function configureDefaults(options: ConfigureDefaultsOptions): void;
+/** The version of the addon and what it supports. */
+export interface Capabilities {
+  version: string;
+  target: string;
+  keyAlgorithms: Array<string>;
+  digestAlgorithms: Array<DigestAlgorithm>;
+  backends: Array<string>;
+  padesLevels: Array<string>;
+}
+/**
+ * Report the version of the addon and what it supports, so applications can detect features at
+ * runtime rather than by the version of the package, whose prebuilt binaries differ by platform.
+ */
+export function capabilities(): Capabilities;
+export interface SignWithSignerOptions extends SigningOptions {
   cert: Buffer;
-  keyPath: string;
+  signer: string;
+  signerOptions?: Record<string, string>;
+}
+/**
+ * Sign data with a registered signer.
+ *
+ * Signers registered with `registerSigner` take precedence over the `backends` of `capabilities()`,
+ * such as `gcloud`, which read their configuration from `signerOptions`.
+ */
+export function signWithSigner(options: SignWithSignerOptions & DetailedOutput): SigningResult;
+export function signWithSigner(options: SignWithSignerOptions): Buffer;
+/**
+ * Sign data with a registered signer without blocking the event loop.
+ *
+ * Signers registered with `registerSigner` call back into JS on the thread they were registered
+ * on, so only the signers of the other backends are found on the thread pool.
+ */
+export function signWithSignerAsync(
+  options: SignWithSignerOptions & DetailedOutput,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<SigningResult>;
+export function signWithSignerAsync(
+  options: SignWithSignerOptions,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<Buffer>;
+/**
+ * Register a JS signer for use with `signWithSigner`.
+ *
+ * The callback receives the DER encoded signed attributes as a Buffer and must return the
+ * signature as a Buffer. The signature algorithm is derived from the signing certificate.
+ */
+export function registerSigner(name: string, callback: (...args: any[]) => any): void;
+/** Remove a JS signer registered with `registerSigner`. */
+export function unregisterSigner(name: string): boolean;
+export interface ParallelSignerOptions {
+  cert: Buffer;
+  signer: string;
+  signerOptions?: Record<string, string>;
+}
+export interface SignWithSignersOptions extends SigningOptions {
+  signers: Array<ParallelSignerOptions>;
+}
+/**
+ * Sign data with several registered signers at once, producing a signer info for each.
+ *
+ * Signers are resolved as for `signWithSigner`. The attribute options apply to every signer.
+ */
+export function signWithSigners(
+  options: SignWithSignersOptions & DetailedOutput,
+): SigningResult;
+export function signWithSigners(options: SignWithSignersOptions): Buffer;
+/**
+ * Sign data with several registered signers at once without blocking the event loop.
+ *
+ * Signers are resolved as for `signWithSignerAsync`.
+ */
+export function signWithSignersAsync(
+  options: SignWithSignersOptions & DetailedOutput,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<SigningResult>;
+export function signWithSignersAsync(
+  options: SignWithSignersOptions,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<Buffer>;
+export interface SignDigestBatchOptions extends SigningOptions {
+  digests: Array<Buffer>;
+  cert: Buffer;
+}
+/**
+ * Sign a batch of precomputed digests with one call to a remote signer, returning a signature
+ * per digest.
+ *
+ * The callback receives the DER encoded signed attributes of every signature as an array of
+ * Buffers and must return the array of their signatures, so a remote signer that authorizes
+ * each request, such as a CSC provider, is authorized once for the whole batch.
+ */
+export function signDigestBatch(
+  options: SignDigestBatchOptions,
+  callback: (messages: Array<Buffer>) => Array<Buffer>,
+): Array<Buffer>;
+/**
+ * Sign a batch of precomputed digests with one call to a remote signer, assembling and
+ * timestamping the signatures without blocking the event loop.
+ *
+ * The callback is called as by `signDigestBatch`, on the event loop before this returns, so it
+ * still returns the signatures synchronously.
+ */
+export function signDigestBatchAsync(
+  options: SignDigestBatchOptions,
+  callback: (messages: Array<Buffer>) => Array<Buffer>,
+  signal?: AbortSignal,
+): Promise<Array<Buffer>>;
+export interface PrepareSignedAttributesOptions extends SigningOptions {
+  digest: Buffer;
+  cert: Buffer;
+}
+export interface PreparedSignedAttributes {
+  /** The DER encoded signed attributes, which the external signer signs. */
+  signedAttributesDer: Buffer;
+  /**
+   * The digest of the signed attributes, for signers that sign a digest, computed with the
+   * digest algorithm of the certificate's key. Missing for Ed25519, which signs the signed
+   * attributes themselves.
+   */
+  signedAttributesDigest?: Buffer;
+  /**
+   * The digest algorithm of `signedAttributesDigest`, `sha256` for RSA and ECDSA P-256
+   * certificates and `sha384` for P-384.
+   */
+  signedAttributesDigestAlgorithm?: DigestAlgorithm;
+  /** The signing time of the signed attributes, to pass to `assembleSignedData`. */
+  signingTime: string;
+}
+/**
+ * Prepare the signed attributes of a signature of a precomputed digest, for an external signer
+ * to sign and `assembleSignedData` to assemble the signed data with.
+ */
+export function prepareSignedAttributes(
+  options: PrepareSignedAttributesOptions,
+): PreparedSignedAttributes;
+export interface AssembleSignedDataOptions extends SigningOptions {
+  signedAttributesDer: Buffer;
+  signature: Buffer;
+  digest: Buffer;
+  cert: Buffer;
+  signingTime: string;
+}
+/**
+ * Assemble the signed data of a signature prepared with `prepareSignedAttributes`, from the
+ * signature of its signed attributes by an external signer.
+ *
+ * The signed attributes are built again from the options, which must be those they were
+ * prepared with, and the signature is checked against the certificate before the timestamp is
+ * requested.
+ */
+export function assembleSignedData(options: AssembleSignedDataOptions): Buffer;
+/**
+ * Assemble the signed data of a signature prepared with `prepareSignedAttributes` without
+ * blocking the event loop.
+ */
+export function assembleSignedDataAsync(
+  options: AssembleSignedDataOptions,
+  signal?: AbortSignal,
+): Promise<Buffer>;
+export interface SignerPoolMemberOptions {
+  cert: Buffer;
+  signer: string;
+  signerOptions?: Record<string, string>;
+}
+export interface RegisterSignerPoolOptions {
+  name: string;
+  members: Array<SignerPoolMemberOptions>;
+  failureThreshold?: number;
+  cooldownMs?: number;
+}
+/**
+ * Register a pool of signers for use with `signWithSignerPool`.
+ *
+ * Requests are distributed round-robin across the members. A member failing `failureThreshold`
+ * times in a row (default 3) is skipped for `cooldownMs` (default 30 seconds). Members in their
+ * cooldown are only tried when every member is in it.
+ */
+export function registerSignerPool(options: RegisterSignerPoolOptions): void;
+/** Remove a pool registered with `registerSignerPool`. */
+export function unregisterSignerPool(name: string): boolean;
+export interface SignerPoolMemberStatus {
+  healthy: boolean;
+  consecutiveFailures: number;
+}
+/** Get the health of each member of a pool, in registration order. */
+export function signerPoolStatus(name: string): Array<SignerPoolMemberStatus>;
+export interface SignWithSignerPoolOptions extends SigningOptions {
+  pool: string;
+}
+/**
+ * Sign data with the next healthy member of a pool.
+ *
+ * If a member fails to sign, the next member is tried until one succeeds. Failures unrelated
+ * to the key, such as an unreachable timestamp server, are returned immediately.
+ */
+export function signWithSignerPool(
+  options: SignWithSignerPoolOptions & DetailedOutput,
+): SigningResult;
+export function signWithSignerPool(options: SignWithSignerPoolOptions): Buffer;
+/**
+ * Sign data with the next healthy member of a pool without blocking the event loop.
+ *
+ * Members are resolved as for `signWithSignerAsync`, so a member registered with
+ * `registerSigner` fails and the next one is tried.
+ */
+export function signWithSignerPoolAsync(
+  options: SignWithSignerPoolOptions & DetailedOutput,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<SigningResult>;
+export function signWithSignerPoolAsync(
+  options: SignWithSignerPoolOptions,
+  signal?: AbortSignal,
+  onProgress?: (stage: SigningStage) => void,
+): Promise<Buffer>;
+export interface AddUnsignedAttributesOptions {
+  signedData: Buffer;
+  unsignedAttributes: Array<CustomAttribute>;
+  signerIndex?: number;
+}
+/**
+ * Add unsigned attributes to an existing signature without re-signing.
+ *
+ * The attributes are added to the first signer info unless `signerIndex` is given. Everything
+ * else in the signed data is kept as it was.
+ */
+export function addUnsignedAttributes(options: AddUnsignedAttributesOptions): Buffer;
+export interface CounterSignOptions {
+  signedData: Buffer;
+  signerIndex?: number;
+  cert: Buffer;
+  signer: string;
+  signerOptions?: Record<string, string>;
   signingTime?: string;
   timestampServer?: string;
 }
-/** Sign data with Google Cloud. */
-export function signWithGCloud(options: SignWithGCloudOptions): Buffer;
+/**
+ * Counter-sign a signer info of an existing signature with a registered signer.
+ *
+ * The counter-signature signs the signature value of the signer info at `signerIndex`, the
+ * first by default, and is added to it as a countersignature unsigned attribute. The
+ * certificates of the counter-signer are added to the signed data.
+ */
+export function counterSign(options: CounterSignOptions): Buffer;
+/**
+ * Counter-sign a signer info of an existing signature without blocking the event loop.
+ *
+ * Signers are resolved as for `signWithSignerAsync`.
+ */
+export function counterSignAsync(
+  options: CounterSignOptions,
+  signal?: AbortSignal,
+): Promise<Buffer>;
+export interface RequestTimestampOptions {
+  content?: Buffer;
+  digest?: Buffer;
+  digestAlgorithm?: DigestAlgorithm;
+  timestampServer?: string;
+}
+/**
+ * Request an RFC 3161 timestamp token for content or its digest, e.g. for the document
+ * timestamp of a PDF, returning the DER encoded TimeStampToken.
+ */
+export function requestTimestamp(options: RequestTimestampOptions): Buffer;
+/** Request a timestamp token without blocking the event loop. */
+export function requestTimestampAsync(
+  options: RequestTimestampOptions,
+  signal?: AbortSignal,
+): Promise<Buffer>;
+export interface CreateDocumentTimestampOptions {
+  content?: Buffer;
+  contentPath?: string;
+  digest?: Buffer;
+  digestAlgorithm?: DigestAlgorithm;
+  timestampServer?: string;
+  outputPath?: string;
+  maxSize?: number;
+}
+/**
+ * Create the CMS of a document timestamp (PAdES-B-LTA), the timestamp token of the byte range
+ * of a PDF for the /Contents of a DocTimeStamp signature with the ETSI.RFC3161 sub filter.
+ */
+export function createDocumentTimestamp(options: CreateDocumentTimestampOptions): Buffer;
+/** Create the CMS of a document timestamp without blocking the event loop. */
+export function createDocumentTimestampAsync(
+  options: CreateDocumentTimestampOptions,
+  signal?: AbortSignal,
+): Promise<Buffer>;
+export interface AddArchiveTimestampOptions {
+  signedData: Buffer;
+  content?: Uint8Array;
+  signerIndex?: number;
+  timestampServer?: string;
+  digestAlgorithm?: DigestAlgorithm;
+}
+/**
+ * Add an archive timestamp (archive-time-stamp-v3) to an existing signature, for CAdES-A.
+ *
+ * The timestamp covers the content, the signer info at `signerIndex`, the first by default,
+ * and the certificates, CRLs and unsigned attributes present at this point, so validation data
+ * should be added first. The content must be given for detached signatures.
+ */
+export function addArchiveTimestamp(options: AddArchiveTimestampOptions): Buffer;
+/** Add an archive timestamp to an existing signature without blocking the event loop. */
+export function addArchiveTimestampAsync(
+  options: AddArchiveTimestampOptions,
+  signal?: AbortSignal,
+): Promise<Buffer>;
+export interface CreateEvidenceRecordOptions {
+  data: Array<Buffer>;
+  timestampServer?: string;
+  digestAlgorithm?: DigestAlgorithm;
+}
+/**
+ * Create an Evidence Record (RFC 4998) with an archive timestamp over a group of data objects,
+ * such as a signed PDF and its validation data, for archives that ingest ERS.
+ */
+export function createEvidenceRecord(options: CreateEvidenceRecordOptions): Buffer;
+/** Create an Evidence Record without blocking the event loop. */
+export function createEvidenceRecordAsync(
+  options: CreateEvidenceRecordOptions,
+  signal?: AbortSignal,
+): Promise<Buffer>;
+export interface FetchRevocationInfoOptions {
+  cert: Buffer;
+}
+/**
+ * Fetch the OCSP responses and CRLs of a certificate chain, the signing certificate first and
+ * its issuers after it or in the trust store, e.g. for the DSS dictionary of a PDF or the
+ * `revocationValues` option.
+ */
+export function fetchRevocationInfo(options: FetchRevocationInfoOptions): RevocationValues;
+/** Fetch the revocation data of a certificate chain without blocking the event loop. */
+export function fetchRevocationInfoAsync(
+  options: FetchRevocationInfoOptions,
+  signal?: AbortSignal,
+): Promise<RevocationValues>;
+export interface ParsedCms {
+  version: number;
+  digestAlgorithms: Array<string>;
+  contentType: string;
+  content?: Buffer;
+  certificates: Array<ParsedCertificate>;
+  signerInfos: Array<ParsedSignerInfo>;
+}
+export interface ParsedCertificate {
+  subject: string;
+  issuer: string;
+  serialNumber: string;
+  notBefore: string;
+  notAfter: string;
+  der: Buffer;
+}
+export interface ParsedSignerInfo {
+  version: number;
+  issuer?: string;
+  serialNumber?: string;
+  subjectKeyIdentifier?: Buffer;
+  digestAlgorithm: string;
+  signatureAlgorithm: string;
+  signature: Buffer;
+  messageDigest?: Buffer;
+  signedAttributes: Array<ParsedAttribute>;
+  signedAttributesDer?: Buffer;
+  unsignedAttributes: Array<ParsedAttribute>;
+  timestamps: Array<ParsedTimestamp>;
+}
+export interface ParsedAttribute {
+  oid: string;
+  name?: string;
+  values: Array<Buffer>;
+}
+export interface ParsedTimestamp {
+  time: string;
+  policy: string;
+  serialNumber: string;
+  hashAlgorithm: string;
+  hashedMessage: Buffer;
+}
+/**
+ * Decode a CMS signature and return its structure, for debugging and audit logging.
+ *
+ * OIDs are returned in dotted form and serial numbers in hex. Attribute values are returned
+ * DER encoded, and timestamp tokens are also decoded to their TSTInfo.
+ */
+export function parseCms(signedData: Buffer): ParsedCms;
+/** A profile whose requirements on the CMS of a signature `checkCompliance` evaluates. */
+export type ComplianceProfile =
+  | 'CAdES-B-B'
+  | 'CAdES-B-T'
+  | 'CAdES-B-LT'
+  | 'CAdES-B-LTA'
+  | 'PAdES-B-B'
+  | 'PAdES-B-T'
+  | 'QES'
+  | 'ZertES'
+  | 'QSeal';
+export interface ComplianceReport {
+  profile: ComplianceProfile;
+  compliant: boolean;
+  missing: Array<string>;
+}
+/**
+ * Check that a signer info of a CMS signature has the elements a profile requires, such as the
+ * signature timestamp of PAdES-B-T, so pipelines can gate on compliance.
+ */
+export function checkCompliance(
+  signedData: Buffer,
+  profile: ComplianceProfile,
+  signerIndex?: number,
+): ComplianceReport;
+export interface VerifySignedDataOptions {
+  signature: Buffer;
+  content?: Buffer;
+  trustedRoots?: Buffer;
+}
+export interface SignedDataVerification {
+  valid: boolean;
+  signers: Array<VerifiedSigner>;
+}
+export interface VerifiedSigner {
+  subject: string;
+  serialNumber: string;
+  digestValid: boolean;
+  signatureValid: boolean;
+  signingTime?: string;
+  timestampTime?: string;
+  timestampValid?: boolean;
+  chainValid: boolean;
+  problems: Array<string>;
+}
+/**
+ * Verify a CMS signature: the message digest of each signer info against the content, its
+ * signature against its certificate, its signature timestamp and the certificate chain, so
+ * callers can check signatures without shelling out to OpenSSL.
+ */
+export function verifySignedData(options: VerifySignedDataOptions): SignedDataVerification;
+/**
+ * Wrap a MIME entity and a detached CMS signature of it in an S/MIME multipart/signed message,
+ * so emails can be signed with the same key as documents.
+ */
+export function createSmimeMessage(body: Buffer, signature: Buffer): Buffer;
+export interface EncryptBiometricDataOptions {
+  data: Buffer;
+  recipients: Buffer;
+}
+/**
+ * Encrypt biometric data from a signature pad to the certificates of its trustees, as a CMS
+ * AuthEnvelopedData to embed in the signature dictionary next to the signature.
+ */
+export function encryptBiometricData(options: EncryptBiometricDataOptions): Buffer;
+export interface CreateSignatureAppearanceOptions {
+  width: number;
+  height: number;
+  page?: number;
+  x?: number;
+  y?: number;
+  name?: string;
+  reason?: string;
+  location?: string;
+  signingTime?: string;
+  image?: Buffer;
+  fontSize?: number;
+}
+export interface SignatureAppearance {
+  page: number;
+  rect: Array<number>;
+  bbox: Array<number>;
+  content: Buffer;
+  fontSize: number;
+  font: AppearanceFont;
+  image?: AppearanceImage;
+}
+export interface AppearanceFont {
+  resourceName: string;
+  baseFont: string;
+  encoding: string;
+}
+export interface AppearanceImage {
+  resourceName: string;
+  width: number;
+  height: number;
+  colorSpace: 'DeviceGray' | 'DeviceRGB' | 'DeviceCMYK';
+  bitsPerComponent: number;
+  filter: 'DCTDecode' | 'FlateDecode';
+  decode?: Array<number>;
+  data: Buffer;
+  softMask?: Buffer;
+}
+/**
+ * Build the appearance stream of a visible signature, a stamp with the signer's name, the
+ * reason, location and date and an optional image of a handwritten signature, with where the
+ * widget annotation goes on the page.
+ */
+export function createSignatureAppearance(
+  options: CreateSignatureAppearanceOptions,
+): SignatureAppearance;
+export interface CreateCertificationReferenceOptions {
+  permissions?: DocMdpPermissions;
+}
+export interface CertificationReference {
+  permissions: number;
+  transformMethod: string;
+  version: string;
+  reference: string;
+}
+/**
+ * Build the DocMDP signature reference of a certification signature, which limits the changes
+ * allowed to the document after it, for the signature dictionary of the PDF.
+ */
+export function createCertificationReference(
+  options?: CreateCertificationReferenceOptions,
+): CertificationReference;
+export interface SelfTestOptions {
+  timestampServer?: string;
+}
+export interface SelfTestResult {
+  capability: string;
+  passed: boolean;
+  code?: string;
+  message?: string;
+  duration: number;
+}
+/**
+ * Sign and verify an embedded fixture document with each supported key algorithm, for
+ * deployment smoke tests of the prebuilt binaries.
+ */
+export function runSelfTest(options?: SelfTestOptions): Array<SelfTestResult>;
+export interface TestSignerOptions {
+  cert?: Buffer;
+  keyPath?: string;
+  signer?: string;
+  signerOptions?: Record<string, string>;
+}
+export interface TestSignerResult {
+  algorithm: string;
+  signatureSize: number;
+}
+/**
+ * Check that a key can be used for signing.
+ *
+ * Creates the key with the signer the signing functions use, a Google Cloud key unless another
+ * signer is named, signs a throwaway message and, if a certificate is provided, verifies the
+ * signature against it with the algorithm the key signs with.
+ */
+export function testSigner(options: TestSignerOptions): TestSignerResult;
+/** Check that a key can be used for signing without blocking the event loop. */
+export function testSignerAsync(
+  options: TestSignerOptions,
+  signal?: AbortSignal,
+): Promise<TestSignerResult>;
+export interface ValidateSigningSetupOptions {
+  p12?: Buffer;
+  p12Path?: string;
+  p12Source?: string;
+  password?: string;
+  passwordSource?: string;
+  friendlyName?: string;
+  thumbprint?: string;
+  cert?: Buffer;
+  privateKey?: Buffer;
+  privateKeyPassword?: string;
+  format?: InputFormat;
+  keyPath?: string;
+  timestampServer?: string;
+}
+export interface SigningSetupReport {
+  ready: boolean;
+  backend?: 'p12' | 'privateKey' | 'gcloud';
+  signerSubject?: string;
+  checks: Array<SigningSetupCheck>;
+  warnings: Array<string>;
+}
+export interface SigningSetupCheck {
+  check: 'key' | 'signing' | 'certificate' | 'timestamp';
+  passed: boolean;
+  code?: string;
+  message?: string;
+  duration: number;
+}
+/**
+ * Check that a key, its certificates and the timestamp server are ready for signing, by
+ * loading the key, signing a throwaway message and requesting a timestamp of it, for
+ * deployment key ceremonies.
+ *
+ * Failures are reported per check rather than thrown, and a check that depends on a failed
+ * one isn't run.
+ */
+export function validateSigningSetup(options: ValidateSigningSetupOptions): SigningSetupReport;
+/**
+ * Check that a key, its certificates and the timestamp server are ready for signing without
+ * blocking the event loop.
+ */
+export function validateSigningSetupAsync(
+  options: ValidateSigningSetupOptions,
+  signal?: AbortSignal,
+): Promise<SigningSetupReport>;
+export interface EnrollWithEjbcaOptions {
+  url: string;
+  clientCert: Buffer;
+  clientKey?: Buffer;
+  clientKeyPassword?: string;
+  caCert?: Buffer;
+  privateKey?: Buffer;
+  privateKeyPassword?: string;
+  commonName: string;
+  certificateProfileName: string;
+  endEntityProfileName: string;
+  certificateAuthorityName: string;
+  username: string;
+  enrollmentCode: string;
+}
+export interface EnrollWithEjbcaResult {
+  cert: Buffer;
+  privateKey: Buffer;
+}
+/**
+ * Enroll for a certificate with an EJBCA instance.
+ *
+ * The returned certificate chain and private key can be passed to `signWithPrivateKey`.
+ */
+export function enrollWithEjbca(options: EnrollWithEjbcaOptions): EnrollWithEjbcaResult;
+/** Enroll for a certificate with an EJBCA instance without blocking the event loop. */
+export function enrollWithEjbcaAsync(
+  options: EnrollWithEjbcaOptions,
+  signal?: AbortSignal,
+): Promise<EnrollWithEjbcaResult>;
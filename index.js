@@ -14,6 +14,12 @@ let localFileExisted = false;
 let loadError = null;
 
 function isMusl() {
+  // Reading ldd works in Deno and Bun too, whose process.report may lack the glibc version
+  try {
+    return readFileSync('/usr/bin/ldd', 'utf8').includes('musl');
+  } catch (e) {
+    // Fall back to process.report
+  }
   // For Node 10
   if (!process.report || typeof process.report.getReport !== 'function') {
     try {
@@ -271,8 +277,112 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`);
 }
 
-const { signWithPrivateKey, signWithP12, signWithGCloud } = nativeBinding;
+const {
+  signWithPrivateKey,
+  signWithP12,
+  inspectP12,
+  signWithGCloud,
+  signWithPrivateKeyAsync,
+  signWithP12Async,
+  signWithGCloudAsync,
+  Signer,
+  loadKey,
+  unloadKey,
+  signWithKey,
+  signWithKeyAsync,
+  ContentDigest,
+  setLogger,
+  configureDefaults,
+  capabilities,
+  signWithSigner,
+  registerSigner,
+  unregisterSigner,
+  signWithSigners,
+  signDigestBatch,
+  prepareSignedAttributes,
+  assembleSignedData,
+  registerSignerPool,
+  unregisterSignerPool,
+  signerPoolStatus,
+  signWithSignerPool,
+  addUnsignedAttributes,
+  counterSign,
+  requestTimestamp,
+  requestTimestampAsync,
+  createDocumentTimestamp,
+  createDocumentTimestampAsync,
+  addArchiveTimestamp,
+  addArchiveTimestampAsync,
+  createEvidenceRecord,
+  createEvidenceRecordAsync,
+  fetchRevocationInfo,
+  fetchRevocationInfoAsync,
+  parseCms,
+  checkCompliance,
+  verifySignedData,
+  createSmimeMessage,
+  encryptBiometricData,
+  createSignatureAppearance,
+  createCertificationReference,
+  runSelfTest,
+  testSigner,
+  testSignerAsync,
+  validateSigningSetup,
+  validateSigningSetupAsync,
+  enrollWithEjbca,
+  enrollWithEjbcaAsync,
+} = nativeBinding;
 
 module.exports.signWithPrivateKey = signWithPrivateKey;
 module.exports.signWithP12 = signWithP12;
+module.exports.inspectP12 = inspectP12;
 module.exports.signWithGCloud = signWithGCloud;
+module.exports.signWithPrivateKeyAsync = signWithPrivateKeyAsync;
+module.exports.signWithP12Async = signWithP12Async;
+module.exports.signWithGCloudAsync = signWithGCloudAsync;
+module.exports.Signer = Signer;
+module.exports.loadKey = loadKey;
+module.exports.unloadKey = unloadKey;
+module.exports.signWithKey = signWithKey;
+module.exports.signWithKeyAsync = signWithKeyAsync;
+module.exports.ContentDigest = ContentDigest;
+module.exports.setLogger = setLogger;
+module.exports.configureDefaults = configureDefaults;
+module.exports.capabilities = capabilities;
+module.exports.signWithSigner = signWithSigner;
+module.exports.registerSigner = registerSigner;
+module.exports.unregisterSigner = unregisterSigner;
+module.exports.signWithSigners = signWithSigners;
+module.exports.signDigestBatch = signDigestBatch;
+module.exports.prepareSignedAttributes = prepareSignedAttributes;
+module.exports.assembleSignedData = assembleSignedData;
+module.exports.registerSignerPool = registerSignerPool;
+module.exports.unregisterSignerPool = unregisterSignerPool;
+module.exports.signerPoolStatus = signerPoolStatus;
+module.exports.signWithSignerPool = signWithSignerPool;
+module.exports.addUnsignedAttributes = addUnsignedAttributes;
+module.exports.counterSign = counterSign;
+module.exports.requestTimestamp = requestTimestamp;
+module.exports.requestTimestampAsync = requestTimestampAsync;
+module.exports.createDocumentTimestamp = createDocumentTimestamp;
+module.exports.createDocumentTimestampAsync = createDocumentTimestampAsync;
+module.exports.addArchiveTimestamp = addArchiveTimestamp;
+module.exports.addArchiveTimestampAsync = addArchiveTimestampAsync;
+module.exports.createEvidenceRecord = createEvidenceRecord;
+module.exports.createEvidenceRecordAsync = createEvidenceRecordAsync;
+module.exports.fetchRevocationInfo = fetchRevocationInfo;
+module.exports.fetchRevocationInfoAsync = fetchRevocationInfoAsync;
+module.exports.parseCms = parseCms;
+module.exports.checkCompliance = checkCompliance;
+module.exports.verifySignedData = verifySignedData;
+module.exports.createSmimeMessage = createSmimeMessage;
+module.exports.encryptBiometricData = encryptBiometricData;
+module.exports.createSignatureAppearance = createSignatureAppearance;
+module.exports.createCertificationReference = createCertificationReference;
+module.exports.runSelfTest = runSelfTest;
+module.exports.testSigner = testSigner;
+module.exports.testSignerAsync = testSignerAsync;
+module.exports.validateSigningSetup = validateSigningSetup;
+module.exports.validateSigningSetupAsync = validateSigningSetupAsync;
+module.exports.enrollWithEjbca = enrollWithEjbca;
+module.exports.enrollWithEjbcaAsync = enrollWithEjbcaAsync;
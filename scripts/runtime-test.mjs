@@ -0,0 +1,21 @@
+// Smoke test of the bindings, run in CI with Node.js, Bun and Deno.
+import assert from 'node:assert/strict';
+import { createHash } from 'node:crypto';
+import { createRequire } from 'node:module';
+
+const require = createRequire(import.meta.url);
+const { ContentDigest, signWithPrivateKey, signWithPrivateKeyAsync } = require('../index.js');
+
+const content = Buffer.from('documenso/pdf-sign');
+
+const digest = new ContentDigest();
+digest.update(content);
+assert.deepEqual(digest.digest(), createHash('sha256').update(content).digest());
+
+// Errors keep their code across the Node-API boundary, thrown and rejected.
+const options = { content, cert: Buffer.alloc(0), privateKey: Buffer.alloc(0) };
+assert.throws(() => signWithPrivateKey(options), { code: 'ERR_INVALID_OPTION' });
+await assert.rejects(signWithPrivateKeyAsync(options), { code: 'ERR_INVALID_OPTION' });
+
+const runtime = globalThis.Deno ? 'Deno' : globalThis.Bun ? 'Bun' : 'Node.js';
+console.log(`The bindings work with ${runtime}`);
@@ -0,0 +1,171 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use pdf_sign_core::parse_signing_time;
+use pdf_sign_core::{asic, jades, validation, xades};
+
+use crate::errors::CmsError;
+use crate::signers::ReusableSigner;
+use crate::{defaults, Result};
+
+#[napi(object)]
+pub struct XadesSignOptions {
+  pub content: Buffer,
+  pub uri: String,
+  pub mime_type: Option<String>,
+  pub signing_time: Option<String>,
+  pub timestamp_server: Option<String>,
+}
+
+#[napi(object)]
+pub struct JadesSignOptions {
+  pub payload: Buffer,
+  pub content_type: Option<String>,
+  pub signing_time: Option<String>,
+  pub timestamp_server: Option<String>,
+  pub detached: Option<bool>,
+  pub compact: Option<bool>,
+}
+
+#[napi(object)]
+pub struct AsicFile {
+  pub name: String,
+  pub content: Buffer,
+  pub mime_type: Option<String>,
+}
+
+#[napi(object)]
+pub struct AsicSignOptions {
+  pub files: Vec<AsicFile>,
+  pub container_type: Option<String>,
+  pub signing_time: Option<String>,
+  pub timestamp_server: Option<String>,
+}
+
+impl ReusableSigner {
+  pub fn sign_xades(&self, options: XadesSignOptions) -> Result<Buffer> {
+    let XadesSignOptions {
+      content,
+      uri,
+      mime_type,
+      signing_time,
+      timestamp_server,
+    } = options;
+
+    let timestamp_server = defaults::timestamp_server(timestamp_server)
+      .map(|url| validation::timestamp_server("timestampServer", &url))
+      .transpose()?;
+
+    if uri.is_empty() {
+      return Err(CmsError::InvalidOption(
+        "uri",
+        String::from("expected the URI the content is referenced by, e.g. its file name"),
+      ));
+    }
+
+    let certs = self
+      .certs
+      .iter()
+      .map(|cert| cert.parsed().cloned())
+      .collect::<Result<Vec<_>>>()?;
+
+    let signature = xades::DetachedSignature {
+      signing_key: self.signing_key.as_ref(),
+      certs: &certs,
+      content: &content,
+      uri: &uri,
+      mime_type: mime_type.as_deref().unwrap_or("application/octet-stream"),
+      signing_time: parse_signing_time(signing_time),
+      time_stamp_url: timestamp_server.as_ref(),
+      limiter: self.limiter.as_deref(),
+    }
+    .sign()?;
+
+    Ok(Buffer::from(signature.into_bytes()))
+  }
+
+  pub fn sign_jades(&self, options: JadesSignOptions) -> Result<Buffer> {
+    let JadesSignOptions {
+      payload,
+      content_type,
+      signing_time,
+      timestamp_server,
+      detached,
+      compact,
+    } = options;
+
+    let compact = compact.unwrap_or(false);
+
+    // The default timestamp server only applies to the JSON serialization, which can carry it.
+    let timestamp_server = match compact {
+      true => timestamp_server,
+      false => defaults::timestamp_server(timestamp_server),
+    }
+    .map(|url| validation::timestamp_server("timestampServer", &url))
+    .transpose()?;
+
+    let certs = self
+      .certs
+      .iter()
+      .map(|cert| cert.parsed().cloned())
+      .collect::<Result<Vec<_>>>()?;
+
+    let signature = jades::Signature {
+      signing_key: self.signing_key.as_ref(),
+      certs: &certs,
+      payload: &payload,
+      content_type: content_type.as_deref(),
+      signing_time: parse_signing_time(signing_time),
+      time_stamp_url: timestamp_server.as_ref(),
+      detached: detached.unwrap_or(false),
+      compact,
+      limiter: self.limiter.as_deref(),
+    }
+    .sign()?;
+
+    Ok(Buffer::from(signature.into_bytes()))
+  }
+
+  pub fn sign_asic(&self, options: AsicSignOptions) -> Result<Buffer> {
+    let AsicSignOptions {
+      files,
+      container_type,
+      signing_time,
+      timestamp_server,
+    } = options;
+
+    let kind = container_type
+      .map(|name| asic::Kind::parse("containerType", &name))
+      .transpose()?
+      .unwrap_or(asic::Kind::Extended);
+
+    let timestamp_server = defaults::timestamp_server(timestamp_server)
+      .map(|url| validation::timestamp_server("timestampServer", &url))
+      .transpose()?;
+
+    let files = files
+      .iter()
+      .map(|file| asic::File {
+        name: &file.name,
+        content: &file.content,
+        mime_type: file
+          .mime_type
+          .as_deref()
+          .unwrap_or("application/octet-stream"),
+      })
+      .collect::<Vec<_>>();
+
+    let container = asic::Container {
+      kind,
+      files: &files,
+      signing_key: self.signing_key.as_ref(),
+      certs: &self.certs,
+      signing_time: parse_signing_time(signing_time),
+      time_stamp_url: timestamp_server.as_ref(),
+      limiter: self.limiter.clone(),
+    }
+    .sign()?;
+
+    Ok(Buffer::from(container))
+  }
+}
@@ -0,0 +1,41 @@
+use std::sync::RwLock;
+
+use x509_certificate::CapturedX509Certificate;
+
+/// Defaults for options left out of a call, set once by `configureDefaults`.
+#[derive(Default)]
+pub struct Defaults {
+  pub timestamp_server: Option<String>,
+  pub digest_algorithm: Option<String>,
+  pub trust_store: Vec<CapturedX509Certificate>,
+}
+
+static DEFAULTS: RwLock<Defaults> = RwLock::new(Defaults {
+  timestamp_server: None,
+  digest_algorithm: None,
+  trust_store: Vec::new(),
+});
+
+/// Replace all defaults.
+pub fn set(defaults: Defaults) {
+  *DEFAULTS.write().unwrap_or_else(|error| error.into_inner()) = defaults;
+}
+
+/// The timestamp server given for a call, or the default one.
+pub fn timestamp_server(timestamp_server: Option<String>) -> Option<String> {
+  timestamp_server.or_else(|| read(|defaults| defaults.timestamp_server.clone()))
+}
+
+/// The digest algorithm given for a call, or the default one.
+pub fn digest_algorithm(digest_algorithm: Option<String>) -> Option<String> {
+  digest_algorithm.or_else(|| read(|defaults| defaults.digest_algorithm.clone()))
+}
+
+/// The trusted certificates, empty if no trust store is configured.
+pub fn trust_store() -> Vec<CapturedX509Certificate> {
+  read(|defaults| defaults.trust_store.clone())
+}
+
+fn read<T>(f: impl FnOnce(&Defaults) -> T) -> T {
+  f(&DEFAULTS.read().unwrap_or_else(|error| error.into_inner()))
+}
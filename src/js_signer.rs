@@ -0,0 +1,249 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::thread::{self, ThreadId};
+
+use cryptographic_message_syntax::Bytes;
+use napi::{Env, JsBuffer, JsFunction, JsObject, Ref};
+use rsa::pkcs8::der::zeroize::Zeroizing;
+use x509_certificate::{
+  DigestAlgorithm, KeyAlgorithm, KeyInfoSigner, Sign, Signature, SignatureAlgorithm, Signer,
+  X509CertificateError,
+};
+
+use crate::errors::CmsError;
+use crate::signer::{SignerBackend, SignerInput, SignerKey};
+
+thread_local! {
+  /// Signers registered from JS. References are only valid for the env that created them,
+  /// so every thread (i.e. worker) has its own set.
+  static JS_SIGNERS: RefCell<HashMap<String, Ref<()>>> = RefCell::new(HashMap::new());
+
+  /// The env the signers were registered with, to call them with.
+  static JS_ENV: Cell<Option<Env>> = const { Cell::new(None) };
+
+  static CLEANUP_REGISTERED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Register a JS callback under `name`, replacing any callback previously registered with it.
+pub fn register(mut env: Env, name: String, callback: JsFunction) -> Result<(), CmsError> {
+  if !CLEANUP_REGISTERED.get() {
+    // References must be released before the env goes away, not when the thread exits.
+    env
+      .add_env_cleanup_hook(env, |env| {
+        for (_, mut reference) in JS_SIGNERS.take() {
+          let _ = reference.unref(env);
+        }
+
+        JS_ENV.set(None);
+      })
+      .map_err(|error| CmsError::SignerRegistrationError.caused_by(error))?;
+
+    CLEANUP_REGISTERED.set(true);
+  }
+
+  JS_ENV.set(Some(env));
+
+  let reference = env
+    .create_reference(callback)
+    .map_err(|error| CmsError::SignerRegistrationError.caused_by(error))?;
+
+  if let Some(mut previous) =
+    JS_SIGNERS.with(|signers| signers.borrow_mut().insert(name, reference))
+  {
+    previous
+      .unref(env)
+      .map_err(|error| CmsError::SignerRegistrationError.caused_by(error))?;
+  }
+
+  Ok(())
+}
+
+/// Remove the JS callback registered under `name`, returning whether one was registered.
+pub fn unregister(env: Env, name: &str) -> Result<bool, CmsError> {
+  match JS_SIGNERS.with(|signers| signers.borrow_mut().remove(name)) {
+    Some(mut reference) => {
+      reference
+        .unref(env)
+        .map_err(|error| CmsError::SignerRegistrationError.caused_by(error))?;
+
+      Ok(true)
+    }
+    None => Ok(false),
+  }
+}
+
+/// The backend of a JS callback registered on this thread, which signs with a [JsSigner].
+pub struct JsBackend {
+  name: String,
+}
+
+impl JsBackend {
+  /// The backend of the JS callback registered under `name`, `None` if there is none.
+  pub fn find(name: &str) -> Option<Self> {
+    JS_SIGNERS
+      .with(|signers| signers.borrow().contains_key(name))
+      .then(|| Self {
+        name: name.to_string(),
+      })
+  }
+}
+
+impl SignerBackend for JsBackend {
+  fn create(&self, input: SignerInput) -> Result<SignerKey, CmsError> {
+    let cert = input.signing_certificate()?;
+
+    let key_algorithm = cert
+      .key_algorithm()
+      .ok_or(CmsError::UnsupportedKeyAlgorithm)?;
+
+    let signature_algorithm = SignatureAlgorithm::from_oid_and_digest_algorithm(
+      cert.key_algorithm_oid(),
+      DigestAlgorithm::Sha256,
+    )
+    .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+    let signing_key = JsSigner {
+      name: self.name.clone(),
+      thread: thread::current().id(),
+      key_algorithm,
+      signature_algorithm,
+      public_key: cert.public_key_data(),
+    };
+
+    Ok(SignerKey {
+      signing_key: Box::new(signing_key),
+      certs: input.certs,
+    })
+  }
+}
+
+/// Signer calling back into JS to sign the DER encoded signed attributes.
+///
+/// The callback receives the message as a Buffer and must synchronously return the signature as a
+/// Buffer. The key and signature algorithm are derived from the signing certificate.
+///
+/// The callback is looked up when signing, on the thread that created the signer, so the signer
+/// holds no JS values and signing on another thread fails rather than calling into the wrong env.
+pub struct JsSigner {
+  name: String,
+  thread: ThreadId,
+  key_algorithm: KeyAlgorithm,
+  signature_algorithm: SignatureAlgorithm,
+  public_key: Bytes,
+}
+
+impl KeyInfoSigner for JsSigner {}
+
+impl Sign for JsSigner {
+  fn sign(&self, message: &[u8]) -> Result<(Vec<u8>, SignatureAlgorithm), X509CertificateError> {
+    let signature = self.try_sign(message)?;
+    let algorithm = self.signature_algorithm()?;
+
+    Ok((signature.into(), algorithm))
+  }
+
+  fn key_algorithm(&self) -> Option<KeyAlgorithm> {
+    Some(self.key_algorithm)
+  }
+
+  fn signature_algorithm(&self) -> Result<SignatureAlgorithm, X509CertificateError> {
+    Ok(self.signature_algorithm)
+  }
+
+  fn private_key_data(&self) -> Option<Zeroizing<Vec<u8>>> {
+    None
+  }
+
+  fn public_key_data(&self) -> Bytes {
+    self.public_key.clone()
+  }
+
+  fn rsa_primes(
+    &self,
+  ) -> Result<Option<(Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>)>, X509CertificateError> {
+    Ok(None)
+  }
+}
+
+impl Signer<Signature> for JsSigner {
+  fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+    if thread::current().id() != self.thread {
+      return Err(signature::Error::from_source(
+        "a registered signer only signs on the thread it was registered on",
+      ));
+    }
+
+    let env = JS_ENV
+      .get()
+      .ok_or_else(|| signature::Error::from_source("the signer is no longer registered"))?;
+
+    let callback = JS_SIGNERS
+      .with(|signers| {
+        signers
+          .borrow()
+          .get(&self.name)
+          .map(|reference| env.get_reference_value::<JsFunction>(reference))
+      })
+      .ok_or_else(|| signature::Error::from_source("the signer is no longer registered"))?
+      .map_err(signature::Error::from_source)?;
+
+    let message = env
+      .create_buffer_with_data(msg.to_vec())
+      .map_err(signature::Error::from_source)?;
+
+    tracing::debug!("Calling registered signer");
+
+    let signature = callback
+      .call(None, &[message.into_raw()])
+      .and_then(|result| result.try_into())
+      .and_then(|buffer: JsBuffer| buffer.into_value())
+      .map_err(signature::Error::from_source)?;
+
+    Ok(Signature::from(signature.to_vec()))
+  }
+}
+
+/// Call a JS callback with a batch of messages to sign, such as the DER encoded signed
+/// attributes of several documents, and return the signatures it returns for them in order.
+///
+/// The callback receives an array of Buffers and must synchronously return an array of as many
+/// Buffers.
+pub fn sign_batch(
+  env: Env,
+  callback: &JsFunction,
+  messages: &[Vec<u8>],
+) -> Result<Vec<Vec<u8>>, CmsError> {
+  let call = || -> napi::Result<Vec<Vec<u8>>> {
+    let mut array = env.create_array_with_length(messages.len())?;
+
+    for (index, message) in messages.iter().enumerate() {
+      array.set_element(
+        index as u32,
+        env.create_buffer_with_data(message.clone())?.into_raw(),
+      )?;
+    }
+
+    let result: JsObject = callback.call(None, &[array])?.try_into()?;
+
+    (0..result.get_array_length()?)
+      .map(|index| {
+        let buffer: JsBuffer = result.get_element(index)?;
+        Ok(buffer.into_value()?.to_vec())
+      })
+      .collect()
+  };
+
+  tracing::debug!(messages = messages.len(), "Calling batch signer");
+
+  let signatures = call().map_err(|error| CmsError::SigningError.caused_by(error))?;
+
+  if signatures.len() != messages.len() {
+    return Err(CmsError::SigningError.caused_by(format!(
+      "expected {} signatures, got {}",
+      messages.len(),
+      signatures.len()
+    )));
+  }
+
+  Ok(signatures)
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::errors::CmsError;
+use crate::signers::ReusableSigner;
+
+/// The handle of the next key loaded. Handles aren't reused, so a stale handle can't sign with
+/// another key.
+static NEXT_HANDLE: AtomicU32 = AtomicU32::new(1);
+
+fn keys() -> &'static RwLock<HashMap<u32, Arc<ReusableSigner>>> {
+  static KEYS: OnceLock<RwLock<HashMap<u32, Arc<ReusableSigner>>>> = OnceLock::new();
+
+  KEYS.get_or_init(RwLock::default)
+}
+
+/// Keep a loaded key for later signatures, returning its handle.
+///
+/// Keys are shared by the whole process, so a handle can be passed to other worker threads.
+pub fn insert(signer: ReusableSigner) -> u32 {
+  let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+
+  keys()
+    .write()
+    .unwrap_or_else(|err| err.into_inner())
+    .insert(handle, Arc::new(signer));
+
+  handle
+}
+
+/// Drop the key of a handle, returning whether it was loaded.
+pub fn remove(handle: u32) -> bool {
+  keys()
+    .write()
+    .unwrap_or_else(|err| err.into_inner())
+    .remove(&handle)
+    .is_some()
+}
+
+pub fn find(handle: u32) -> Result<Arc<ReusableSigner>, CmsError> {
+  keys()
+    .read()
+    .unwrap_or_else(|err| err.into_inner())
+    .get(&handle)
+    .cloned()
+    .ok_or_else(|| CmsError::UnknownKeyHandle.with_detail("handle", handle))
+}
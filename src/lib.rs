@@ -1,196 +1,1689 @@
-mod errors;
-mod gcloud_signer;
+mod containers;
+mod defaults;
+mod js_signer;
+mod key_handles;
+mod logging;
+mod results;
+pub mod signer;
+mod signer_pool;
+mod signers;
+mod timestamps;
+
+pub use containers::*;
+pub use pdf_sign_core::errors;
+pub use results::*;
+pub use signers::*;
+pub use timestamps::*;
+
+use std::collections::HashMap;
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::JsUnknown;
 use napi_derive::napi;
 
-use chrono;
-use cryptographic_message_syntax::{asn1::rfc5652, Bytes, Oid, SignedDataBuilder, SignerBuilder};
+use cryptographic_message_syntax::{
+  asn1::{rfc3161, rfc5652},
+  Oid,
+};
+use defaults::Defaults;
 use errors::CmsError;
-use gcloud_signer::GCloudSigner;
-use p12::PFX;
-use pem::{encode, Pem};
-use x509_certificate::{CapturedX509Certificate, InMemorySigningKeyPair}; // Add the log crate for better logging
+use pdf_sign_core::certificates;
+use pdf_sign_core::ejbca::{self, EjbcaClient, EjbcaEnrollment};
+use pdf_sign_core::input::{self, InputFormat};
+use pdf_sign_core::keys;
+use pdf_sign_core::parse_signing_time;
+use pdf_sign_core::signing::{self, SignatureScheme};
+use pdf_sign_core::{
+  appearance, attributes, biometric, certification, cms, compliance, http, revocation, self_test,
+  smime, validation,
+};
+use signer::{SignerInput, SignerKey, SignerOptions};
+use x509_certificate::rfc5652::AttributeValue;
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm};
+
+/// Errors are thrown to JavaScript by the exported functions, see [CmsError::into_js_error].
+type Result<T, E = CmsError> = std::result::Result<T, E>;
+
+/// Where the signer claims to be, for the signerLocation attribute.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SignerLocation {
+  pub country_name: Option<String>,
+  pub locality_name: Option<String>,
+  pub postal_address: Option<Vec<String>>,
+}
+
+/// A description of the signed content, for the contentHints attribute.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct ContentHints {
+  pub description: Option<String>,
+  pub content_type: Option<String>,
+}
+
+/// A capability for the smimeCapabilities attribute, as a dotted OID and optional DER encoded
+/// parameters.
+#[napi(object)]
+#[derive(Clone)]
+pub struct SmimeCapability {
+  pub oid: String,
+  pub parameters: Option<Buffer>,
+}
+
+/// An attribute given as a dotted OID and a DER encoded value.
+#[napi(object)]
+#[derive(Clone)]
+pub struct CustomAttribute {
+  pub oid: String,
+  pub value: Buffer,
+}
+
+/// DER encoded CRLs and OCSP responses, for the revocationValues and revocationRefs attributes.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct RevocationValues {
+  pub crls: Option<Vec<Buffer>>,
+  pub ocsp_responses: Option<Vec<Buffer>>,
+}
+
+/// An operation that waits on the network, such as a timestamp server, run on the libuv thread
+/// pool for the async variants of the functions that aren't signing functions.
+pub struct BlockingTask<T> {
+  run: Option<Box<dyn FnOnce() -> Result<T> + Send>>,
+}
+
+impl<T> BlockingTask<T> {
+  fn new(run: impl FnOnce() -> Result<T> + Send + 'static) -> Self {
+    Self {
+      run: Some(Box::new(run)),
+    }
+  }
+}
+
+impl<T: ToNapiValue + TypeName + Send + 'static> Task for BlockingTask<T> {
+  type Output = Result<T>;
+  type JsValue = T;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let run = self
+      .run
+      .take()
+      .ok_or_else(|| napi::Error::from_reason("The task has already run"))?;
+
+    Ok(run())
+  }
+
+  fn resolve(&mut self, env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    js_result(env, output)
+  }
+}
+
+/// Set up logging when the addon is loaded, so that setting `PDF_SIGN_LOG` to a level logs events
+/// to stderr without any code.
+#[napi::module_init]
+fn init() {
+  logging::init();
+}
+
+#[napi(object)]
+pub struct LogEvent {
+  pub level: String,
+  pub target: String,
+  pub message: String,
+  pub fields: HashMap<String, String>,
+}
+
+type LogCallback = ThreadsafeFunction<LogEvent, ErrorStrategy::Fatal>;
+
+/// Pass log events to a callback instead of logging them to stderr.
+///
+/// Events up to the level, `info` by default, are passed to the callback. Without a callback,
+/// events are logged to stderr again, up to the level or the one set by `PDF_SIGN_LOG`, as they
+/// are when the worker thread that set the callback exits.
+#[napi]
+pub fn set_logger(
+  mut env: Env,
+  callback: Option<LogCallback>,
+  level: Option<String>,
+) -> napi::Result<()> {
+  let level = js_result(env, level.as_deref().map(logging::parse_level).transpose())?;
+
+  let Some(mut callback) = callback else {
+    logging::set_logger(None, level.unwrap_or_else(logging::env_level));
+    return Ok(());
+  };
+
+  // Logging should not keep the process alive.
+  callback.unref(&env)?;
+
+  let generation = logging::set_logger(
+    Some(Box::new(move |record| {
+      let event = LogEvent {
+        level: record.level.as_str().to_lowercase(),
+        target: record.target,
+        message: record.message,
+        fields: record.fields.into_iter().collect(),
+      };
+
+      callback.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+    })),
+    level.unwrap_or(logging::LevelFilter::INFO),
+  );
+
+  // The logger is shared by all threads, but the callback belongs to this one's env.
+  env.add_env_cleanup_hook(generation, logging::remove_logger)?;
+
+  Ok(())
+}
 
 #[napi(object)]
 #[derive(Default)]
-pub struct SignWithPrivateKeyOptions {
-  pub content: Buffer,
-  pub cert: Buffer,
-  pub private_key: Buffer,
-  pub signing_time: Option<String>,
+pub struct ConfigureDefaultsOptions {
   pub timestamp_server: Option<String>,
+  pub digest_algorithm: Option<String>,
+  pub trust_store: Option<Buffer>,
+  pub proxy: Option<String>,
 }
 
-/// Sign data with the private key.
-#[napi]
-pub fn sign_with_private_key(options: SignWithPrivateKeyOptions) -> Result<Buffer> {
-  let SignWithPrivateKeyOptions {
-    content,
-    cert,
-    private_key,
-    signing_time,
+/// Set defaults for options left out of later calls, replacing any defaults set before.
+///
+/// The digest algorithm is checked against the key by the signing functions, like one given
+/// for the call. The trust store is a PEM bundle of the certificates that dry runs expect the
+/// signing certificate to chain up to. The proxy is used for requests to timestamp servers and
+/// EJBCA.
+#[napi(js_name = "configureDefaults")]
+pub fn configure_defaults_js(env: Env, options: ConfigureDefaultsOptions) -> napi::Result<()> {
+  js_result(env, configure_defaults(options))
+}
+
+fn configure_defaults(options: ConfigureDefaultsOptions) -> Result<()> {
+  let ConfigureDefaultsOptions {
     timestamp_server,
+    digest_algorithm,
+    trust_store,
+    proxy,
   } = options;
 
-  let x509_certs = CapturedX509Certificate::from_pem_multiple(cert.to_vec())
-    .map_err(|_| CmsError::CertificateParseError)?;
+  validation::signing_options(
+    None,
+    None,
+    None,
+    digest_algorithm.as_deref(),
+    timestamp_server.as_deref(),
+  )?;
+
+  let trust_store = trust_store
+    .map(|trust_store| {
+      validation::encoded(
+        "trustStore",
+        &trust_store,
+        "certificates",
+        InputFormat::Auto,
+      )?;
+
+      input::parsed_certificates("trustStore", &trust_store, InputFormat::Auto)
+    })
+    .transpose()?
+    .unwrap_or_default();
+
+  http::set_proxy(proxy.as_deref())?;
 
-  let private_key_cert = InMemorySigningKeyPair::from_pkcs8_pem(&private_key)
-    .map_err(|_| CmsError::PrivateKeyParseError)?;
+  defaults::set(Defaults {
+    timestamp_server,
+    digest_algorithm,
+    trust_store,
+  });
+
+  Ok(())
+}
+
+/// The version of the addon and what it supports.
+#[napi(object)]
+pub struct Capabilities {
+  pub version: String,
+  pub target: String,
+  pub key_algorithms: Vec<String>,
+  pub digest_algorithms: Vec<String>,
+  pub backends: Vec<String>,
+  pub pades_levels: Vec<String>,
+}
 
-  let mut signer = SignerBuilder::new(&private_key_cert, x509_certs.first().unwrap().clone());
+/// Report the version of the addon and what it supports, so applications can detect features at
+/// runtime rather than by the version of the package, whose prebuilt binaries differ by platform.
+#[napi]
+pub fn capabilities() -> Capabilities {
+  let strings = |values: &[&str]| values.iter().map(|value| value.to_string()).collect();
 
-  if let Some(timestamp_server) = timestamp_server {
-    signer = signer
-      .time_stamp_url(timestamp_server)
-      .map_err(|_| CmsError::TimestampServerParseError)?;
+  Capabilities {
+    version: env!("PDF_SIGN_VERSION").to_string(),
+    target: env!("PDF_SIGN_TARGET").to_string(),
+    key_algorithms: strings(&["rsa", "ecdsa-p256", "ecdsa-p384", "ed25519"]),
+    digest_algorithms: strings(&pdf_sign_core::DIGEST_ALGORITHMS),
+    // The backends of the signing functions, which `signWithSigner` signs with by name too.
+    backends: signer::backend_names(),
+    // B-LT with embedded revocation info, B-LTA with a document timestamp added to the PDF.
+    pades_levels: strings(&["B-B", "B-T", "B-LT", "B-LTA"]),
   }
+}
 
-  create_signed_data(CreateSignedDataOptions {
-    content,
-    signer,
-    signing_time,
-    certs: Some(x509_certs),
-  })
+#[napi(object)]
+#[derive(Default)]
+pub struct AddUnsignedAttributesOptions {
+  pub signed_data: Buffer,
+  pub unsigned_attributes: Vec<CustomAttribute>,
+  pub signer_index: Option<u32>,
+}
+
+/// Add unsigned attributes to an existing signature without re-signing.
+///
+/// The attributes are added to the first signer info unless `signerIndex` is given. Everything
+/// else in the signed data is kept as it was.
+#[napi(js_name = "addUnsignedAttributes")]
+pub fn add_unsigned_attributes_js(
+  env: Env,
+  options: AddUnsignedAttributesOptions,
+) -> napi::Result<Buffer> {
+  js_result(env, add_unsigned_attributes(options))
+}
+
+fn add_unsigned_attributes(options: AddUnsignedAttributesOptions) -> Result<Buffer> {
+  let AddUnsignedAttributesOptions {
+    signed_data,
+    unsigned_attributes,
+    signer_index,
+  } = options;
+
+  add_custom_unsigned_attributes(
+    &signed_data,
+    Some(signer_index.unwrap_or(0) as usize),
+    &[],
+    &unsigned_attributes,
+  )
 }
 
 #[napi(object)]
 #[derive(Default)]
-pub struct SignWithP12Options {
-  pub content: Buffer,
+pub struct CounterSignOptions {
+  pub signed_data: Buffer,
+  pub signer_index: Option<u32>,
   pub cert: Buffer,
-  pub password: Option<String>,
+  pub signer: String,
+  pub signer_options: Option<HashMap<String, String>>,
   pub signing_time: Option<String>,
   pub timestamp_server: Option<String>,
 }
 
-/// Sign data with a P12 container.
+/// Counter-sign a signer info of an existing signature with a registered signer.
+///
+/// The counter-signature signs the signature value of the signer info at `signerIndex`, the
+/// first by default, and is added to it as a countersignature unsigned attribute. The
+/// certificates of the counter-signer are added to the signed data.
+#[napi(js_name = "counterSign")]
+pub fn counter_sign_js(env: Env, options: CounterSignOptions) -> napi::Result<Buffer> {
+  js_result(env, counter_sign(options))
+}
+
+/// Counter-sign a signer info of an existing signature without blocking the event loop.
+///
+/// Signers are resolved as for `signWithSignerAsync`.
 #[napi]
-pub fn sign_with_p12(options: SignWithP12Options) -> Result<Buffer> {
-  let SignWithP12Options {
-    content,
+pub fn counter_sign_async(
+  options: CounterSignOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<Buffer>> {
+  AsyncTask::with_optional_signal(BlockingTask::new(move || counter_sign(options)), signal)
+}
+
+fn counter_sign(options: CounterSignOptions) -> Result<Buffer> {
+  let CounterSignOptions {
+    signed_data,
+    signer_index,
     cert,
-    password,
+    signer: signer_name,
+    signer_options,
     signing_time,
     timestamp_server,
   } = options;
 
-  let pfx = PFX::parse(&cert).map_err(|_| CmsError::P12ParseError)?;
+  let timestamp_server = defaults::timestamp_server(timestamp_server);
+
+  if let Some(timestamp_server) = &timestamp_server {
+    validation::timestamp_server("timestampServer", timestamp_server)?;
+  }
+
+  validation::encoded("cert", &cert, "a certificate", InputFormat::Auto)?;
+
+  let mut signed_data = cms::SignedData::decode(&signed_data)?;
+
+  let SignerKey {
+    signing_key,
+    certs: x509_certs,
+  } = signer::create_signer(
+    &signer_name,
+    SignerInput {
+      options: signer_options.unwrap_or_default(),
+      certs: input::certificates("cert", &cert, InputFormat::Auto)?,
+      ..Default::default()
+    },
+  )?;
 
-  let password = password.unwrap_or(String::from(""));
+  let x509_cert = x509_certs
+    .first()
+    .ok_or(CmsError::NoCertificate)?
+    .parsed()?;
 
-  let bags = pfx
-    .key_bags(&password)
-    .map_err(|_| CmsError::PrivateKeyBagError)?;
+  let signer = SigningOptions {
+    timestamp_server,
+    ..Default::default()
+  }
+  .signer(signing_key.as_ref(), x509_cert)?;
+
+  let signer_info = signed_data.signer_info_mut(signer_index.unwrap_or(0) as usize)?;
+
+  let counter_signature = signer.build_counter_signature(
+    &signer_info.signature.to_bytes(),
+    &parse_signing_time(signing_time).into(),
+  )?;
 
-  let private_key_bag = bags.first().ok_or(CmsError::NoPrivateKey)?;
+  signer_info.add_unsigned_attribute_value(
+    attributes::oid(rfc5652::OID_COUNTER_SIGNATURE),
+    attributes::attribute_value(counter_signature.encode_ref()),
+  )?;
 
-  let bags = pfx
-    .cert_x509_bags(&password)
-    .map_err(|_| errors::CmsError::CertBagError)?;
+  signed_data.add_certificates(&x509_certs)?;
 
-  // Ensure that there is at least one certificate
-  bags.first().ok_or(errors::CmsError::NoCertificate)?;
+  Ok(Buffer::from(signed_data.encode()))
+}
+
+#[napi(object)]
+pub struct FetchRevocationInfoOptions {
+  pub cert: Buffer,
+}
+
+/// Fetch the OCSP responses and CRLs of a certificate chain, the signing certificate first and
+/// its issuers after it or in the trust store, e.g. for the DSS dictionary of a PDF or the
+/// `revocationValues` option.
+#[napi(js_name = "fetchRevocationInfo")]
+pub fn fetch_revocation_info_js(
+  env: Env,
+  options: FetchRevocationInfoOptions,
+) -> napi::Result<RevocationValues> {
+  js_result(env, fetch_revocation_info(options))
+}
+
+/// Fetch the revocation data of a certificate chain without blocking the event loop.
+#[napi(js_name = "fetchRevocationInfoAsync")]
+pub fn fetch_revocation_info_async(
+  options: FetchRevocationInfoOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<RevocationValues>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || fetch_revocation_info(options)),
+    signal,
+  )
+}
 
-  // Convert the x509 bags to CapturedX509Certificate's
-  let x509_certs = bags
+fn fetch_revocation_info(options: FetchRevocationInfoOptions) -> Result<RevocationValues> {
+  let FetchRevocationInfoOptions { cert } = options;
+
+  validation::encoded("cert", &cert, "a certificate", InputFormat::Auto)?;
+
+  let mut certs = input::parsed_certificates("cert", &cert, InputFormat::Auto)?;
+  let cert = match certs.is_empty() {
+    true => return Err(CmsError::NoCertificate),
+    false => certs.remove(0),
+  };
+  certs.extend(defaults::trust_store());
+
+  let revocation::RevocationInfo {
+    crls,
+    ocsp_responses,
+  } = revocation::fetch(&cert, &certs)?;
+
+  Ok(RevocationValues {
+    crls: Some(crls.into_iter().map(Buffer::from).collect()),
+    ocsp_responses: Some(ocsp_responses.into_iter().map(Buffer::from).collect()),
+  })
+}
+
+#[napi(object)]
+pub struct ParsedCms {
+  pub version: u32,
+  pub digest_algorithms: Vec<String>,
+  pub content_type: String,
+  pub content: Option<Buffer>,
+  pub certificates: Vec<ParsedCertificate>,
+  pub signer_infos: Vec<ParsedSignerInfo>,
+}
+
+#[napi(object)]
+pub struct ParsedCertificate {
+  pub subject: String,
+  pub issuer: String,
+  pub serial_number: String,
+  pub not_before: String,
+  pub not_after: String,
+  pub der: Buffer,
+}
+
+#[napi(object)]
+pub struct ParsedSignerInfo {
+  pub version: u32,
+  pub issuer: Option<String>,
+  pub serial_number: Option<String>,
+  pub subject_key_identifier: Option<Buffer>,
+  pub digest_algorithm: String,
+  pub signature_algorithm: String,
+  pub signature: Buffer,
+  pub message_digest: Option<Buffer>,
+  pub signed_attributes: Vec<ParsedAttribute>,
+  pub signed_attributes_der: Option<Buffer>,
+  pub unsigned_attributes: Vec<ParsedAttribute>,
+  pub timestamps: Vec<ParsedTimestamp>,
+}
+
+#[napi(object)]
+pub struct ParsedAttribute {
+  pub oid: String,
+  pub name: Option<String>,
+  pub values: Vec<Buffer>,
+}
+
+#[napi(object)]
+pub struct ParsedTimestamp {
+  pub time: String,
+  pub policy: String,
+  pub serial_number: String,
+  pub hash_algorithm: String,
+  pub hashed_message: Buffer,
+}
+
+/// Decode a CMS signature and return its structure, for debugging and audit logging.
+///
+/// OIDs are returned in dotted form and serial numbers in hex. Attribute values are returned
+/// DER encoded, and timestamp tokens are also decoded to their TSTInfo.
+#[napi(js_name = "parseCms")]
+pub fn parse_cms_js(env: Env, signed_data: Buffer) -> napi::Result<ParsedCms> {
+  js_result(env, parse_cms(signed_data))
+}
+
+fn parse_cms(signed_data: Buffer) -> Result<ParsedCms> {
+  let signed_data = cms::SignedData::decode(&signed_data)?;
+
+  let (content_type, content) = signed_data.encapsulated_content()?;
+
+  let certificates = signed_data
+    .x509_certificates()?
     .iter()
-    .map(|bag| {
-      CapturedX509Certificate::from_pem(encode(&Pem::new("CERTIFICATE", bag.to_vec()))).unwrap()
-    })
-    .collect::<Vec<_>>();
+    .map(parsed_certificate)
+    .collect::<Result<Vec<_>>>()?;
 
-  let cert = x509_certs
+  let signer_infos = signed_data
+    .signer_infos
     .iter()
-    .map(|cert| cert.encode_pem())
-    .collect::<Vec<_>>()
-    .join("\n");
+    .map(parse_signer_info)
+    .collect::<Result<Vec<_>>>()?;
+
+  Ok(ParsedCms {
+    version: signed_data.version()?.into(),
+    digest_algorithms: signed_data
+      .digest_algorithm_oids()?
+      .iter()
+      .map(Oid::to_string)
+      .collect(),
+    content_type: content_type.to_string(),
+    content: content.map(|content| Buffer::from(content.to_vec())),
+    certificates,
+    signer_infos,
+  })
+}
+
+/// Helper function to parse a signer info for `parseCms`.
+fn parse_signer_info(signer_info: &cms::SignerInfo) -> Result<ParsedSignerInfo> {
+  let (issuer, serial_number, subject_key_identifier) = match signer_info.sid()? {
+    rfc5652::SignerIdentifier::IssuerAndSerialNumber(issuer_and_serial_number) => (
+      Some(parse_name(&issuer_and_serial_number.issuer)?),
+      Some(hex::encode(
+        issuer_and_serial_number.serial_number.as_slice(),
+      )),
+      None,
+    ),
+    rfc5652::SignerIdentifier::SubjectKeyIdentifier(key_identifier) => (
+      None,
+      None,
+      Some(Buffer::from(key_identifier.to_bytes().to_vec())),
+    ),
+  };
+
+  let unsigned_attributes = signer_info.unsigned_attribute_values()?;
+
+  let timestamps = unsigned_attributes
+    .iter()
+    .filter(|(oid, _)| *oid == rfc3161::OID_TIME_STAMP_TOKEN)
+    .flat_map(|(_, values)| values)
+    .map(|value| parse_timestamp(value.as_slice()))
+    .collect::<Result<Vec<_>>>()?;
+
+  Ok(ParsedSignerInfo {
+    version: signer_info.version()?.into(),
+    issuer,
+    serial_number,
+    subject_key_identifier,
+    digest_algorithm: signer_info.digest_algorithm_oid()?.to_string(),
+    signature_algorithm: signer_info.signature_algorithm_oid()?.to_string(),
+    signature: Buffer::from(signer_info.signature.to_bytes().to_vec()),
+    message_digest: signer_info
+      .message_digest()?
+      .map(|message_digest| Buffer::from(message_digest.to_vec())),
+    signed_attributes: parse_attributes(signer_info.signed_attribute_values()?),
+    signed_attributes_der: signer_info.signed_attributes_der().map(Buffer::from),
+    unsigned_attributes: parse_attributes(unsigned_attributes),
+    timestamps,
+  })
+}
+
+/// Helper function to convert attributes for `parseCms`.
+fn parse_attributes(attributes: Vec<(Oid, Vec<bcder::Captured>)>) -> Vec<ParsedAttribute> {
+  attributes
+    .into_iter()
+    .map(|(oid, values)| ParsedAttribute {
+      oid: oid.to_string(),
+      name: attributes::attribute_name(&oid).map(String::from),
+      values: values
+        .into_iter()
+        .map(|value| Buffer::from(value.as_slice()))
+        .collect(),
+    })
+    .collect()
+}
+
+/// Helper function to decode the TSTInfo of a timestamp token for `parseCms`.
+fn parse_timestamp(token: &[u8]) -> Result<ParsedTimestamp> {
+  let tst_info = tst_info(token)?;
+
+  Ok(ParsedTimestamp {
+    time: chrono::DateTime::<chrono::Utc>::from(tst_info.gen_time).to_rfc3339(),
+    policy: tst_info.policy.to_string(),
+    serial_number: hex::encode(tst_info.serial_number.as_slice()),
+    hash_algorithm: tst_info
+      .message_imprint
+      .hash_algorithm
+      .algorithm
+      .to_string(),
+    hashed_message: Buffer::from(tst_info.message_imprint.hashed_message.to_bytes().to_vec()),
+  })
+}
+
+/// Helper function to verify the signatures of a timestamp token and the digests of its TSTInfo.
+fn time_stamp_token_valid(token: &[u8]) -> bool {
+  cryptographic_message_syntax::SignedData::parse_ber(token).is_ok_and(|token| {
+    token.signers().next().is_some()
+      && token.signers().all(|signer| {
+        signer.verify_signature_with_signed_data(&token).is_ok()
+          && signer
+            .verify_message_digest_with_signed_data(&token)
+            .is_ok()
+      })
+  })
+}
+
+/// Helper function to check the certificate chain of the timestamp authority of a timestamp
+/// token at `time`, with the certificates of the token followed by `certs`.
+fn time_stamp_chain_problems(
+  token: &[u8],
+  certs: &[CapturedX509Certificate],
+  time: chrono::DateTime<chrono::Utc>,
+  trusted_roots: &[CapturedX509Certificate],
+) -> Result<Vec<String>> {
+  let token = cms::SignedData::decode(token)?;
+  let tsa_certs = [token.x509_certificates()?, certs.to_vec()].concat();
 
-  let private_key = encode(&Pem::new("PRIVATE KEY", private_key_bag.to_vec()));
+  let Some(cert) = token
+    .signer_infos
+    .first()
+    .map(|signer_info| signer_info.find_certificate(&tsa_certs))
+    .transpose()?
+    .flatten()
+  else {
+    return Ok(vec![String::from(
+      "The signature timestamp has no certificate for the timestamp authority",
+    )]);
+  };
 
-  sign_with_private_key(SignWithPrivateKeyOptions {
+  chain_problems(&tsa_certs, cert, time, trusted_roots)
+}
+
+/// Helper function to decode the TSTInfo a timestamp token encapsulates.
+fn tst_info(token: &[u8]) -> Result<rfc3161::TstInfo> {
+  let token = cms::SignedData::decode(token)?;
+
+  match token.encapsulated_content()? {
+    (content_type, Some(content)) if content_type == rfc3161::OID_CONTENT_TYPE_TST_INFO => {
+      bcder::Mode::Ber
+        .decode(content, rfc3161::TstInfo::take_from)
+        .map_err(|error| CmsError::SignedDataParseError.caused_by(error))
+    }
+    _ => Err(CmsError::SignedDataParseError),
+  }
+}
+
+/// Helper function to describe a certificate for `parseCms` and `inspectP12`.
+fn parsed_certificate(cert: &CapturedX509Certificate) -> Result<ParsedCertificate> {
+  Ok(ParsedCertificate {
+    subject: parse_name(cert.subject_name())?,
+    issuer: parse_name(cert.issuer_name())?,
+    serial_number: hex::encode(cert.serial_number_asn1().as_slice()),
+    not_before: cert.validity_not_before().to_rfc3339(),
+    not_after: cert.validity_not_after().to_rfc3339(),
+    der: Buffer::from(cert.constructed_data()),
+  })
+}
+
+/// Helper function to format a distinguished name for `parseCms`.
+fn parse_name(name: &x509_certificate::rfc3280::Name) -> Result<String> {
+  name
+    .user_friendly_str()
+    .map_err(|error| CmsError::SignedDataParseError.caused_by(error))
+}
+
+#[napi(object)]
+pub struct ComplianceReport {
+  pub profile: String,
+  pub compliant: bool,
+  pub missing: Vec<String>,
+}
+
+/// Check that a signer info of a CMS signature has the elements a profile requires, such as the
+/// signature timestamp of PAdES-B-T, so pipelines can gate on compliance.
+#[napi(js_name = "checkCompliance")]
+pub fn check_compliance_js(
+  env: Env,
+  signed_data: Buffer,
+  profile: String,
+  signer_index: Option<u32>,
+) -> napi::Result<ComplianceReport> {
+  js_result(env, check_compliance(signed_data, profile, signer_index))
+}
+
+fn check_compliance(
+  signed_data: Buffer,
+  profile: String,
+  signer_index: Option<u32>,
+) -> Result<ComplianceReport> {
+  let parsed_profile = compliance::Profile::parse("profile", &profile)?;
+  let signed_data = cms::SignedData::decode(&signed_data)?;
+
+  let missing = compliance::check(
+    &signed_data,
+    signer_index.unwrap_or(0) as usize,
+    parsed_profile,
+  )?;
+
+  Ok(ComplianceReport {
+    profile,
+    compliant: missing.is_empty(),
+    missing,
+  })
+}
+
+#[napi(object)]
+pub struct VerifySignedDataOptions {
+  pub signature: Buffer,
+  pub content: Option<Buffer>,
+  pub trusted_roots: Option<Buffer>,
+}
+
+#[napi(object)]
+pub struct SignedDataVerification {
+  pub valid: bool,
+  pub signers: Vec<VerifiedSigner>,
+}
+
+#[napi(object)]
+pub struct VerifiedSigner {
+  pub subject: String,
+  pub serial_number: String,
+  pub digest_valid: bool,
+  pub signature_valid: bool,
+  pub signing_time: Option<String>,
+  pub timestamp_time: Option<String>,
+  pub timestamp_valid: Option<bool>,
+  pub chain_valid: bool,
+  pub problems: Vec<String>,
+}
+
+/// The signing time may be this many seconds after the time of the signature timestamp, for
+/// signers whose clock is ahead of the timestamp authority's.
+const SIGNING_TIME_SKEW_SECONDS: i64 = 300;
+
+/// Verify a CMS signature: the message digest of each signer info against the content, its
+/// signature against its certificate, its signature timestamp and the certificate chain, so
+/// callers can check signatures without shelling out to OpenSSL.
+#[napi(js_name = "verifySignedData")]
+pub fn verify_signed_data_js(
+  env: Env,
+  options: VerifySignedDataOptions,
+) -> napi::Result<SignedDataVerification> {
+  js_result(env, verify_signed_data(options))
+}
+
+fn verify_signed_data(options: VerifySignedDataOptions) -> Result<SignedDataVerification> {
+  let VerifySignedDataOptions {
+    signature,
     content,
-    cert: Buffer::from(cert.as_bytes()),
-    private_key: Buffer::from(private_key.as_bytes()),
+    trusted_roots,
+  } = options;
+
+  let trusted_roots = match trusted_roots {
+    Some(trusted_roots) => {
+      validation::encoded(
+        "trustedRoots",
+        &trusted_roots,
+        "certificates",
+        InputFormat::Auto,
+      )?;
+
+      input::parsed_certificates("trustedRoots", &trusted_roots, InputFormat::Auto)?
+    }
+    None => defaults::trust_store(),
+  };
+
+  let parsed = cms::SignedData::decode(&signature)?;
+  let certs = parsed.x509_certificates()?;
+
+  let content = match (content, parsed.encapsulated_content()?.1) {
+    (Some(content), _) => content.to_vec(),
+    (None, Some(content)) => content.to_vec(),
+    (None, None) => {
+      return Err(CmsError::InvalidOption(
+        "content",
+        String::from("expected the content of a detached signature"),
+      ))
+    }
+  };
+
+  if parsed.signer_infos.is_empty() {
+    return Err(CmsError::SignerInfoNotFound.with_detail("index", 0));
+  }
+
+  let signers = parsed
+    .signer_infos
+    .iter()
+    .enumerate()
+    .map(|(index, signer_info)| {
+      verify_signer(signer_info, &certs, &content, &trusted_roots)
+        .map_err(|error| error.with_detail("index", index))
+    })
+    .collect::<Result<Vec<_>>>()?;
+
+  Ok(SignedDataVerification {
+    valid: signers.iter().all(|signer| signer.problems.is_empty()),
+    signers,
+  })
+}
+
+/// Helper function to verify a signer info for `verifySignedData`.
+///
+/// Certificates are checked at the time of the signature timestamp if it's valid, otherwise at
+/// the claimed signing time, or now if there's neither.
+fn verify_signer(
+  signer_info: &cms::SignerInfo,
+  certs: &[CapturedX509Certificate],
+  content: &[u8],
+  trusted_roots: &[CapturedX509Certificate],
+) -> Result<VerifiedSigner> {
+  let cert = signer_certificate(certs, signer_info)?;
+  let mut problems = Vec::new();
+
+  let digest_algorithm = DigestAlgorithm::try_from(&signer_info.digest_algorithm_oid()?)
+    .map_err(|_| CmsError::UnsupportedDigestAlgorithm)?;
+
+  // Without signed attributes, the signature is over the content itself.
+  let digest_valid = signer_info
+    .message_digest()?
+    .is_none_or(|message_digest| digest_algorithm.digest_data(content) == message_digest.as_ref());
+
+  if !digest_valid {
+    problems.push(String::from("The message digest doesn't match the content"));
+  }
+
+  let signature_valid = signer_info.signature_valid(&cert, Some(content))?;
+
+  if !signature_valid {
+    problems.push(String::from(
+      "The signature isn't valid for the signing certificate",
+    ));
+  }
+
+  let signing_time = signer_signing_time(signer_info)?;
+
+  let token = signer_info
+    .unsigned_attribute_values()?
+    .into_iter()
+    .find(|(oid, _)| *oid == rfc3161::OID_TIME_STAMP_TOKEN)
+    .and_then(|(_, values)| values.into_iter().next());
+
+  let mut timestamp_time = None;
+  let mut timestamp_valid = None;
+
+  if let Some(token) = token {
+    let tst_info = tst_info(token.as_slice())?;
+    let time = chrono::DateTime::<chrono::Utc>::from(tst_info.gen_time);
+
+    // The token is signed by the timestamp authority over the hash of the signature value.
+    let imprint_matches = DigestAlgorithm::try_from(
+      &tst_info.message_imprint.hash_algorithm.algorithm,
+    )
+    .is_ok_and(|digest_algorithm| {
+      digest_algorithm.digest_data(&signer_info.signature.to_bytes())
+        == tst_info.message_imprint.hashed_message.to_bytes().as_ref()
+    });
+    // The certificate of the timestamp authority is checked like the signer's, at the time of
+    // the token, along the chain in the token and then in the signed data.
+    let signed = imprint_matches && time_stamp_token_valid(token.as_slice());
+
+    if !signed {
+      problems.push(String::from(
+        "The signature timestamp isn't valid for the signature",
+      ));
+    }
+
+    let tsa_problems = time_stamp_chain_problems(token.as_slice(), certs, time, trusted_roots)?;
+    let valid = signed && tsa_problems.is_empty();
+
+    if signing_time.is_some_and(|signing_time| {
+      signing_time - time > chrono::Duration::seconds(SIGNING_TIME_SKEW_SECONDS)
+    }) {
+      problems.push(String::from(
+        "The signing time is after the time of the signature timestamp",
+      ));
+    }
+
+    problems.extend(tsa_problems);
+    timestamp_time = Some(time);
+    timestamp_valid = Some(valid);
+  }
+
+  let time = timestamp_time
+    .filter(|_| timestamp_valid == Some(true))
+    .or(signing_time)
+    .unwrap_or_else(chrono::Utc::now);
+
+  let chain = chain_problems(certs, &cert, time, trusted_roots)?;
+  let chain_valid = chain.is_empty();
+  problems.extend(chain);
+
+  Ok(VerifiedSigner {
+    subject: parse_name(cert.subject_name())?,
+    serial_number: hex::encode(cert.serial_number_asn1().as_slice()),
+    digest_valid,
+    signature_valid,
+    signing_time: signing_time.map(|time| time.to_rfc3339()),
+    timestamp_time: timestamp_time.map(|time| time.to_rfc3339()),
+    timestamp_valid,
+    chain_valid,
+    problems,
+  })
+}
+
+/// Wrap a MIME entity and a detached CMS signature of it in an S/MIME multipart/signed message,
+/// so emails can be signed with the same key as documents.
+#[napi(js_name = "createSmimeMessage")]
+pub fn create_smime_message_js(env: Env, body: Buffer, signature: Buffer) -> napi::Result<Buffer> {
+  js_result(
+    env,
+    smime::multipart_signed(&body, &signature).map(Buffer::from),
+  )
+}
+
+#[napi(object)]
+pub struct EncryptBiometricDataOptions {
+  pub data: Buffer,
+  pub recipients: Buffer,
+}
+
+/// Encrypt biometric data from a signature pad to the certificates of its trustees, as a CMS
+/// AuthEnvelopedData to embed in the signature dictionary next to the signature.
+#[napi(js_name = "encryptBiometricData")]
+pub fn encrypt_biometric_data_js(
+  env: Env,
+  options: EncryptBiometricDataOptions,
+) -> napi::Result<Buffer> {
+  js_result(env, encrypt_biometric_data(options))
+}
+
+fn encrypt_biometric_data(options: EncryptBiometricDataOptions) -> Result<Buffer> {
+  let EncryptBiometricDataOptions { data, recipients } = options;
+
+  validation::encoded(
+    "recipients",
+    &recipients,
+    "a certificate",
+    InputFormat::Auto,
+  )?;
+
+  let recipients = input::parsed_certificates("recipients", &recipients, InputFormat::Auto)?;
+
+  Ok(Buffer::from(biometric::encrypt(&data, &recipients)?))
+}
+
+#[napi(object)]
+pub struct CreateSignatureAppearanceOptions {
+  pub width: f64,
+  pub height: f64,
+  pub page: Option<u32>,
+  pub x: Option<f64>,
+  pub y: Option<f64>,
+  pub name: Option<String>,
+  pub reason: Option<String>,
+  pub location: Option<String>,
+  pub signing_time: Option<String>,
+  pub image: Option<Buffer>,
+  pub font_size: Option<f64>,
+}
+
+#[napi(object)]
+pub struct SignatureAppearance {
+  pub page: u32,
+  pub rect: Vec<f64>,
+  pub bbox: Vec<f64>,
+  pub content: Buffer,
+  pub font_size: f64,
+  pub font: AppearanceFont,
+  pub image: Option<AppearanceImage>,
+}
+
+#[napi(object)]
+pub struct AppearanceFont {
+  pub resource_name: String,
+  pub base_font: String,
+  pub encoding: String,
+}
+
+#[napi(object)]
+pub struct AppearanceImage {
+  pub resource_name: String,
+  pub width: u32,
+  pub height: u32,
+  pub color_space: String,
+  pub bits_per_component: u32,
+  pub filter: String,
+  pub decode: Option<Vec<f64>>,
+  pub data: Buffer,
+  pub soft_mask: Option<Buffer>,
+}
+
+/// Build the appearance stream of a visible signature, a stamp with the signer's name, the
+/// reason, location and date and an optional image of a handwritten signature, with where the
+/// widget annotation goes on the page.
+#[napi(js_name = "createSignatureAppearance")]
+pub fn create_signature_appearance_js(
+  env: Env,
+  options: CreateSignatureAppearanceOptions,
+) -> napi::Result<SignatureAppearance> {
+  js_result(env, create_signature_appearance(options))
+}
+
+fn create_signature_appearance(
+  options: CreateSignatureAppearanceOptions,
+) -> Result<SignatureAppearance> {
+  let CreateSignatureAppearanceOptions {
+    width,
+    height,
+    page,
+    x,
+    y,
+    name,
+    reason,
+    location,
     signing_time,
-    timestamp_server,
+    image,
+    font_size,
+  } = options;
+
+  let (x, y) = (x.unwrap_or_default(), y.unwrap_or_default());
+  for (option, value) in [("x", x), ("y", y)] {
+    if !value.is_finite() {
+      return Err(CmsError::InvalidOption(
+        option,
+        String::from("expected a number"),
+      ));
+    }
+  }
+
+  if let Some(image) = &image {
+    validation::non_empty("image", image)?;
+  }
+
+  let stream = appearance::Appearance {
+    width,
+    height,
+    name: name.as_deref(),
+    reason: reason.as_deref(),
+    location: location.as_deref(),
+    signing_time: parse_signing_time(signing_time),
+    image: image.as_deref(),
+    font_size,
+  }
+  .build()?;
+
+  Ok(SignatureAppearance {
+    page: page.unwrap_or_default(),
+    rect: vec![x, y, x + width, y + height],
+    bbox: vec![0.0, 0.0, width, height],
+    content: Buffer::from(stream.content),
+    font_size: stream.font_size,
+    font: AppearanceFont {
+      resource_name: appearance::FONT_RESOURCE.to_string(),
+      base_font: appearance::BASE_FONT.to_string(),
+      encoding: appearance::FONT_ENCODING.to_string(),
+    },
+    image: stream.image.map(|image| AppearanceImage {
+      resource_name: appearance::IMAGE_RESOURCE.to_string(),
+      width: image.width,
+      height: image.height,
+      color_space: image.color_space.to_string(),
+      bits_per_component: u32::from(appearance::BITS_PER_COMPONENT),
+      filter: image.filter.to_string(),
+      decode: image.inverted.then(|| [1.0, 0.0].repeat(4)),
+      data: Buffer::from(image.data),
+      soft_mask: image.soft_mask.map(Buffer::from),
+    }),
   })
 }
 
 #[napi(object)]
 #[derive(Default)]
-pub struct SignWithGCloudOptions {
-  pub content: Buffer,
-  pub cert: Buffer,
-  pub key_path: String,
-  pub signing_time: Option<String>,
+pub struct CreateCertificationReferenceOptions {
+  pub permissions: Option<String>,
+}
+
+#[napi(object)]
+pub struct CertificationReference {
+  pub permissions: u32,
+  pub transform_method: String,
+  pub version: String,
+  pub reference: String,
+}
+
+/// Build the DocMDP signature reference of a certification signature, which limits the changes
+/// allowed to the document after it, for the signature dictionary of the PDF.
+#[napi(js_name = "createCertificationReference")]
+pub fn create_certification_reference_js(
+  env: Env,
+  options: Option<CreateCertificationReferenceOptions>,
+) -> napi::Result<CertificationReference> {
+  js_result(
+    env,
+    create_certification_reference(options.unwrap_or_default()),
+  )
+}
+
+fn create_certification_reference(
+  options: CreateCertificationReferenceOptions,
+) -> Result<CertificationReference> {
+  let permissions = options
+    .permissions
+    .map(|name| certification::Permissions::parse("permissions", &name))
+    .transpose()?
+    .unwrap_or_default();
+
+  Ok(CertificationReference {
+    permissions: u32::from(permissions.value()),
+    transform_method: certification::TRANSFORM_METHOD.to_string(),
+    version: certification::TRANSFORM_PARAMS_VERSION.to_string(),
+    reference: certification::reference(permissions),
+  })
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SelfTestOptions {
   pub timestamp_server: Option<String>,
 }
 
-/// Sign data with Google Cloud.
-#[napi(js_name = "signWithGCloud")]
-pub fn sign_with_gcloud(options: SignWithGCloudOptions) -> Result<Buffer> {
-  let SignWithGCloudOptions {
-    content,
+#[napi(object)]
+pub struct SelfTestResult {
+  pub capability: String,
+  pub passed: bool,
+  pub code: Option<String>,
+  pub message: Option<String>,
+  pub duration: f64,
+}
+
+/// Sign and verify an embedded fixture document with each supported key algorithm, for
+/// deployment smoke tests of the prebuilt binaries.
+#[napi(js_name = "runSelfTest")]
+pub fn run_self_test_js(
+  env: Env,
+  options: Option<SelfTestOptions>,
+) -> napi::Result<Vec<SelfTestResult>> {
+  js_result(env, run_self_test(options.unwrap_or_default()))
+}
+
+fn run_self_test(options: SelfTestOptions) -> Result<Vec<SelfTestResult>> {
+  let SelfTestOptions { timestamp_server } = options;
+
+  let timestamp_server = defaults::timestamp_server(timestamp_server)
+    .map(|url| validation::timestamp_server("timestampServer", &url))
+    .transpose()?;
+
+  Ok(
+    self_test::run(timestamp_server.as_ref())
+      .into_iter()
+      .map(|check| {
+        let error = check.result.err();
+
+        SelfTestResult {
+          capability: check.capability.to_string(),
+          passed: error.is_none(),
+          code: error.as_ref().map(|error| error.code().to_string()),
+          message: error.as_ref().map(|error| match error.cause() {
+            Some(cause) => format!("{}: {}", error, cause),
+            None => error.to_string(),
+          }),
+          duration: check.duration.as_secs_f64() * 1000.0,
+        }
+      })
+      .collect(),
+  )
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct TestSignerOptions {
+  pub cert: Option<Buffer>,
+  pub key_path: Option<String>,
+  pub signer: Option<String>,
+  pub signer_options: Option<SignerOptions>,
+}
+
+#[napi(object)]
+pub struct TestSignerResult {
+  pub algorithm: String,
+  pub signature_size: u32,
+}
+
+/// Message signed by `test_signer`, never used for real documents.
+const TEST_SIGNER_MESSAGE: &[u8] = b"documenso/pdf-sign test signature";
+
+/// Check that a key can be used for signing.
+///
+/// Creates the key with the signer the signing functions use, a Google Cloud key unless another
+/// signer is named, signs a throwaway message and, if a certificate is provided, verifies the
+/// signature against it with the algorithm the key signs with.
+#[napi(js_name = "testSigner")]
+pub fn test_signer_js(env: Env, options: TestSignerOptions) -> napi::Result<TestSignerResult> {
+  js_result(env, test_signer(options))
+}
+
+/// Check that a key can be used for signing without blocking the event loop.
+#[napi(js_name = "testSignerAsync")]
+pub fn test_signer_async(
+  options: TestSignerOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<TestSignerResult>> {
+  AsyncTask::with_optional_signal(BlockingTask::new(move || test_signer(options)), signal)
+}
+
+fn test_signer(options: TestSignerOptions) -> Result<TestSignerResult> {
+  let TestSignerOptions {
     cert,
     key_path,
-    signing_time,
-    timestamp_server,
+    signer: signer_name,
+    signer_options,
   } = options;
 
-  let x509_certs = CapturedX509Certificate::from_pem_multiple(cert.to_vec())
-    .map_err(|_| errors::CmsError::CertificateParseError)?;
+  let mut signer_options = signer_options.unwrap_or_default();
+
+  if let Some(key_path) = key_path {
+    signer_options.insert(String::from("keyPath"), key_path);
+  }
+
+  let certs = cert
+    .map(|cert| input::certificates("cert", &cert, InputFormat::Auto))
+    .transpose()?
+    .unwrap_or_default();
+
+  let SignerKey { signing_key, certs } = signer::create_signer(
+    signer_name.as_deref().unwrap_or("gcloud"),
+    SignerInput {
+      options: signer_options,
+      certs,
+      ..Default::default()
+    },
+  )?;
+
+  let signature_algorithm = signing_key
+    .signature_algorithm()
+    .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+  let signature = signing_key
+    .try_sign(TEST_SIGNER_MESSAGE)
+    .map_err(CmsError::signing_failed)?;
 
-  let gcloud_signer = GCloudSigner::new(key_path.clone());
-  let mut signer = SignerBuilder::new(&gcloud_signer, x509_certs.first().unwrap().clone());
+  if let Some(cert) = certs.first() {
+    let x509_cert = cert.parsed()?;
 
-  if let Some(timestamp_server) = timestamp_server {
-    signer = signer
-      .time_stamp_url(timestamp_server)
-      .map_err(|_| CmsError::TimestampServerParseError)?;
+    let key_algorithm = x509_cert
+      .key_algorithm()
+      .ok_or(CmsError::UnsupportedKeyAlgorithm)?;
+    let verification_algorithm = signature_algorithm
+      .resolve_verification_algorithm(key_algorithm)
+      .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+    x509_cert
+      .verify_signed_data_with_algorithm(TEST_SIGNER_MESSAGE, &signature, verification_algorithm)
+      .map_err(|error| CmsError::CertificateKeyMismatch.caused_by(error))?;
   }
 
-  create_signed_data(CreateSignedDataOptions {
-    content,
-    signer,
-    signing_time,
-    certs: Some(x509_certs),
+  Ok(TestSignerResult {
+    algorithm: Oid::from(signature_algorithm).to_string(),
+    signature_size: signature.as_ref().len() as u32,
   })
 }
 
-pub struct CreateSignedDataOptions<'a> {
-  pub content: Buffer,
-  pub signer: SignerBuilder<'a>,
-  pub signing_time: Option<String>,
-  pub certs: Option<Vec<CapturedX509Certificate>>,
+#[napi(object)]
+#[derive(Default)]
+pub struct ValidateSigningSetupOptions {
+  pub p12: Option<Buffer>,
+  pub p12_path: Option<String>,
+  pub p12_source: Option<String>,
+  pub password: Option<String>,
+  pub password_source: Option<String>,
+  pub friendly_name: Option<String>,
+  pub thumbprint: Option<String>,
+  pub cert: Option<Buffer>,
+  pub private_key: Option<Buffer>,
+  pub private_key_password: Option<String>,
+  pub format: Option<String>,
+  pub key_path: Option<String>,
+  pub timestamp_server: Option<String>,
 }
 
-/// Helper function to create signed data.
-fn create_signed_data<'a>(options: CreateSignedDataOptions<'a>) -> Result<Buffer> {
-  let CreateSignedDataOptions {
-    content,
-    signer,
-    signing_time,
-    certs,
+#[napi(object)]
+pub struct SigningSetupReport {
+  pub ready: bool,
+  pub backend: Option<String>,
+  pub signer_subject: Option<String>,
+  pub checks: Vec<SigningSetupCheck>,
+  pub warnings: Vec<String>,
+}
+
+#[napi(object)]
+pub struct SigningSetupCheck {
+  pub check: String,
+  pub passed: bool,
+  pub code: Option<String>,
+  pub message: Option<String>,
+  pub duration: f64,
+}
+
+/// Check that a key, its certificates and the timestamp server are ready for signing, by
+/// loading the key, signing a throwaway message and requesting a timestamp of it, for
+/// deployment key ceremonies.
+///
+/// Failures are reported per check rather than thrown, and a check that depends on a failed
+/// one isn't run.
+#[napi(js_name = "validateSigningSetup")]
+pub fn validate_signing_setup_js(
+  env: Env,
+  options: ValidateSigningSetupOptions,
+) -> napi::Result<SigningSetupReport> {
+  js_result(env, Ok(validate_signing_setup(options)))
+}
+
+/// Check that a key, its certificates and the timestamp server are ready for signing without
+/// blocking the event loop.
+#[napi(js_name = "validateSigningSetupAsync")]
+pub fn validate_signing_setup_async(
+  options: ValidateSigningSetupOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<SigningSetupReport>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || Ok(validate_signing_setup(options))),
+    signal,
+  )
+}
+
+fn validate_signing_setup(options: ValidateSigningSetupOptions) -> SigningSetupReport {
+  let ValidateSigningSetupOptions {
+    p12,
+    p12_path,
+    p12_source,
+    password,
+    password_source,
+    friendly_name,
+    thumbprint,
+    cert,
+    private_key,
+    private_key_password,
+    format,
+    key_path,
+    timestamp_server,
+  } = options;
+
+  let mut checks = Vec::new();
+  let mut signer = None;
+
+  checks.push(setup_check("key", || {
+    signer = Some(ReusableSigner::create(SignerKeyOptions {
+      p12,
+      p12_path,
+      p12_source,
+      password,
+      password_source,
+      friendly_name,
+      thumbprint,
+      cert,
+      private_key,
+      private_key_password,
+      format,
+      key_path,
+      max_concurrent_requests: None,
+      max_requests_per_second: None,
+    })?);
+
+    Ok(Vec::new())
+  }));
+
+  let mut report = None;
+
+  if let Some(signer) = &signer {
+    // Remote keys, such as Google Cloud KMS keys, are first used here.
+    checks.push(setup_check("signing", || {
+      let signed_data = sign_with_parsed_key(
+        signer.signing_key.as_ref(),
+        signer.certs.clone(),
+        None,
+        SignatureScheme::default(),
+        SigningOptions {
+          content: Some(Buffer::from(TEST_SIGNER_MESSAGE)),
+          ..Default::default()
+        },
+      )?;
+
+      let dry_run = dry_run_report(&signed_data, false, None)?;
+
+      if dry_run.key_matches_certificate != Some(true) {
+        return Err(CmsError::CertificateKeyMismatch);
+      }
+
+      report = Some(dry_run);
+
+      Ok(Vec::new())
+    }));
+  }
+
+  if let Some(report) = &report {
+    checks.push(setup_check("certificate", || Ok(report.problems.clone())));
+  }
+
+  // The configured default is checked too, as that's what signing would use.
+  if let Some(url) = defaults::timestamp_server(timestamp_server) {
+    checks.push(setup_check("timestamp", || {
+      let url = validation::timestamp_server("timestampServer", &url)?;
+      let token = signing::time_stamp_token(&url, TEST_SIGNER_MESSAGE, DigestAlgorithm::Sha256)?;
+
+      parse_timestamp(token.as_slice())?;
+
+      Ok(Vec::new())
+    }));
+  }
+
+  let ready = checks.iter().all(|check| check.passed) && report.is_some();
+
+  tracing::info!(ready, checks = checks.len(), "Validated signing setup");
+
+  SigningSetupReport {
+    ready,
+    backend: signer.as_ref().map(|signer| signer.backend.to_string()),
+    signer_subject: report.as_ref().map(|report| report.signer_subject.clone()),
+    checks,
+    warnings: report.map(|report| report.warnings).unwrap_or_default(),
+  }
+}
+
+/// Helper function to run a check of `validateSigningSetup`, which fails with an error or with
+/// the problems it returns.
+fn setup_check(check: &str, run: impl FnOnce() -> Result<Vec<String>>) -> SigningSetupCheck {
+  let started = std::time::Instant::now();
+  let result = run();
+  let duration = started.elapsed().as_secs_f64() * 1000.0;
+
+  let (code, message) = match result {
+    Ok(problems) if problems.is_empty() => (None, None),
+    Ok(problems) => (None, Some(problems.join("; "))),
+    Err(error) => {
+      tracing::warn!(
+        check,
+        code = error.code(),
+        "Signing setup check failed: {}",
+        error
+      );
+
+      (
+        Some(error.code().to_string()),
+        Some(match error.cause() {
+          Some(cause) => format!("{}: {}", error, cause),
+          None => error.to_string(),
+        }),
+      )
+    }
+  };
+
+  SigningSetupCheck {
+    check: check.to_string(),
+    passed: message.is_none(),
+    code,
+    message,
+    duration,
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct EnrollWithEjbcaOptions {
+  pub url: String,
+  pub client_cert: Buffer,
+  pub client_key: Option<Buffer>,
+  pub client_key_password: Option<String>,
+  pub ca_cert: Option<Buffer>,
+  pub private_key: Option<Buffer>,
+  pub private_key_password: Option<String>,
+  pub common_name: String,
+  pub certificate_profile_name: String,
+  pub end_entity_profile_name: String,
+  pub certificate_authority_name: String,
+  pub username: String,
+  pub enrollment_code: String,
+}
+
+#[napi(object)]
+pub struct EnrollWithEjbcaResult {
+  pub cert: Buffer,
+  pub private_key: Buffer,
+}
+
+/// Enroll for a certificate with an EJBCA instance.
+///
+/// The returned certificate chain and private key can be passed to `signWithPrivateKey`.
+#[napi(js_name = "enrollWithEjbca")]
+pub fn enroll_with_ejbca_js(
+  env: Env,
+  options: EnrollWithEjbcaOptions,
+) -> napi::Result<EnrollWithEjbcaResult> {
+  js_result(env, enroll_with_ejbca(options))
+}
+
+/// Enroll for a certificate with an EJBCA instance without blocking the event loop.
+#[napi(js_name = "enrollWithEjbcaAsync")]
+pub fn enroll_with_ejbca_async(
+  options: EnrollWithEjbcaOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<EnrollWithEjbcaResult>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || enroll_with_ejbca(options)),
+    signal,
+  )
+}
+
+fn enroll_with_ejbca(options: EnrollWithEjbcaOptions) -> Result<EnrollWithEjbcaResult> {
+  let EnrollWithEjbcaOptions {
+    url,
+    client_cert,
+    client_key,
+    client_key_password,
+    ca_cert,
+    private_key,
+    private_key_password,
+    common_name,
+    certificate_profile_name,
+    end_entity_profile_name,
+    certificate_authority_name,
+    username,
+    enrollment_code,
   } = options;
 
-  let signing_time = signing_time
-    .and_then(|time| time.parse::<chrono::DateTime<chrono::Utc>>().ok())
-    .unwrap_or(chrono::Utc::now());
+  let private_key = match private_key {
+    Some(private_key) => {
+      validation::encoded(
+        "privateKey",
+        &private_key,
+        "a private key",
+        InputFormat::Auto,
+      )?;
+
+      input::private_key_der(
+        "privateKey",
+        &private_key,
+        private_key_password.as_deref(),
+        InputFormat::Auto,
+      )?
+    }
+    None => ejbca::generate_private_key()?,
+  };
+
+  let private_key_pair = keys::private_key_from_der(&private_key)
+    .map_err(|error| error.with_detail("option", "privateKey"))?;
+
+  let certificate_request = ejbca::certificate_request(&private_key_pair, &common_name)?;
+
+  // The client key is read from the client certificate if it's a bundle with both.
+  let (client_key_option, client_key) = match &client_key {
+    Some(client_key) => ("clientKey", client_key.as_ref()),
+    None => ("clientCert", client_cert.as_ref()),
+  };
+
+  let client_key = input::private_key_der(
+    client_key_option,
+    client_key,
+    client_key_password.as_deref(),
+    InputFormat::Auto,
+  )
+  .map_err(|error| match error {
+    // The password of an encrypted client key is an option of its own.
+    CmsError::InvalidOption("privateKeyPassword", reason) => {
+      CmsError::InvalidOption("clientKeyPassword", reason)
+    }
+    error => error,
+  })?;
+
+  let client_certs = input::certificates("clientCert", &client_cert, InputFormat::Auto)?;
+
+  let ca_certs = ca_cert
+    .map(|ca_cert| input::certificates("caCert", &ca_cert, InputFormat::Auto))
+    .transpose()?
+    .unwrap_or_default();
+
+  let client = EjbcaClient::new(url, &client_certs, &client_key, &ca_certs)?;
+
+  let x509_certs = client.enroll(EjbcaEnrollment {
+    certificate_request: &certificate_request,
+    certificate_profile_name: &certificate_profile_name,
+    end_entity_profile_name: &end_entity_profile_name,
+    certificate_authority_name: &certificate_authority_name,
+    username: &username,
+    enrollment_code: &enrollment_code,
+  })?;
+
+  let cert = x509_certs
+    .iter()
+    .map(|cert| cert.encode_pem())
+    .collect::<Vec<_>>()
+    .join("\n");
+
+  let private_key = keys::encode_private_key_pem(&private_key);
+
+  Ok(EnrollWithEjbcaResult {
+    cert: Buffer::from(cert.as_bytes()),
+    private_key: Buffer::from(private_key.as_bytes()),
+  })
+}
+
+/// Helper function to throw errors to JavaScript with their code and cause.
+fn js_result<T>(env: Env, result: Result<T>) -> napi::Result<T> {
+  result.map_err(|error| {
+    tracing::warn!(code = error.code(), cause = error.cause(), "{}", error);
+
+    js_error(env, error)
+  })
+}
+
+/// Helper function to create a JavaScript error with the `code` and, if known, the `cause` of an
+/// error.
+fn js_error(env: Env, error: CmsError) -> napi::Error {
+  let create_error = || -> napi::Result<JsUnknown> {
+    let mut js_error = env.create_error(napi::Error::from_reason(error.to_string()))?;
+    js_error.set_named_property("code", env.create_string(error.code())?)?;
+
+    if let Some(cause) = error.cause() {
+      let cause = env.create_error(napi::Error::from_reason(cause))?;
+      js_error.set_named_property("cause", cause)?;
+    }
+
+    let mut details = env.create_object()?;
+
+    // A detail attached later, closer to the caller, takes precedence.
+    for (name, value) in error.details().into_iter().rev() {
+      details.set_named_property(name, env.create_string(&value)?)?;
+    }
+
+    js_error.set_named_property("details", details)?;
+
+    Ok(js_error.into_unknown())
+  };
+
+  match create_error() {
+    Ok(js_error) => napi::Error::from(js_error),
+    Err(create_error) => create_error,
+  }
+}
+
+/// Helper function to add unsigned attributes to the signer info at `index` of signed data, or
+/// to every signer info if no index is given.
+fn add_custom_unsigned_attributes(
+  signed_data: &[u8],
+  index: Option<usize>,
+  attribute_values: &[(Oid, Vec<AttributeValue>)],
+  unsigned_attributes: &[CustomAttribute],
+) -> Result<Buffer> {
+  let mut signed_data = cms::SignedData::decode(signed_data)?;
 
-  let mut builder = SignedDataBuilder::default()
-    .content_type(Oid(Bytes::from(rfc5652::OID_ID_DATA.as_ref())))
-    .content_external(content.to_vec())
-    .signing_time(signing_time.into())
-    .signer(signer);
+  let signer_infos = match index {
+    Some(index) => vec![signed_data.signer_info_mut(index)?],
+    None => signed_data.signer_infos.iter_mut().collect(),
+  };
 
-  if let Some(certs) = certs {
-    builder = builder.certificates(certs.into_iter());
+  for signer_info in signer_infos {
+    signer_info.add_unsigned_attributes(
+      attribute_values,
+      unsigned_attributes
+        .iter()
+        .map(|attribute| (attribute.oid.as_str(), attribute.value.as_ref())),
+    )?;
   }
 
-  builder
-    .build_der()
-    .map_err(|_| CmsError::BuildSignedDataError.into())
-    .map(|data| Buffer::from(data))
+  Ok(Buffer::from(signed_data.encode()))
 }
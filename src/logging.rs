@@ -0,0 +1,269 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Once, RwLock};
+use std::time::{Duration, Instant};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+use crate::errors::CmsError;
+
+pub use tracing::level_filters::LevelFilter;
+
+/// Environment variable with the level of events logged to stderr when no handler is set.
+const LOG_LEVEL_ENV: &str = "PDF_SIGN_LOG";
+
+/// An event logged by the signing code or its dependencies, such as the HTTP client.
+#[derive(Clone)]
+pub struct LogRecord {
+  pub level: Level,
+  pub target: String,
+  pub message: String,
+  pub fields: Vec<(String, String)>,
+}
+
+impl fmt::Display for LogRecord {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {}: {}", self.level, self.target, self.message)?;
+
+    for (name, value) in &self.fields {
+      write!(f, " {}={}", name, value)?;
+    }
+
+    Ok(())
+  }
+}
+
+pub type Handler = Box<dyn Fn(LogRecord) + Send + Sync>;
+
+/// An event captured by [with_trace], with when it was logged.
+pub struct TraceRecord {
+  pub time: chrono::DateTime<chrono::Utc>,
+  /// The time since the traced operation started.
+  pub elapsed: Duration,
+  pub record: LogRecord,
+}
+
+struct Trace {
+  started: Instant,
+  records: Vec<TraceRecord>,
+}
+
+thread_local! {
+  static TRACE: RefCell<Option<Trace>> = const { RefCell::new(None) };
+}
+
+/// The crates whose debug events are traced, while only info events and above are traced for
+/// dependencies such as the HTTP client.
+const TRACED_TARGETS: [&str; 2] = ["documenso_pdf_sign", "pdf_sign_core"];
+
+struct Logger {
+  level: LevelFilter,
+  handler: Option<Handler>,
+  /// Incremented whenever the logger is set, to tell whether a handler is still the current one.
+  generation: u64,
+}
+
+static LOGGER: RwLock<Logger> = RwLock::new(Logger {
+  level: LevelFilter::OFF,
+  handler: None,
+  generation: 0,
+});
+
+/// Parse a log level, one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+pub fn parse_level(level: &str) -> Result<LevelFilter, CmsError> {
+  LevelFilter::from_str(level).map_err(|error| CmsError::InvalidLogLevel.caused_by(error))
+}
+
+/// The level set by the environment, or off if it isn't set or invalid.
+pub fn env_level() -> LevelFilter {
+  std::env::var(LOG_LEVEL_ENV)
+    .ok()
+    .and_then(|level| parse_level(&level).ok())
+    .unwrap_or(LevelFilter::OFF)
+}
+
+/// Install the subscriber passing events to the logger, with the level from the environment.
+///
+/// The addon is loaded again by every worker thread, which must not replace a logger set by
+/// another thread.
+pub fn init() {
+  static INIT: Once = Once::new();
+
+  INIT.call_once(|| {
+    set_logger(None, env_level());
+
+    // Fails only if a subscriber is already installed, which then keeps receiving the events.
+    let _ = tracing::subscriber::set_global_default(LogSubscriber {
+      next_span_id: AtomicU64::new(1),
+    });
+  });
+}
+
+/// Pass events up to a level to a handler, or log them to stderr without one.
+///
+/// Returns the generation of the logger, for [remove_logger].
+pub fn set_logger(handler: Option<Handler>, level: LevelFilter) -> u64 {
+  let mut logger = LOGGER.write().unwrap_or_else(|error| error.into_inner());
+  let generation = logger.generation + 1;
+
+  *logger = Logger {
+    level,
+    handler,
+    generation,
+  };
+
+  generation
+}
+
+/// Log to stderr again with the level from the environment, unless the logger of the generation
+/// has been replaced since.
+///
+/// The handler of a worker thread can't be called once the worker exits, so it's removed then.
+pub fn remove_logger(generation: u64) {
+  let mut logger = LOGGER.write().unwrap_or_else(|error| error.into_inner());
+
+  if logger.generation == generation {
+    *logger = Logger {
+      level: env_level(),
+      handler: None,
+      generation: generation + 1,
+    };
+  }
+}
+
+/// Run an operation, capturing the events it logs on this thread as well as logging them, if
+/// `enabled`.
+///
+/// Events are captured whatever the level of the logger, so a trace can be returned with a
+/// result without logging everything else at the debug level too.
+pub fn with_trace<T>(enabled: bool, op: impl FnOnce() -> T) -> (T, Option<Vec<TraceRecord>>) {
+  if !enabled {
+    return (op(), None);
+  }
+
+  let previous = TRACE.with(|trace| {
+    trace.replace(Some(Trace {
+      started: Instant::now(),
+      records: Vec::new(),
+    }))
+  });
+
+  let result = op();
+
+  let trace = TRACE.with(|trace| trace.replace(previous));
+
+  (result, trace.map(|trace| trace.records))
+}
+
+fn is_traced(metadata: &Metadata<'_>) -> bool {
+  let level = if TRACED_TARGETS
+    .iter()
+    .any(|target| metadata.target().starts_with(target))
+  {
+    Level::DEBUG
+  } else {
+    Level::INFO
+  };
+
+  *metadata.level() <= level && TRACE.with(|trace| trace.borrow().is_some())
+}
+
+/// A subscriber passing events to the logger. Spans are not tracked, since the events carry
+/// everything needed to follow an operation.
+struct LogSubscriber {
+  next_span_id: AtomicU64,
+}
+
+impl Subscriber for LogSubscriber {
+  fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+    // The level can change at any time, so whether a callsite is enabled can't be cached.
+    Interest::sometimes()
+  }
+
+  fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+    let logger = LOGGER.read().unwrap_or_else(|error| error.into_inner());
+    logger.level >= *metadata.level() || is_traced(metadata)
+  }
+
+  fn new_span(&self, _span: &Attributes<'_>) -> Id {
+    Id::from_u64(self.next_span_id.fetch_add(1, Ordering::Relaxed))
+  }
+
+  fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+  fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+  fn event(&self, event: &Event<'_>) {
+    let mut visitor = FieldVisitor::default();
+    event.record(&mut visitor);
+
+    let metadata = event.metadata();
+    let record = LogRecord {
+      level: *metadata.level(),
+      target: metadata.target().to_string(),
+      message: visitor.message,
+      fields: visitor.fields,
+    };
+
+    if is_traced(metadata) {
+      TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+          trace.records.push(TraceRecord {
+            time: chrono::Utc::now(),
+            elapsed: trace.started.elapsed(),
+            record: record.clone(),
+          });
+        }
+      });
+    }
+
+    let logger = LOGGER.read().unwrap_or_else(|error| error.into_inner());
+
+    if logger.level < *metadata.level() {
+      return;
+    }
+
+    match &logger.handler {
+      Some(handler) => handler(record),
+      None => eprintln!("[pdf-sign] {}", record),
+    }
+  }
+
+  fn enter(&self, _span: &Id) {}
+
+  fn exit(&self, _span: &Id) {}
+}
+
+/// Collects the message and the other fields of an event as strings.
+#[derive(Default)]
+struct FieldVisitor {
+  message: String,
+  fields: Vec<(String, String)>,
+}
+
+impl Visit for FieldVisitor {
+  fn record_str(&mut self, field: &Field, value: &str) {
+    if field.name() == "message" {
+      self.message = value.to_string();
+    } else {
+      self
+        .fields
+        .push((field.name().to_string(), value.to_string()));
+    }
+  }
+
+  fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+    if field.name() == "message" {
+      self.message = format!("{:?}", value);
+    } else {
+      self
+        .fields
+        .push((field.name().to_string(), format!("{:?}", value)));
+    }
+  }
+}
@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use cryptographic_message_syntax::asn1::{rfc3161, rfc5652};
+use pdf_sign_core::digest_algorithm_name;
+use pdf_sign_core::{cms, progress};
+use serde::Serialize;
+use x509_certificate::asn1time::Time;
+use x509_certificate::{CapturedX509Certificate, DigestAlgorithm};
+
+use crate::errors::CmsError;
+use crate::{defaults, logging, parse_name, parse_timestamp, Result};
+
+/// Signed data with the details of its first signer, returned with the `detailed` option.
+#[napi(object)]
+pub struct SigningResult {
+  pub signature: Buffer,
+  pub size: u32,
+  pub signing_time: Option<String>,
+  pub timestamp_time: Option<String>,
+  pub signer_subject: String,
+  pub serial_number: String,
+  pub timings: SigningTimings,
+  pub warnings: Vec<String>,
+  pub trace: Option<Vec<TraceEvent>>,
+  pub audit: AuditRecord,
+}
+
+/// A record of a signing operation for compliance trails, logged as JSON and returned with the
+/// detailed result.
+#[napi(object)]
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+  pub operation: String,
+  pub backend: String,
+  pub dry_run: bool,
+  pub outcome: String,
+  pub error_code: Option<String>,
+  pub started_at: String,
+  pub duration: f64,
+  pub document_digest: Option<String>,
+  pub digest_algorithm: Option<String>,
+  pub signer_fingerprint: Option<String>,
+  pub signer_subject: Option<String>,
+  pub signing_time: Option<String>,
+  pub timestamp_time: Option<String>,
+  pub timestamp_authority: Option<String>,
+}
+
+/// How many milliseconds the stages of a signing operation took, see [progress::Timings].
+#[napi(object)]
+pub struct SigningTimings {
+  pub parsing: f64,
+  pub hashing: f64,
+  pub signing: f64,
+  pub timestamping: f64,
+  pub writing: f64,
+  pub total: f64,
+}
+
+impl From<progress::Timings> for SigningTimings {
+  fn from(timings: progress::Timings) -> Self {
+    let progress::Timings {
+      parsing,
+      hashing,
+      signing,
+      timestamping,
+      writing,
+    } = timings;
+
+    let millis = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+
+    Self {
+      parsing: millis(parsing),
+      hashing: millis(hashing),
+      signing: millis(signing),
+      timestamping: millis(timestamping),
+      writing: millis(writing),
+      total: millis(parsing + hashing + signing + timestamping + writing),
+    }
+  }
+}
+
+/// An event logged while signing, returned with the `debugTrace` option.
+#[napi(object)]
+pub struct TraceEvent {
+  pub time: String,
+  /// Milliseconds since the signing operation started.
+  pub elapsed: f64,
+  pub level: String,
+  pub target: String,
+  pub message: String,
+  pub fields: HashMap<String, String>,
+}
+
+impl From<logging::TraceRecord> for TraceEvent {
+  fn from(trace_record: logging::TraceRecord) -> Self {
+    let logging::TraceRecord {
+      time,
+      elapsed,
+      record,
+    } = trace_record;
+
+    Self {
+      time: time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+      elapsed: elapsed.as_secs_f64() * 1000.0,
+      level: record.level.as_str().to_lowercase(),
+      target: record.target,
+      message: record.message,
+      fields: record.fields.into_iter().collect(),
+    }
+  }
+}
+
+/// What a signing operation would produce, returned with the `dryRun` option.
+#[napi(object)]
+pub struct DryRunReport {
+  pub signer_subject: String,
+  pub serial_number: String,
+  pub not_before: String,
+  pub not_after: String,
+  pub certificate_count: u32,
+  pub signature_size: u32,
+  pub timestamped: bool,
+  pub key_matches_certificate: Option<bool>,
+  pub problems: Vec<String>,
+  pub warnings: Vec<String>,
+  pub trace: Option<Vec<TraceEvent>>,
+}
+
+/// The signing function and the kind of key of a signing operation, for its audit record.
+#[derive(Clone, Copy)]
+pub struct Operation {
+  name: &'static str,
+  backend: &'static str,
+}
+
+impl Operation {
+  pub fn new(name: &'static str, backend: &'static str) -> Self {
+    Self { name, backend }
+  }
+}
+
+/// The target of the events carrying audit records, by which log handlers pick them out.
+const AUDIT_TARGET: &str = "documenso_pdf_sign::audit";
+
+/// What the signing functions return, selected by the `detailed`, `dryRun` and `debugTrace`
+/// options, where `trace` is set if the events logged while signing are returned too.
+#[derive(Clone, Copy)]
+pub enum Output {
+  Signature,
+  Detailed {
+    trace: bool,
+  },
+  /// A dry run, where `placeholder` is set if the key was replaced by a [PlaceholderSigner].
+  DryRun {
+    placeholder: bool,
+    trace: bool,
+  },
+}
+
+impl Output {
+  pub fn new(
+    detailed: Option<bool>,
+    dry_run: Option<bool>,
+    debug_trace: Option<bool>,
+    placeholder: bool,
+  ) -> Self {
+    let trace = debug_trace.unwrap_or(false);
+
+    // A trace is returned with the detailed result, as there is nowhere to put it otherwise.
+    match (dry_run.unwrap_or(false), detailed.unwrap_or(false) || trace) {
+      (true, _) => Output::DryRun { placeholder, trace },
+      (false, true) => Output::Detailed { trace },
+      (false, false) => Output::Signature,
+    }
+  }
+
+  fn trace(self) -> bool {
+    match self {
+      Output::Signature => false,
+      Output::Detailed { trace } | Output::DryRun { trace, .. } => trace,
+    }
+  }
+}
+
+/// Helper function to sign, returning the signed data in the form selected by the options.
+///
+/// The stages are timed for the detailed result and logged, so slow timestamp servers and keys
+/// show up in the logs. Every operation, failed or not, is audited by an info event with the
+/// audit record as JSON in its `record` field.
+pub fn signing_output(
+  operation: Operation,
+  sign: impl FnOnce() -> Result<Buffer>,
+  output: Output,
+) -> Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let started_at = chrono::Utc::now();
+
+  let (signed_data, trace) = logging::with_trace(output.trace(), || {
+    let (signed_data, timings) = progress::with_timings(sign);
+    let timings = SigningTimings::from(timings);
+
+    tracing::info!(
+      parsing_ms = timings.parsing,
+      hashing_ms = timings.hashing,
+      signing_ms = timings.signing,
+      timestamping_ms = timings.timestamping,
+      writing_ms = timings.writing,
+      total_ms = timings.total,
+      "Timed signing operation"
+    );
+
+    let audit = audit_record(
+      operation,
+      matches!(output, Output::DryRun { .. }),
+      started_at,
+      timings.total,
+      signed_data.as_deref(),
+    );
+
+    if let Ok(record) = serde_json::to_string(&audit) {
+      tracing::info!(target: AUDIT_TARGET, record, "Audited signing operation");
+    }
+
+    signed_data.map(|signed_data| (signed_data, timings, audit))
+  });
+  let (signed_data, timings, audit) = signed_data?;
+  let trace = trace.map(|trace| trace.into_iter().map(TraceEvent::from).collect());
+
+  match output {
+    Output::Signature => Ok(Either3::A(signed_data)),
+    Output::Detailed { .. } => signing_result(signed_data, timings, trace, audit).map(Either3::B),
+    Output::DryRun { placeholder, .. } => {
+      dry_run_report(&signed_data, placeholder, trace).map(Either3::C)
+    }
+  }
+}
+
+/// Helper function to read the details of the first signer back from signed data.
+fn signing_result(
+  signed_data: Buffer,
+  timings: SigningTimings,
+  trace: Option<Vec<TraceEvent>>,
+  audit: AuditRecord,
+) -> Result<SigningResult> {
+  let parsed = cms::SignedData::decode(&signed_data)?;
+  let signer_info = first_signer_info(&parsed)?;
+  let certs = parsed.x509_certificates()?;
+  let cert = signer_certificate(&certs, signer_info)?;
+
+  let timestamp_time = signer_info
+    .unsigned_attribute_values()?
+    .into_iter()
+    .find(|(oid, _)| *oid == rfc3161::OID_TIME_STAMP_TOKEN)
+    .and_then(|(_, values)| values.into_iter().next())
+    .map(|value| parse_timestamp(value.as_slice()).map(|timestamp| timestamp.time))
+    .transpose()?;
+
+  Ok(SigningResult {
+    size: signed_data.len() as u32,
+    signing_time: signer_signing_time(signer_info)?.map(|time| time.to_rfc3339()),
+    timestamp_time,
+    signer_subject: parse_name(cert.subject_name())?,
+    serial_number: hex::encode(cert.serial_number_asn1().as_slice()),
+    signature: signed_data,
+    timings,
+    warnings: signing_warnings(&certs, &cert, signer_info)?,
+    trace,
+    audit,
+  })
+}
+
+/// Helper function to create the audit record of a signing operation.
+///
+/// The details of the first signer are read back from the signed data, so they are what the
+/// signature says rather than what was asked for. They are left out if that fails, which is
+/// logged, as the signature is returned regardless.
+fn audit_record(
+  operation: Operation,
+  dry_run: bool,
+  started_at: chrono::DateTime<chrono::Utc>,
+  duration: f64,
+  signed_data: std::result::Result<&[u8], &CmsError>,
+) -> AuditRecord {
+  let mut record = AuditRecord {
+    operation: operation.name.to_string(),
+    backend: operation.backend.to_string(),
+    dry_run,
+    outcome: String::from(match signed_data {
+      Ok(_) => "success",
+      Err(_) => "failure",
+    }),
+    error_code: signed_data.err().map(|error| error.code().to_string()),
+    started_at: started_at.to_rfc3339(),
+    duration,
+    document_digest: None,
+    digest_algorithm: None,
+    signer_fingerprint: None,
+    signer_subject: None,
+    signing_time: None,
+    timestamp_time: None,
+    timestamp_authority: None,
+  };
+
+  if let Ok(signed_data) = signed_data {
+    if let Err(error) = audit_details(&mut record, signed_data) {
+      tracing::warn!(
+        code = error.code(),
+        "Failed to read the audit details of the signature: {}",
+        error
+      );
+    }
+  }
+
+  record
+}
+
+/// Helper function to add the details of the first signer of signed data to an audit record.
+fn audit_details(record: &mut AuditRecord, signed_data: &[u8]) -> Result<()> {
+  let parsed = cms::SignedData::decode(signed_data)?;
+  let signer_info = first_signer_info(&parsed)?;
+  let certs = parsed.x509_certificates()?;
+  let cert = signer_certificate(&certs, signer_info)?;
+
+  let digest_algorithm = signer_info.digest_algorithm_oid()?;
+
+  record.document_digest = signer_info.message_digest()?.map(hex::encode);
+  record.digest_algorithm = Some(match DigestAlgorithm::try_from(&digest_algorithm) {
+    Ok(digest_algorithm) => String::from(digest_algorithm_name(digest_algorithm)),
+    Err(_) => digest_algorithm.to_string(),
+  });
+  record.signer_fingerprint = Some(hex::encode(
+    DigestAlgorithm::Sha256.digest_data(cert.constructed_data()),
+  ));
+  record.signer_subject = Some(parse_name(cert.subject_name())?);
+  record.signing_time = signer_signing_time(signer_info)?.map(|time| time.to_rfc3339());
+
+  let token = signer_info
+    .unsigned_attribute_values()?
+    .into_iter()
+    .find(|(oid, _)| *oid == rfc3161::OID_TIME_STAMP_TOKEN)
+    .and_then(|(_, values)| values.into_iter().next());
+
+  if let Some(token) = token {
+    record.timestamp_time = Some(parse_timestamp(token.as_slice())?.time);
+
+    // The TSA is named by the certificate of the token, which servers may leave out.
+    let token = cms::SignedData::decode(token.as_slice())?;
+    let tsa_certs = token.x509_certificates()?;
+
+    record.timestamp_authority = first_signer_info(&token)
+      .and_then(|signer_info| signer_certificate(&tsa_certs, signer_info))
+      .ok()
+      .map(|cert| parse_name(cert.subject_name()))
+      .transpose()?;
+  }
+
+  Ok(())
+}
+
+/// Helper function to check the signed data of a dry run.
+///
+/// The signature is verified against the signing certificate unless a placeholder was signed,
+/// and the certificates are checked along the chain included in the signed data, as far as it
+/// goes, for their validity at the signing time and their issuers' signatures.
+pub fn dry_run_report(
+  signed_data: &[u8],
+  placeholder: bool,
+  trace: Option<Vec<TraceEvent>>,
+) -> Result<DryRunReport> {
+  let parsed = cms::SignedData::decode(signed_data)?;
+  let signer_info = first_signer_info(&parsed)?;
+  let certs = parsed.x509_certificates()?;
+  let cert = signer_certificate(&certs, signer_info)?;
+
+  let signing_time = signer_signing_time(signer_info)?.unwrap_or_else(chrono::Utc::now);
+  let mut problems = Vec::new();
+
+  let key_matches_certificate = (!placeholder)
+    .then(|| signer_info.signature_valid(&cert, None))
+    .transpose()?;
+
+  if key_matches_certificate == Some(false) {
+    problems.push(String::from(
+      "The private key doesn't match the signing certificate",
+    ));
+  }
+
+  problems.extend(chain_problems(
+    &certs,
+    &cert,
+    signing_time,
+    &defaults::trust_store(),
+  )?);
+
+  let timestamped = signer_info
+    .unsigned_attribute_values()?
+    .iter()
+    .any(|(oid, _)| *oid == rfc3161::OID_TIME_STAMP_TOKEN);
+
+  Ok(DryRunReport {
+    signer_subject: parse_name(cert.subject_name())?,
+    serial_number: hex::encode(cert.serial_number_asn1().as_slice()),
+    not_before: cert.validity_not_before().to_rfc3339(),
+    not_after: cert.validity_not_after().to_rfc3339(),
+    certificate_count: certs.len() as u32,
+    signature_size: signed_data.len() as u32,
+    timestamped,
+    key_matches_certificate,
+    problems,
+    warnings: signing_warnings(&certs, &cert, signer_info)?,
+    trace,
+  })
+}
+
+/// Helper function to check a certificate along the chain in `certs`, as far as it goes, for
+/// its validity at `time` and its issuers' signatures, and that the chain ends at a certificate
+/// in the trust store, unless it's empty.
+pub fn chain_problems(
+  certs: &[CapturedX509Certificate],
+  cert: &CapturedX509Certificate,
+  time: chrono::DateTime<chrono::Utc>,
+  trust_store: &[CapturedX509Certificate],
+) -> Result<Vec<String>> {
+  let mut problems = Vec::new();
+  let mut current = cert;
+
+  loop {
+    if !current.time_constraints_valid(Some(time)) {
+      problems.push(format!(
+        "The certificate {} isn't valid at the signing time",
+        parse_name(current.subject_name())?
+      ));
+    }
+
+    if current.subject_is_issuer() {
+      break;
+    }
+
+    let Some(issuer) = certs.iter().find(|cert| {
+      cert.subject_name() == current.issuer_name()
+        && cert.constructed_data() != current.constructed_data()
+    }) else {
+      break;
+    };
+
+    if current.verify_signed_by_certificate(issuer).is_err() {
+      problems.push(format!(
+        "The certificate {} isn't signed by {}",
+        parse_name(current.subject_name())?,
+        parse_name(issuer.subject_name())?
+      ));
+      break;
+    }
+
+    current = issuer;
+  }
+
+  if !trust_store.is_empty()
+    && !trust_store.iter().any(|trusted| {
+      trusted.constructed_data() == current.constructed_data()
+        || (trusted.subject_name() == current.issuer_name()
+          && current.verify_signed_by_certificate(trusted).is_ok())
+    })
+  {
+    problems.push(format!(
+      "The certificate {} isn't issued by a certificate in the trust store",
+      parse_name(current.subject_name())?
+    ));
+  }
+
+  Ok(problems)
+}
+
+/// Certificates expiring within this many days are warned about.
+const EXPIRY_WARNING_DAYS: i64 = 30;
+
+/// Helper function to find what doesn't make signed data invalid but is likely to cause trouble
+/// with validators, which is also logged.
+///
+/// A missing issuer is only warned about for the signing certificate, because the root of a
+/// chain is usually left out on purpose and can't be told apart from a missing intermediate.
+fn signing_warnings(
+  certs: &[CapturedX509Certificate],
+  cert: &CapturedX509Certificate,
+  signer_info: &cms::SignerInfo,
+) -> Result<Vec<String>> {
+  let mut warnings = Vec::new();
+
+  let remaining = cert.validity_not_after() - chrono::Utc::now();
+
+  if remaining > chrono::Duration::zero() && remaining < chrono::Duration::days(EXPIRY_WARNING_DAYS)
+  {
+    warnings.push(format!(
+      "The certificate {} expires in {} days",
+      parse_name(cert.subject_name())?,
+      remaining.num_days()
+    ));
+  }
+
+  let has_issuer = |certs: &[CapturedX509Certificate]| {
+    certs.iter().any(|issuer| {
+      issuer.subject_name() == cert.issuer_name()
+        && issuer.constructed_data() != cert.constructed_data()
+    })
+  };
+
+  if !cert.subject_is_issuer() && !has_issuer(certs) && !has_issuer(&defaults::trust_store()) {
+    warnings.push(format!(
+      "The issuer {} of the signing certificate isn't included",
+      parse_name(cert.issuer_name())?
+    ));
+  }
+
+  for (oid, values) in signer_info.unsigned_attribute_values()? {
+    if oid != rfc3161::OID_TIME_STAMP_TOKEN {
+      continue;
+    }
+
+    for token in values {
+      if cms::SignedData::decode(token.as_slice())?
+        .x509_certificates()?
+        .is_empty()
+      {
+        warnings.push(String::from(
+          "The timestamp token doesn't include the certificate of the timestamp server",
+        ));
+      }
+    }
+  }
+
+  for warning in &warnings {
+    tracing::warn!(warning, "Signature warning");
+  }
+
+  Ok(warnings)
+}
+
+/// Helper function to get the first signer info of signed data.
+fn first_signer_info(signed_data: &cms::SignedData) -> Result<&cms::SignerInfo> {
+  signed_data
+    .signer_infos
+    .first()
+    .ok_or(CmsError::SignedDataParseError)
+}
+
+/// Helper function to find the certificate of a signer among the certificates of signed data.
+pub fn signer_certificate(
+  certs: &[CapturedX509Certificate],
+  signer_info: &cms::SignerInfo,
+) -> Result<CapturedX509Certificate> {
+  signer_info
+    .find_certificate(certs)?
+    .cloned()
+    .ok_or(CmsError::NoCertificate)
+}
+
+/// Helper function to decode the signing time attribute of a signer, if it has one.
+pub fn signer_signing_time(
+  signer_info: &cms::SignerInfo,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+  signer_info
+    .signed_attribute_values()?
+    .into_iter()
+    .find(|(oid, _)| *oid == rfc5652::OID_SIGNING_TIME)
+    .and_then(|(_, values)| values.into_iter().next())
+    .map(|value| {
+      bcder::Mode::Der
+        .decode(value.as_slice(), Time::take_from)
+        .map(chrono::DateTime::<chrono::Utc>::from)
+        .map_err(|error| CmsError::SignedDataParseError.caused_by(error))
+    })
+    .transpose()
+}
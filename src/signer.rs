@@ -0,0 +1,18 @@
+pub use pdf_sign_core::signer_backend::{
+  backend_names, register_backend, SignerBackend, SignerInput, SignerKey, SignerOptions,
+};
+
+use pdf_sign_core::signer_backend;
+
+use crate::errors::CmsError;
+use crate::js_signer::JsBackend;
+
+/// Create a key with the signer registered under `name`, preferring signers registered from JS
+/// on this thread over the backends of the core, which include the built-in `privateKey`, `p12`
+/// and `gcloud` backends.
+pub fn create_signer(name: &str, input: SignerInput) -> Result<SignerKey, CmsError> {
+  match JsBackend::find(name) {
+    Some(backend) => backend.create(input),
+    None => signer_backend::create_signer(name, input),
+  }
+}
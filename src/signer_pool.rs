@@ -0,0 +1,266 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+use cryptographic_message_syntax::Bytes;
+use rsa::pkcs8::der::zeroize::Zeroizing;
+use x509_certificate::{
+  CapturedX509Certificate, KeyAlgorithm, KeyInfoSigner, Sign, Signature, SignatureAlgorithm,
+  Signer, X509CertificateError,
+};
+
+use crate::certificates::LazyCertificate;
+use crate::errors::CmsError;
+use crate::signer::SignerOptions;
+
+/// Number of consecutive failures after which a member is taken out of rotation.
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+/// How long an unhealthy member stays out of rotation before it is tried again.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// A key in a pool, along with the certificate chain it signs for.
+pub struct SignerPoolMember {
+  cert: CapturedX509Certificate,
+  pub certs: Vec<LazyCertificate>,
+  pub signer: String,
+  pub signer_options: SignerOptions,
+  health: Mutex<MemberHealth>,
+}
+
+#[derive(Default)]
+struct MemberHealth {
+  consecutive_failures: u32,
+  unhealthy_until: Option<Instant>,
+}
+
+impl SignerPoolMember {
+  pub fn new(
+    certs: Vec<LazyCertificate>,
+    signer: String,
+    signer_options: SignerOptions,
+  ) -> Result<Self, CmsError> {
+    let cert = certs
+      .first()
+      .ok_or(CmsError::NoCertificate)?
+      .parsed()?
+      .clone();
+
+    Ok(Self {
+      cert,
+      certs,
+      signer,
+      signer_options,
+      health: Mutex::default(),
+    })
+  }
+
+  /// The signing certificate, which is always the first in the chain.
+  pub fn cert(&self) -> &CapturedX509Certificate {
+    &self.cert
+  }
+
+  fn unhealthy_until(&self, now: Instant) -> Option<Instant> {
+    self
+      .health
+      .lock()
+      .unwrap_or_else(|err| err.into_inner())
+      .unhealthy_until
+      .filter(|until| *until > now)
+  }
+}
+
+pub struct MemberStatus {
+  pub healthy: bool,
+  pub consecutive_failures: u32,
+}
+
+/// Distributes signing requests round-robin across members, skipping unhealthy ones.
+pub struct SignerPool {
+  members: Vec<SignerPoolMember>,
+  next: AtomicUsize,
+  failure_threshold: u32,
+  cooldown: Duration,
+}
+
+impl SignerPool {
+  pub fn new(
+    members: Vec<SignerPoolMember>,
+    failure_threshold: u32,
+    cooldown: Duration,
+  ) -> Result<Self, CmsError> {
+    if members.is_empty() {
+      return Err(CmsError::EmptySignerPool);
+    }
+
+    Ok(Self {
+      members,
+      next: AtomicUsize::new(0),
+      failure_threshold: failure_threshold.max(1),
+      cooldown,
+    })
+  }
+
+  /// Members in the order they should be tried for the next request.
+  ///
+  /// Healthy members, starting at the round-robin cursor. Members in their cooldown are skipped,
+  /// unless every member is, in which case they are tried soonest to recover first, so a request
+  /// is still attempted.
+  pub fn candidates(&self) -> Vec<(usize, &SignerPoolMember)> {
+    let now = Instant::now();
+    let start = self.next.fetch_add(1, Ordering::Relaxed);
+    let len = self.members.len();
+
+    let (healthy, mut unhealthy): (Vec<_>, Vec<_>) = (0..len)
+      .map(|offset| (start + offset) % len)
+      .map(|index| (index, self.members[index].unhealthy_until(now)))
+      .partition(|(_, until)| until.is_none());
+
+    let candidates = if healthy.is_empty() {
+      unhealthy.sort_by_key(|(_, until)| *until);
+      unhealthy
+    } else {
+      healthy
+    };
+
+    candidates
+      .into_iter()
+      .map(|(index, _)| (index, &self.members[index]))
+      .collect()
+  }
+
+  pub fn record_success(&self, index: usize) {
+    let mut health = self.members[index]
+      .health
+      .lock()
+      .unwrap_or_else(|err| err.into_inner());
+
+    *health = MemberHealth::default();
+  }
+
+  pub fn record_failure(&self, index: usize) {
+    let mut health = self.members[index]
+      .health
+      .lock()
+      .unwrap_or_else(|err| err.into_inner());
+
+    health.consecutive_failures += 1;
+
+    if health.consecutive_failures >= self.failure_threshold {
+      health.unhealthy_until = Some(Instant::now() + self.cooldown);
+    }
+  }
+
+  pub fn status(&self) -> Vec<MemberStatus> {
+    let now = Instant::now();
+
+    self
+      .members
+      .iter()
+      .map(|member| MemberStatus {
+        healthy: member.unhealthy_until(now).is_none(),
+        consecutive_failures: member
+          .health
+          .lock()
+          .unwrap_or_else(|err| err.into_inner())
+          .consecutive_failures,
+      })
+      .collect()
+  }
+}
+
+fn pools() -> &'static RwLock<HashMap<String, Arc<SignerPool>>> {
+  static POOLS: OnceLock<RwLock<HashMap<String, Arc<SignerPool>>>> = OnceLock::new();
+
+  POOLS.get_or_init(RwLock::default)
+}
+
+/// Register a pool under `name`, replacing any pool previously registered with it.
+pub fn register_pool(name: String, pool: SignerPool) {
+  pools()
+    .write()
+    .unwrap_or_else(|err| err.into_inner())
+    .insert(name, Arc::new(pool));
+}
+
+/// Remove the pool registered under `name`, returning whether one was registered.
+pub fn unregister_pool(name: &str) -> bool {
+  pools()
+    .write()
+    .unwrap_or_else(|err| err.into_inner())
+    .remove(name)
+    .is_some()
+}
+
+pub fn find_pool(name: &str) -> Result<Arc<SignerPool>, CmsError> {
+  pools()
+    .read()
+    .unwrap_or_else(|err| err.into_inner())
+    .get(name)
+    .cloned()
+    .ok_or_else(|| CmsError::UnknownSignerPool.with_detail("pool", name))
+}
+
+/// Wraps a signer to tell key failures apart from other failures while building the CMS.
+pub struct TrackedSigner<'a> {
+  inner: &'a dyn KeyInfoSigner,
+  failed: Cell<bool>,
+}
+
+impl<'a> TrackedSigner<'a> {
+  pub fn new(inner: &'a dyn KeyInfoSigner) -> Self {
+    Self {
+      inner,
+      failed: Cell::new(false),
+    }
+  }
+
+  /// Whether the wrapped signer failed to produce a signature.
+  pub fn failed(&self) -> bool {
+    self.failed.get()
+  }
+}
+
+impl KeyInfoSigner for TrackedSigner<'_> {}
+
+impl Sign for TrackedSigner<'_> {
+  fn sign(&self, message: &[u8]) -> Result<(Vec<u8>, SignatureAlgorithm), X509CertificateError> {
+    let signature = self.try_sign(message)?;
+    let algorithm = self.signature_algorithm()?;
+
+    Ok((signature.into(), algorithm))
+  }
+
+  fn key_algorithm(&self) -> Option<KeyAlgorithm> {
+    self.inner.key_algorithm()
+  }
+
+  fn signature_algorithm(&self) -> Result<SignatureAlgorithm, X509CertificateError> {
+    self.inner.signature_algorithm()
+  }
+
+  fn private_key_data(&self) -> Option<Zeroizing<Vec<u8>>> {
+    self.inner.private_key_data()
+  }
+
+  fn public_key_data(&self) -> Bytes {
+    self.inner.public_key_data()
+  }
+
+  fn rsa_primes(
+    &self,
+  ) -> Result<Option<(Zeroizing<Vec<u8>>, Zeroizing<Vec<u8>>)>, X509CertificateError> {
+    self.inner.rsa_primes()
+  }
+}
+
+impl Signer<Signature> for TrackedSigner<'_> {
+  fn try_sign(&self, msg: &[u8]) -> Result<Signature, signature::Error> {
+    self
+      .inner
+      .try_sign(msg)
+      .inspect_err(|_| self.failed.set(true))
+  }
+}
@@ -0,0 +1,2146 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::JsFunction;
+use napi_derive::napi;
+
+use cryptographic_message_syntax::{asn1::rfc5652, Bytes, Oid};
+use pdf_sign_core::batch_signer::BatchSigner;
+use pdf_sign_core::certificates::LazyCertificate;
+use pdf_sign_core::input::{self, InputFormat};
+use pdf_sign_core::keys::{self, P12Entry};
+use pdf_sign_core::limiter::Limiter;
+use pdf_sign_core::placeholder_signer::PlaceholderSigner;
+use pdf_sign_core::signing::{self, SignatureScheme, SignedDataBuilder, SignerBuilder};
+use pdf_sign_core::{attributes, compliance, credentials, progress, revocation, validation};
+use pdf_sign_core::{
+  digest_algorithm_name, parse_cms_version, parse_digest_algorithm, parse_signing_time,
+};
+use x509_certificate::{
+  CapturedX509Certificate, DigestAlgorithm, KeyInfoSigner, Sign, SignatureAlgorithm,
+};
+use zeroize::Zeroizing;
+
+use crate::containers::{AsicSignOptions, JadesSignOptions, XadesSignOptions};
+use crate::errors::CmsError;
+use crate::results::{signing_output, DryRunReport, Operation, Output, SigningResult};
+use crate::signer::{SignerInput, SignerKey, SignerOptions};
+use crate::signer_pool::{self, SignerPool, SignerPoolMember, TrackedSigner};
+use crate::{
+  defaults, js_result, js_signer, key_handles, parse_name, parsed_certificate, signer,
+  BlockingTask, ContentHints, CustomAttribute, ParsedCertificate, Result, RevocationValues,
+  SignerLocation, SmimeCapability,
+};
+
+/// The options of a signature and its output, shared by the signing functions, which take them
+/// in the same object as the options of their key.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SigningOptions {
+  pub content: Option<Buffer>,
+  pub content_path: Option<String>,
+  /// The precomputed digest of the content, computed with the digest algorithm the key signs
+  /// with: SHA-256 for RSA and ECDSA P-256 keys, SHA-384 for P-384 and SHA-512 for Ed25519.
+  pub digest: Option<Buffer>,
+  pub digest_algorithm: Option<String>,
+  pub signing_time: Option<String>,
+  pub timestamp_server: Option<String>,
+  pub commitment_type: Option<String>,
+  pub signer_location: Option<SignerLocation>,
+  pub content_hints: Option<ContentHints>,
+  pub seal: Option<bool>,
+  pub smime_capabilities: Option<Vec<SmimeCapability>>,
+  pub signed_attributes: Option<Vec<CustomAttribute>>,
+  pub unsigned_attributes: Option<Vec<CustomAttribute>>,
+  pub attached: Option<bool>,
+  pub include_signing_time: Option<bool>,
+  pub null_parameters: Option<bool>,
+  pub signed_data_version: Option<u32>,
+  pub signer_info_version: Option<u32>,
+  pub cert_values: Option<Buffer>,
+  pub revocation_values: Option<RevocationValues>,
+  pub embed_revocation_info: Option<bool>,
+  pub certificate_refs: Option<Buffer>,
+  pub revocation_refs: Option<RevocationValues>,
+  pub output_path: Option<String>,
+  pub max_size: Option<u32>,
+  pub detailed: Option<bool>,
+  pub debug_trace: Option<bool>,
+}
+
+/// The options of a signing function with its [SigningOptions], both read from the one object
+/// it's called with.
+pub struct WithSigningOptions<T> {
+  pub options: T,
+  pub signing: SigningOptions,
+}
+
+impl<T: FromNapiValue> FromNapiValue for WithSigningOptions<T> {
+  unsafe fn from_napi_value(env: sys::napi_env, value: sys::napi_value) -> napi::Result<Self> {
+    Ok(Self {
+      options: T::from_napi_value(env, value)?,
+      signing: SigningOptions::from_napi_value(env, value)?,
+    })
+  }
+}
+
+impl SigningOptions {
+  /// Apply the configured defaults and check the content, digest and timestamp server options,
+  /// before any key is read.
+  pub fn with_defaults(mut self) -> Result<Self> {
+    self.timestamp_server = defaults::timestamp_server(self.timestamp_server.take());
+    self.digest_algorithm = defaults::digest_algorithm(self.digest_algorithm.take());
+
+    validation::signing_options(
+      self.content.as_deref(),
+      self.content_path.as_deref(),
+      self.digest.as_deref(),
+      self.digest_algorithm.as_deref(),
+      self.timestamp_server.as_deref(),
+    )?;
+
+    Ok(self)
+  }
+
+  /// Leave out what a dry run doesn't do, returning whether it's offline.
+  ///
+  /// Dry runs sign in memory and write nothing, reaching remote services only if asked to.
+  fn dry_run(&mut self, dry_run: Option<bool>, check_reachability: Option<bool>) -> bool {
+    let dry_run = dry_run.unwrap_or(false);
+    let offline = dry_run && !check_reachability.unwrap_or(false);
+
+    if offline {
+      self.timestamp_server = None;
+      self.embed_revocation_info = None;
+    }
+
+    if dry_run {
+      self.output_path = None;
+    }
+
+    offline
+  }
+
+  /// What the signing function returns, see [Output::new].
+  fn output(&self, dry_run: Option<bool>, placeholder: bool) -> Output {
+    Output::new(self.detailed, dry_run, self.debug_trace, placeholder)
+  }
+
+  /// A signer of the key with the signed attributes and timestamp server of the options.
+  pub fn signer<'a>(
+    &self,
+    signing_key: &'a dyn KeyInfoSigner,
+    cert: &CapturedX509Certificate,
+  ) -> Result<SignerBuilder<'a>> {
+    // A seal is created by an organization rather than a person, which its certificate states.
+    let commitment_type = match self.seal.unwrap_or(false) {
+      true => {
+        if !compliance::is_seal_certificate(cert)? {
+          return Err(
+            CmsError::NotSealCertificate.with_detail("subject", parse_name(cert.subject_name())?),
+          );
+        }
+
+        if self.signer_location.is_some() {
+          return Err(CmsError::InvalidOption(
+            "signerLocation",
+            String::from("a seal is created by an organization, not by a person at a location"),
+          ));
+        }
+
+        Some(
+          self
+            .commitment_type
+            .clone()
+            .unwrap_or_else(|| String::from("proofOfOrigin")),
+        )
+      }
+      false => self.commitment_type.clone(),
+    };
+
+    let mut signed_attribute_values = vec![(
+      attributes::oid(attributes::OID_SIGNING_CERTIFICATE_V2),
+      vec![attributes::signing_certificate_v2(cert)],
+    )];
+
+    if let Some(commitment_type) = commitment_type {
+      signed_attribute_values.push((
+        attributes::oid(attributes::OID_COMMITMENT_TYPE_INDICATION),
+        vec![attributes::commitment_type_indication(&commitment_type)?],
+      ));
+    }
+
+    if let Some(signer_location) = &self.signer_location {
+      signed_attribute_values.push((
+        attributes::oid(attributes::OID_SIGNER_LOCATION),
+        vec![attributes::signer_location(
+          signer_location.country_name.as_deref(),
+          signer_location.locality_name.as_deref(),
+          signer_location.postal_address.as_deref(),
+        )?],
+      ));
+    }
+
+    if let Some(content_hints) = &self.content_hints {
+      signed_attribute_values.push((
+        attributes::oid(attributes::OID_CONTENT_HINTS),
+        vec![attributes::content_hints(
+          content_hints.description.as_deref(),
+          content_hints.content_type.as_deref(),
+        )?],
+      ));
+    }
+
+    if let Some(smime_capabilities) = &self.smime_capabilities {
+      signed_attribute_values.push((
+        attributes::oid(attributes::OID_SMIME_CAPABILITIES),
+        vec![attributes::smime_capabilities(
+          smime_capabilities
+            .iter()
+            .map(|capability| (capability.oid.as_str(), capability.parameters.as_deref())),
+        )?],
+      ));
+    }
+
+    if let Some(signed_attributes) = &self.signed_attributes {
+      attributes::add_custom_attributes(
+        &mut signed_attribute_values,
+        signed_attributes
+          .iter()
+          .map(|attribute| (attribute.oid.as_str(), attribute.value.as_ref())),
+        &attributes::RESERVED_SIGNED_ATTRIBUTES,
+      )?;
+    }
+
+    let mut signer = SignerBuilder::new(signing_key, cert.clone());
+
+    for (oid, values) in signed_attribute_values {
+      signer = signer.signed_attribute(oid, values);
+    }
+
+    if let Some(timestamp_server) = &self.timestamp_server {
+      signer = signer
+        .time_stamp_url(timestamp_server)
+        .map_err(|error| CmsError::TimestampServerParseError.caused_by(error))?;
+    }
+
+    Ok(signer)
+  }
+
+  /// Convert the options into the builder of the core crate for signed data signed by
+  /// `signers`, including the certificates `certs`.
+  pub fn into_core<'a>(
+    self,
+    signers: Vec<SignerBuilder<'a>>,
+    certs: Vec<LazyCertificate>,
+  ) -> Result<SignedDataOutput<'a>> {
+    let SigningOptions {
+      content,
+      content_path,
+      digest,
+      digest_algorithm,
+      signing_time,
+      timestamp_server: _,
+      commitment_type: _,
+      signer_location: _,
+      content_hints: _,
+      seal: _,
+      smime_capabilities: _,
+      signed_attributes: _,
+      unsigned_attributes,
+      attached,
+      include_signing_time,
+      null_parameters,
+      signed_data_version,
+      signer_info_version,
+      cert_values,
+      revocation_values,
+      embed_revocation_info,
+      certificate_refs,
+      revocation_refs,
+      output_path,
+      max_size,
+      detailed: _,
+      debug_trace: _,
+    } = self;
+
+    // Each key signs with its own digest algorithm, which a digest algorithm given for the call
+    // or by default must match, as must that of a digest, SHA-256 unless given.
+    if digest_algorithm.is_some() || digest.is_some() {
+      let requested = parse_digest_algorithm(digest_algorithm.as_deref())?;
+
+      for signer in &signers {
+        let digest_algorithm = signer.digest_algorithm()?;
+
+        if digest_algorithm != requested {
+          return Err(CmsError::InvalidOption(
+            "digestAlgorithm",
+            format!(
+              "the signing key signs with {}, not {}",
+              digest_algorithm_name(digest_algorithm),
+              digest_algorithm_name(requested)
+            ),
+          ));
+        }
+      }
+    }
+
+    let signing_time = parse_signing_time(signing_time);
+
+    let mut builder = SignedDataBuilder::default()
+      .content_type(Oid(Bytes::from(rfc5652::OID_ID_DATA.as_ref())))
+      .signing_time(
+        include_signing_time
+          .unwrap_or(true)
+          .then(|| signing_time.into()),
+      )
+      .null_parameters(null_parameters.unwrap_or(true))
+      .version(parse_cms_version(signed_data_version)?)
+      .signer_info_version(parse_cms_version(signer_info_version)?);
+
+    // The revocation data of each signer's chain is signed with it, so the signature validates
+    // long after the certificates expire or are revoked. The chains are found among the
+    // certificates of the signed data and the trust store.
+    let issuers = match embed_revocation_info.unwrap_or(false) {
+      true => certs
+        .iter()
+        .map(|cert| cert.parsed().cloned())
+        .chain(defaults::trust_store().into_iter().map(Ok))
+        .collect::<Result<Vec<_>>>()?,
+      false => Vec::new(),
+    };
+
+    for mut signer in signers {
+      if embed_revocation_info.unwrap_or(false) {
+        let info = revocation::fetch(signer.signing_certificate(), &issuers)?;
+
+        // A self-signed signing certificate has no revocation data to embed.
+        if !info.crls.is_empty() || !info.ocsp_responses.is_empty() {
+          signer = signer.signed_attribute(
+            attributes::oid(attributes::OID_REVOCATION_INFO_ARCHIVAL),
+            vec![attributes::revocation_info_archival(
+              &info.crls,
+              &info.ocsp_responses,
+            )?],
+          );
+        }
+      }
+
+      builder = builder.signer(signer);
+    }
+
+    // Large content can be read from a file rather than passed through JavaScript. The Buffer is
+    // shared with eContent rather than copied, so large content isn't held twice.
+    builder = match (content, content_path, digest, attached.unwrap_or(false)) {
+      (Some(content), None, None, true) => builder.content_inline(Bytes::from_owner(content)),
+      (Some(content), None, None, false) => builder.content_external(Bytes::from_owner(content)),
+      (None, Some(content_path), None, true) => {
+        builder.content_inline(std::fs::read(&content_path).map_err(|error| {
+          CmsError::FileReadError
+            .caused_by(error)
+            .with_detail("path", &content_path)
+        })?)
+      }
+      // Detached signatures only need the digest, so the file is hashed as it's read.
+      (None, Some(content_path), None, false) => builder.content_file(content_path),
+      (None, None, Some(digest), false) => {
+        let digest_algorithm = parse_digest_algorithm(digest_algorithm.as_deref())?;
+
+        signing::check_digest(&digest, digest_algorithm)?;
+
+        builder.content_digest(digest_algorithm, digest.to_vec())
+      }
+      _ => return Err(CmsError::InvalidContent),
+    };
+
+    builder = builder.certificates(certs.into_iter());
+
+    let mut unsigned_attribute_values = Vec::new();
+
+    if let Some(cert_values) = cert_values {
+      let certs = input::parsed_certificates("certValues", &cert_values, InputFormat::Auto)?;
+
+      unsigned_attribute_values.push((
+        attributes::oid(attributes::OID_CERT_VALUES),
+        vec![attributes::certificate_values(&certs)?],
+      ));
+    }
+
+    if let Some(RevocationValues {
+      crls,
+      ocsp_responses,
+    }) = revocation_values
+    {
+      unsigned_attribute_values.push((
+        attributes::oid(attributes::OID_REVOCATION_VALUES),
+        vec![attributes::revocation_values(
+          &crls.unwrap_or_default(),
+          &ocsp_responses.unwrap_or_default(),
+        )?],
+      ));
+    }
+
+    if let Some(certificate_refs) = certificate_refs {
+      let certs =
+        input::parsed_certificates("certificateRefs", &certificate_refs, InputFormat::Auto)?;
+
+      unsigned_attribute_values.push((
+        attributes::oid(attributes::OID_CERTIFICATE_REFS),
+        vec![attributes::complete_certificate_refs(&certs)],
+      ));
+    }
+
+    if let Some(RevocationValues {
+      crls,
+      ocsp_responses,
+    }) = revocation_refs
+    {
+      unsigned_attribute_values.push((
+        attributes::oid(attributes::OID_REVOCATION_REFS),
+        vec![attributes::complete_revocation_refs(
+          &crls.unwrap_or_default(),
+          &ocsp_responses.unwrap_or_default(),
+        )?],
+      ));
+    }
+
+    attributes::add_custom_attributes(
+      &mut unsigned_attribute_values,
+      unsigned_attributes
+        .iter()
+        .flatten()
+        .map(|attribute| (attribute.oid.as_str(), attribute.value.as_ref())),
+      &[],
+    )?;
+
+    // The unsigned attributes are encoded with the signed data, rather than added to it after.
+    Ok(SignedDataOutput {
+      builder: builder.unsigned_attributes(unsigned_attribute_values),
+      max_size,
+      output_path,
+    })
+  }
+}
+
+/// Signed data to build with the core crate, with the size it must fit and where to write it.
+pub struct SignedDataOutput<'a> {
+  builder: SignedDataBuilder<'a>,
+  max_size: Option<u32>,
+  output_path: Option<String>,
+}
+
+impl SignedDataOutput<'_> {
+  /// Build and sign the signed data, checking its size and writing it to the output path, if
+  /// any.
+  pub fn build(self) -> Result<Buffer> {
+    let signed_data = Buffer::from(self.builder.build_der()?);
+
+    tracing::info!(size = signed_data.len(), "Created signed data");
+
+    write_signed_data(signed_data, self.max_size, self.output_path)
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignWithPrivateKeyOptions {
+  pub cert: Buffer,
+  pub private_key: Buffer,
+  pub private_key_password: Option<String>,
+  pub format: Option<String>,
+  pub signature_scheme: Option<String>,
+  pub dry_run: Option<bool>,
+  pub check_reachability: Option<bool>,
+}
+
+/// Sign data with the private key.
+#[napi(js_name = "signWithPrivateKey")]
+pub fn sign_with_private_key_js(
+  env: Env,
+  options: WithSigningOptions<SignWithPrivateKeyOptions>,
+) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let output = options.signing.output(options.options.dry_run, false);
+  js_result(
+    env,
+    signing_output(
+      Operation::new("signWithPrivateKey", "privateKey"),
+      || sign_with_private_key(options),
+      output,
+    ),
+  )
+}
+
+fn sign_with_private_key(options: WithSigningOptions<SignWithPrivateKeyOptions>) -> Result<Buffer> {
+  let WithSigningOptions {
+    options:
+      SignWithPrivateKeyOptions {
+        cert,
+        private_key,
+        private_key_password,
+        format,
+        signature_scheme,
+        dry_run,
+        check_reachability,
+      },
+    signing,
+  } = options;
+
+  let mut signing = signing.with_defaults()?;
+
+  let signature_scheme = signature_scheme
+    .map(|name| SignatureScheme::parse("signatureScheme", &name))
+    .transpose()?
+    .unwrap_or_default();
+  let format = format
+    .map(|name| InputFormat::parse("format", &name))
+    .transpose()?
+    .unwrap_or_default();
+  validation::encoded("cert", &cert, "a certificate", format)?;
+
+  signing.dry_run(dry_run, check_reachability);
+
+  let SignerKey { signing_key, certs } = signer::create_signer(
+    "privateKey",
+    private_key_input(
+      private_key,
+      private_key_password,
+      format,
+      input::certificates("cert", &cert, format)?,
+    ),
+  )?;
+
+  sign_with_parsed_key(signing_key.as_ref(), certs, None, signature_scheme, signing)
+}
+
+/// The input of the `privateKey` backend.
+fn private_key_input(
+  private_key: Buffer,
+  private_key_password: Option<String>,
+  format: InputFormat,
+  certs: Vec<LazyCertificate>,
+) -> SignerInput {
+  let mut options = SignerOptions::new();
+
+  if let Some(private_key_password) = private_key_password {
+    options.insert(String::from("privateKeyPassword"), private_key_password);
+  }
+
+  // The format is parsed already, so the backend reads the key the same way.
+  match format {
+    InputFormat::Pem => options.insert(String::from("format"), String::from("pem")),
+    InputFormat::Der => options.insert(String::from("format"), String::from("der")),
+    InputFormat::Auto => None,
+  };
+
+  SignerInput {
+    options,
+    data: HashMap::from([("privateKey", Zeroizing::new(private_key.to_vec()))]),
+    certs,
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignWithP12Options {
+  pub cert: Option<Buffer>,
+  pub cert_path: Option<String>,
+  pub cert_source: Option<String>,
+  pub password: Option<String>,
+  pub password_source: Option<String>,
+  pub friendly_name: Option<String>,
+  pub thumbprint: Option<String>,
+  pub signature_scheme: Option<String>,
+  pub dry_run: Option<bool>,
+  pub check_reachability: Option<bool>,
+}
+
+/// Sign data with a P12 container.
+#[napi(js_name = "signWithP12")]
+pub fn sign_with_p12_js(
+  env: Env,
+  options: WithSigningOptions<SignWithP12Options>,
+) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let output = options.signing.output(options.options.dry_run, false);
+  js_result(
+    env,
+    signing_output(
+      Operation::new("signWithP12", "p12"),
+      || sign_with_p12(options),
+      output,
+    ),
+  )
+}
+
+fn sign_with_p12(options: WithSigningOptions<SignWithP12Options>) -> Result<Buffer> {
+  let WithSigningOptions {
+    options:
+      SignWithP12Options {
+        cert,
+        cert_path,
+        cert_source,
+        password,
+        password_source,
+        friendly_name,
+        thumbprint,
+        signature_scheme,
+        dry_run,
+        check_reachability,
+      },
+    signing,
+  } = options;
+
+  let mut signing = signing.with_defaults()?;
+
+  let signature_scheme = signature_scheme
+    .map(|name| SignatureScheme::parse("signatureScheme", &name))
+    .transpose()?
+    .unwrap_or_default();
+
+  signing.dry_run(dry_run, check_reachability);
+
+  let P12Input {
+    p12: cert,
+    password,
+  } = P12Input::read(
+    ["cert", "certPath", "certSource"],
+    (cert, cert_path, cert_source),
+    (password, password_source),
+    true,
+  )?
+  .ok_or_else(|| {
+    CmsError::InvalidOption(
+      "cert",
+      format!(
+        "expected one of cert, certPath or certSource, or {} to be set",
+        credentials::P12_BASE64_VAR
+      ),
+    )
+  })?;
+
+  let SignerKey { signing_key, certs } =
+    signer::create_signer("p12", p12_input(cert, password, friendly_name, thumbprint))?;
+
+  sign_with_parsed_key(signing_key.as_ref(), certs, None, signature_scheme, signing)
+}
+
+/// The input of the `p12` backend, whose key comes with the certificates of the container.
+fn p12_input(
+  p12: Zeroizing<Vec<u8>>,
+  password: Zeroizing<String>,
+  friendly_name: Option<String>,
+  thumbprint: Option<String>,
+) -> SignerInput {
+  let options = [("friendlyName", friendly_name), ("thumbprint", thumbprint)]
+    .into_iter()
+    .filter_map(|(name, value)| Some((String::from(name), value?)))
+    .collect();
+
+  SignerInput {
+    options,
+    data: HashMap::from([
+      ("p12", p12),
+      ("password", Zeroizing::new(password.as_bytes().to_vec())),
+    ]),
+    certs: Vec::new(),
+  }
+}
+
+/// The input of the `gcloud` backend.
+fn gcloud_input(key_path: String, certs: Vec<LazyCertificate>) -> SignerInput {
+  SignerInput {
+    options: SignerOptions::from([(String::from("keyPath"), key_path)]),
+    certs,
+    ..Default::default()
+  }
+}
+
+/// A P12 container and its password, given as a Buffer, a path or a credential source.
+struct P12Input {
+  p12: Zeroizing<Vec<u8>>,
+  password: Zeroizing<String>,
+}
+
+impl P12Input {
+  /// Read the P12 container of the options named `names`, or of the `PDF_SIGN_P12_BASE64`
+  /// environment variable if none is given and `from_env` is set. `None` if there is neither.
+  fn read(
+    names: [&'static str; 3],
+    p12: (Option<Buffer>, Option<String>, Option<String>),
+    password: (Option<String>, Option<String>),
+    from_env: bool,
+  ) -> Result<Option<Self>> {
+    let (p12, from_env) = match p12 {
+      (Some(p12), None, None) => {
+        validation::p12(names[0], &p12)?;
+        (Zeroizing::new(p12.to_vec()), false)
+      }
+      (None, Some(p12_path), None) => {
+        let p12 = std::fs::read(&p12_path).map_err(|error| {
+          CmsError::FileReadError
+            .caused_by(error)
+            .with_detail("path", &p12_path)
+        })?;
+        validation::p12(names[1], &p12)?;
+        (Zeroizing::new(p12), false)
+      }
+      (None, None, Some(source)) => {
+        let p12 = credentials::read(names[2], &source)?;
+        validation::p12(names[2], &p12)?;
+        (p12, false)
+      }
+      (None, None, None) => match credentials::p12_from_env()?.filter(|_| from_env) {
+        Some(p12) => {
+          validation::p12(credentials::P12_BASE64_VAR, &p12)?;
+          (p12, true)
+        }
+        None => return Ok(None),
+      },
+      _ => {
+        return Err(CmsError::InvalidOption(
+          names[0],
+          format!(
+            "expected only one of {}, {} or {}",
+            names[0], names[1], names[2]
+          ),
+        ))
+      }
+    };
+
+    let password = match password {
+      (Some(password), None) => Zeroizing::new(password),
+      (None, Some(source)) => credentials::read_string("passwordSource", &source)?,
+      (None, None) if from_env => credentials::p12_password_from_env()?,
+      (None, None) => Zeroizing::new(String::new()),
+      (Some(_), Some(_)) => {
+        return Err(CmsError::InvalidOption(
+          "passwordSource",
+          String::from("expected only one of password and passwordSource"),
+        ))
+      }
+    };
+
+    Ok(Some(Self { p12, password }))
+  }
+}
+
+/// A certificate of a P12 container, returned by `inspectP12`.
+#[napi(object)]
+pub struct P12EntryInfo {
+  pub friendly_name: Option<String>,
+  pub has_private_key: bool,
+  /// The hex SHA-1 thumbprint of the certificate, as Windows shows it.
+  pub thumbprint: String,
+  /// The hex SHA-256 fingerprint of the certificate.
+  pub fingerprint: String,
+  pub certificate: ParsedCertificate,
+}
+
+/// List the certificates of a P12 container with their friendly names and whether it has their
+/// private key, to choose the `friendlyName` or `thumbprint` to sign with.
+#[napi(js_name = "inspectP12")]
+pub fn inspect_p12_js(
+  env: Env,
+  p12: Buffer,
+  password: Option<String>,
+) -> napi::Result<Vec<P12EntryInfo>> {
+  js_result(env, inspect_p12(&p12, password.as_deref().unwrap_or("")))
+}
+
+fn inspect_p12(p12: &[u8], password: &str) -> Result<Vec<P12EntryInfo>> {
+  validation::p12("p12", p12)?;
+
+  keys::list_p12(p12, password)?
+    .into_iter()
+    .map(|entry| {
+      let P12Entry {
+        friendly_name,
+        certificate,
+        has_private_key,
+      } = entry;
+
+      let cert = CapturedX509Certificate::from_der(certificate)
+        .map_err(|error| CmsError::CertificateParseError.caused_by(error))?;
+
+      Ok(P12EntryInfo {
+        friendly_name,
+        has_private_key,
+        thumbprint: hex::encode(DigestAlgorithm::Sha1.digest_data(cert.constructed_data())),
+        fingerprint: hex::encode(DigestAlgorithm::Sha256.digest_data(cert.constructed_data())),
+        certificate: parsed_certificate(&cert)?,
+      })
+    })
+    .collect()
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignWithGCloudOptions {
+  pub cert: Buffer,
+  pub key_path: String,
+  pub format: Option<String>,
+  pub dry_run: Option<bool>,
+  pub check_reachability: Option<bool>,
+}
+
+/// Sign data with Google Cloud.
+#[napi(js_name = "signWithGCloud")]
+pub fn sign_with_gcloud_js(
+  env: Env,
+  options: WithSigningOptions<SignWithGCloudOptions>,
+) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let output = options.signing.output(
+    options.options.dry_run,
+    !options.options.check_reachability.unwrap_or(false),
+  );
+  js_result(
+    env,
+    signing_output(
+      Operation::new("signWithGCloud", "gcloud"),
+      || sign_with_gcloud(options),
+      output,
+    ),
+  )
+}
+
+fn sign_with_gcloud(options: WithSigningOptions<SignWithGCloudOptions>) -> Result<Buffer> {
+  let WithSigningOptions {
+    options:
+      SignWithGCloudOptions {
+        cert,
+        key_path,
+        format,
+        dry_run,
+        check_reachability,
+      },
+    signing,
+  } = options;
+
+  let mut signing = signing.with_defaults()?;
+
+  let format = format
+    .map(|name| InputFormat::parse("format", &name))
+    .transpose()?
+    .unwrap_or_default();
+  validation::encoded("cert", &cert, "a certificate", format)?;
+  validation::key_path("keyPath", &key_path)?;
+
+  let offline = signing.dry_run(dry_run, check_reachability);
+
+  let x509_certs = input::certificates("cert", &cert, format)?;
+
+  let cert = x509_certs
+    .first()
+    .ok_or(CmsError::NoCertificate)?
+    .parsed()?;
+
+  // Offline dry runs sign a placeholder of the size the key would produce.
+  let signing_key: Box<dyn KeyInfoSigner> = if offline {
+    tracing::debug!("Signing a placeholder instead of the Google Cloud key");
+    Box::new(PlaceholderSigner::new(cert)?)
+  } else {
+    signer::create_signer("gcloud", gcloud_input(key_path, x509_certs.clone()))?.signing_key
+  };
+
+  let signer = signing.signer(signing_key.as_ref(), cert)?;
+
+  signing.into_core(vec![signer], x509_certs)?.build()
+}
+
+/// A callback receiving the name of each stage of a signing operation as it begins.
+type ProgressCallback = ThreadsafeFunction<String, ErrorStrategy::Fatal>;
+
+/// A signing operation run on the libuv thread pool, for the async signing functions.
+pub struct SignTask {
+  operation: Operation,
+  sign: Option<Box<dyn FnOnce() -> Result<Buffer> + Send>>,
+  output: Output,
+  on_progress: Option<ProgressCallback>,
+}
+
+impl SignTask {
+  fn new(
+    operation: Operation,
+    sign: impl FnOnce() -> Result<Buffer> + Send + 'static,
+    output: Output,
+    on_progress: Option<ProgressCallback>,
+  ) -> Self {
+    Self {
+      operation,
+      sign: Some(Box::new(sign)),
+      output,
+      on_progress,
+    }
+  }
+}
+
+impl Task for SignTask {
+  type Output = Result<Either3<Buffer, SigningResult, DryRunReport>>;
+  type JsValue = Either3<Buffer, SigningResult, DryRunReport>;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let sign = self
+      .sign
+      .take()
+      .ok_or_else(|| napi::Error::from_reason("The signing task has already run"))?;
+
+    let operation = self.operation;
+    let output = self.output;
+
+    Ok(match self.on_progress.clone() {
+      Some(on_progress) => progress::with_reporter(
+        move |stage| {
+          on_progress.call(
+            stage.as_str().to_string(),
+            ThreadsafeFunctionCallMode::NonBlocking,
+          );
+        },
+        || signing_output(operation, sign, output),
+      ),
+      None => signing_output(operation, sign, output),
+    })
+  }
+
+  fn resolve(&mut self, env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    // Errors are created here rather than returned from `compute`, which has no env to create
+    // them with their code and cause.
+    js_result(env, output)
+  }
+}
+
+/// Sign data with the private key without blocking the event loop.
+#[napi]
+pub fn sign_with_private_key_async(
+  options: WithSigningOptions<SignWithPrivateKeyOptions>,
+  signal: Option<AbortSignal>,
+  on_progress: Option<ProgressCallback>,
+) -> AsyncTask<SignTask> {
+  let output = options.signing.output(options.options.dry_run, false);
+
+  AsyncTask::with_optional_signal(
+    SignTask::new(
+      Operation::new("signWithPrivateKeyAsync", "privateKey"),
+      move || sign_with_private_key(options),
+      output,
+      on_progress,
+    ),
+    signal,
+  )
+}
+
+/// Sign data with a P12 container without blocking the event loop.
+#[napi]
+pub fn sign_with_p12_async(
+  options: WithSigningOptions<SignWithP12Options>,
+  signal: Option<AbortSignal>,
+  on_progress: Option<ProgressCallback>,
+) -> AsyncTask<SignTask> {
+  let output = options.signing.output(options.options.dry_run, false);
+
+  AsyncTask::with_optional_signal(
+    SignTask::new(
+      Operation::new("signWithP12Async", "p12"),
+      move || sign_with_p12(options),
+      output,
+      on_progress,
+    ),
+    signal,
+  )
+}
+
+/// Sign data with Google Cloud without blocking the event loop.
+#[napi(js_name = "signWithGCloudAsync")]
+pub fn sign_with_gcloud_async(
+  options: WithSigningOptions<SignWithGCloudOptions>,
+  signal: Option<AbortSignal>,
+  on_progress: Option<ProgressCallback>,
+) -> AsyncTask<SignTask> {
+  let output = options.signing.output(
+    options.options.dry_run,
+    !options.options.check_reachability.unwrap_or(false),
+  );
+
+  AsyncTask::with_optional_signal(
+    SignTask::new(
+      Operation::new("signWithGCloudAsync", "gcloud"),
+      move || sign_with_gcloud(options),
+      output,
+      on_progress,
+    ),
+    signal,
+  )
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignerKeyOptions {
+  pub p12: Option<Buffer>,
+  pub p12_path: Option<String>,
+  pub p12_source: Option<String>,
+  pub password: Option<String>,
+  pub password_source: Option<String>,
+  pub friendly_name: Option<String>,
+  pub thumbprint: Option<String>,
+  pub cert: Option<Buffer>,
+  pub private_key: Option<Buffer>,
+  pub private_key_password: Option<String>,
+  pub format: Option<String>,
+  pub key_path: Option<String>,
+  pub max_concurrent_requests: Option<u32>,
+  pub max_requests_per_second: Option<f64>,
+}
+
+/// A key and its certificates, parsed once and reused for any number of signatures.
+///
+/// The key is a P12 container, a PEM encoded private key or a Google Cloud KMS key, whose
+/// client and connection are kept for later signatures. The requests of all signatures to the
+/// key and timestamp server share its limits.
+#[napi(js_name = "Signer")]
+#[derive(Clone)]
+pub struct ReusableSigner {
+  pub(crate) signing_key: Arc<dyn KeyInfoSigner + Send + Sync>,
+  pub(crate) certs: Vec<LazyCertificate>,
+  pub(crate) limiter: Option<Arc<Limiter>>,
+  /// The kind of key, for audit records.
+  pub(crate) backend: &'static str,
+}
+
+#[napi]
+impl ReusableSigner {
+  #[napi(constructor)]
+  pub fn new(env: Env, options: SignerKeyOptions) -> napi::Result<Self> {
+    js_result(env, Self::create(options))
+  }
+
+  /// Sign data with the key.
+  #[napi(js_name = "sign")]
+  pub fn sign_js(
+    &self,
+    env: Env,
+    options: SigningOptions,
+  ) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+    js_result(env, self.sign_output("signer.sign", options))
+  }
+
+  /// Sign data with the key without blocking the event loop.
+  #[napi]
+  pub fn sign_async(
+    &self,
+    options: SigningOptions,
+    signal: Option<AbortSignal>,
+    on_progress: Option<ProgressCallback>,
+  ) -> AsyncTask<SignTask> {
+    let output = options.output(None, false);
+    let signing_key = self.signing_key.clone();
+    let certs = self.certs.clone();
+    let limiter = self.limiter.clone();
+
+    AsyncTask::with_optional_signal(
+      SignTask::new(
+        Operation::new("signer.signAsync", self.backend),
+        move || sign_with_key(signing_key.as_ref(), certs, limiter, options),
+        output,
+        on_progress,
+      ),
+      signal,
+    )
+  }
+
+  /// Create a detached XAdES signature of content with the key.
+  #[napi(js_name = "signXades")]
+  pub fn sign_xades_js(&self, env: Env, options: XadesSignOptions) -> napi::Result<Buffer> {
+    js_result(env, self.sign_xades(options))
+  }
+
+  /// Create a JAdES signature of a payload with the key.
+  #[napi(js_name = "signJades")]
+  pub fn sign_jades_js(&self, env: Env, options: JadesSignOptions) -> napi::Result<Buffer> {
+    js_result(env, self.sign_jades(options))
+  }
+
+  /// Package files and a CAdES signature of them in an ASiC container with the key.
+  #[napi(js_name = "signAsic")]
+  pub fn sign_asic_js(&self, env: Env, options: AsicSignOptions) -> napi::Result<Buffer> {
+    js_result(env, self.sign_asic(options))
+  }
+
+  /// Create a detached XAdES signature of content with the key without blocking the event loop.
+  #[napi]
+  pub fn sign_xades_async(
+    &self,
+    options: XadesSignOptions,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<BlockingTask<Buffer>> {
+    let signer = self.clone();
+
+    AsyncTask::with_optional_signal(
+      BlockingTask::new(move || signer.sign_xades(options)),
+      signal,
+    )
+  }
+
+  /// Create a JAdES signature of a payload with the key without blocking the event loop.
+  #[napi]
+  pub fn sign_jades_async(
+    &self,
+    options: JadesSignOptions,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<BlockingTask<Buffer>> {
+    let signer = self.clone();
+
+    AsyncTask::with_optional_signal(
+      BlockingTask::new(move || signer.sign_jades(options)),
+      signal,
+    )
+  }
+
+  /// Package files and a CAdES signature of them in an ASiC container with the key without
+  /// blocking the event loop.
+  #[napi]
+  pub fn sign_asic_async(
+    &self,
+    options: AsicSignOptions,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<BlockingTask<Buffer>> {
+    let signer = self.clone();
+
+    AsyncTask::with_optional_signal(BlockingTask::new(move || signer.sign_asic(options)), signal)
+  }
+}
+
+impl ReusableSigner {
+  /// Sign data with the key, auditing the signature as the operation `name`.
+  fn sign_output(
+    &self,
+    name: &'static str,
+    options: SigningOptions,
+  ) -> Result<Either3<Buffer, SigningResult, DryRunReport>> {
+    let output = options.output(None, false);
+
+    signing_output(
+      Operation::new(name, self.backend),
+      || {
+        sign_with_key(
+          self.signing_key.as_ref(),
+          self.certs.clone(),
+          self.limiter.clone(),
+          options,
+        )
+      },
+      output,
+    )
+  }
+
+  pub fn create(options: SignerKeyOptions) -> Result<Self> {
+    let SignerKeyOptions {
+      p12,
+      p12_path,
+      p12_source,
+      password,
+      password_source,
+      friendly_name,
+      thumbprint,
+      cert,
+      private_key,
+      private_key_password,
+      format,
+      key_path,
+      max_concurrent_requests,
+      max_requests_per_second,
+    } = options;
+
+    let limiter = match (max_concurrent_requests, max_requests_per_second) {
+      (None, None) => None,
+      (max_concurrent, max_per_second) => {
+        Some(Arc::new(Limiter::new(max_concurrent, max_per_second)?))
+      }
+    };
+
+    let p12 = P12Input::read(
+      ["p12", "p12Path", "p12Source"],
+      (p12, p12_path, p12_source),
+      (password, password_source),
+      private_key.is_none() && key_path.is_none(),
+    )?;
+
+    let format = format
+      .map(|name| InputFormat::parse("format", &name))
+      .transpose()?
+      .unwrap_or_default();
+
+    let key_certs = |cert: Option<Buffer>| {
+      let cert = cert.ok_or_else(|| {
+        CmsError::InvalidOption("cert", String::from("required with privateKey or keyPath"))
+      })?;
+      validation::encoded("cert", &cert, "a certificate", format)?;
+
+      input::certificates("cert", &cert, format)
+    };
+
+    let (backend, input) = match (p12, private_key, key_path) {
+      (Some(P12Input { p12, password }), None, None) => {
+        ("p12", p12_input(p12, password, friendly_name, thumbprint))
+      }
+      (None, Some(private_key), None) => (
+        "privateKey",
+        private_key_input(private_key, private_key_password, format, key_certs(cert)?),
+      ),
+      (None, None, Some(key_path)) => {
+        validation::key_path("keyPath", &key_path)?;
+
+        ("gcloud", gcloud_input(key_path, key_certs(cert)?))
+      }
+      _ => {
+        return Err(CmsError::InvalidOption(
+          "p12",
+          String::from("expected exactly one of p12, p12Path, p12Source, privateKey or keyPath"),
+        ))
+      }
+    };
+
+    let SignerKey { signing_key, certs } = signer::create_signer(backend, input)?;
+
+    if certs.is_empty() {
+      return Err(CmsError::NoCertificate);
+    }
+
+    Ok(Self {
+      signing_key: Arc::from(signing_key),
+      certs,
+      limiter,
+      backend,
+    })
+  }
+}
+
+/// Load a key and its certificates once, returning a handle to sign with any number of times.
+///
+/// Unlike a `Signer`, the handle is a number that can be passed to other worker threads, which
+/// all share the loaded key.
+#[napi(js_name = "loadKey")]
+pub fn load_key_js(env: Env, options: SignerKeyOptions) -> napi::Result<u32> {
+  js_result(
+    env,
+    ReusableSigner::create(options).map(key_handles::insert),
+  )
+}
+
+/// Drop a loaded key, returning whether the handle was loaded.
+#[napi]
+pub fn unload_key(handle: u32) -> bool {
+  key_handles::remove(handle)
+}
+
+/// Sign data with a loaded key.
+#[napi(js_name = "signWithKey")]
+pub fn sign_with_key_js(
+  env: Env,
+  handle: u32,
+  options: SigningOptions,
+) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let signer = js_result(env, key_handles::find(handle))?;
+  js_result(env, signer.sign_output("signWithKey", options))
+}
+
+/// Sign data with a loaded key without blocking the event loop.
+#[napi]
+pub fn sign_with_key_async(
+  handle: u32,
+  options: SigningOptions,
+  signal: Option<AbortSignal>,
+  on_progress: Option<ProgressCallback>,
+) -> AsyncTask<SignTask> {
+  let output = options.output(None, false);
+  // The key is looked up again when signing, to reject rather than throw for unknown handles.
+  let backend = key_handles::find(handle).map_or("unknown", |signer| signer.backend);
+
+  AsyncTask::with_optional_signal(
+    SignTask::new(
+      Operation::new("signWithKeyAsync", backend),
+      move || {
+        let signer = key_handles::find(handle)?;
+
+        sign_with_key(
+          signer.signing_key.as_ref(),
+          signer.certs.clone(),
+          signer.limiter.clone(),
+          options,
+        )
+      },
+      output,
+      on_progress,
+    ),
+    signal,
+  )
+}
+
+/// Helper function to sign data with the key of a `Signer`.
+fn sign_with_key(
+  signing_key: &dyn KeyInfoSigner,
+  certs: Vec<LazyCertificate>,
+  limiter: Option<Arc<Limiter>>,
+  options: SigningOptions,
+) -> Result<Buffer> {
+  sign_with_parsed_key(
+    signing_key,
+    certs,
+    limiter,
+    SignatureScheme::default(),
+    options.with_defaults()?,
+  )
+}
+
+/// Helper function to sign with a parsed key and its certificates, the signing certificate
+/// first, once the options are validated and the defaults applied.
+pub fn sign_with_parsed_key(
+  signing_key: &dyn KeyInfoSigner,
+  certs: Vec<LazyCertificate>,
+  limiter: Option<Arc<Limiter>>,
+  signature_scheme: SignatureScheme,
+  signing: SigningOptions,
+) -> Result<Buffer> {
+  let cert = certs.first().ok_or(CmsError::NoCertificate)?.parsed()?;
+
+  let signer = signing.signer(signing_key, cert)?;
+
+  let signer = match limiter {
+    Some(limiter) => signer.limiter(limiter),
+    None => signer,
+  }
+  .signature_scheme(signature_scheme);
+
+  signing.into_core(vec![signer], certs)?.build()
+}
+
+/// Computes the digest of content incrementally, e.g. while reading it from a stream, for the
+/// `digest` option of the signing functions.
+#[napi]
+pub struct ContentDigest {
+  context: Option<ring::digest::Context>,
+}
+
+#[napi]
+impl ContentDigest {
+  #[napi(constructor)]
+  pub fn new(env: Env, digest_algorithm: Option<String>) -> napi::Result<Self> {
+    let digest_algorithm = defaults::digest_algorithm(digest_algorithm);
+    let digest_algorithm = js_result(env, parse_digest_algorithm(digest_algorithm.as_deref()))?;
+
+    Ok(Self {
+      context: Some(digest_algorithm.digester()),
+    })
+  }
+
+  /// Add a chunk of the content.
+  #[napi]
+  pub fn update(&mut self, env: Env, chunk: Buffer) -> napi::Result<()> {
+    let context = js_result(env, self.context.as_mut().ok_or(CmsError::DigestError))?;
+    context.update(&chunk);
+
+    Ok(())
+  }
+
+  /// Finish the digest, after which no more chunks can be added.
+  #[napi]
+  pub fn digest(&mut self, env: Env) -> napi::Result<Buffer> {
+    let context = js_result(env, self.context.take().ok_or(CmsError::DigestError))?;
+
+    Ok(Buffer::from(context.finish().as_ref()))
+  }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignWithSignerOptions {
+  pub cert: Buffer,
+  pub signer: String,
+  pub signer_options: Option<HashMap<String, String>>,
+}
+
+/// Sign data with a registered signer.
+///
+/// Signers registered with `registerSigner` take precedence over built-in backends such as
+/// `gcloud`, which read their configuration from `signerOptions`.
+#[napi(js_name = "signWithSigner")]
+pub fn sign_with_signer_js(
+  env: Env,
+  options: WithSigningOptions<SignWithSignerOptions>,
+) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let output = options.signing.output(None, false);
+  js_result(
+    env,
+    signing_output(
+      Operation::new("signWithSigner", "registered"),
+      || sign_with_signer(options),
+      output,
+    ),
+  )
+}
+
+fn sign_with_signer(options: WithSigningOptions<SignWithSignerOptions>) -> Result<Buffer> {
+  let WithSigningOptions {
+    options:
+      SignWithSignerOptions {
+        cert,
+        signer: signer_name,
+        signer_options,
+      },
+    signing,
+  } = options;
+
+  let signing = signing.with_defaults()?;
+
+  validation::encoded("cert", &cert, "a certificate", InputFormat::Auto)?;
+
+  let SignerKey { signing_key, certs } = signer::create_signer(
+    &signer_name,
+    SignerInput {
+      options: signer_options.unwrap_or_default(),
+      certs: input::certificates("cert", &cert, InputFormat::Auto)?,
+      ..Default::default()
+    },
+  )?;
+
+  let cert = certs.first().ok_or(CmsError::NoCertificate)?.parsed()?;
+
+  let signer = signing.signer(signing_key.as_ref(), cert)?;
+
+  signing.into_core(vec![signer], certs)?.build()
+}
+
+/// Sign data with a registered signer without blocking the event loop.
+///
+/// Signers registered with `registerSigner` call back into JS on the thread they were registered
+/// on, so only the signers of the other backends are found on the thread pool.
+#[napi]
+pub fn sign_with_signer_async(
+  options: WithSigningOptions<SignWithSignerOptions>,
+  signal: Option<AbortSignal>,
+  on_progress: Option<ProgressCallback>,
+) -> AsyncTask<SignTask> {
+  let output = options.signing.output(None, false);
+
+  AsyncTask::with_optional_signal(
+    SignTask::new(
+      Operation::new("signWithSignerAsync", "registered"),
+      move || sign_with_signer(options),
+      output,
+      on_progress,
+    ),
+    signal,
+  )
+}
+
+/// Register a JS signer for use with `signWithSigner`.
+///
+/// The callback receives the DER encoded signed attributes as a Buffer and must return the
+/// signature as a Buffer. The signature algorithm is derived from the signing certificate.
+#[napi(js_name = "registerSigner")]
+pub fn register_signer_js(env: Env, name: String, callback: JsFunction) -> napi::Result<()> {
+  js_result(env, register_signer(env, name, callback))
+}
+
+fn register_signer(env: Env, name: String, callback: JsFunction) -> Result<()> {
+  js_signer::register(env, name, callback)?;
+
+  Ok(())
+}
+
+/// Remove a JS signer registered with `registerSigner`.
+#[napi(js_name = "unregisterSigner")]
+pub fn unregister_signer_js(env: Env, name: String) -> napi::Result<bool> {
+  js_result(env, unregister_signer(env, name))
+}
+
+fn unregister_signer(env: Env, name: String) -> Result<bool> {
+  js_signer::unregister(env, &name)
+}
+
+#[napi(object)]
+pub struct ParallelSignerOptions {
+  pub cert: Buffer,
+  pub signer: String,
+  pub signer_options: Option<HashMap<String, String>>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignWithSignersOptions {
+  pub signers: Vec<ParallelSignerOptions>,
+}
+
+/// Sign data with several registered signers at once, producing a signer info for each.
+///
+/// Signers are resolved as for `signWithSigner`. The attribute options apply to every signer.
+#[napi(js_name = "signWithSigners")]
+pub fn sign_with_signers_js(
+  env: Env,
+  options: WithSigningOptions<SignWithSignersOptions>,
+) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let output = options.signing.output(None, false);
+  js_result(
+    env,
+    signing_output(
+      Operation::new("signWithSigners", "registered"),
+      || sign_with_signers(options),
+      output,
+    ),
+  )
+}
+
+fn sign_with_signers(options: WithSigningOptions<SignWithSignersOptions>) -> Result<Buffer> {
+  let WithSigningOptions {
+    options: SignWithSignersOptions { signers },
+    signing,
+  } = options;
+
+  let signing = signing.with_defaults()?;
+
+  if signers.is_empty() {
+    return Err(CmsError::NoSigners);
+  }
+
+  for parallel_signer in &signers {
+    validation::encoded(
+      "signers.cert",
+      &parallel_signer.cert,
+      "a certificate",
+      InputFormat::Auto,
+    )?;
+  }
+
+  let signers = signers
+    .into_iter()
+    .map(|parallel_signer| {
+      signer::create_signer(
+        &parallel_signer.signer,
+        SignerInput {
+          options: parallel_signer.signer_options.unwrap_or_default(),
+          certs: input::certificates("signers.cert", &parallel_signer.cert, InputFormat::Auto)?,
+          ..Default::default()
+        },
+      )
+    })
+    .collect::<Result<Vec<_>>>()?;
+
+  let signer_builders = signers
+    .iter()
+    .map(|SignerKey { signing_key, certs }| {
+      signing.signer(
+        signing_key.as_ref(),
+        certs.first().ok_or(CmsError::NoCertificate)?.parsed()?,
+      )
+    })
+    .collect::<Result<Vec<_>>>()?;
+
+  let certs = signers
+    .iter()
+    .flat_map(|SignerKey { certs, .. }| certs.iter().cloned())
+    .collect();
+
+  signing.into_core(signer_builders, certs)?.build()
+}
+
+/// Sign data with several registered signers at once without blocking the event loop.
+///
+/// Signers are resolved as for `signWithSignerAsync`.
+#[napi]
+pub fn sign_with_signers_async(
+  options: WithSigningOptions<SignWithSignersOptions>,
+  signal: Option<AbortSignal>,
+  on_progress: Option<ProgressCallback>,
+) -> AsyncTask<SignTask> {
+  let output = options.signing.output(None, false);
+
+  AsyncTask::with_optional_signal(
+    SignTask::new(
+      Operation::new("signWithSignersAsync", "registered"),
+      move || sign_with_signers(options),
+      output,
+      on_progress,
+    ),
+    signal,
+  )
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignDigestBatchOptions {
+  pub digests: Vec<Buffer>,
+  pub cert: Buffer,
+}
+
+/// Sign a batch of precomputed digests with one call to a remote signer, returning a signature
+/// per digest.
+///
+/// The callback receives the DER encoded signed attributes of every signature as an array of
+/// Buffers and must return the array of their signatures, so a remote signer that authorizes
+/// each request, such as a CSC provider, is authorized once for the whole batch.
+#[napi(js_name = "signDigestBatch")]
+pub fn sign_digest_batch_js(
+  env: Env,
+  options: WithSigningOptions<SignDigestBatchOptions>,
+  callback: JsFunction,
+) -> napi::Result<Vec<Buffer>> {
+  js_result(env, sign_digest_batch(env, options, callback))
+}
+
+fn sign_digest_batch(
+  env: Env,
+  options: WithSigningOptions<SignDigestBatchOptions>,
+  callback: JsFunction,
+) -> Result<Vec<Buffer>> {
+  SignedBatch::sign(env, options, &callback)?.assemble()
+}
+
+/// Sign a batch of precomputed digests with one call to a remote signer, assembling and
+/// timestamping the signatures without blocking the event loop.
+///
+/// The callback is called as by `signDigestBatch`, on the event loop before this returns, so it
+/// still returns the signatures synchronously.
+#[napi]
+pub fn sign_digest_batch_async(
+  env: Env,
+  options: WithSigningOptions<SignDigestBatchOptions>,
+  callback: JsFunction,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<Vec<Buffer>>> {
+  let batch = SignedBatch::sign(env, options, &callback);
+
+  AsyncTask::with_optional_signal(BlockingTask::new(move || batch?.assemble()), signal)
+}
+
+/// The digests of a batch and the signatures of their signed attributes by the callback of
+/// `signDigestBatch`, to assemble the signed data of each with.
+struct SignedBatch {
+  external: ExternalSignature,
+  digests: Vec<Buffer>,
+  /// The signed attributes of each digest, in order, with their signature.
+  signatures: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl SignedBatch {
+  /// Build the signed attributes of every digest and have the callback sign them in one call.
+  fn sign(
+    env: Env,
+    options: WithSigningOptions<SignDigestBatchOptions>,
+    callback: &JsFunction,
+  ) -> Result<Self> {
+    let WithSigningOptions {
+      options: SignDigestBatchOptions { digests, cert },
+      signing,
+    } = options;
+
+    if digests.is_empty() {
+      return Err(CmsError::InvalidOption(
+        "digests",
+        String::from("expected at least one digest"),
+      ));
+    }
+
+    // Every signature of the batch would be written to the same file.
+    if signing.output_path.is_some() {
+      return Err(CmsError::InvalidOption(
+        "outputPath",
+        String::from("a batch returns a signature per digest rather than writing them"),
+      ));
+    }
+
+    let external = ExternalSignature::new(&cert, signing)?;
+
+    for (index, digest) in digests.iter().enumerate() {
+      validation::non_empty("digests", digest)
+        .map_err(|error| error.with_detail("index", index))?;
+    }
+
+    // The first pass learns the signed attributes to sign, without a timestamp.
+    let collector = BatchSigner::collect(external.certificate()?)?;
+
+    for (index, digest) in digests.iter().enumerate() {
+      external
+        .sign(&collector, digest, false)
+        .map_err(|error| error.with_detail("index", index))?;
+    }
+
+    let messages = collector.into_messages();
+    let signatures = js_signer::sign_batch(env, callback, &messages)?;
+
+    tracing::info!(signatures = signatures.len(), "Signed batch");
+
+    Ok(Self {
+      external,
+      digests,
+      signatures: messages.into_iter().zip(signatures).collect(),
+    })
+  }
+
+  /// Assemble the signed data of each digest with its signature.
+  fn assemble(self) -> Result<Vec<Buffer>> {
+    let x509_cert = self.external.certificate()?;
+
+    self
+      .signatures
+      .into_iter()
+      .zip(&self.digests)
+      .enumerate()
+      .map(|(index, ((message, signature), digest))| {
+        let signed = BatchSigner::signed(x509_cert, message, signature)?;
+
+        self
+          .external
+          .sign(&signed, digest, true)
+          .map_err(|error| error.with_detail("index", index))
+      })
+      .collect()
+  }
+}
+
+/// The options of a signature by an external signer, which build the same signed attributes for
+/// the pass that collects the message to sign and the one that adds its signature.
+struct ExternalSignature {
+  /// The signing certificate, followed by the rest of the chain to include.
+  certs: Vec<LazyCertificate>,
+  /// The options of the signature, but for the digest, which is given for each signature.
+  signing: SigningOptions,
+}
+
+impl ExternalSignature {
+  /// An external signature by the certificate `cert` with the `signing` options, whose signing
+  /// time is fixed so both passes sign the same signed attributes.
+  fn new(cert: &Buffer, signing: SigningOptions) -> Result<Self> {
+    let mut signing = signing.with_defaults()?;
+
+    validation::encoded("cert", cert, "a certificate", InputFormat::Auto)?;
+
+    signing.signing_time = Some(parse_signing_time(signing.signing_time.take()).to_rfc3339());
+
+    Ok(Self {
+      certs: input::certificates("cert", cert, InputFormat::Auto)?,
+      signing,
+    })
+  }
+
+  fn certificate(&self) -> Result<&CapturedX509Certificate> {
+    self.certs.first().ok_or(CmsError::NoCertificate)?.parsed()
+  }
+
+  /// Sign `digest`, timestamping and writing the signed data only in the `last` pass, the one
+  /// that adds the signature.
+  fn sign(&self, signing_key: &dyn KeyInfoSigner, digest: &Buffer, last: bool) -> Result<Buffer> {
+    let mut signing = SigningOptions {
+      digest: Some(digest.clone()),
+      ..self.signing.clone()
+    };
+
+    if !last {
+      signing.timestamp_server = None;
+      signing.output_path = None;
+      signing.max_size = None;
+    }
+
+    let signer = signing.signer(signing_key, self.certificate()?)?;
+
+    signing.into_core(vec![signer], self.certs.clone())?.build()
+  }
+
+  /// The DER encoded signed attributes to sign, collected by signing without a timestamp, and
+  /// the algorithm to sign them with.
+  fn message(&self, digest: &Buffer) -> Result<(Vec<u8>, SignatureAlgorithm)> {
+    let collector = BatchSigner::collect(self.certificate()?)?;
+
+    self.sign(&collector, digest, false)?;
+
+    let signature_algorithm = collector
+      .signature_algorithm()
+      .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+    let message = collector
+      .into_messages()
+      .pop()
+      .ok_or(CmsError::BuildSignedDataError)?;
+
+    Ok((message, signature_algorithm))
+  }
+}
+
+/// Helper function to get the digest of the options of `prepareSignedAttributes` and
+/// `assembleSignedData`, which sign a precomputed digest.
+fn external_digest(signing: &SigningOptions) -> Result<Buffer> {
+  signing.digest.clone().ok_or_else(|| {
+    CmsError::InvalidOption(
+      "digest",
+      String::from("expected the precomputed digest of the content"),
+    )
+  })
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct PrepareSignedAttributesOptions {
+  pub cert: Buffer,
+}
+
+#[napi(object)]
+pub struct PreparedSignedAttributes {
+  /// The DER encoded signed attributes, which the external signer signs.
+  pub signed_attributes_der: Buffer,
+  /// The digest of the signed attributes, for signers that sign a digest, computed with the
+  /// digest algorithm of the certificate's key. Missing for Ed25519, which signs the signed
+  /// attributes themselves.
+  pub signed_attributes_digest: Option<Buffer>,
+  /// The digest algorithm of `signedAttributesDigest`, `sha256` for RSA and ECDSA P-256
+  /// certificates and `sha384` for P-384.
+  pub signed_attributes_digest_algorithm: Option<String>,
+  /// The signing time of the signed attributes, to pass to `assembleSignedData`.
+  pub signing_time: String,
+}
+
+/// Prepare the signed attributes of a signature of a precomputed digest, for an external signer
+/// to sign and `assembleSignedData` to assemble the signed data with.
+#[napi(js_name = "prepareSignedAttributes")]
+pub fn prepare_signed_attributes_js(
+  env: Env,
+  options: WithSigningOptions<PrepareSignedAttributesOptions>,
+) -> napi::Result<PreparedSignedAttributes> {
+  js_result(env, prepare_signed_attributes(options))
+}
+
+fn prepare_signed_attributes(
+  options: WithSigningOptions<PrepareSignedAttributesOptions>,
+) -> Result<PreparedSignedAttributes> {
+  let WithSigningOptions {
+    options: PrepareSignedAttributesOptions { cert },
+    signing,
+  } = options;
+
+  let digest = external_digest(&signing)?;
+  let external = ExternalSignature::new(&cert, signing)?;
+
+  let (message, signature_algorithm) = external.message(&digest)?;
+
+  tracing::debug!(size = message.len(), "Prepared signed attributes");
+
+  let digest_algorithm = signature_algorithm.digest_algorithm();
+
+  Ok(PreparedSignedAttributes {
+    signed_attributes_digest: digest_algorithm
+      .map(|algorithm| algorithm.digest_data(&message).into()),
+    signed_attributes_digest_algorithm: digest_algorithm
+      .map(|algorithm| String::from(digest_algorithm_name(algorithm))),
+    signed_attributes_der: message.into(),
+    signing_time: external.signing.signing_time.unwrap_or_default(),
+  })
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct AssembleSignedDataOptions {
+  pub signed_attributes_der: Buffer,
+  pub signature: Buffer,
+  pub cert: Buffer,
+}
+
+/// Assemble the signed data of a signature prepared with `prepareSignedAttributes`, from the
+/// signature of its signed attributes by an external signer.
+///
+/// The signed attributes are built again from the options, which must be those they were
+/// prepared with, and the signature is checked against the certificate before the timestamp is
+/// requested.
+#[napi(js_name = "assembleSignedData")]
+pub fn assemble_signed_data_js(
+  env: Env,
+  options: WithSigningOptions<AssembleSignedDataOptions>,
+) -> napi::Result<Buffer> {
+  js_result(env, assemble_signed_data(options))
+}
+
+fn assemble_signed_data(options: WithSigningOptions<AssembleSignedDataOptions>) -> Result<Buffer> {
+  let WithSigningOptions {
+    options:
+      AssembleSignedDataOptions {
+        signed_attributes_der,
+        signature,
+        cert,
+      },
+    signing,
+  } = options;
+
+  // The signed attributes are only built again the same way at the time they were prepared.
+  if signing.signing_time.is_none() {
+    return Err(CmsError::InvalidOption(
+      "signingTime",
+      String::from("expected the signing time returned by prepareSignedAttributes"),
+    ));
+  }
+
+  let digest = external_digest(&signing)?;
+  let external = ExternalSignature::new(&cert, signing)?;
+
+  let (expected, signature_algorithm) = external.message(&digest)?;
+
+  if signed_attributes_der.as_ref() != expected.as_slice() {
+    return Err(CmsError::InvalidOption(
+      "signedAttributesDer",
+      String::from(
+        "expected the signed attributes prepared with the same digest, certificate, signing time \
+         and options",
+      ),
+    ));
+  }
+
+  let x509_cert = external.certificate()?;
+
+  let key_algorithm = x509_cert
+    .key_algorithm()
+    .ok_or(CmsError::UnsupportedKeyAlgorithm)?;
+  let verification_algorithm = signature_algorithm
+    .resolve_verification_algorithm(key_algorithm)
+    .map_err(|error| CmsError::UnsupportedKeyAlgorithm.caused_by(error))?;
+
+  x509_cert
+    .verify_signed_data_with_algorithm(&expected, &signature, verification_algorithm)
+    .map_err(|error| CmsError::CertificateKeyMismatch.caused_by(error))?;
+
+  let signed = BatchSigner::signed(x509_cert, expected, signature.to_vec())?;
+
+  external.sign(&signed, &digest, true)
+}
+
+/// Assemble the signed data of a signature prepared with `prepareSignedAttributes` without
+/// blocking the event loop.
+#[napi]
+pub fn assemble_signed_data_async(
+  options: WithSigningOptions<AssembleSignedDataOptions>,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<Buffer>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || assemble_signed_data(options)),
+    signal,
+  )
+}
+
+#[napi(object)]
+pub struct SignerPoolMemberOptions {
+  pub cert: Buffer,
+  pub signer: String,
+  pub signer_options: Option<HashMap<String, String>>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct RegisterSignerPoolOptions {
+  pub name: String,
+  pub members: Vec<SignerPoolMemberOptions>,
+  pub failure_threshold: Option<u32>,
+  pub cooldown_ms: Option<u32>,
+}
+
+/// Register a pool of signers for use with `signWithSignerPool`.
+///
+/// Requests are distributed round-robin across the members. A member failing `failureThreshold`
+/// times in a row (default 3) is skipped for `cooldownMs` (default 30 seconds). Members in their
+/// cooldown are only tried when every member is in it.
+#[napi(js_name = "registerSignerPool")]
+pub fn register_signer_pool_js(env: Env, options: RegisterSignerPoolOptions) -> napi::Result<()> {
+  js_result(env, register_signer_pool(options))
+}
+
+fn register_signer_pool(options: RegisterSignerPoolOptions) -> Result<()> {
+  let RegisterSignerPoolOptions {
+    name,
+    members,
+    failure_threshold,
+    cooldown_ms,
+  } = options;
+
+  let members = members
+    .into_iter()
+    .map(|member| {
+      SignerPoolMember::new(
+        input::certificates("members.cert", &member.cert, InputFormat::Auto)?,
+        member.signer,
+        member.signer_options.unwrap_or_default(),
+      )
+    })
+    .collect::<std::result::Result<Vec<_>, CmsError>>()?;
+
+  let pool = SignerPool::new(
+    members,
+    failure_threshold.unwrap_or(signer_pool::DEFAULT_FAILURE_THRESHOLD),
+    cooldown_ms
+      .map(|ms| std::time::Duration::from_millis(ms.into()))
+      .unwrap_or(signer_pool::DEFAULT_COOLDOWN),
+  )?;
+
+  signer_pool::register_pool(name, pool);
+
+  Ok(())
+}
+
+/// Remove a pool registered with `registerSignerPool`.
+#[napi]
+pub fn unregister_signer_pool(name: String) -> bool {
+  signer_pool::unregister_pool(&name)
+}
+
+#[napi(object)]
+pub struct SignerPoolMemberStatus {
+  pub healthy: bool,
+  pub consecutive_failures: u32,
+}
+
+/// Get the health of each member of a pool, in registration order.
+#[napi(js_name = "signerPoolStatus")]
+pub fn signer_pool_status_js(env: Env, name: String) -> napi::Result<Vec<SignerPoolMemberStatus>> {
+  js_result(env, signer_pool_status(name))
+}
+
+fn signer_pool_status(name: String) -> Result<Vec<SignerPoolMemberStatus>> {
+  let pool = signer_pool::find_pool(&name)?;
+
+  Ok(
+    pool
+      .status()
+      .into_iter()
+      .map(|status| SignerPoolMemberStatus {
+        healthy: status.healthy,
+        consecutive_failures: status.consecutive_failures,
+      })
+      .collect(),
+  )
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SignWithSignerPoolOptions {
+  pub pool: String,
+}
+
+/// Sign data with the next healthy member of a pool.
+///
+/// If a member fails to sign, the next member is tried until one succeeds. Failures unrelated
+/// to the key, such as an unreachable timestamp server, are returned immediately.
+#[napi(js_name = "signWithSignerPool")]
+pub fn sign_with_signer_pool_js(
+  env: Env,
+  options: WithSigningOptions<SignWithSignerPoolOptions>,
+) -> napi::Result<Either3<Buffer, SigningResult, DryRunReport>> {
+  let output = options.signing.output(None, false);
+  js_result(
+    env,
+    signing_output(
+      Operation::new("signWithSignerPool", "pool"),
+      || sign_with_signer_pool(options),
+      output,
+    ),
+  )
+}
+
+fn sign_with_signer_pool(options: WithSigningOptions<SignWithSignerPoolOptions>) -> Result<Buffer> {
+  let WithSigningOptions {
+    options: SignWithSignerPoolOptions { pool },
+    signing,
+  } = options;
+
+  let signing = signing.with_defaults()?;
+
+  let pool = signer_pool::find_pool(&pool)?;
+
+  let mut last_error = CmsError::SigningError;
+
+  for (index, member) in pool.candidates() {
+    let input = SignerInput {
+      options: member.signer_options.clone(),
+      certs: member.certs.clone(),
+      ..Default::default()
+    };
+
+    let signing_key = match signer::create_signer(&member.signer, input) {
+      Ok(SignerKey { signing_key, .. }) => signing_key,
+      Err(err) => {
+        pool.record_failure(index);
+        last_error = err;
+        continue;
+      }
+    };
+
+    let tracked_signer = TrackedSigner::new(signing_key.as_ref());
+
+    let signer = signing.signer(&tracked_signer, member.cert())?;
+
+    let result = signing
+      .clone()
+      .into_core(vec![signer], member.certs.clone())
+      .and_then(SignedDataOutput::build);
+
+    match result {
+      Ok(signed_data) => {
+        pool.record_success(index);
+
+        return Ok(signed_data);
+      }
+      Err(err) if tracked_signer.failed() => {
+        pool.record_failure(index);
+        last_error = err;
+      }
+      Err(err) => return Err(err),
+    }
+  }
+
+  Err(last_error)
+}
+
+/// Sign data with the next healthy member of a pool without blocking the event loop.
+///
+/// Members are resolved as for `signWithSignerAsync`, so a member registered with
+/// `registerSigner` fails and the next one is tried.
+#[napi]
+pub fn sign_with_signer_pool_async(
+  options: WithSigningOptions<SignWithSignerPoolOptions>,
+  signal: Option<AbortSignal>,
+  on_progress: Option<ProgressCallback>,
+) -> AsyncTask<SignTask> {
+  let output = options.signing.output(None, false);
+
+  AsyncTask::with_optional_signal(
+    SignTask::new(
+      Operation::new("signWithSignerPoolAsync", "pool"),
+      move || sign_with_signer_pool(options),
+      output,
+      on_progress,
+    ),
+    signal,
+  )
+}
+
+/// Helper function to check the size of signed data and write it to the output path, if any.
+pub fn write_signed_data(
+  signed_data: Buffer,
+  max_size: Option<u32>,
+  output_path: Option<String>,
+) -> Result<Buffer> {
+  // Signed data that doesn't fit the space reserved for it, e.g. the /Contents placeholder of a
+  // PDF, would be truncated or overwrite what follows, so it's never returned or written.
+  if let Some(max_size) = max_size.map(|max_size| max_size as usize) {
+    if signed_data.len() > max_size {
+      tracing::warn!(
+        size = signed_data.len(),
+        max_size,
+        "Signed data exceeds the reserved size"
+      );
+      return Err(CmsError::SignatureTooLarge(signed_data.len(), max_size));
+    }
+  }
+
+  if let Some(output_path) = output_path {
+    std::fs::write(&output_path, &signed_data).map_err(|error| {
+      CmsError::FileWriteError
+        .caused_by(error)
+        .with_detail("path", &output_path)
+    })?;
+
+    tracing::debug!(path = output_path, "Wrote signed data");
+  }
+
+  Ok(signed_data)
+}
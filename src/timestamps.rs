@@ -0,0 +1,266 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use pdf_sign_core::parse_digest_algorithm;
+use pdf_sign_core::signing;
+use pdf_sign_core::{archive, cms, evidence_record, validation};
+
+use crate::errors::CmsError;
+use crate::signers::write_signed_data;
+use crate::{defaults, js_result, BlockingTask, Result};
+
+/// The URL of the timestamp server to use, or the configured default.
+///
+/// A timestamp token is all these functions create, so they can't do without a server.
+fn timestamp_url(timestamp_server: Option<String>) -> Result<reqwest::Url> {
+  match defaults::timestamp_server(timestamp_server) {
+    Some(timestamp_server) => validation::timestamp_server("timestampServer", &timestamp_server),
+    None => Err(CmsError::InvalidOption(
+      "timestampServer",
+      String::from("required unless a default timestamp server is configured"),
+    )),
+  }
+}
+
+#[napi(object)]
+pub struct RequestTimestampOptions {
+  pub content: Option<Buffer>,
+  pub digest: Option<Buffer>,
+  pub digest_algorithm: Option<String>,
+  pub timestamp_server: Option<String>,
+}
+
+/// Request an RFC 3161 timestamp token for content or its digest, e.g. for the document
+/// timestamp of a PDF, returning the DER encoded TimeStampToken.
+#[napi(js_name = "requestTimestamp")]
+pub fn request_timestamp_js(env: Env, options: RequestTimestampOptions) -> napi::Result<Buffer> {
+  js_result(env, request_timestamp(options))
+}
+
+/// Request a timestamp token without blocking the event loop.
+#[napi(js_name = "requestTimestampAsync")]
+pub fn request_timestamp_async(
+  options: RequestTimestampOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<Buffer>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || request_timestamp(options)),
+    signal,
+  )
+}
+
+fn request_timestamp(options: RequestTimestampOptions) -> Result<Buffer> {
+  let RequestTimestampOptions {
+    content,
+    digest,
+    digest_algorithm,
+    timestamp_server,
+  } = options;
+
+  let digest_algorithm = defaults::digest_algorithm(digest_algorithm);
+
+  validation::signing_options(
+    content.as_deref(),
+    None,
+    digest.as_deref(),
+    digest_algorithm.as_deref(),
+    None,
+  )?;
+
+  let url = timestamp_url(timestamp_server)?;
+  let digest_algorithm = parse_digest_algorithm(digest_algorithm.as_deref())?;
+
+  let token = match (content, digest) {
+    (Some(content), None) => signing::time_stamp_token(&url, &content, digest_algorithm)?,
+    (None, Some(digest)) => signing::time_stamp_token_for_digest(&url, &digest, digest_algorithm)?,
+    _ => return Err(CmsError::InvalidContent),
+  };
+
+  Ok(Buffer::from(token.into_bytes().to_vec()))
+}
+
+#[napi(object)]
+pub struct CreateDocumentTimestampOptions {
+  pub content: Option<Buffer>,
+  pub content_path: Option<String>,
+  pub digest: Option<Buffer>,
+  pub digest_algorithm: Option<String>,
+  pub timestamp_server: Option<String>,
+  pub output_path: Option<String>,
+  pub max_size: Option<u32>,
+}
+
+/// Create the CMS of a document timestamp (PAdES-B-LTA), the timestamp token of the byte range
+/// of a PDF for the /Contents of a DocTimeStamp signature with the ETSI.RFC3161 sub filter.
+#[napi(js_name = "createDocumentTimestamp")]
+pub fn create_document_timestamp_js(
+  env: Env,
+  options: CreateDocumentTimestampOptions,
+) -> napi::Result<Buffer> {
+  js_result(env, create_document_timestamp(options))
+}
+
+/// Create the CMS of a document timestamp without blocking the event loop.
+#[napi(js_name = "createDocumentTimestampAsync")]
+pub fn create_document_timestamp_async(
+  options: CreateDocumentTimestampOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<Buffer>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || create_document_timestamp(options)),
+    signal,
+  )
+}
+
+fn create_document_timestamp(options: CreateDocumentTimestampOptions) -> Result<Buffer> {
+  let CreateDocumentTimestampOptions {
+    content,
+    content_path,
+    digest,
+    digest_algorithm,
+    timestamp_server,
+    output_path,
+    max_size,
+  } = options;
+
+  let digest_algorithm = defaults::digest_algorithm(digest_algorithm);
+
+  validation::signing_options(
+    content.as_deref(),
+    content_path.as_deref(),
+    digest.as_deref(),
+    digest_algorithm.as_deref(),
+    None,
+  )?;
+
+  let url = timestamp_url(timestamp_server)?;
+  let digest_algorithm = parse_digest_algorithm(digest_algorithm.as_deref())?;
+
+  // There's no signer, so the digest of the byte range is timestamped directly.
+  let digest = match (content, content_path, digest) {
+    (Some(content), None, None) => digest_algorithm.digest_data(&content),
+    (None, Some(content_path), None) => {
+      signing::digest_file(content_path.as_ref(), &[digest_algorithm])?.remove(0)
+    }
+    (None, None, Some(digest)) => digest.to_vec(),
+    _ => return Err(CmsError::InvalidContent),
+  };
+
+  let token = signing::time_stamp_token_for_digest(&url, &digest, digest_algorithm)?;
+
+  tracing::info!(size = token.len(), "Created document timestamp");
+
+  write_signed_data(
+    Buffer::from(token.into_bytes().to_vec()),
+    max_size,
+    output_path,
+  )
+}
+
+#[napi(object)]
+pub struct AddArchiveTimestampOptions {
+  pub signed_data: Buffer,
+  pub content: Option<Buffer>,
+  pub signer_index: Option<u32>,
+  pub timestamp_server: Option<String>,
+  pub digest_algorithm: Option<String>,
+}
+
+/// Add an archive timestamp (archive-time-stamp-v3) to an existing signature, for CAdES-A.
+///
+/// The timestamp covers the content, the signer info at `signerIndex`, the first by default,
+/// and the certificates, CRLs and unsigned attributes present at this point, so validation data
+/// should be added first. The content must be given for detached signatures.
+#[napi(js_name = "addArchiveTimestamp")]
+pub fn add_archive_timestamp_js(
+  env: Env,
+  options: AddArchiveTimestampOptions,
+) -> napi::Result<Buffer> {
+  js_result(env, add_archive_timestamp(options))
+}
+
+/// Add an archive timestamp to an existing signature without blocking the event loop.
+#[napi(js_name = "addArchiveTimestampAsync")]
+pub fn add_archive_timestamp_async(
+  options: AddArchiveTimestampOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<Buffer>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || add_archive_timestamp(options)),
+    signal,
+  )
+}
+
+fn add_archive_timestamp(options: AddArchiveTimestampOptions) -> Result<Buffer> {
+  let AddArchiveTimestampOptions {
+    signed_data,
+    content,
+    signer_index,
+    timestamp_server,
+    digest_algorithm,
+  } = options;
+
+  let url = timestamp_url(timestamp_server)?;
+  let digest_algorithm = defaults::digest_algorithm(digest_algorithm);
+
+  let mut signed_data = cms::SignedData::decode(&signed_data)?;
+
+  archive::add_archive_time_stamp_v3(
+    &mut signed_data,
+    signer_index.unwrap_or(0) as usize,
+    content.as_deref(),
+    &url,
+    parse_digest_algorithm(digest_algorithm.as_deref())?,
+  )?;
+
+  Ok(Buffer::from(signed_data.encode()))
+}
+
+#[napi(object)]
+pub struct CreateEvidenceRecordOptions {
+  pub data: Vec<Buffer>,
+  pub timestamp_server: Option<String>,
+  pub digest_algorithm: Option<String>,
+}
+
+/// Create an Evidence Record (RFC 4998) with an archive timestamp over a group of data objects,
+/// such as a signed PDF and its validation data, for archives that ingest ERS.
+#[napi(js_name = "createEvidenceRecord")]
+pub fn create_evidence_record_js(
+  env: Env,
+  options: CreateEvidenceRecordOptions,
+) -> napi::Result<Buffer> {
+  js_result(env, create_evidence_record(options))
+}
+
+/// Create an Evidence Record without blocking the event loop.
+#[napi(js_name = "createEvidenceRecordAsync")]
+pub fn create_evidence_record_async(
+  options: CreateEvidenceRecordOptions,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<BlockingTask<Buffer>> {
+  AsyncTask::with_optional_signal(
+    BlockingTask::new(move || create_evidence_record(options)),
+    signal,
+  )
+}
+
+fn create_evidence_record(options: CreateEvidenceRecordOptions) -> Result<Buffer> {
+  let CreateEvidenceRecordOptions {
+    data,
+    timestamp_server,
+    digest_algorithm,
+  } = options;
+
+  let url = timestamp_url(timestamp_server)?;
+  let digest_algorithm = defaults::digest_algorithm(digest_algorithm);
+  let data = data.iter().map(|data| data.as_ref()).collect::<Vec<_>>();
+
+  let evidence_record = evidence_record::create(
+    &data,
+    &url,
+    parse_digest_algorithm(digest_algorithm.as_deref())?,
+  )?;
+
+  Ok(Buffer::from(evidence_record))
+}